@@ -1,14 +1,80 @@
 #[cfg(all(target_os = "linux", feature = "binding-recompile"))]
 extern crate bindgen;
 
-#[cfg(all(target_os = "linux", feature = "binding-recompile"))]
+#[cfg(any(
+    all(target_os = "linux", feature = "binding-recompile"),
+    feature = "aaguid_metadata"
+))]
 use std::path::PathBuf;
 
-#[cfg(any(not(target_os = "linux"), not(feature = "binding-recompile")))]
-fn main() {}
+fn main() {
+    #[cfg(all(target_os = "linux", feature = "binding-recompile"))]
+    hidraw_bindings();
+    #[cfg(feature = "aaguid_metadata")]
+    aaguid_metadata();
+}
+
+/// Generates the table behind `AAGuid::metadata()` from the payload of a FIDO Metadata
+/// Service BLOB (a JSON object with an "entries" array, see
+/// https://fidoalliance.org/specs/mds/fido-metadata-service-v3.0-ps-20210518.html#metadata-blob-payload-entry-dictionary).
+/// The bundled file only lists a few authenticators. To get all of them, point the
+/// AUTHENTICATOR_AAGUID_METADATA environment variable at the decoded payload of the
+/// current BLOB.
+#[cfg(feature = "aaguid_metadata")]
+fn aaguid_metadata() {
+    println!("cargo:rerun-if-env-changed=AUTHENTICATOR_AAGUID_METADATA");
+    let path = std::env::var_os("AUTHENTICATOR_AAGUID_METADATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("src/ctap2/aaguid_metadata.json"));
+    println!("cargo:rerun-if-changed={}", path.display());
+
+    let file = std::fs::File::open(&path).expect("Couldn't open AAGUID metadata");
+    let payload: serde_json::Value =
+        serde_json::from_reader(file).expect("Couldn't parse AAGUID metadata");
+    let mut entries = Vec::new();
+    for entry in payload["entries"]
+        .as_array()
+        .expect("AAGUID metadata has no entries")
+    {
+        // Entries of U2F and UAF authenticators have no AAGUID
+        let aaguid = match entry["aaguid"].as_str() {
+            Some(aaguid) => aaguid.replace('-', ""),
+            None => continue,
+        };
+        assert_eq!(aaguid.len(), 32, "Invalid AAGUID {aaguid}");
+        let aaguid: Vec<u8> = (0..32)
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&aaguid[i..i + 2], 16).expect("Invalid AAGUID"))
+            .collect();
+        let statement = &entry["metadataStatement"];
+        let name = statement["description"]
+            .as_str()
+            .expect("Metadata statement without description");
+        let icon = statement["icon"].as_str();
+        let transports: Vec<&str> = statement["authenticatorGetInfo"]["transports"]
+            .as_array()
+            .map(|transports| transports.iter().filter_map(|t| t.as_str()).collect())
+            .unwrap_or_default();
+        entries.push((aaguid, name, icon, transports));
+    }
+    // Sorted, for a binary search
+    entries.sort();
+    entries.dedup_by(|a, b| a.0 == b.0);
+
+    let mut table = String::from("pub static AAGUID_METADATA: &[Entry] = &[\n");
+    for (aaguid, name, icon, transports) in entries {
+        table.push_str(&format!(
+            "    ({aaguid:?}, {name:?}, {icon:?}, &{transports:?}),\n"
+        ));
+    }
+    table.push_str("];\n");
+    let out_path = PathBuf::from(std::env::var_os("OUT_DIR").expect("OUT_DIR not set"));
+    std::fs::write(out_path.join("aaguid_metadata.rs"), table)
+        .expect("Couldn't write AAGUID metadata");
+}
 
 #[cfg(all(target_os = "linux", feature = "binding-recompile"))]
-fn main() {
+fn hidraw_bindings() {
     let bindings = bindgen::Builder::default()
         .header("src/transport/linux/hidwrapper.h")
         .allowlist_var("_HIDIOCGRDESCSIZE")
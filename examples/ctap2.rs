@@ -180,6 +180,7 @@ fn main() {
         }],
         user_verification_req: UserVerificationRequirement::Preferred,
         resident_key_req: ResidentKeyRequirement::Discouraged,
+        default_rk_cred_protect: None,
         extensions: AuthenticationExtensionsClientInputs {
             cred_props: Some(true),
             hmac_create_secret: Some(matches.opt_present("hmac_secret")),
@@ -191,6 +192,11 @@ fn main() {
         },
         pin: None,
         use_ctap1_fallback: fallback,
+        enterprise_attestation: None,
+        enterprise_attestation_rp_ids: vec![],
+        skip_attestation_statement: false,
+        reject_backup_eligible: false,
+        deadline: None,
     };
 
     let attestation_object;
@@ -247,6 +253,9 @@ fn main() {
         },
         pin: None,
         use_ctap1_fallback: fallback,
+        reassert_selected_credential: false,
+        reject_backup_eligible: false,
+        deadline: None,
     };
 
     loop {
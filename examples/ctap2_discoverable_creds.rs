@@ -168,12 +168,18 @@ fn register_user(manager: &mut AuthenticatorService, username: &str, timeout_ms:
         }],
         user_verification_req: UserVerificationRequirement::Required,
         resident_key_req: ResidentKeyRequirement::Required,
+        default_rk_cred_protect: None,
         extensions: AuthenticationExtensionsClientInputs {
             cred_props: Some(true),
             ..Default::default()
         },
         pin: None,
         use_ctap1_fallback: false,
+        enterprise_attestation: None,
+        enterprise_attestation_rp_ids: vec![],
+        skip_attestation_statement: false,
+        reject_backup_eligible: false,
+        deadline: None,
     };
 
     let attestation_object;
@@ -344,6 +350,9 @@ fn main() {
         extensions: Default::default(),
         pin: None,
         use_ctap1_fallback: false,
+        reassert_selected_credential: false,
+        reject_backup_eligible: false,
+        deadline: None,
     };
 
     loop {
@@ -410,6 +410,9 @@ fn handle_credential_management(
                 }
             }
         }
+        Some(CredentialManagementResult::EnumerationProgress(remaining)) => {
+            println!("Fetching credentials, {remaining} remaining");
+        }
         None
         | Some(CredentialManagementResult::DeleteSucess)
         | Some(CredentialManagementResult::UpdateSuccess) => {
@@ -5,13 +5,12 @@
 use authenticator::{
     authenticatorservice::{AuthenticatorService, RegisterArgs, SignArgs},
     crypto::COSEAlgorithm,
-    ctap2::commands::StatusCode,
     ctap2::server::{
         PublicKeyCredentialDescriptor, PublicKeyCredentialParameters,
         PublicKeyCredentialUserEntity, RelyingParty, ResidentKeyRequirement, Transport,
         UserVerificationRequirement,
     },
-    errors::{AuthenticatorError, CommandError, HIDError, UnsupportedOption},
+    errors::{AuthenticatorError, UnsupportedOption},
     statecallback::StateCallback,
     Pin, StatusPinUv, StatusUpdate,
 };
@@ -163,9 +162,15 @@ fn main() {
         exclude_list: vec![],
         user_verification_req: UserVerificationRequirement::Preferred,
         resident_key_req: ResidentKeyRequirement::Discouraged,
+        default_rk_cred_protect: None,
         extensions: Default::default(),
         pin: None,
         use_ctap1_fallback: false,
+        enterprise_attestation: None,
+        enterprise_attestation_rp_ids: vec![],
+        skip_attestation_statement: false,
+        reject_backup_eligible: false,
+        deadline: None,
     };
 
     let mut registered_key_handle = None;
@@ -233,6 +238,9 @@ fn main() {
         extensions: Default::default(),
         pin: None,
         use_ctap1_fallback: false,
+        reassert_selected_credential: false,
+        reject_backup_eligible: false,
+        deadline: None,
         user_verification_req: UserVerificationRequirement::Preferred,
         user_presence_req: true,
     };
@@ -273,10 +281,7 @@ fn main() {
                 ctap_args.allow_list.push(registered_handle);
                 continue;
             }
-            Err(AuthenticatorError::HIDError(HIDError::Command(CommandError::StatusCode(
-                StatusCode::NoCredentials,
-                None,
-            ))))
+            Err(AuthenticatorError::NoMatchingCredentials(_))
             | Err(AuthenticatorError::UnsupportedOption(UnsupportedOption::EmptyAllowList)) => {
                 if ctap_args.allow_list.is_empty() {
                     // Try again with a list of false creds. We should end up here again.
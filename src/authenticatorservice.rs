@@ -4,14 +4,40 @@
 
 use crate::ctap2::commands::client_pin::Pin;
 use crate::ctap2::server::{
-    AuthenticationExtensionsClientInputs, PublicKeyCredentialDescriptor,
-    PublicKeyCredentialParameters, PublicKeyCredentialUserEntity, RelyingParty,
-    ResidentKeyRequirement, UserVerificationRequirement,
+    AuthenticationExtensionsClientInputs, CredentialProtectionPolicy,
+    PublicKeyCredentialDescriptor, PublicKeyCredentialParameters, PublicKeyCredentialUserEntity,
+    RelyingParty, ResidentKeyRequirement, UserVerificationRequirement,
 };
 use crate::errors::*;
 use crate::manager::Manager;
 use crate::statecallback::StateCallback;
 use std::sync::{mpsc::Sender, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Upper bound on the wall-clock time of a whole register or sign operation. Unlike the
+/// per-transaction timeout, this also covers the time spent in retries, pre-flight requests
+/// and PIN/UV round trips. Once it has passed, the in-flight request is cancelled and the
+/// operation fails with `AuthenticatorError::Timeout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    pub fn at(instant: Instant) -> Self {
+        Deadline(instant)
+    }
+
+    pub fn after(duration: Duration) -> Self {
+        Deadline(Instant::now() + duration)
+    }
+
+    pub fn has_expired(&self) -> bool {
+        Instant::now() >= self.0
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(Instant::now())
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct RegisterArgs {
@@ -23,9 +49,26 @@ pub struct RegisterArgs {
     pub exclude_list: Vec<PublicKeyCredentialDescriptor>,
     pub user_verification_req: UserVerificationRequirement,
     pub resident_key_req: ResidentKeyRequirement,
+    /// The credential protection policy to ask for, if a discoverable credential is created,
+    /// the relying party did not choose a policy, and the authenticator supports credProtect.
+    /// Browsers commonly use `UserVerificationOptionalWithCredentialIDList` here, so that
+    /// the credential can't be found without user verification.
+    pub default_rk_cred_protect: Option<CredentialProtectionPolicy>,
     pub extensions: AuthenticationExtensionsClientInputs,
     pub pin: Option<Pin>,
     pub use_ctap1_fallback: bool,
+    pub enterprise_attestation: Option<u64>,
+    /// RP IDs that may receive vendor-facilitated enterprise attestation. A request for
+    /// `enterprise_attestation = Some(1)` from any other RP is dropped, as browsers do.
+    pub enterprise_attestation_rp_ids: Vec<String>,
+    /// Keep the attestation statement of the authenticator unparsed, see
+    /// `AttestationStatement::Unparsed`.
+    pub skip_attestation_statement: bool,
+    /// Fail with `AuthenticatorError::BackupEligibleCredential` if the new credential may be
+    /// synced to other devices, for relying parties that require device-bound keys. Note that
+    /// the authenticator has created the credential at this point.
+    pub reject_backup_eligible: bool,
+    pub deadline: Option<Deadline>,
 }
 
 #[derive(Debug, Clone)]
@@ -39,6 +82,15 @@ pub struct SignArgs {
     pub extensions: AuthenticationExtensionsClientInputs,
     pub pin: Option<Pin>,
     pub use_ctap1_fallback: bool,
+    /// If the authenticator returns multiple discoverable credentials and the user picks one,
+    /// run the assertion again with only the chosen credential in the allow list. This
+    /// requires another user interaction, but produces an assertion that is bound to the
+    /// selected account instead of relying on the order of GetNextAssertion.
+    pub reassert_selected_credential: bool,
+    /// Fail with `AuthenticatorError::BackupEligibleCredential` if the assertion was made with
+    /// a credential that may be synced to other devices.
+    pub reject_backup_eligible: bool,
+    pub deadline: Option<Deadline>,
 }
 
 pub trait AuthenticatorTransport {
@@ -141,6 +193,8 @@ impl AuthenticatorService {
         if iterable_transports.is_empty() {
             return Err(AuthenticatorError::NoConfiguredTransports);
         }
+        args.relying_party.validate()?;
+        PublicKeyCredentialParameters::validate_list(&args.pub_cred_params)?;
 
         debug!(
             "register called with {} transports, iterable is {}",
@@ -179,6 +233,7 @@ impl AuthenticatorService {
         if iterable_transports.is_empty() {
             return Err(AuthenticatorError::NoConfiguredTransports);
         }
+        RelyingParty::from(args.relying_party_id.as_str()).validate()?;
 
         for (idx, transport_mutex) in iterable_transports.iter().enumerate() {
             let mut transports_to_cancel = iterable_transports.clone();
@@ -318,8 +373,8 @@ mod tests {
     use super::{AuthenticatorService, AuthenticatorTransport, Pin, RegisterArgs, SignArgs};
     use crate::consts::PARAMETER_SIZE;
     use crate::ctap2::server::{
-        PublicKeyCredentialUserEntity, RelyingParty, ResidentKeyRequirement,
-        UserVerificationRequirement,
+        PublicKeyCredentialParameters, PublicKeyCredentialUserEntity, RelyingParty,
+        ResidentKeyRequirement, UserVerificationRequirement,
     };
     use crate::errors::AuthenticatorError;
     use crate::statecallback::StateCallback;
@@ -445,13 +500,19 @@ mod tests {
                         name: Some("A. User".to_string()),
                         display_name: None,
                     },
-                    pub_cred_params: vec![],
+                    pub_cred_params: PublicKeyCredentialParameters::default_algorithms(),
                     exclude_list: vec![],
                     user_verification_req: UserVerificationRequirement::Preferred,
                     resident_key_req: ResidentKeyRequirement::Preferred,
+                    default_rk_cred_protect: None,
                     extensions: Default::default(),
                     pin: None,
                     use_ctap1_fallback: false,
+                    enterprise_attestation: None,
+                    enterprise_attestation_rp_ids: vec![],
+                    skip_attestation_statement: false,
+                    reject_backup_eligible: false,
+                    deadline: None,
                 },
                 status_tx.clone(),
                 StateCallback::new(Box::new(move |_rv| {})),
@@ -473,6 +534,9 @@ mod tests {
                     extensions: Default::default(),
                     pin: None,
                     use_ctap1_fallback: false,
+                    reassert_selected_credential: false,
+                    reject_backup_eligible: false,
+                    deadline: None,
                 },
                 status_tx,
                 StateCallback::new(Box::new(move |_rv| {})),
@@ -487,6 +551,75 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_invalid_relying_party() {
+        init();
+        let (status_tx, _) = channel::<StatusUpdate>();
+
+        let mut s = AuthenticatorService::new().unwrap();
+        s.add_transport(Box::new(TestTransportDriver::new(false).unwrap()));
+
+        assert_matches!(
+            s.register(
+                1_000,
+                RegisterArgs {
+                    client_data_hash: mk_challenge(),
+                    relying_party: RelyingParty {
+                        id: String::new(),
+                        name: None,
+                    },
+                    origin: "example.com".to_string(),
+                    user: PublicKeyCredentialUserEntity {
+                        id: "user_id".as_bytes().to_vec(),
+                        name: Some("A. User".to_string()),
+                        display_name: None,
+                    },
+                    pub_cred_params: PublicKeyCredentialParameters::default_algorithms(),
+                    exclude_list: vec![],
+                    user_verification_req: UserVerificationRequirement::Preferred,
+                    resident_key_req: ResidentKeyRequirement::Preferred,
+                    default_rk_cred_protect: None,
+                    extensions: Default::default(),
+                    pin: None,
+                    use_ctap1_fallback: false,
+                    enterprise_attestation: None,
+                    enterprise_attestation_rp_ids: vec![],
+                    skip_attestation_statement: false,
+                    reject_backup_eligible: false,
+                    deadline: None,
+                },
+                status_tx.clone(),
+                StateCallback::new(Box::new(move |_rv| {})),
+            )
+            .unwrap_err(),
+            crate::errors::AuthenticatorError::InvalidRelyingPartyInput
+        );
+
+        assert_matches!(
+            s.sign(
+                1_000,
+                SignArgs {
+                    client_data_hash: mk_challenge(),
+                    origin: "example.com".to_string(),
+                    relying_party_id: String::new(),
+                    allow_list: vec![],
+                    user_verification_req: UserVerificationRequirement::Preferred,
+                    user_presence_req: true,
+                    extensions: Default::default(),
+                    pin: None,
+                    use_ctap1_fallback: false,
+                    reassert_selected_credential: false,
+                    reject_backup_eligible: false,
+                    deadline: None,
+                },
+                status_tx,
+                StateCallback::new(Box::new(move |_rv| {})),
+            )
+            .unwrap_err(),
+            crate::errors::AuthenticatorError::InvalidRelyingPartyInput
+        );
+    }
+
     #[test]
     fn test_cancellation_register() {
         init();
@@ -521,13 +654,19 @@ mod tests {
                         name: Some("A. User".to_string()),
                         display_name: None,
                     },
-                    pub_cred_params: vec![],
+                    pub_cred_params: PublicKeyCredentialParameters::default_algorithms(),
                     exclude_list: vec![],
                     user_verification_req: UserVerificationRequirement::Preferred,
                     resident_key_req: ResidentKeyRequirement::Preferred,
+                    default_rk_cred_protect: None,
                     extensions: Default::default(),
                     pin: None,
                     use_ctap1_fallback: false,
+                    enterprise_attestation: None,
+                    enterprise_attestation_rp_ids: vec![],
+                    skip_attestation_statement: false,
+                    reject_backup_eligible: false,
+                    deadline: None,
                 },
                 status_tx,
                 callback.clone(),
@@ -572,6 +711,9 @@ mod tests {
                     extensions: Default::default(),
                     pin: None,
                     use_ctap1_fallback: false,
+                    reassert_selected_credential: false,
+                    reject_backup_eligible: false,
+                    deadline: None,
                 },
                 status_tx,
                 callback.clone(),
@@ -616,13 +758,19 @@ mod tests {
                         name: Some("A. User".to_string()),
                         display_name: None,
                     },
-                    pub_cred_params: vec![],
+                    pub_cred_params: PublicKeyCredentialParameters::default_algorithms(),
                     exclude_list: vec![],
                     user_verification_req: UserVerificationRequirement::Preferred,
                     resident_key_req: ResidentKeyRequirement::Preferred,
+                    default_rk_cred_protect: None,
                     extensions: Default::default(),
                     pin: None,
                     use_ctap1_fallback: false,
+                    enterprise_attestation: None,
+                    enterprise_attestation_rp_ids: vec![],
+                    skip_attestation_statement: false,
+                    reject_backup_eligible: false,
+                    deadline: None,
                 },
                 status_tx,
                 callback.clone(),
@@ -51,6 +51,10 @@ const CTAPHID_CANCEL: u8 = TYPE_INIT | 0x11; // Cancel outstanding requests
 const CTAPHID_KEEPALIVE: u8 = TYPE_INIT | 0x3b; // Keepalive sent to authenticator every 100ms and whenever a status changes
 const CTAPHID_ERROR: u8 = TYPE_INIT | 0x3f; // Error response
 
+// CTAPHID_KEEPALIVE status codes
+pub const STATUS_PROCESSING: u8 = 0x01; // The authenticator is still processing the request
+pub const STATUS_UPNEEDED: u8 = 0x02; // The authenticator is waiting for user presence
+
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 #[repr(u8)]
 pub enum HIDCmd {
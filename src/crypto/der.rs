@@ -1,12 +1,14 @@
 use super::CryptoError;
 
+pub const TAG_BOOLEAN: u8 = 0x01;
 pub const TAG_INTEGER: u8 = 0x02;
 pub const TAG_BIT_STRING: u8 = 0x03;
-#[cfg(all(test, feature = "crypto_nss"))]
 pub const TAG_OCTET_STRING: u8 = 0x04;
 pub const TAG_NULL: u8 = 0x05;
 pub const TAG_OBJECT_ID: u8 = 0x06;
+pub const TAG_ENUMERATED: u8 = 0x0a;
 pub const TAG_SEQUENCE: u8 = 0x30;
+pub const TAG_SET: u8 = 0x31;
 
 // Object identifiers in DER tag-length-value form
 pub const OID_EC_PUBLIC_KEY_BYTES: &[u8] = &[
@@ -22,6 +24,10 @@ pub const OID_RS256_BYTES: &[u8] = &[
     /* RFC 4055 (sha256WithRSAEncryption) */
     0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b,
 ];
+pub const OID_RSA_ENCRYPTION_BYTES: &[u8] = &[
+    /* RFC 8017 (rsaEncryption) */
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01,
+];
 
 pub type Result<T> = std::result::Result<T, CryptoError>;
 
@@ -97,7 +103,7 @@ pub fn sequence(items: &[&[u8]]) -> Result<Vec<u8>> {
     Ok(out)
 }
 
-#[cfg(all(test, feature = "crypto_nss"))]
+#[cfg(test)]
 pub fn octet_string(val: &[u8]) -> Result<Vec<u8>> {
     let mut out = Vec::with_capacity(MAX_TAG_AND_LENGTH_BYTES + val.len());
     write_tag_and_length(&mut out, TAG_OCTET_STRING, val.len())?;
@@ -105,7 +111,7 @@ pub fn octet_string(val: &[u8]) -> Result<Vec<u8>> {
     Ok(out)
 }
 
-#[cfg(all(test, feature = "crypto_nss"))]
+#[cfg(test)]
 pub fn context_specific_explicit_tag(tag: u8, content: &[u8]) -> Result<Vec<u8>> {
     let mut out = Vec::with_capacity(MAX_TAG_AND_LENGTH_BYTES + content.len());
     write_tag_and_length(&mut out, 0xa0 + tag, content.len())?;
@@ -115,7 +121,7 @@ pub fn context_specific_explicit_tag(tag: u8, content: &[u8]) -> Result<Vec<u8>>
 
 // Given "tag || len || value || rest" where tag and len are of length one, len is in [0, 127],
 // and value is of length len, returns (value, rest)
-#[cfg(all(test, feature = "crypto_nss"))]
+#[cfg(feature = "crypto_nss")]
 fn expect_tag_with_short_len(tag: u8, z: &[u8]) -> Result<(&[u8], &[u8])> {
     if z.is_empty() {
         return Err(CryptoError::MalformedInput);
@@ -138,7 +144,7 @@ fn expect_tag_with_short_len(tag: u8, z: &[u8]) -> Result<(&[u8], &[u8])> {
 // with r and s < 2^256, returns a 64 byte array containing
 // r and s encoded as 32 byte zero-padded big endian unsigned
 // integers
-#[cfg(all(test, feature = "crypto_nss"))]
+#[cfg(feature = "crypto_nss")]
 pub fn read_p256_sig(z: &[u8]) -> Result<Vec<u8>> {
     // Strip the tag and length.
     let (z, rest) = expect_tag_with_short_len(TAG_SEQUENCE, z)?;
@@ -183,3 +189,128 @@ pub fn read_p256_sig(z: &[u8]) -> Result<Vec<u8>> {
     }
     Ok(out)
 }
+
+/// A DER encoded element, as returned by `read_element()`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Element<'a> {
+    /// The first identifier octet: the class, the constructed bit and, for tag numbers
+    /// below 31, the tag number.
+    pub identifier: u8,
+    /// The tag number, also for tags in the high-tag-number form.
+    pub tag_number: u32,
+    pub contents: &'a [u8],
+}
+
+// Given "tag || len || value || rest", returns (element, rest). Lengths of up to 3 bytes
+// are supported, the indefinite length form is not (it is not allowed in DER).
+pub fn read_element(z: &[u8]) -> Result<(Element<'_>, &[u8])> {
+    let (&identifier, mut z) = z.split_first().ok_or(CryptoError::MalformedInput)?;
+    let mut tag_number = u32::from(identifier & 0x1f);
+    if tag_number == 0x1f {
+        // High-tag-number form: base 128, with the high bit set on all but the last byte
+        tag_number = 0;
+        loop {
+            let (&b, rest) = z.split_first().ok_or(CryptoError::MalformedInput)?;
+            z = rest;
+            if tag_number > (u32::MAX >> 7) {
+                return Err(CryptoError::MalformedInput);
+            }
+            tag_number = (tag_number << 7) | u32::from(b & 0x7f);
+            if b & 0x80 == 0 {
+                break;
+            }
+        }
+    }
+    let (&len, mut z) = z.split_first().ok_or(CryptoError::MalformedInput)?;
+    let len = if len < 0x80 {
+        len as usize
+    } else {
+        let len_bytes = (len & 0x7f) as usize;
+        if len_bytes == 0 || len_bytes > 3 || z.len() < len_bytes {
+            return Err(CryptoError::MalformedInput);
+        }
+        let (len, rest) = z.split_at(len_bytes);
+        z = rest;
+        len.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize)
+    };
+    if z.len() < len {
+        return Err(CryptoError::MalformedInput);
+    }
+    let (contents, rest) = z.split_at(len);
+    let element = Element {
+        identifier,
+        tag_number,
+        contents,
+    };
+    Ok((element, rest))
+}
+
+// Like read_element(), but fails if the identifier octet is not `tag`. Returns (value, rest).
+pub fn expect_element(tag: u8, z: &[u8]) -> Result<(&[u8], &[u8])> {
+    let (element, rest) = read_element(z)?;
+    if element.identifier != tag {
+        return Err(CryptoError::MalformedInput);
+    }
+    Ok((element.contents, rest))
+}
+
+// The value of a non-negative INTEGER or ENUMERATED, that fits into a u64
+pub fn read_u64(contents: &[u8]) -> Result<u64> {
+    match contents {
+        [] => Err(CryptoError::MalformedInput),
+        [first, ..] if first & 0x80 != 0 => Err(CryptoError::MalformedInput),
+        [0, rest @ ..] if rest.len() == 8 => {
+            Ok(rest.iter().fold(0, |acc, b| (acc << 8) | *b as u64))
+        }
+        _ if contents.len() > 8 => Err(CryptoError::MalformedInput),
+        _ => Ok(contents.iter().fold(0, |acc, b| (acc << 8) | *b as u64)),
+    }
+}
+
+// Returns the contents of the extnValue of the X.509 certificate extension with the given
+// object identifier (without tag and length), or None if the certificate has no such
+// extension.
+//   Certificate  ::=  SEQUENCE  {
+//        tbsCertificate       TBSCertificate,
+//        signatureAlgorithm   AlgorithmIdentifier,
+//        signatureValue       BIT STRING  }
+//   TBSCertificate  ::=  SEQUENCE  {
+//        version         [0]  EXPLICIT Version DEFAULT v1,
+//        ...
+//        extensions      [3]  EXPLICIT Extensions OPTIONAL }
+//   Extension  ::=  SEQUENCE  {
+//        extnID      OBJECT IDENTIFIER,
+//        critical    BOOLEAN DEFAULT FALSE,
+//        extnValue   OCTET STRING  }
+pub fn certificate_extension<'a>(cert: &'a [u8], oid: &[u8]) -> Result<Option<&'a [u8]>> {
+    let (cert, _) = expect_element(TAG_SEQUENCE, cert)?;
+    let (mut tbs, _) = expect_element(TAG_SEQUENCE, cert)?;
+    let mut extensions = None;
+    while !tbs.is_empty() {
+        let (element, rest) = read_element(tbs)?;
+        if element.identifier == 0xa3 {
+            extensions = Some(element.contents);
+        }
+        tbs = rest;
+    }
+    let extensions = match extensions {
+        Some(extensions) => extensions,
+        None => return Ok(None),
+    };
+    let (mut extensions, _) = expect_element(TAG_SEQUENCE, extensions)?;
+    while !extensions.is_empty() {
+        let (extension, rest) = expect_element(TAG_SEQUENCE, extensions)?;
+        extensions = rest;
+        let (extn_id, mut extension) = expect_element(TAG_OBJECT_ID, extension)?;
+        if extn_id != oid {
+            continue;
+        }
+        let (element, rest) = read_element(extension)?;
+        if element.identifier == TAG_BOOLEAN {
+            extension = rest;
+        }
+        let (extn_value, _) = expect_element(TAG_OCTET_STRING, extension)?;
+        return Ok(Some(extn_value));
+    }
+    Ok(None)
+}
@@ -42,16 +42,18 @@ pub fn gen_p256() -> Result<(Vec<u8>, Vec<u8>)> {
     unimplemented!()
 }
 
+pub fn ed25519_verify_raw(_public: &[u8], _signature: &[u8], _data: &[u8]) -> Result<()> {
+    unimplemented!()
+}
+
 pub fn ecdsa_p256_sha256_sign_raw(_private: &[u8], _data: &[u8]) -> Result<Vec<u8>> {
     unimplemented!()
 }
 
-#[allow(dead_code)]
-#[cfg(test)]
-pub fn test_ecdsa_p256_sha256_verify_raw(
-    _public: &[u8],
-    _signature: &[u8],
-    _data: &[u8],
-) -> Result<()> {
+pub fn ecdsa_p256_sha256_verify_raw(_public: &[u8], _signature: &[u8], _data: &[u8]) -> Result<()> {
+    unimplemented!()
+}
+
+pub fn rsa_pkcs1_sha256_verify_raw(_public: &[u8], _signature: &[u8], _data: &[u8]) -> Result<()> {
     unimplemented!()
 }
@@ -6,12 +6,15 @@ use crate::ctap2::commands::client_pin::PinUvAuthTokenPermission;
 use crate::ctap2::commands::get_info::AuthenticatorInfo;
 use crate::errors::AuthenticatorError;
 use crate::{ctap2::commands::CommandError, transport::errors::HIDError};
+use base64::Engine;
 use serde::{
     de::{Error as SerdeError, MapAccess, Unexpected, Visitor},
     ser::SerializeMap,
     Deserialize, Deserializer, Serialize, Serializer,
 };
 use serde_bytes::ByteBuf;
+use serde_json as json;
+use sha2::{Digest, Sha256};
 use std::convert::TryFrom;
 use std::fmt;
 
@@ -31,14 +34,18 @@ mod dummy;
 use dummy as backend;
 
 use backend::{
-    decrypt_aes_256_cbc_no_pad, ecdhe_p256_raw, encrypt_aes_256_cbc_no_pad, gen_p256, hmac_sha256,
-    random_bytes, sha256,
+    decrypt_aes_256_cbc_no_pad, ecdhe_p256_raw, ecdsa_p256_sha256_verify_raw, ed25519_verify_raw,
+    encrypt_aes_256_cbc_no_pad, gen_p256, hmac_sha256, random_bytes, rsa_pkcs1_sha256_verify_raw,
+    sha256,
 };
 
-mod der;
+pub(crate) mod der;
+pub(crate) mod x509;
 
 pub use backend::ecdsa_p256_sha256_sign_raw;
 
+const AES_BLOCK_LEN: usize = 16;
+
 pub struct PinUvAuthProtocol(Box<dyn PinProtocolImpl + Send + Sync>);
 impl PinUvAuthProtocol {
     pub fn id(&self) -> u64 {
@@ -120,16 +127,23 @@ trait PinProtocolImpl: ClonablePinProtocolImpl {
             key: COSEKeyType::EC2(client_cose_ec2_key),
         };
 
-        let shared_secret = SharedSecret {
-            pin_protocol: PinUvAuthProtocol(self.clone_box()),
-            key: self.kdf(&shared_point)?,
-            inputs: PublicInputs {
+        self.shared_secret(
+            &shared_point,
+            PublicInputs {
                 peer: peer_cose_key.clone(),
                 client: client_cose_key,
             },
-        };
+        )
+    }
 
-        Ok(shared_secret)
+    /// The part of `encapsulate` after the ECDH, split out so it can be checked against
+    /// known vectors with a fixed client key.
+    fn shared_secret(&self, z: &[u8], inputs: PublicInputs) -> Result<SharedSecret, CryptoError> {
+        Ok(SharedSecret {
+            pin_protocol: PinUvAuthProtocol(self.clone_box()),
+            key: self.kdf(z)?,
+            inputs,
+        })
     }
 }
 
@@ -141,13 +155,13 @@ impl TryFrom<&AuthenticatorInfo> for PinUvAuthProtocol {
         // "If there are multiple mutually supported protocols, and the platform
         // has no preference, it SHOULD select the one listed first in
         // pinUvAuthProtocols."
+        // We do have a preference: Protocol 2 authenticates with the full HMAC, and
+        // encrypts with random IVs. So we select the highest protocol we both support.
         if let Some(pin_protocols) = &info.pin_protocols {
-            for proto_id in pin_protocols.iter() {
-                match proto_id {
-                    1 => return Ok(PinUvAuthProtocol(Box::new(PinUvAuth1 {}))),
-                    2 => return Ok(PinUvAuthProtocol(Box::new(PinUvAuth2 {}))),
-                    _ => continue,
-                }
+            match pin_protocols.iter().filter(|id| matches!(id, 1 | 2)).max() {
+                Some(1) => return Ok(PinUvAuthProtocol(Box::new(PinUvAuth1 {}))),
+                Some(2) => return Ok(PinUvAuthProtocol(Box::new(PinUvAuth2 {}))),
+                _ => {}
             }
         } else {
             match info.max_supported_version() {
@@ -200,6 +214,9 @@ impl PinProtocolImpl for PinUvAuth1 {
         // decrypt(key, demCiphertext) → plaintext | error
         //      If the size of ciphertext is not a multiple of the AES block length, return error.
         //      Otherwise return the AES-256-CBC decryption of ciphertext using an all-zero IV.
+        if ciphertext.len() % AES_BLOCK_LEN != 0 {
+            return Err(CryptoError::MalformedInput);
+        }
         decrypt_aes_256_cbc_no_pad(key, None, ciphertext)
     }
 
@@ -261,11 +278,16 @@ impl PinProtocolImpl for PinUvAuth2 {
         //      2. If demCiphertext is less than 16 bytes in length, return an error
         //      3. Split demCiphertext after the 16th byte to produce two subspans, iv and ct.
         //      4. Return the AES-256-CBC decryption of ct using key and iv.
-        if key.len() < 64 || ciphertext.len() < 16 {
+        if key.len() < 64 {
             return Err(CryptoError::LibraryFailure);
         }
+        // The length check is not in the spec, but the AES layer would reject (or worse) a
+        // partial block anyway.
+        if ciphertext.len() < AES_BLOCK_LEN || ciphertext.len() % AES_BLOCK_LEN != 0 {
+            return Err(CryptoError::MalformedInput);
+        }
         let key = &key[32..64];
-        let (iv, ct) = ciphertext.split_at(16);
+        let (iv, ct) = ciphertext.split_at(AES_BLOCK_LEN);
         decrypt_aes_256_cbc_no_pad(key, Some(iv), ct)
     }
 
@@ -362,6 +384,21 @@ impl PinUvAuthToken {
     }
 }
 
+#[cfg(test)]
+impl PinUvAuthToken {
+    pub(crate) fn new_test_token(
+        pin_protocol: PinUvAuthProtocol,
+        pin_token: Vec<u8>,
+        permissions: PinUvAuthTokenPermission,
+    ) -> Self {
+        PinUvAuthToken {
+            pin_protocol,
+            pin_token,
+            permissions,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct PinUvAuthParam {
     pin_auth: Vec<u8>,
@@ -574,6 +611,20 @@ impl<'de> Deserialize<'de> for COSEAlgorithm {
                     SerdeError::invalid_value(Unexpected::Signed(v), &"valid COSEAlgorithm")
                 })
             }
+
+            // A few algorithm identifiers (e.g. the AES-GCM ones) are positive, and get
+            // encoded as unsigned integers.
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+            where
+                E: SerdeError,
+            {
+                i64::try_from(v)
+                    .ok()
+                    .and_then(|i| COSEAlgorithm::try_from(i).ok())
+                    .ok_or_else(|| {
+                        SerdeError::invalid_value(Unexpected::Unsigned(v), &"valid COSEAlgorithm")
+                    })
+            }
         }
 
         deserializer.deserialize_any(COSEAlgorithmVisitor)
@@ -784,12 +835,21 @@ pub struct COSERSAKey {
 
 impl COSERSAKey {
     pub fn der_spki(&self) -> Result<Vec<u8>, CryptoError> {
+        self.der_spki_with_algorithm(der::OID_RS256_BYTES)
+    }
+
+    // The crypto backends only accept RSA keys with the rsaEncryption algorithm identifier.
+    fn der_spki_for_verification(&self) -> Result<Vec<u8>, CryptoError> {
+        self.der_spki_with_algorithm(der::OID_RSA_ENCRYPTION_BYTES)
+    }
+
+    fn der_spki_with_algorithm(&self, algorithm: &[u8]) -> Result<Vec<u8>, CryptoError> {
         // SubjectPublicKeyInfo
         der::sequence(&[
             // algorithm: AlgorithmIdentifier
             &der::sequence(&[
                 // algorithm
-                &der::object_id(der::OID_RS256_BYTES)?,
+                &der::object_id(algorithm)?,
                 // parameters
                 &der::null()?,
             ])?,
@@ -884,6 +944,155 @@ impl COSEKey {
             COSEKeyType::RSA(rsa_key) => rsa_key.der_spki(),
         }
     }
+
+    /// SHA-256 over the CTAP2 canonical CBOR encoding of the key. Two encodings of the
+    /// same key (e.g. with differently ordered map entries) have the same thumbprint.
+    pub fn thumbprint(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        // Serializing into a Vec does not fail, and our Serialize impl emits the map
+        // entries in canonical order.
+        hasher.update(serde_cbor::to_vec(self).unwrap_or_default());
+
+        let mut output = [0u8; 32];
+        output.copy_from_slice(hasher.finalize().as_slice());
+        output
+    }
+
+    /// Verifies `signature` over `data` with this key. Supported are EdDSA with Ed25519 keys,
+    /// ES256 with P-256 keys (DER encoded signatures, as WebAuthn uses them), and RS256.
+    pub fn verify_signature(&self, signature: &[u8], data: &[u8]) -> Result<(), CryptoError> {
+        match (self.alg, &self.key) {
+            (COSEAlgorithm::EDDSA, COSEKeyType::OKP(key)) if key.curve == Curve::Ed25519 => {
+                ed25519_verify_raw(&key.der_spki()?, signature, data)
+            }
+            (COSEAlgorithm::ES256, COSEKeyType::EC2(key)) if key.curve == Curve::SECP256R1 => {
+                ecdsa_p256_sha256_verify_raw(&key.der_spki()?, signature, data)
+            }
+            (COSEAlgorithm::RS256, COSEKeyType::RSA(key)) => {
+                rsa_pkcs1_sha256_verify_raw(&key.der_spki_for_verification()?, signature, data)
+            }
+            _ => Err(CryptoError::UnsupportedAlgorithm(self.alg)),
+        }
+    }
+
+    /// The key as a JSON Web Key (RFC 7517), with the coordinates base64url encoded.
+    /// The "alg" member is only set if the algorithm has a JOSE name (RFC 7518 and RFC 8037).
+    pub fn to_jwk(&self) -> json::Value {
+        let b64 = |data: &[u8]| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(data);
+        let mut jwk = json::Map::new();
+        match &self.key {
+            COSEKeyType::EC2(key) => {
+                jwk.insert("kty".into(), "EC".into());
+                jwk.insert("crv".into(), jwk_curve_name(key.curve).into());
+                jwk.insert("x".into(), b64(&key.x).into());
+                jwk.insert("y".into(), b64(&key.y).into());
+            }
+            COSEKeyType::OKP(key) => {
+                jwk.insert("kty".into(), "OKP".into());
+                jwk.insert("crv".into(), jwk_curve_name(key.curve).into());
+                jwk.insert("x".into(), b64(&key.x).into());
+            }
+            COSEKeyType::RSA(key) => {
+                jwk.insert("kty".into(), "RSA".into());
+                jwk.insert("n".into(), b64(&key.n).into());
+                jwk.insert("e".into(), b64(&key.e).into());
+            }
+        }
+        if let Some(alg) = jwk_algorithm_name(self.alg) {
+            jwk.insert("alg".into(), alg.into());
+        }
+        json::Value::Object(jwk)
+    }
+
+    /// Parses a JSON Web Key as produced by `to_jwk`. The "alg" member is required, since
+    /// a COSEKey always carries its algorithm.
+    pub fn from_jwk(jwk: &json::Value) -> Result<Self, CryptoError> {
+        let member = |name: &str| {
+            jwk.get(name)
+                .and_then(json::Value::as_str)
+                .ok_or(CryptoError::MalformedInput)
+        };
+        let bytes = |name: &str| {
+            base64::engine::general_purpose::URL_SAFE_NO_PAD
+                .decode(member(name)?)
+                .map_err(|_| CryptoError::MalformedInput)
+        };
+        let curve = || {
+            let crv = member("crv")?;
+            [
+                Curve::SECP256R1,
+                Curve::SECP384R1,
+                Curve::SECP521R1,
+                Curve::X25519,
+                Curve::X448,
+                Curve::Ed25519,
+                Curve::Ed448,
+            ]
+            .iter()
+            .copied()
+            .find(|curve| jwk_curve_name(*curve) == crv)
+            .ok_or(CryptoError::UnknownKeyType)
+        };
+
+        let alg = member("alg")?;
+        let alg = JWK_ALGORITHMS
+            .iter()
+            .find(|(_, name)| *name == alg)
+            .map(|(alg, _)| *alg)
+            .ok_or(CryptoError::UnknownAlgorithm)?;
+        let key = match member("kty")? {
+            "EC" => COSEKeyType::EC2(COSEEC2Key {
+                curve: curve()?,
+                x: bytes("x")?,
+                y: bytes("y")?,
+            }),
+            "OKP" => COSEKeyType::OKP(COSEOKPKey {
+                curve: curve()?,
+                x: bytes("x")?,
+            }),
+            "RSA" => COSEKeyType::RSA(COSERSAKey {
+                n: bytes("n")?,
+                e: bytes("e")?,
+            }),
+            _ => return Err(CryptoError::UnknownKeyType),
+        };
+        Ok(COSEKey { alg, key })
+    }
+}
+
+/// COSE algorithms with a registered JOSE name.
+const JWK_ALGORITHMS: [(COSEAlgorithm, &str); 12] = [
+    (COSEAlgorithm::ES256, "ES256"),
+    (COSEAlgorithm::ES384, "ES384"),
+    (COSEAlgorithm::ES512, "ES512"),
+    (COSEAlgorithm::ES256K, "ES256K"),
+    (COSEAlgorithm::EDDSA, "EdDSA"),
+    (COSEAlgorithm::RS256, "RS256"),
+    (COSEAlgorithm::RS384, "RS384"),
+    (COSEAlgorithm::RS512, "RS512"),
+    (COSEAlgorithm::PS256, "PS256"),
+    (COSEAlgorithm::PS384, "PS384"),
+    (COSEAlgorithm::PS512, "PS512"),
+    (COSEAlgorithm::INSECURE_RS1, "RS1"),
+];
+
+fn jwk_algorithm_name(alg: COSEAlgorithm) -> Option<&'static str> {
+    JWK_ALGORITHMS
+        .iter()
+        .find(|(a, _)| *a == alg)
+        .map(|(_, name)| *name)
+}
+
+fn jwk_curve_name(curve: Curve) -> &'static str {
+    match curve {
+        Curve::SECP256R1 => "P-256",
+        Curve::SECP384R1 => "P-384",
+        Curve::SECP521R1 => "P-521",
+        Curve::X25519 => "X25519",
+        Curve::X448 => "X448",
+        Curve::Ed25519 => "Ed25519",
+        Curve::Ed448 => "Ed448",
+    }
 }
 
 impl<'de> Deserialize<'de> for COSEKey {
@@ -1065,7 +1274,7 @@ pub enum CryptoError {
     // UnexpectedTag,
     // UnexpectedType,
     // Unimplemented,
-    // VerificationFailed,
+    VerificationFailed,
     // SigningFailed,
     // InvalidArgument,
     UnknownKeyType,
@@ -1075,6 +1284,8 @@ pub enum CryptoError {
     UnsupportedAlgorithm(COSEAlgorithm),
     UnsupportedCurve(Curve),
     UnsupportedKeyType,
+    /// The signature algorithm (first) differs from the algorithm of the key (second).
+    AlgorithmMismatch(COSEAlgorithm, COSEAlgorithm),
     Backend(String),
 }
 
@@ -1151,11 +1362,11 @@ mod test {
     use std::convert::TryFrom;
 
     #[cfg(feature = "crypto_nss")]
-    use super::backend::{ecdsa_p256_sha256_sign_raw, test_ecdsa_p256_sha256_verify_raw};
+    use super::backend::{ecdsa_p256_sha256_sign_raw, ecdsa_p256_sha256_verify_raw};
     use super::{
         backend::hmac_sha256, backend::sha256, backend::test_ecdh_p256_raw, COSEAlgorithm, COSEKey,
-        Curve, PinProtocolImpl, PinUvAuth1, PinUvAuth2, PinUvAuthProtocol, PublicInputs,
-        SharedSecret,
+        CryptoError, Curve, PinProtocolImpl, PinUvAuth1, PinUvAuth2, PinUvAuthProtocol,
+        PinUvAuthTokenPermission, PublicInputs, SharedSecret,
     };
     use crate::crypto::{COSEEC2Key, COSEKeyType, COSEOKPKey, COSERSAKey};
     use crate::ctap2::attestation::AAGuid;
@@ -1163,6 +1374,7 @@ mod test {
     use crate::ctap2::commands::get_info::{
         tests::AAGUID_RAW, AuthenticatorOptions, AuthenticatorVersion,
     };
+    use crate::ctap2::commands::CommandError;
     use crate::util::decode_hex;
     use crate::AuthenticatorInfo;
     use serde_cbor::de::from_slice;
@@ -1206,6 +1418,30 @@ mod test {
         0xa5,
     ];
 
+    #[test]
+    fn test_cose_algorithm_try_from() {
+        assert_eq!(COSEAlgorithm::try_from(-7).unwrap(), COSEAlgorithm::ES256);
+        assert_eq!(COSEAlgorithm::try_from(1).unwrap(), COSEAlgorithm::A128GCM);
+        assert!(matches!(
+            COSEAlgorithm::try_from(-1234),
+            Err(CryptoError::UnknownAlgorithm)
+        ));
+
+        // Negative and positive identifiers are encoded as different CBOR major types
+        assert_eq!(
+            from_slice::<COSEAlgorithm>(&[0x26]).unwrap(), // -7
+            COSEAlgorithm::ES256
+        );
+        assert_eq!(
+            from_slice::<COSEAlgorithm>(&[0x01]).unwrap(), // 1
+            COSEAlgorithm::A128GCM
+        );
+        let err = from_slice::<COSEAlgorithm>(&[0x39, 0x04, 0xd1]).unwrap_err(); // -1234
+        assert!(err.to_string().contains("-1234"), "{}", err);
+        let err = from_slice::<COSEAlgorithm>(&[0x19, 0x04, 0xd2]).unwrap_err(); // 1234
+        assert!(err.to_string().contains("1234"), "{}", err);
+    }
+
     #[test]
     fn test_rsa_key_to_der_spki() {
         // $ ascii2der | xxd -i
@@ -1383,6 +1619,158 @@ mod test {
         assert_eq!(key_data, serialized);
     }
 
+    #[test]
+    fn test_cose_key_thumbprint() {
+        let key_data = decode_hex("A5010203262001215820A5FD5CE1B1C458C530A54FA61B31BF6B04BE8B97AFDE54DD8CBB69275A8A1BE1225820FA3A3231DD9DEED9D1897BE5A6228C59501E4BCD12975D3DFF730F01278EA61C");
+        let key: COSEKey = from_slice(&key_data).unwrap();
+        assert_eq!(
+            key.thumbprint().to_vec(),
+            decode_hex("47155221423C5F98BE1A551AC60C05AE573089C4C2C1B64247B2DE742B5D7E87")
+        );
+
+        // Same key with the map entries in a non-canonical order
+        let reordered = decode_hex("A501020326225820FA3A3231DD9DEED9D1897BE5A6228C59501E4BCD12975D3DFF730F01278EA61C215820A5FD5CE1B1C458C530A54FA61B31BF6B04BE8B97AFDE54DD8CBB69275A8A1BE12001");
+        let reordered: COSEKey = from_slice(&reordered).unwrap();
+        assert_eq!(key.thumbprint(), reordered.thumbprint());
+
+        let other = COSEKey {
+            alg: COSEAlgorithm::ES384,
+            ..key.clone()
+        };
+        assert_ne!(key.thumbprint(), other.thumbprint());
+    }
+
+    #[test]
+    fn test_es256_verify_signature() {
+        // Generated with pyca/cryptography
+        //   sk = ec.generate_private_key(ec.SECP256R1())
+        //   sig = sk.sign(b"signed data", ec.ECDSA(hashes.SHA256()))
+        let key = COSEKey {
+            alg: COSEAlgorithm::ES256,
+            key: COSEKeyType::EC2(COSEEC2Key {
+                curve: Curve::SECP256R1,
+                x: decode_hex("BCB8F206123CF3BD6765270D92F928684FF31FD2C31565FE603E7853FC878F34"),
+                y: decode_hex("9F7CEB7295B9D8B1418BBE778FCE5AC56E83EE198BAE80EA47B392A477C49C66"),
+            }),
+        };
+        let mut signature = decode_hex(concat!(
+            "304402200E2279B9B14ECAA5CAA7EF9E0BC25073B6E251EE5FEC9F88B0B661AD",
+            "A3DA4D5A022048EEBDAD1399796D5BC2617003B2A3323749A43760237CE6B8E6",
+            "744902A99C31",
+        ));
+        assert!(key.verify_signature(&signature, b"signed data").is_ok());
+        assert!(matches!(
+            key.verify_signature(&signature, b"other data"),
+            Err(CryptoError::VerificationFailed)
+        ));
+        signature[10] ^= 0x01;
+        assert!(key.verify_signature(&signature, b"signed data").is_err());
+    }
+
+    #[test]
+    fn test_rs256_verify_signature() {
+        // Generated with pyca/cryptography
+        //   sk = rsa.generate_private_key(65537, 2048)
+        //   sig = sk.sign(b"signed data", padding.PKCS1v15(), hashes.SHA256())
+        let mut key = COSEKey {
+            alg: COSEAlgorithm::RS256,
+            key: COSEKeyType::RSA(COSERSAKey {
+                n: decode_hex(concat!(
+                    "C2BC366A2F50E3B3F696684769C9B3810F04C2D23E8F36A0860981AC8C604903",
+                    "EF3246269D966625CF2F4612CCCE0D47D023B1C6180EA17EC3E1C357A91E8CB2",
+                    "DCEDFF3B896FD35F6F119B0626A9F71B5F1CE9444ACA4F7E68B489201EF1DA6C",
+                    "B5BBFEC3FD0B083F3038CF6170CE5DF80ACC1FB56227EDC092F805D1CF0D604C",
+                    "2561EC62F0C3F93955E1532ED81222EAA453322644543B44EFCB8BCBDB378A6C",
+                    "B4025CDBE2F60540840F947B9AF0AF7AB737DDEB3810081E47BC1BC125975EBB",
+                    "4CCB5734FA61A7F45A0521E5AB0A3843B11D4777D830EB5F84017DBD12547D2C",
+                    "14D035A1A0792CDEB09B033EA5861D945E7668C459454AB644502D5522DCFCF3",
+                )),
+                e: vec![0x01, 0x00, 0x01],
+            }),
+        };
+        let mut signature = decode_hex(concat!(
+            "B2718CA190850E3693EADC24B00DA15B3AACAB30B5566DCB5221CAA155AAC7DA",
+            "8988482AC7CCD250A7C9116A80D628C94A1A4F3B2D5727A0BB7E2A9D2C3AAAF8",
+            "1B35661C499CA053BED2F68A9736B1DDD83DB7CD9914A7EE93A3972DC4D665C2",
+            "C380BD98ED1C8E95FE9A237CF2A1F25079B88082D8B92EBBDB7B2B45A35DFF54",
+            "020A18365A35865D3E5E97B63073DECB1BCA23B2A8CFBA402B689EEF6BE0AB9A",
+            "F3C6D43C32DE84CFE0C8A5612197D62AD4BC6B249133029C6C151C264D88A7A9",
+            "D6A651AC83966795319F304E61EFF8E84681CC44AC80458F2C2817C165C57AD5",
+            "C459BD83B1B7D425E3F84EBE635A802BB925237ADAC9F8566D6F26942EB0E9CF",
+        ));
+        assert!(key.verify_signature(&signature, b"signed data").is_ok());
+        assert!(matches!(
+            key.verify_signature(&signature, b"other data"),
+            Err(CryptoError::VerificationFailed)
+        ));
+        signature[10] ^= 0x01;
+        assert!(matches!(
+            key.verify_signature(&signature, b"signed data"),
+            Err(CryptoError::VerificationFailed)
+        ));
+
+        // The algorithm has to fit the key type
+        key.alg = COSEAlgorithm::ES256;
+        assert!(matches!(
+            key.verify_signature(&signature, b"signed data"),
+            Err(CryptoError::UnsupportedAlgorithm(COSEAlgorithm::ES256))
+        ));
+    }
+
+    #[test]
+    fn test_cose_key_to_jwk() {
+        let key_data = decode_hex("A5010203262001215820A5FD5CE1B1C458C530A54FA61B31BF6B04BE8B97AFDE54DD8CBB69275A8A1BE1225820FA3A3231DD9DEED9D1897BE5A6228C59501E4BCD12975D3DFF730F01278EA61C");
+        let key: COSEKey = from_slice(&key_data).unwrap();
+        let jwk = key.to_jwk();
+        assert_eq!(
+            jwk,
+            serde_json::json!({
+                "kty": "EC",
+                "crv": "P-256",
+                "x": "pf1c4bHEWMUwpU-mGzG_awS-i5ev3lTdjLtpJ1qKG-E",
+                "y": "-joyMd2d7tnRiXvlpiKMWVAeS80Sl109_3MPASeOphw",
+                "alg": "ES256",
+            })
+        );
+        assert_eq!(COSEKey::from_jwk(&jwk).unwrap(), key);
+
+        let okp_key = COSEKey {
+            alg: COSEAlgorithm::EDDSA,
+            key: COSEKeyType::OKP(COSEOKPKey {
+                curve: Curve::Ed25519,
+                x: SAMPLE_ED25519_KEY.to_vec(),
+            }),
+        };
+        let jwk = okp_key.to_jwk();
+        assert_eq!(jwk["kty"], "OKP");
+        assert_eq!(jwk["crv"], "Ed25519");
+        assert_eq!(COSEKey::from_jwk(&jwk).unwrap(), okp_key);
+
+        let rsa_key = COSEKey {
+            alg: COSEAlgorithm::RS256,
+            key: COSEKeyType::RSA(COSERSAKey {
+                e: vec![1, 0, 1],
+                n: SAMPLE_RSA_MODULUS.to_vec(),
+            }),
+        };
+        let jwk = rsa_key.to_jwk();
+        assert_eq!(jwk["kty"], "RSA");
+        assert_eq!(jwk["e"], "AQAB");
+        assert_eq!(COSEKey::from_jwk(&jwk).unwrap(), rsa_key);
+
+        // There is no JOSE name for the algorithm used in key agreement
+        let ecdh_key = COSEKey {
+            alg: COSEAlgorithm::ECDH_ES_HKDF256,
+            key: key.key.clone(),
+        };
+        let jwk = ecdh_key.to_jwk();
+        assert!(jwk.get("alg").is_none());
+        assert!(matches!(
+            COSEKey::from_jwk(&jwk),
+            Err(CryptoError::MalformedInput)
+        ));
+    }
+
     #[test]
     #[allow(non_snake_case)]
     fn test_shared_secret() {
@@ -1446,6 +1834,145 @@ mod test {
         assert_eq!(pin_hash_enc, PIN_HASH_ENC);
     }
 
+    /// The python-fido2 client and device keys from `test_shared_secret`, and the ECDH output Z
+    /// for them.
+    #[allow(non_snake_case)]
+    fn pin_protocol_test_inputs() -> (Vec<u8>, PublicInputs) {
+        let EC_PRIV =
+            decode_hex("7452E599FEE739D8A653F6A507343D12D382249108A651402520B72F24FE7684");
+        let client = COSEEC2Key {
+            curve: Curve::SECP256R1,
+            x: decode_hex("44D78D7989B97E62EA993496C9EF6E8FD58B8B00715F9A89153DDD9C4657E47F"),
+            y: decode_hex("EC802EE7D22BD4E100F12E48537EB4E7E96ED3A47A0A3BD5F5EEAB65001664F9"),
+        };
+        let peer = COSEEC2Key {
+            curve: Curve::SECP256R1,
+            x: decode_hex("0501D5BC78DA9252560A26CB08FCC60CBE0B6D3B8E1D1FCEE514FAC0AF675168"),
+            y: decode_hex("D551B3ED46F665731F95B4532939C25D91DB7EB844BD96D4ABD4083785F8DF47"),
+        };
+        let z =
+            test_ecdh_p256_raw(&peer.der_spki().unwrap(), &client.x, &client.y, &EC_PRIV).unwrap();
+        assert_eq!(
+            z,
+            decode_hex("001bc4b24d2372ce13715cbf731e660586bb713408f0d1f5ad79cb1088f9e53c")
+        );
+        let inputs = PublicInputs {
+            client: COSEKey {
+                alg: COSEAlgorithm::ECDH_ES_HKDF256,
+                key: COSEKeyType::EC2(client),
+            },
+            peer: COSEKey {
+                alg: COSEAlgorithm::ECDH_ES_HKDF256,
+                key: COSEKeyType::EC2(peer),
+            },
+        };
+        (z, inputs)
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_pin_protocol_1_vectors() {
+        // Same values as in `test_shared_secret`, but going through the PinUvAuth1 key
+        // derivation, plus the pinUvAuthParam for CLIENT_DATA_HASH.
+        let SHARED = decode_hex("c42a039d548100dfba521e487debcbbb8b66bb7496f8b1862a7a395ed83e1a1c");
+        let TOKEN_ENC = decode_hex("7A9F98E31B77BE90F9C64D12E9635040");
+        let TOKEN = decode_hex("aff12c6dcfbf9df52f7a09211e8865cd");
+        let CLIENT_DATA_HASH = [0x11; 32];
+        let PIN_UV_AUTH_PARAM = decode_hex("27d834232995612042a99b4b2025a01a");
+
+        let (z, inputs) = pin_protocol_test_inputs();
+        let shared_secret = PinUvAuth1 {}.shared_secret(&z, inputs).unwrap();
+        assert_eq!(shared_secret.pin_protocol.id(), 1);
+        assert_eq!(shared_secret.key, SHARED);
+        // Protocol 1 uses an all-zero IV, so encryption is deterministic
+        assert_eq!(shared_secret.encrypt(&TOKEN).unwrap(), TOKEN_ENC);
+
+        let token = shared_secret
+            .decrypt_pin_token(PinUvAuthTokenPermission::MakeCredential, &TOKEN_ENC)
+            .unwrap();
+        assert_eq!(token.pin_token, TOKEN);
+        let param = token.derive(&CLIENT_DATA_HASH).unwrap();
+        assert_eq!(param.pin_auth, PIN_UV_AUTH_PARAM);
+        assert_eq!(param.pin_protocol.id(), 1);
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_pin_protocol_2_vectors() {
+        // Generated with `pin_protocol_test_inputs` and
+        //
+        // ```python
+        // # z, as in pin_protocol_test_inputs
+        // def hkdf(info):
+        //     return HKDF(algorithm=hashes.SHA256(), length=32, salt=b"\x00" * 32, info=info).derive(z)
+        // shared = hkdf(b"CTAP2 HMAC key") + hkdf(b"CTAP2 AES key")
+        //
+        // iv = bytes(range(16))
+        // token = bytes(range(32))
+        // enc = Cipher(algorithms.AES(shared[32:]), modes.CBC(iv)).encryptor()
+        // token_enc = iv + enc.update(token) + enc.finalize()
+        //
+        // h = hmac.HMAC(token, hashes.SHA256())
+        // h.update(b"\x11" * 32)
+        // pin_uv_auth_param = h.finalize()
+        // ```
+        let SHARED = decode_hex("65ef955dd8cfcacab489ad582d64b872299cec1970aeffb10c90b9d9f4b4f1a746cc03964825ccbaf159fde1958b2063871bd5b66ecf28972eaac583214a22d8");
+        let TOKEN_ENC = decode_hex("000102030405060708090a0b0c0d0e0f25d38170e84a14410d23a37ca10d175b112c2c1663fd172f26fff2f6f2910121");
+        let TOKEN = decode_hex("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+        let CLIENT_DATA_HASH = [0x11; 32];
+        let PIN_UV_AUTH_PARAM =
+            decode_hex("19c89035532577d8d991258817b7ff62e37dc37e42237d66034ad0ca5602fe0c");
+
+        let (z, inputs) = pin_protocol_test_inputs();
+        let shared_secret = PinUvAuth2 {}.shared_secret(&z, inputs).unwrap();
+        assert_eq!(shared_secret.pin_protocol.id(), 2);
+        assert_eq!(shared_secret.key, SHARED);
+
+        let token = shared_secret
+            .decrypt_pin_token(PinUvAuthTokenPermission::GetAssertion, &TOKEN_ENC)
+            .unwrap();
+        assert_eq!(token.pin_token, TOKEN);
+        let param = token.derive(&CLIENT_DATA_HASH).unwrap();
+        assert_eq!(param.pin_auth, PIN_UV_AUTH_PARAM);
+        assert_eq!(param.pin_protocol.id(), 2);
+
+        // Protocol 2 uses a random IV, so we can only check the round trip
+        let ciphertext = shared_secret.encrypt(&TOKEN).unwrap();
+        assert_eq!(ciphertext.len(), 16 + TOKEN.len());
+        assert_ne!(ciphertext, TOKEN_ENC);
+        assert_eq!(shared_secret.decrypt(&ciphertext).unwrap(), TOKEN);
+    }
+
+    #[test]
+    fn test_decrypt_malformed_ciphertext() {
+        let key = [0u8; 64];
+        for ciphertext_len in [0, 15, 17, 31, 33] {
+            let ciphertext = vec![0u8; ciphertext_len];
+            if ciphertext_len != 0 {
+                assert!(matches!(
+                    PinUvAuth1 {}.decrypt(&key[..32], &ciphertext),
+                    Err(CryptoError::MalformedInput)
+                ));
+            }
+            assert!(matches!(
+                PinUvAuth2 {}.decrypt(&key, &ciphertext),
+                Err(CryptoError::MalformedInput)
+            ));
+        }
+        assert_eq!(
+            PinUvAuth1 {}.decrypt(&key[..32], &[0u8; 32]).unwrap().len(),
+            32
+        );
+        assert_eq!(PinUvAuth2 {}.decrypt(&key, &[0u8; 48]).unwrap().len(), 32);
+
+        // Errors surface as CommandError::Crypto
+        let err = CommandError::from(PinUvAuth1 {}.decrypt(&key[..32], &[0u8; 20]).unwrap_err());
+        assert!(matches!(
+            err,
+            CommandError::Crypto(CryptoError::MalformedInput)
+        ));
+    }
+
     #[test]
     fn test_pin_uv_auth2_kdf() {
         // We don't pull a complete HKDF implementation from the crypto backend, so we need to
@@ -1556,10 +2083,20 @@ mod test {
             ..Default::default()
         };
 
-        // Valid pin_protocols
+        // Valid pin_protocols, the highest one wins
         info.pin_protocols = Some(vec![1, 2]);
         let pin = PinUvAuthProtocol::try_from(&info).unwrap();
-        assert_eq!(pin.id(), 1); // The one listed first
+        assert_eq!(pin.id(), 2);
+        info.pin_protocols = Some(vec![2, 1]);
+        let pin = PinUvAuthProtocol::try_from(&info).unwrap();
+        assert_eq!(pin.id(), 2);
+        info.pin_protocols = Some(vec![1]);
+        let pin = PinUvAuthProtocol::try_from(&info).unwrap();
+        assert_eq!(pin.id(), 1);
+        // Unknown protocols are skipped
+        info.pin_protocols = Some(vec![3, 1]);
+        let pin = PinUvAuthProtocol::try_from(&info).unwrap();
+        assert_eq!(pin.id(), 1);
 
         // Invalid pin_protocols
         info.pin_protocols = Some(vec![0, 10]);
@@ -1609,20 +2146,16 @@ mod test {
         assert_ne!(good_signature, good_signature2);
 
         // Good signature verifies
-        assert!(test_ecdsa_p256_sha256_verify_raw(&good_spki, &good_signature, &good_data).is_ok());
+        assert!(ecdsa_p256_sha256_verify_raw(&good_spki, &good_signature, &good_data).is_ok());
 
         // Wrong data does not verify
         let other_data = vec![0, 0, 0, 0, 5, 6, 7, 8];
-        assert!(
-            test_ecdsa_p256_sha256_verify_raw(&good_spki, &good_signature, &other_data).is_err()
-        );
+        assert!(ecdsa_p256_sha256_verify_raw(&good_spki, &good_signature, &other_data).is_err());
 
         // Wrong signature does not verify
         let other_signature =
             ecdsa_p256_sha256_sign_raw(&good_private, &other_data).expect("could not sign");
-        assert!(
-            test_ecdsa_p256_sha256_verify_raw(&good_spki, &other_signature, &good_data).is_err()
-        );
+        assert!(ecdsa_p256_sha256_verify_raw(&good_spki, &other_signature, &good_data).is_err());
 
         // Wrong key does not verify
         let (_, other_public) =
@@ -1631,8 +2164,6 @@ mod test {
             COSEKeyType::EC2(ref x) => x.der_spki().expect("could not serialize public key"),
             _ => unreachable!(),
         };
-        assert!(
-            test_ecdsa_p256_sha256_verify_raw(&other_spki, &good_signature, &good_data).is_err()
-        );
+        assert!(ecdsa_p256_sha256_verify_raw(&other_spki, &good_signature, &good_data).is_err());
     }
 }
@@ -1,9 +1,9 @@
-use super::CryptoError;
+use super::{COSEAlgorithm, CryptoError};
 use nss_gk_api::p11::{
     PK11Origin, PK11_CreateContextBySymKey, PK11_Decrypt, PK11_DigestFinal, PK11_DigestOp,
     PK11_Encrypt, PK11_ExportDERPrivateKeyInfo, PK11_GenerateKeyPairWithOpFlags,
     PK11_GenerateRandom, PK11_HashBuf, PK11_ImportDERPrivateKeyInfoAndReturnKey, PK11_ImportSymKey,
-    PK11_PubDeriveWithKDF, PK11_SignWithMechanism, PrivateKey, PublicKey,
+    PK11_PubDeriveWithKDF, PK11_SignWithMechanism, PK11_VerifyWithMechanism, PrivateKey, PublicKey,
     SECKEY_DecodeDERSubjectPublicKeyInfo, SECKEY_ExtractPublicKey, SECOidTag, Slot,
     SubjectPublicKeyInfo, AES_BLOCK_SIZE, PK11_ATTR_EXTRACTABLE, PK11_ATTR_INSENSITIVE,
     PK11_ATTR_SESSION, SHA256_LENGTH,
@@ -11,7 +11,7 @@ use nss_gk_api::p11::{
 use nss_gk_api::{IntoResult, SECItem, SECItemBorrowed, ScopedSECItem, PR_FALSE};
 use pkcs11_bindings::{
     CKA_DERIVE, CKA_ENCRYPT, CKA_SIGN, CKD_NULL, CKF_DERIVE, CKM_AES_CBC, CKM_ECDH1_DERIVE,
-    CKM_ECDSA_SHA256, CKM_EC_KEY_PAIR_GEN, CKM_SHA256_HMAC, CKM_SHA512_HMAC,
+    CKM_ECDSA_SHA256, CKM_EC_KEY_PAIR_GEN, CKM_SHA256_HMAC, CKM_SHA256_RSA_PKCS, CKM_SHA512_HMAC,
 };
 use std::convert::TryFrom;
 use std::os::raw::{c_int, c_uint};
@@ -19,9 +19,6 @@ use std::ptr;
 
 use super::der;
 
-#[cfg(test)]
-use nss_gk_api::p11::PK11_VerifyWithMechanism;
-
 impl From<nss_gk_api::Error> for CryptoError {
     fn from(e: nss_gk_api::Error) -> Self {
         CryptoError::Backend(format!("{e}"))
@@ -121,6 +118,11 @@ pub fn gen_p256() -> Result<(Vec<u8>, Vec<u8>)> {
     Ok((pkcs8_priv, sec1_pub))
 }
 
+pub fn ed25519_verify_raw(_public: &[u8], _signature: &[u8], _data: &[u8]) -> Result<()> {
+    // nss-gk-api does not expose EdDSA yet
+    Err(CryptoError::UnsupportedAlgorithm(COSEAlgorithm::EDDSA))
+}
+
 pub fn ecdsa_p256_sha256_sign_raw(private: &[u8], data: &[u8]) -> Result<Vec<u8>> {
     nss_gk_api::init();
 
@@ -456,12 +458,9 @@ pub fn test_ecdh_p256_raw(
     Ok(shared_point)
 }
 
-#[cfg(test)]
-pub fn test_ecdsa_p256_sha256_verify_raw(
-    public: &[u8],
-    signature: &[u8],
-    data: &[u8],
-) -> Result<()> {
+/// ECDSA signature verification. Takes a DER SubjectPublicKeyInfo, and a DER encoded
+/// Ecdsa-Sig-Value.
+pub fn ecdsa_p256_sha256_verify_raw(public: &[u8], signature: &[u8], data: &[u8]) -> Result<()> {
     nss_gk_api::init();
 
     let signature = der::read_p256_sig(signature)?;
@@ -479,3 +478,22 @@ pub fn test_ecdsa_p256_sha256_verify_raw(
     }
     Ok(())
 }
+
+/// RSASSA-PKCS1-v1_5 signature verification with SHA-256. Takes a DER SubjectPublicKeyInfo.
+pub fn rsa_pkcs1_sha256_verify_raw(public: &[u8], signature: &[u8], data: &[u8]) -> Result<()> {
+    nss_gk_api::init();
+
+    let public = nss_public_key_from_der_spki(public)?;
+    unsafe {
+        PK11_VerifyWithMechanism(
+            *public,
+            CKM_SHA256_RSA_PKCS,
+            ptr::null_mut(),
+            SECItemBorrowed::wrap(signature).as_mut(),
+            SECItemBorrowed::wrap(data).as_mut(),
+            ptr::null_mut(),
+        )
+        .into_result()?
+    }
+    Ok(())
+}
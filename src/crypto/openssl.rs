@@ -7,7 +7,7 @@ use openssl::hash::{hash, MessageDigest};
 use openssl::nid::Nid;
 use openssl::pkey::{PKey, Private, Public};
 use openssl::rand::rand_bytes;
-use openssl::sign::Signer;
+use openssl::sign::{Signer, Verifier};
 use openssl::symm::{Cipher, Crypter, Mode};
 use std::os::raw::c_int;
 
@@ -164,6 +164,18 @@ pub fn test_ecdh_p256_raw(
     Ok(shared_point)
 }
 
+/// Ed25519 signature verification. Takes a DER SubjectPublicKeyInfo. The data is not hashed
+/// beforehand, as Ed25519 does this internally.
+pub fn ed25519_verify_raw(public: &[u8], signature: &[u8], data: &[u8]) -> Result<()> {
+    let public = PKey::public_key_from_der(public)?;
+    let mut verifier = Verifier::new_without_digest(&public)?;
+    if verifier.verify_oneshot(signature, data)? {
+        Ok(())
+    } else {
+        Err(CryptoError::VerificationFailed)
+    }
+}
+
 pub fn gen_p256() -> Result<(Vec<u8>, Vec<u8>)> {
     unimplemented!()
 }
@@ -172,12 +184,23 @@ pub fn ecdsa_p256_sha256_sign_raw(_private: &[u8], _data: &[u8]) -> Result<Vec<u
     unimplemented!()
 }
 
-#[allow(dead_code)]
-#[cfg(test)]
-pub fn test_ecdsa_p256_sha256_verify_raw(
-    _public: &[u8],
-    _signature: &[u8],
-    _data: &[u8],
-) -> Result<()> {
-    unimplemented!()
+fn verify_raw(digest: MessageDigest, public: &[u8], signature: &[u8], data: &[u8]) -> Result<()> {
+    let public = PKey::public_key_from_der(public)?;
+    let mut verifier = Verifier::new(digest, &public)?;
+    if verifier.verify_oneshot(signature, data)? {
+        Ok(())
+    } else {
+        Err(CryptoError::VerificationFailed)
+    }
+}
+
+/// ECDSA signature verification. Takes a DER SubjectPublicKeyInfo, and a DER encoded
+/// Ecdsa-Sig-Value.
+pub fn ecdsa_p256_sha256_verify_raw(public: &[u8], signature: &[u8], data: &[u8]) -> Result<()> {
+    verify_raw(MessageDigest::sha256(), public, signature, data)
+}
+
+/// RSASSA-PKCS1-v1_5 signature verification with SHA-256. Takes a DER SubjectPublicKeyInfo.
+pub fn rsa_pkcs1_sha256_verify_raw(public: &[u8], signature: &[u8], data: &[u8]) -> Result<()> {
+    verify_raw(MessageDigest::sha256(), public, signature, data)
 }
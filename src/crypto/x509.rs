@@ -0,0 +1,341 @@
+//! Just enough X.509 (RFC 5280) to verify attestation certificate chains.
+
+use super::backend::{ecdsa_p256_sha256_verify_raw, rsa_pkcs1_sha256_verify_raw};
+use super::der;
+use super::{COSEAlgorithm, CryptoError};
+use std::ops::Range;
+
+pub type Result<T> = std::result::Result<T, CryptoError>;
+
+// Object identifiers in DER tag-length-value form
+const OID_ECDSA_WITH_SHA256: &[u8] = &[
+    /* RFC 5758 (ecdsa-with-SHA256) */
+    0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02,
+];
+const OID_BASIC_CONSTRAINTS: &[u8] = &[
+    /* RFC 5280 (id-ce-basicConstraints) */ 0x55, 0x1d, 0x13,
+];
+
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+
+/// A parsed DER certificate. Names and keys are kept DER encoded.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Certificate<'a> {
+    /// The encoded TBSCertificate, which the signature is over
+    pub tbs_certificate: &'a [u8],
+    /// The object identifier of the signature algorithm, without tag and length
+    pub signature_algorithm: &'a [u8],
+    pub signature: &'a [u8],
+    pub issuer: &'a [u8],
+    pub subject: &'a [u8],
+    /// Start of the validity period, in seconds since the Unix epoch
+    pub not_before: i64,
+    /// End of the validity period (inclusive), in seconds since the Unix epoch
+    pub not_after: i64,
+    pub subject_public_key_info: &'a [u8],
+    /// The cA flag of the basic constraints extension, false if there is none
+    pub is_ca: bool,
+}
+
+// Returns (encoded element, rest), i.e. the element including tag and length
+fn split_element(tag: u8, z: &[u8]) -> Result<(&[u8], &[u8])> {
+    let (_, rest) = der::expect_element(tag, z)?;
+    Ok((&z[..z.len() - rest.len()], rest))
+}
+
+impl<'a> Certificate<'a> {
+    //   Certificate  ::=  SEQUENCE  {
+    //        tbsCertificate       TBSCertificate,
+    //        signatureAlgorithm   AlgorithmIdentifier,
+    //        signatureValue       BIT STRING  }
+    //   TBSCertificate  ::=  SEQUENCE  {
+    //        version         [0]  EXPLICIT Version DEFAULT v1,
+    //        serialNumber         CertificateSerialNumber,
+    //        signature            AlgorithmIdentifier,
+    //        issuer               Name,
+    //        validity             Validity,
+    //        subject              Name,
+    //        subjectPublicKeyInfo SubjectPublicKeyInfo,
+    //        ... }
+    pub fn from_der(cert: &'a [u8]) -> Result<Self> {
+        let (z, _) = der::expect_element(der::TAG_SEQUENCE, cert)?;
+        let (tbs_certificate, z) = split_element(der::TAG_SEQUENCE, z)?;
+        let (algorithm, z) = der::expect_element(der::TAG_SEQUENCE, z)?;
+        let (signature_algorithm, _) = der::expect_element(der::TAG_OBJECT_ID, algorithm)?;
+        let (signature, _) = der::expect_element(der::TAG_BIT_STRING, z)?;
+        // The first byte is the number of unused bits, which has to be 0 for signatures
+        let signature = match signature {
+            [0, signature @ ..] => signature,
+            _ => return Err(CryptoError::MalformedInput),
+        };
+
+        let (tbs, _) = der::expect_element(der::TAG_SEQUENCE, tbs_certificate)?;
+        let (element, rest) = der::read_element(tbs)?;
+        let tbs = if element.identifier == 0xa0 {
+            rest
+        } else {
+            tbs
+        };
+        let (_serial_number, tbs) = der::expect_element(der::TAG_INTEGER, tbs)?;
+        let (_signature, tbs) = der::expect_element(der::TAG_SEQUENCE, tbs)?;
+        let (issuer, tbs) = split_element(der::TAG_SEQUENCE, tbs)?;
+        let (validity, tbs) = der::expect_element(der::TAG_SEQUENCE, tbs)?;
+        let (subject, tbs) = split_element(der::TAG_SEQUENCE, tbs)?;
+        let (subject_public_key_info, _) = split_element(der::TAG_SEQUENCE, tbs)?;
+
+        let (not_before, validity) = read_time(validity)?;
+        let (not_after, _) = read_time(validity)?;
+        let is_ca = match der::certificate_extension(cert, OID_BASIC_CONSTRAINTS)? {
+            Some(basic_constraints) => read_basic_constraints(basic_constraints)?,
+            None => false,
+        };
+
+        Ok(Certificate {
+            tbs_certificate,
+            signature_algorithm,
+            signature,
+            issuer,
+            subject,
+            not_before,
+            not_after,
+            subject_public_key_info,
+            is_ca,
+        })
+    }
+
+    /// `time` is in seconds since the Unix epoch.
+    pub fn is_valid_at(&self, time: i64) -> bool {
+        self.not_before <= time && time <= self.not_after
+    }
+
+    /// Checks that this certificate is signed with the key of `issuer`. Only ecdsa-with-SHA256
+    /// and sha256WithRSAEncryption are supported. Names are not compared.
+    pub fn verify_signed_by(&self, issuer: &Certificate) -> Result<()> {
+        let verify = match self.signature_algorithm {
+            OID_ECDSA_WITH_SHA256 => ecdsa_p256_sha256_verify_raw,
+            der::OID_RS256_BYTES => rsa_pkcs1_sha256_verify_raw,
+            _ => return Err(CryptoError::UnknownSignatureScheme),
+        };
+        verify(
+            issuer.subject_public_key_info,
+            self.signature,
+            self.tbs_certificate,
+        )
+    }
+
+    /// The COSE algorithm to use with the subject public key: ES256 for P-256 keys, RS256
+    /// for RSA keys. Other keys are `CryptoError::UnsupportedKeyType`.
+    //   SubjectPublicKeyInfo  ::=  SEQUENCE  {
+    //        algorithm            AlgorithmIdentifier,
+    //        subjectPublicKey     BIT STRING  }
+    pub fn public_key_algorithm(&self) -> Result<COSEAlgorithm> {
+        let (spki, _) = der::expect_element(der::TAG_SEQUENCE, self.subject_public_key_info)?;
+        let (algorithm, _) = der::expect_element(der::TAG_SEQUENCE, spki)?;
+        let (key_type, parameters) = der::expect_element(der::TAG_OBJECT_ID, algorithm)?;
+        match key_type {
+            der::OID_EC_PUBLIC_KEY_BYTES => {
+                let (curve, _) = der::expect_element(der::TAG_OBJECT_ID, parameters)?;
+                if curve != der::OID_SECP256R1_BYTES {
+                    return Err(CryptoError::UnsupportedKeyType);
+                }
+                Ok(COSEAlgorithm::ES256)
+            }
+            der::OID_RSA_ENCRYPTION_BYTES => Ok(COSEAlgorithm::RS256),
+            _ => Err(CryptoError::UnsupportedKeyType),
+        }
+    }
+
+    /// Verifies a signature over `data`, made with the subject key and `alg`. The algorithm
+    /// has to be the one of `public_key_algorithm()`.
+    pub fn verify_signature(
+        &self,
+        alg: COSEAlgorithm,
+        signature: &[u8],
+        data: &[u8],
+    ) -> Result<()> {
+        let key_alg = self.public_key_algorithm()?;
+        if alg != key_alg {
+            return Err(CryptoError::AlgorithmMismatch(alg, key_alg));
+        }
+        let verify = match key_alg {
+            COSEAlgorithm::ES256 => ecdsa_p256_sha256_verify_raw,
+            COSEAlgorithm::RS256 => rsa_pkcs1_sha256_verify_raw,
+            other => return Err(CryptoError::UnsupportedAlgorithm(other)),
+        };
+        verify(self.subject_public_key_info, signature, data)
+    }
+}
+
+/// A parsed certificate that owns its encoding, e.g. to keep it in a cache. `certificate()`
+/// returns the parsed `Certificate` without parsing it again.
+#[derive(Debug)]
+pub struct OwnedCertificate {
+    der: Vec<u8>,
+    tbs_certificate: Range<usize>,
+    signature_algorithm: Range<usize>,
+    signature: Range<usize>,
+    issuer: Range<usize>,
+    subject: Range<usize>,
+    subject_public_key_info: Range<usize>,
+    not_before: i64,
+    not_after: i64,
+    is_ca: bool,
+}
+
+impl OwnedCertificate {
+    pub fn from_der(der: Vec<u8>) -> Result<Self> {
+        let cert = Certificate::from_der(&der)?;
+        // All fields of `cert` are slices of `der`
+        let range = |field: &[u8]| {
+            let start = field.as_ptr() as usize - der.as_ptr() as usize;
+            start..start + field.len()
+        };
+        let tbs_certificate = range(cert.tbs_certificate);
+        let signature_algorithm = range(cert.signature_algorithm);
+        let signature = range(cert.signature);
+        let issuer = range(cert.issuer);
+        let subject = range(cert.subject);
+        let subject_public_key_info = range(cert.subject_public_key_info);
+        let (not_before, not_after, is_ca) = (cert.not_before, cert.not_after, cert.is_ca);
+        Ok(OwnedCertificate {
+            der,
+            tbs_certificate,
+            signature_algorithm,
+            signature,
+            issuer,
+            subject,
+            subject_public_key_info,
+            not_before,
+            not_after,
+            is_ca,
+        })
+    }
+
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+
+    pub fn certificate(&self) -> Certificate<'_> {
+        Certificate {
+            tbs_certificate: &self.der[self.tbs_certificate.clone()],
+            signature_algorithm: &self.der[self.signature_algorithm.clone()],
+            signature: &self.der[self.signature.clone()],
+            issuer: &self.der[self.issuer.clone()],
+            subject: &self.der[self.subject.clone()],
+            not_before: self.not_before,
+            not_after: self.not_after,
+            subject_public_key_info: &self.der[self.subject_public_key_info.clone()],
+            is_ca: self.is_ca,
+        }
+    }
+}
+
+//   BasicConstraints ::= SEQUENCE {
+//        cA                      BOOLEAN DEFAULT FALSE,
+//        pathLenConstraint       INTEGER (0..MAX) OPTIONAL }
+// Returns cA. The path length is not checked, attestation chains are short.
+fn read_basic_constraints(z: &[u8]) -> Result<bool> {
+    let (z, _) = der::expect_element(der::TAG_SEQUENCE, z)?;
+    if z.is_empty() {
+        return Ok(false);
+    }
+    let (element, _) = der::read_element(z)?;
+    match (element.identifier, element.contents) {
+        (der::TAG_BOOLEAN, [0xff]) => Ok(true),
+        // DER doesn't encode default values, but some encoders do anyway
+        (der::TAG_BOOLEAN, [0x00]) => Ok(false),
+        (der::TAG_BOOLEAN, _) => Err(CryptoError::MalformedInput),
+        _ => Ok(false),
+    }
+}
+
+// Reads a UTCTime (YYMMDDHHMMSSZ) or GeneralizedTime (YYYYMMDDHHMMSSZ), as restricted by
+// RFC 5280, 4.1.2.5. Returns (seconds since the Unix epoch, rest).
+fn read_time(z: &[u8]) -> Result<(i64, &[u8])> {
+    let (element, rest) = der::read_element(z)?;
+    let (year, time) = match (element.identifier, element.contents) {
+        (TAG_UTC_TIME, [y1, y2, time @ ..]) => {
+            let year = digits(&[*y1, *y2])?;
+            // Years 50 to 99 are 1950 to 1999, 00 to 49 are 2000 to 2049
+            (if year >= 50 { 1900 + year } else { 2000 + year }, time)
+        }
+        (TAG_GENERALIZED_TIME, [y1, y2, y3, y4, time @ ..]) => {
+            (digits(&[*y1, *y2, *y3, *y4])?, time)
+        }
+        _ => return Err(CryptoError::MalformedInput),
+    };
+    let (month, day, hour, minute, second) = match time {
+        [m1, m2, d1, d2, h1, h2, min1, min2, s1, s2, b'Z'] => (
+            digits(&[*m1, *m2])?,
+            digits(&[*d1, *d2])?,
+            digits(&[*h1, *h2])?,
+            digits(&[*min1, *min2])?,
+            digits(&[*s1, *s2])?,
+        ),
+        _ => return Err(CryptoError::MalformedInput),
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return Err(CryptoError::MalformedInput);
+    }
+    let days = days_from_civil(year, month, day);
+    Ok((days * 86400 + hour * 3600 + minute * 60 + second, rest))
+}
+
+fn digits(digits: &[u8]) -> Result<i64> {
+    digits.iter().try_fold(0, |acc, d| {
+        if d.is_ascii_digit() {
+            Ok(acc * 10 + i64::from(d - b'0'))
+        } else {
+            Err(CryptoError::MalformedInput)
+        }
+    })
+}
+
+// Days since 1970-01-01 of a date in the proleptic Gregorian calendar, see
+// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod test {
+    use super::{days_from_civil, read_basic_constraints, read_time};
+
+    #[test]
+    fn test_read_time() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+
+        let utc_time = b"\x17\x0d261016155021Z";
+        assert_eq!(read_time(utc_time).unwrap(), (1792165821, &[][..]));
+        let generalized_time = b"\x18\x0f21260922155021Z";
+        assert_eq!(read_time(generalized_time).unwrap().0, 4945765821);
+        // 1950 - 2049
+        assert_eq!(read_time(b"\x17\x0d500101000000Z").unwrap().0, -631152000);
+        // Local time, fractional seconds, and invalid dates are not allowed
+        assert!(read_time(b"\x17\x0b2610161550Z").is_err());
+        assert!(read_time(b"\x17\x0f261016155021+0100").is_err());
+        assert!(read_time(b"\x18\x1121260922155021.5Z").is_err());
+        assert!(read_time(b"\x17\x0d261316155021Z").is_err());
+    }
+
+    #[test]
+    fn test_read_basic_constraints() {
+        assert!(!read_basic_constraints(&[0x30, 0x00]).unwrap());
+        assert!(read_basic_constraints(&[0x30, 0x03, 0x01, 0x01, 0xff]).unwrap());
+        assert!(!read_basic_constraints(&[0x30, 0x03, 0x01, 0x01, 0x00]).unwrap());
+        // With pathLenConstraint
+        let with_path_len = [0x30, 0x06, 0x01, 0x01, 0xff, 0x02, 0x01, 0x00];
+        assert!(read_basic_constraints(&with_path_len).unwrap());
+        assert!(!read_basic_constraints(&[0x30, 0x03, 0x02, 0x01, 0x00]).unwrap());
+        assert!(read_basic_constraints(&[0x30, 0x03, 0x01, 0x01, 0x01]).is_err());
+        assert!(read_basic_constraints(&[0x04, 0x00]).is_err());
+    }
+}
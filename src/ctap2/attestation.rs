@@ -1,5 +1,9 @@
-use super::utils::{from_slice_stream, read_be_u16, read_be_u32, read_byte};
-use crate::crypto::COSEAlgorithm;
+use super::utils::{from_slice_stream, read_be_u16, read_be_u32, read_be_u64, read_byte};
+use crate::crypto::{der, x509, COSEAlgorithm, CryptoError};
+use crate::ctap2::client_data::ClientDataHash;
+use crate::ctap2::commands::CommandError;
+#[cfg(feature = "aaguid_metadata")]
+use crate::ctap2::server::Transport;
 use crate::ctap2::server::{CredentialProtectionPolicy, RpIdHash};
 use crate::ctap2::utils::serde_parse_err;
 use crate::{crypto::COSEKey, errors::AuthenticatorError};
@@ -10,8 +14,13 @@ use serde::{
     Deserialize, Deserializer, Serialize,
 };
 use serde_cbor;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::collections::HashMap;
 use std::fmt;
 use std::io::{Cursor, Read};
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum HmacSecretResponse {
@@ -68,17 +77,25 @@ impl<'de> Deserialize<'de> for HmacSecretResponse {
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
 pub struct Extension {
+    #[serde(rename = "credBlob", skip_serializing_if = "Option::is_none")]
+    pub cred_blob: Option<bool>,
     #[serde(rename = "credProtect", skip_serializing_if = "Option::is_none")]
     pub cred_protect: Option<CredentialProtectionPolicy>,
     #[serde(rename = "hmac-secret", skip_serializing_if = "Option::is_none")]
     pub hmac_secret: Option<HmacSecretResponse>,
+    #[serde(rename = "hmac-secret-mc", skip_serializing_if = "Option::is_none")]
+    pub hmac_secret_mc: Option<HmacSecretResponse>,
     #[serde(rename = "minPinLength", skip_serializing_if = "Option::is_none")]
     pub min_pin_length: Option<u64>,
 }
 
 impl Extension {
     pub fn has_some(&self) -> bool {
-        self.min_pin_length.is_some() || self.hmac_secret.is_some() || self.cred_protect.is_some()
+        self.min_pin_length.is_some()
+            || self.cred_blob.is_some()
+            || self.hmac_secret.is_some()
+            || self.hmac_secret_mc.is_some()
+            || self.cred_protect.is_some()
     }
 }
 
@@ -97,6 +114,44 @@ impl AAGuid {
             Ok(AAGuid(payload))
         }
     }
+
+    /// Looks up the authenticator model in the bundled excerpt of the FIDO Metadata Service.
+    #[cfg(feature = "aaguid_metadata")]
+    pub fn metadata(&self) -> Option<AaguidMetadata> {
+        let index = aaguid_metadata::AAGUID_METADATA
+            .binary_search_by(|(aaguid, ..)| aaguid.cmp(&self.0))
+            .ok()?;
+        let (_, name, icon, transports) = aaguid_metadata::AAGUID_METADATA[index];
+        Some(AaguidMetadata {
+            name,
+            icon,
+            transports: transports.iter().map(|t| Transport::from(*t)).collect(),
+        })
+    }
+}
+
+#[cfg(feature = "aaguid_metadata")]
+mod aaguid_metadata {
+    /// (AAGUID, name, icon, transports), sorted by AAGUID
+    pub type Entry = (
+        [u8; 16],
+        &'static str,
+        Option<&'static str>,
+        &'static [&'static str],
+    );
+    // Generated by build.rs
+    include!(concat!(env!("OUT_DIR"), "/aaguid_metadata.rs"));
+}
+
+/// What the FIDO Metadata Service knows about an authenticator model.
+#[cfg(feature = "aaguid_metadata")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AaguidMetadata {
+    /// Human-readable name of the model, e.g. "YubiKey 5 Series"
+    pub name: &'static str,
+    /// The icon as data: URL, if any
+    pub icon: Option<&'static str>,
+    pub transports: Vec<Transport>,
 }
 
 impl fmt::Debug for AAGuid {
@@ -190,8 +245,8 @@ bitflags! {
         const USER_PRESENT = 0x01;
         const RESERVED_1 = 0x02;
         const USER_VERIFIED = 0x04;
-        const RESERVED_3 = 0x08;
-        const RESERVED_4 = 0x10;
+        const BACKUP_ELIGIBLE = 0x08;
+        const BACKUP_STATE = 0x10;
         const RESERVED_5 = 0x20;
         const ATTESTED = 0x40;
         const EXTENSION_DATA = 0x80;
@@ -207,13 +262,114 @@ pub struct AuthenticatorData {
     pub extensions: Extension,
 }
 
+/// Byte ranges of the fields of a serialized `AuthenticatorData`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthenticatorDataLayout {
+    pub rp_id_hash: Range<usize>,
+    pub flags: Range<usize>,
+    pub counter: Range<usize>,
+    /// Only present if the ATTESTED flag is set
+    pub credential_data: Option<Range<usize>>,
+    /// Only present if the EXTENSION_DATA flag is set
+    pub extensions: Option<Range<usize>>,
+}
+
 impl AuthenticatorData {
+    /// Whether the credential may be backed up, i.e. synced to other devices (BE flag).
+    pub fn is_backup_eligible(&self) -> bool {
+        self.flags.contains(AuthenticatorDataFlags::BACKUP_ELIGIBLE)
+    }
+
+    /// Whether the credential is currently backed up (BS flag).
+    pub fn is_backed_up(&self) -> bool {
+        self.flags.contains(AuthenticatorDataFlags::BACKUP_STATE)
+    }
+
     pub fn to_vec(&self) -> Vec<u8> {
         match serde_cbor::value::to_value(self) {
             Ok(serde_cbor::value::Value::Bytes(out)) => out,
             _ => unreachable!(), // Serialize is guaranteed to produce bytes
         }
     }
+
+    /// Parses a serialized authenticator data blob, e.g. as sent to a relying party.
+    /// Unlike the deserialization of full responses, trailing bytes are rejected.
+    pub fn from_bytes(raw: &[u8]) -> Result<Self, CommandError> {
+        let (auth_data, _) = parse_auth_data_strict(raw)?;
+        Ok(auth_data)
+    }
+
+    /// Returns where the individual fields are located in a serialized authenticator data
+    /// blob, as accepted by `from_bytes()`.
+    pub fn layout(raw: &[u8]) -> Result<AuthenticatorDataLayout, CommandError> {
+        let (_, layout) = parse_auth_data_strict(raw)?;
+        Ok(layout)
+    }
+}
+
+fn parse_auth_data_strict(
+    raw: &[u8],
+) -> Result<(AuthenticatorData, AuthenticatorDataLayout), CommandError> {
+    let (auth_data, layout) = parse_auth_data(raw).map_err(CommandError::Deserializing)?;
+    let end = layout
+        .extensions
+        .as_ref()
+        .or(layout.credential_data.as_ref())
+        .unwrap_or(&layout.counter)
+        .end;
+    if end != raw.len() {
+        return Err(CommandError::Deserializing(SerdeError::custom(
+            "trailing data after authenticator data",
+        )));
+    }
+    Ok((auth_data, layout))
+}
+
+fn parse_auth_data<E: SerdeError>(
+    input: &[u8],
+) -> Result<(AuthenticatorData, AuthenticatorDataLayout), E> {
+    let mut cursor = Cursor::new(input);
+    let mut rp_id_hash_raw = [0u8; 32];
+    cursor
+        .read_exact(&mut rp_id_hash_raw)
+        .map_err(|_| serde_parse_err("32 bytes"))?;
+    let rp_id_hash = RpIdHash(rp_id_hash_raw);
+
+    // preserve the flags, even if some reserved values are set.
+    let flags = AuthenticatorDataFlags::from_bits_truncate(read_byte(&mut cursor)?);
+    let counter = read_be_u32(&mut cursor)?;
+    let mut layout = AuthenticatorDataLayout {
+        rp_id_hash: 0..32,
+        flags: 32..33,
+        counter: 33..37,
+        credential_data: None,
+        extensions: None,
+    };
+
+    let mut credential_data = None;
+    if flags.contains(AuthenticatorDataFlags::ATTESTED) {
+        let start = cursor.position() as usize;
+        credential_data = Some(parse_attested_cred_data(&mut cursor)?);
+        layout.credential_data = Some(start..cursor.position() as usize);
+    }
+
+    let extensions = if flags.contains(AuthenticatorDataFlags::EXTENSION_DATA) {
+        let start = cursor.position() as usize;
+        let extensions = from_slice_stream(&mut cursor)?;
+        layout.extensions = Some(start..cursor.position() as usize);
+        extensions
+    } else {
+        Default::default()
+    };
+
+    let auth_data = AuthenticatorData {
+        rp_id_hash,
+        flags,
+        counter,
+        credential_data,
+        extensions,
+    };
+    Ok((auth_data, layout))
 }
 
 impl<'de> Deserialize<'de> for AuthenticatorData {
@@ -234,36 +390,10 @@ impl<'de> Deserialize<'de> for AuthenticatorData {
             where
                 E: SerdeError,
             {
-                let mut cursor = Cursor::new(input);
-                let mut rp_id_hash_raw = [0u8; 32];
-                cursor
-                    .read_exact(&mut rp_id_hash_raw)
-                    .map_err(|_| serde_parse_err("32 bytes"))?;
-                let rp_id_hash = RpIdHash(rp_id_hash_raw);
-
-                // preserve the flags, even if some reserved values are set.
-                let flags = AuthenticatorDataFlags::from_bits_truncate(read_byte(&mut cursor)?);
-                let counter = read_be_u32(&mut cursor)?;
-                let mut credential_data = None;
-                if flags.contains(AuthenticatorDataFlags::ATTESTED) {
-                    credential_data = Some(parse_attested_cred_data(&mut cursor)?);
-                }
-
-                let extensions = if flags.contains(AuthenticatorDataFlags::EXTENSION_DATA) {
-                    from_slice_stream(&mut cursor)?
-                } else {
-                    Default::default()
-                };
-
                 // TODO(baloo): we should check for end of buffer and raise a parse
                 //              parse error if data is still in the buffer
-                Ok(AuthenticatorData {
-                    rp_id_hash,
-                    flags,
-                    counter,
-                    credential_data,
-                    extensions,
-                })
+                let (auth_data, _) = parse_auth_data(input)?;
+                Ok(auth_data)
             }
         }
 
@@ -355,6 +485,511 @@ impl From<&[u8]> for Signature {
     }
 }
 
+// TPM algorithm identifiers, see "TPM 2.0 Part 2: Structures", 6.3 (TPM_ALG_ID)
+pub const TPM_ALG_RSA: u16 = 0x0001;
+pub const TPM_ALG_SHA1: u16 = 0x0004;
+pub const TPM_ALG_SHA256: u16 = 0x000B;
+pub const TPM_ALG_SHA384: u16 = 0x000C;
+pub const TPM_ALG_SHA512: u16 = 0x000D;
+pub const TPM_ALG_NULL: u16 = 0x0010;
+pub const TPM_ALG_ECC: u16 = 0x0023;
+/// `TPMS_ATTEST.magic` of attestation structures created by a TPM
+pub const TPM_GENERATED_VALUE: u32 = 0xff54_4347;
+/// `TPMS_ATTEST.type` of the structure created by TPM2_Certify()
+pub const TPM_ST_ATTEST_CERTIFY: u16 = 0x8017;
+
+// Reads a sized buffer (TPM2B_*): a 16-bit big-endian length, followed by the data
+fn read_tpm2b<E: SerdeError>(data: &mut Cursor<&[u8]>, name: &str) -> Result<Vec<u8>, E> {
+    let len = read_be_u16(data)?;
+    let mut buf = vec![0u8; len as usize];
+    data.read_exact(&mut buf)
+        .map_err(|_| serde_parse_err(name))?;
+    Ok(buf)
+}
+
+fn expect_end<E: SerdeError>(data: &Cursor<&[u8]>, name: &str) -> Result<(), E> {
+    if data.position() as usize != data.get_ref().len() {
+        return Err(serde_parse_err(&format!("{name}: trailing data")));
+    }
+    Ok(())
+}
+
+/// The `certInfo` of a TPM attestation statement: a TPMS_ATTEST structure created by
+/// TPM2_Certify(), see "TPM 2.0 Part 2: Structures", 10.12.
+///
+/// The signature of the statement is over the raw structure, which is kept (see `as_ref()`).
+#[derive(Debug, PartialEq, Eq)]
+pub struct TpmsAttest {
+    /// Should be `TPM_GENERATED_VALUE`
+    pub magic: u32,
+    /// Should be `TPM_ST_ATTEST_CERTIFY`
+    pub attest_type: u16,
+    pub qualified_signer: Vec<u8>,
+    /// For WebAuthn, the hash of authData || clientDataHash, using the hash of `alg`
+    pub extra_data: Vec<u8>,
+    pub clock: u64,
+    pub reset_count: u32,
+    pub restart_count: u32,
+    pub safe: bool,
+    pub firmware_version: u64,
+    /// The name of the certified object, which has to be the name of `pubArea`
+    pub attested_name: Vec<u8>,
+    pub attested_qualified_name: Vec<u8>,
+    raw: Vec<u8>,
+}
+
+impl TpmsAttest {
+    fn parse<E: SerdeError>(input: &[u8]) -> Result<Self, E> {
+        let mut data = Cursor::new(input);
+        let magic = read_be_u32(&mut data)?;
+        let attest_type = read_be_u16(&mut data)?;
+        let qualified_signer = read_tpm2b(&mut data, "qualifiedSigner")?;
+        let extra_data = read_tpm2b(&mut data, "extraData")?;
+        // TPMS_CLOCK_INFO
+        let clock = read_be_u64(&mut data)?;
+        let reset_count = read_be_u32(&mut data)?;
+        let restart_count = read_be_u32(&mut data)?;
+        let safe = read_byte(&mut data)? != 0;
+        let firmware_version = read_be_u64(&mut data)?;
+        // TPMS_CERTIFY_INFO. Other attestation types are not used by WebAuthn.
+        if attest_type != TPM_ST_ATTEST_CERTIFY {
+            return Err(serde_parse_err(&format!(
+                "TPMS_ATTEST: unsupported type {attest_type:#06x}"
+            )));
+        }
+        let attested_name = read_tpm2b(&mut data, "name")?;
+        let attested_qualified_name = read_tpm2b(&mut data, "qualifiedName")?;
+        expect_end(&data, "TPMS_ATTEST")?;
+        Ok(TpmsAttest {
+            magic,
+            attest_type,
+            qualified_signer,
+            extra_data,
+            clock,
+            reset_count,
+            restart_count,
+            safe,
+            firmware_version,
+            attested_name,
+            attested_qualified_name,
+            raw: input.to_vec(),
+        })
+    }
+}
+
+impl AsRef<[u8]> for TpmsAttest {
+    fn as_ref(&self) -> &[u8] {
+        &self.raw
+    }
+}
+
+impl Serialize for TpmsAttest {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.raw)
+    }
+}
+
+impl<'de> Deserialize<'de> for TpmsAttest {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = serde_bytes::ByteBuf::deserialize(deserializer)?;
+        TpmsAttest::parse(&raw)
+    }
+}
+
+/// The public key of a TPMT_PUBLIC structure
+#[derive(Debug, PartialEq, Eq)]
+pub enum TpmPublicKey {
+    Rsa {
+        key_bits: u16,
+        /// Zero means the default exponent 2^16 + 1
+        exponent: u32,
+        modulus: Vec<u8>,
+    },
+    Ecc {
+        /// TPM_ECC_CURVE, e.g. 0x0003 for NIST P-256
+        curve_id: u16,
+        x: Vec<u8>,
+        y: Vec<u8>,
+    },
+}
+
+/// The `pubArea` of a TPM attestation statement: a TPMT_PUBLIC structure describing the
+/// credential public key, see "TPM 2.0 Part 2: Structures", 12.2.4. Only RSA and ECC keys
+/// are supported.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TpmtPublic {
+    pub name_alg: u16,
+    pub object_attributes: u32,
+    pub auth_policy: Vec<u8>,
+    pub key: TpmPublicKey,
+    raw: Vec<u8>,
+}
+
+impl TpmtPublic {
+    fn parse<E: SerdeError>(input: &[u8]) -> Result<Self, E> {
+        let mut data = Cursor::new(input);
+        let key_type = read_be_u16(&mut data)?;
+        let name_alg = read_be_u16(&mut data)?;
+        let object_attributes = read_be_u32(&mut data)?;
+        let auth_policy = read_tpm2b(&mut data, "authPolicy")?;
+        // TPMT_SYM_DEF_OBJECT: algorithm, and keyBits and mode unless it is TPM_ALG_NULL
+        if read_be_u16::<_, E>(&mut data)? != TPM_ALG_NULL {
+            read_be_u32::<_, E>(&mut data)?;
+        }
+        // TPMT_{RSA,ECC}_SCHEME: scheme, and hashAlg unless it is TPM_ALG_NULL
+        if read_be_u16::<_, E>(&mut data)? != TPM_ALG_NULL {
+            read_be_u16::<_, E>(&mut data)?;
+        }
+        let key = match key_type {
+            TPM_ALG_RSA => {
+                let key_bits = read_be_u16(&mut data)?;
+                let exponent = read_be_u32(&mut data)?;
+                let modulus = read_tpm2b(&mut data, "unique")?;
+                TpmPublicKey::Rsa {
+                    key_bits,
+                    exponent,
+                    modulus,
+                }
+            }
+            TPM_ALG_ECC => {
+                let curve_id = read_be_u16(&mut data)?;
+                // TPMT_KDF_SCHEME: scheme, and hashAlg unless it is TPM_ALG_NULL
+                if read_be_u16::<_, E>(&mut data)? != TPM_ALG_NULL {
+                    read_be_u16::<_, E>(&mut data)?;
+                }
+                let x = read_tpm2b(&mut data, "unique.x")?;
+                let y = read_tpm2b(&mut data, "unique.y")?;
+                TpmPublicKey::Ecc { curve_id, x, y }
+            }
+            _ => {
+                return Err(serde_parse_err(&format!(
+                    "TPMT_PUBLIC: unsupported type {key_type:#06x}"
+                )))
+            }
+        };
+        expect_end(&data, "TPMT_PUBLIC")?;
+        Ok(TpmtPublic {
+            name_alg,
+            object_attributes,
+            auth_policy,
+            key,
+            raw: input.to_vec(),
+        })
+    }
+
+    /// The TPM name of the key: nameAlg || H_nameAlg(pubArea). None, if nameAlg is not one
+    /// of the SHA-2 algorithms.
+    pub fn name(&self) -> Option<Vec<u8>> {
+        let digest = match self.name_alg {
+            TPM_ALG_SHA256 => Sha256::digest(&self.raw).to_vec(),
+            TPM_ALG_SHA384 => Sha384::digest(&self.raw).to_vec(),
+            TPM_ALG_SHA512 => Sha512::digest(&self.raw).to_vec(),
+            _ => return None,
+        };
+        let mut name = self.name_alg.to_be_bytes().to_vec();
+        name.extend(digest);
+        Some(name)
+    }
+}
+
+impl AsRef<[u8]> for TpmtPublic {
+    fn as_ref(&self) -> &[u8] {
+        &self.raw
+    }
+}
+
+impl Serialize for TpmtPublic {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.raw)
+    }
+}
+
+impl<'de> Deserialize<'de> for TpmtPublic {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = serde_bytes::ByteBuf::deserialize(deserializer)?;
+        TpmtPublic::parse(&raw)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+// https://www.w3.org/TR/webauthn-2/#sctn-tpm-attestation
+// tpmStmtFormat = {
+//                     ver: "2.0",
+//                     (
+//                         alg: COSEAlgorithmIdentifier,
+//                         x5c: [ aikCert: bytes, * (caCert: bytes) ]
+//                     )
+//                     sig: bytes,
+//                     certInfo: bytes,
+//                     pubArea: bytes
+//                 }
+// The fields are in CTAP2 canonical order.
+#[serde(rename_all = "camelCase")]
+pub struct AttestationStatementTpm {
+    pub alg: COSEAlgorithm, // (1) "alg"
+    pub sig: Signature,     // (2) "sig"
+    pub ver: String,        // (3) "ver"
+    /// Certificate chain in x509 format, starting with the attestation identity key
+    #[serde(rename = "x5c")]
+    pub attestation_cert: Vec<AttestationCertificate>, // (4) "x5c"
+    pub pub_area: TpmtPublic, // (5) "pubArea"
+    pub cert_info: TpmsAttest, // (6) "certInfo"
+}
+
+impl AttestationStatementTpm {
+    /// Whether `certInfo` certifies the key in `pubArea`, i.e. whether its attested name is
+    /// the name of `pubArea`. This does not check the signature or the certificate chain.
+    pub fn is_name_attested(&self) -> bool {
+        self.pub_area.name().as_ref() == Some(&self.cert_info.attested_name)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+// https://www.w3.org/TR/webauthn-2/#sctn-android-key-attestation
+// androidStmtFormat = {
+//                         alg: COSEAlgorithmIdentifier,
+//                         sig: bytes,
+//                         x5c: [ credCert: bytes, * (caCert: bytes) ]
+//                     }
+pub struct AttestationStatementAndroidKey {
+    pub alg: COSEAlgorithm, // (1) "alg"
+    pub sig: Signature,     // (2) "sig"
+    /// Certificate chain in x509 format, starting with the certificate of the credential
+    #[serde(rename = "x5c")]
+    pub attestation_cert: Vec<AttestationCertificate>, // (3) "x5c"
+}
+
+/// OID of the Android key attestation extension (1.3.6.1.4.1.11129.2.1.17)
+const OID_ANDROID_KEY_DESCRIPTION: &[u8] =
+    &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xd6, 0x79, 0x02, 0x01, 0x11];
+
+impl AttestationStatementAndroidKey {
+    /// The attestation extension of the credential certificate, or None if it has none.
+    pub fn key_description(&self) -> Result<Option<AndroidKeyDescription>, CryptoError> {
+        let cert = self
+            .attestation_cert
+            .first()
+            .ok_or(CryptoError::MalformedInput)?;
+        der::certificate_extension(cert.as_ref(), OID_ANDROID_KEY_DESCRIPTION)?
+            .map(AndroidKeyDescription::from_der)
+            .transpose()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AndroidSecurityLevel {
+    Software,
+    TrustedEnvironment,
+    StrongBox,
+    Unknown(u64),
+}
+
+impl From<u64> for AndroidSecurityLevel {
+    fn from(level: u64) -> Self {
+        match level {
+            0 => AndroidSecurityLevel::Software,
+            1 => AndroidSecurityLevel::TrustedEnvironment,
+            2 => AndroidSecurityLevel::StrongBox,
+            _ => AndroidSecurityLevel::Unknown(level),
+        }
+    }
+}
+
+/// `KeyPurpose` of keys that can sign
+pub const ANDROID_KEY_PURPOSE_SIGN: u64 = 2;
+/// `KeyOrigin` of keys generated in the secure hardware
+pub const ANDROID_KEY_ORIGIN_GENERATED: u64 = 0;
+
+/// The entries of an Android `AuthorizationList` that WebAuthn relying parties have to check.
+/// See https://www.w3.org/TR/webauthn-2/#sctn-key-attstn-cert-requirements
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AndroidAuthorizationList {
+    /// `purpose` ([1]), see `ANDROID_KEY_PURPOSE_SIGN`
+    pub purpose: Vec<u64>,
+    /// `allApplications` ([600]), must not be set for WebAuthn credentials
+    pub all_applications: bool,
+    /// `origin` ([702]), see `ANDROID_KEY_ORIGIN_GENERATED`
+    pub origin: Option<u64>,
+}
+
+impl AndroidAuthorizationList {
+    fn from_der(mut list: &[u8]) -> Result<Self, CryptoError> {
+        let mut result = AndroidAuthorizationList::default();
+        while !list.is_empty() {
+            let (element, rest) = der::read_element(list)?;
+            list = rest;
+            // All entries are explicitly tagged (context-specific, constructed)
+            if element.identifier & 0xe0 != 0xa0 {
+                return Err(CryptoError::MalformedInput);
+            }
+            match element.tag_number {
+                1 => {
+                    let (mut set, _) = der::expect_element(der::TAG_SET, element.contents)?;
+                    while !set.is_empty() {
+                        let (purpose, rest) = der::expect_element(der::TAG_INTEGER, set)?;
+                        result.purpose.push(der::read_u64(purpose)?);
+                        set = rest;
+                    }
+                }
+                600 => result.all_applications = true,
+                702 => {
+                    let (origin, _) = der::expect_element(der::TAG_INTEGER, element.contents)?;
+                    result.origin = Some(der::read_u64(origin)?);
+                }
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// The Android key attestation extension, see
+/// https://source.android.com/docs/security/features/keystore/attestation#schema
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AndroidKeyDescription {
+    pub attestation_version: u64,
+    pub attestation_security_level: AndroidSecurityLevel,
+    pub keymaster_version: u64,
+    pub keymaster_security_level: AndroidSecurityLevel,
+    /// For WebAuthn, this has to be the client data hash.
+    pub attestation_challenge: Vec<u8>,
+    pub unique_id: Vec<u8>,
+    pub software_enforced: AndroidAuthorizationList,
+    pub tee_enforced: AndroidAuthorizationList,
+}
+
+impl AndroidKeyDescription {
+    fn from_der(input: &[u8]) -> Result<Self, CryptoError> {
+        let (z, _) = der::expect_element(der::TAG_SEQUENCE, input)?;
+        let (attestation_version, z) = der::expect_element(der::TAG_INTEGER, z)?;
+        let (attestation_security_level, z) = der::expect_element(der::TAG_ENUMERATED, z)?;
+        let (keymaster_version, z) = der::expect_element(der::TAG_INTEGER, z)?;
+        let (keymaster_security_level, z) = der::expect_element(der::TAG_ENUMERATED, z)?;
+        let (attestation_challenge, z) = der::expect_element(der::TAG_OCTET_STRING, z)?;
+        let (unique_id, z) = der::expect_element(der::TAG_OCTET_STRING, z)?;
+        let (software_enforced, z) = der::expect_element(der::TAG_SEQUENCE, z)?;
+        let (tee_enforced, _) = der::expect_element(der::TAG_SEQUENCE, z)?;
+        Ok(AndroidKeyDescription {
+            attestation_version: der::read_u64(attestation_version)?,
+            attestation_security_level: der::read_u64(attestation_security_level)?.into(),
+            keymaster_version: der::read_u64(keymaster_version)?,
+            keymaster_security_level: der::read_u64(keymaster_security_level)?.into(),
+            attestation_challenge: attestation_challenge.to_vec(),
+            unique_id: unique_id.to_vec(),
+            software_enforced: AndroidAuthorizationList::from_der(software_enforced)?,
+            tee_enforced: AndroidAuthorizationList::from_der(tee_enforced)?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+// https://www.w3.org/TR/webauthn-2/#sctn-apple-anonymous-attestation
+// appleStmtFormat = {
+//                       x5c: [ credCert: bytes, * (caCert: bytes) ]
+//                   }
+pub struct AttestationStatementAppleAnonymous {
+    /// Certificate chain in x509 format, starting with the certificate of the credential
+    #[serde(rename = "x5c")]
+    pub attestation_cert: Vec<AttestationCertificate>, // (1) "x5c"
+}
+
+/// OID of the nonce extension of Apple anonymous attestation (1.2.840.113635.100.8.2)
+const OID_APPLE_NONCE: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x63, 0x64, 0x08, 0x02];
+
+impl AttestationStatementAppleAnonymous {
+    /// The nonce of the credential certificate, which should be
+    /// SHA-256(authData || clientDataHash).
+    ///   SEQUENCE { [1] EXPLICIT OCTET STRING nonce }
+    pub fn nonce(&self) -> Result<Vec<u8>, CryptoError> {
+        let cert = self
+            .attestation_cert
+            .first()
+            .ok_or(CryptoError::MalformedInput)?;
+        let extension = der::certificate_extension(cert.as_ref(), OID_APPLE_NONCE)?
+            .ok_or(CryptoError::MalformedInput)?;
+        let (z, _) = der::expect_element(der::TAG_SEQUENCE, extension)?;
+        let (z, _) = der::expect_element(0xa1, z)?;
+        let (nonce, _) = der::expect_element(der::TAG_OCTET_STRING, z)?;
+        Ok(nonce.to_vec())
+    }
+
+    /// Checks the nonce of the credential certificate against the authenticator data and the
+    /// client data hash. This does not check the certificate chain, or that the certificate
+    /// is for the credential public key.
+    pub fn verify_nonce(
+        &self,
+        auth_data: &AuthenticatorData,
+        client_data_hash: &ClientDataHash,
+    ) -> Result<(), CryptoError> {
+        let mut hasher = Sha256::new();
+        hasher.update(auth_data.to_vec());
+        hasher.update(client_data_hash.as_ref());
+        if self.nonce()? != hasher.finalize().as_slice() {
+            return Err(CryptoError::VerificationFailed);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+// https://www.w3.org/TR/webauthn-2/#sctn-android-safetynet-attestation
+// safetynetStmtFormat = {
+//                           ver: text,
+//                           response: bytes
+//                       }
+pub struct AttestationStatementAndroidSafetyNet {
+    /// Version of Google Play Services
+    pub ver: String, // (1) "ver"
+    /// The SafetyNet response, a JWS in compact serialization
+    #[serde(with = "serde_bytes")]
+    pub response: Vec<u8>, // (2) "response"
+}
+
+/// The base64url decoded segments of a JWS in compact serialization.
+#[derive(Debug, PartialEq, Eq)]
+pub struct JwsSegments {
+    pub header: Vec<u8>,
+    pub payload: Vec<u8>,
+    pub signature: Vec<u8>,
+    /// The data the signature is over: the encoded header and payload, separated by a dot
+    pub signing_input: Vec<u8>,
+}
+
+impl AttestationStatementAndroidSafetyNet {
+    /// Splits the response into its segments. The signature is NOT verified, that is up to
+    /// the caller.
+    pub fn jws_segments(&self) -> Result<JwsSegments, CryptoError> {
+        let parts: Vec<&[u8]> = self.response.split(|b| *b == b'.').collect();
+        let (header, payload, signature) = match parts.as_slice() {
+            [header, payload, signature] => (header, payload, signature),
+            _ => return Err(CryptoError::MalformedInput),
+        };
+        let decode = |data: &[u8]| {
+            base64::engine::general_purpose::URL_SAFE_NO_PAD
+                .decode(data)
+                .map_err(|_| CryptoError::MalformedInput)
+        };
+        Ok(JwsSegments {
+            header: decode(header)?,
+            payload: decode(payload)?,
+            signature: decode(signature)?,
+            signing_input: self.response[..header.len() + 1 + payload.len()].to_vec(),
+        })
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Deserialize)]
 // The tag and content attributes here are really for AttestationObject, which contains an
 // "internally tagged" AttestationStatement.
@@ -365,15 +1000,18 @@ pub enum AttestationStatement {
     Packed(AttestationStatementPacked),
     #[serde(rename = "fido-u2f")]
     FidoU2F(AttestationStatementFidoU2F),
-    // The remaining attestation statement formats are deserialized as serde_cbor::Values---we do
-    // not perform any validation of their contents. These are expected to be used primarily when
-    // anonymizing attestation objects that contain attestation statements in these formats.
+    Tpm(AttestationStatementTpm),
     #[serde(rename = "android-key")]
-    AndroidKey(serde_cbor::Value),
+    AndroidKey(AttestationStatementAndroidKey),
+    #[serde(rename = "apple")]
+    AppleAnonymous(AttestationStatementAppleAnonymous),
     #[serde(rename = "android-safetynet")]
-    AndroidSafetyNet(serde_cbor::Value),
-    Apple(serde_cbor::Value),
-    Tpm(serde_cbor::Value),
+    AndroidSafetyNet(AttestationStatementAndroidSafetyNet),
+    /// The attestation statement was not parsed (see `MakeCredentials::skip_attestation_statement`).
+    /// Holds the attestation statement format and the attStmt as the authenticator encoded it,
+    /// so the statement can still be verified later on.
+    #[serde(skip_deserializing)]
+    Unparsed(String, Vec<u8>),
 }
 
 // AttestationStatement::None is serialized as the empty map. We need to enforce
@@ -391,43 +1029,6 @@ where
     Ok(())
 }
 
-// Not all crypto-backends currently provide "crypto::verify()", so we do not implement it yet.
-// Also not sure, if we really need it. Would be a sanity-check only, to verify the signature is valid,
-// before sendig it out.
-// impl AttestationStatement {
-//     pub fn verify(&self, data: &[u8]) -> Result<bool, AuthenticatorError> {
-//         match self {
-//             AttestationStatement::None => Ok(true),
-//             AttestationStatement::Unparsed(_) => Err(AuthenticatorError::Custom(
-//                 "Unparsed attestation object can't be used to verify signature.".to_string(),
-//             )),
-//             AttestationStatement::FidoU2F(att) => {
-//                 let res = crypto::verify(
-//                     crypto::SignatureAlgorithm::ES256,
-//                     &att.attestation_cert[0].as_ref(),
-//                     att.sig.as_ref(),
-//                     data,
-//                 )?;
-//                 Ok(res)
-//             }
-//             AttestationStatement::Packed(att) => {
-//                 if att.alg != Alg::ES256 {
-//                     return Err(AuthenticatorError::Custom(
-//                         "Verification only supported for ES256".to_string(),
-//                     ));
-//                 }
-//                 let res = crypto::verify(
-//                     crypto::SignatureAlgorithm::ES256,
-//                     att.attestation_cert[0].as_ref(),
-//                     att.sig.as_ref(),
-//                     data,
-//                 )?;
-//                 Ok(res)
-//             }
-//         }
-//     }
-// }
-
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 // See https://www.w3.org/TR/webauthn-2/#sctn-fido-u2f-attestation
 // u2fStmtFormat = {
@@ -469,6 +1070,41 @@ pub struct AttestationStatementPacked {
     pub attestation_cert: Vec<AttestationCertificate>, // (3) "x5c"
 }
 
+impl AttestationStatementPacked {
+    /// A packed statement without a certificate chain is signed with the credential
+    /// private key itself.
+    pub fn is_self_attestation(&self) -> bool {
+        self.attestation_cert.is_empty()
+    }
+}
+
+/// Why `AttestationObject::verify_attestation()` failed
+#[derive(Debug)]
+pub enum AttestationError {
+    /// The attestation statement has no certificate chain (none or self attestation), or its
+    /// format is not supported (android-safetynet, unparsed statements)
+    NoCertificateChain,
+    /// A certificate (of the statement or a root) could not be parsed
+    MalformedCertificate,
+    /// A certificate of the chain is expired or not yet valid
+    CertificateNotValid,
+    /// There is no chain from the attestation certificate to one of the roots
+    UntrustedChain,
+    /// The signature of a certificate did not verify against its presumed issuer
+    Signature(CryptoError),
+    /// The AAGUID extension of a packed attestation certificate differs from the AAGUID in
+    /// the authenticator data
+    AaguidMismatch,
+    /// A certificate that issues another one is not a CA, or the attestation certificate of a
+    /// packed statement is one (see the basic constraints extension)
+    BasicConstraints,
+}
+
+/// OID of the FIDO AAGUID certificate extension (1.3.6.1.4.1.45724.1.1.4)
+const OID_FIDO_GEN_CE_AAGUID: &[u8] = &[
+    0x2b, 0x06, 0x01, 0x04, 0x01, 0x82, 0xe5, 0x1c, 0x01, 0x01, 0x04,
+];
+
 // A WebAuthn attestation object is a CBOR map with keys "fmt", "attStmt", and "authData". The
 // "fmt" field determines the type of "attStmt". The flatten attribute here turns the tag and
 // content attributes on AttestationStatement (defined above) into expected keys for
@@ -483,7 +1119,247 @@ pub struct AttestationObject {
     pub att_stmt: AttestationStatement,
 }
 
+/// Memoizes parsed attestation certificates by their DER encoding, so that the certificate
+/// chains of many registrations from the same authenticator model, and the roots they are
+/// verified against, are parsed only once. See `AttestationObject::verify_with_cache()` and
+/// `AttestationObject::verify_attestation_with_cache()`.
+///
+/// Entries are never evicted, long-running services can `clear()` the cache to bound its
+/// memory.
+#[derive(Debug, Default)]
+pub struct CertificateCache {
+    certificates: Mutex<HashMap<Vec<u8>, Arc<x509::OwnedCertificate>>>,
+}
+
+impl CertificateCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The parsed `cert`, from the cache if it was parsed before. Certificates that can't be
+    /// parsed are not cached.
+    pub fn get(
+        &self,
+        cert: &AttestationCertificate,
+    ) -> Result<Arc<x509::OwnedCertificate>, CryptoError> {
+        let mut certificates = self.certificates.lock().unwrap();
+        if let Some(parsed) = certificates.get(cert.as_ref()) {
+            return Ok(Arc::clone(parsed));
+        }
+        let parsed = Arc::new(x509::OwnedCertificate::from_der(cert.as_ref().to_vec())?);
+        certificates.insert(cert.as_ref().to_vec(), Arc::clone(&parsed));
+        Ok(parsed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.certificates.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.certificates.lock().unwrap().clear();
+    }
+}
+
+/// Trust anchors for `AttestationObject::verify_attestation_with_trust_store()`, which are
+/// parsed once when the store is created.
+#[derive(Debug)]
+pub struct TrustStore {
+    roots: Vec<x509::OwnedCertificate>,
+}
+
+impl TrustStore {
+    /// Fails with `AttestationError::MalformedCertificate` if one of `roots` (DER encoded
+    /// certificates) can't be parsed.
+    pub fn new(roots: &[AttestationCertificate]) -> Result<Self, AttestationError> {
+        let roots = roots
+            .iter()
+            .map(|cert| {
+                x509::OwnedCertificate::from_der(cert.as_ref().to_vec())
+                    .map_err(|_| AttestationError::MalformedCertificate)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(TrustStore { roots })
+    }
+
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+}
+
+fn parse_certificates(
+    certs: &[AttestationCertificate],
+    cache: &CertificateCache,
+) -> Result<Vec<Arc<x509::OwnedCertificate>>, AttestationError> {
+    certs
+        .iter()
+        .map(|cert| {
+            cache
+                .get(cert)
+                .map_err(|_| AttestationError::MalformedCertificate)
+        })
+        .collect()
+}
+
 impl AttestationObject {
+    /// The certificate chain of the attestation statement, starting with the attestation
+    /// certificate. Empty for none and self attestation, and for formats that carry their
+    /// certificates differently.
+    fn certificate_chain(&self) -> &[AttestationCertificate] {
+        match &self.att_stmt {
+            AttestationStatement::Packed(att) => &att.attestation_cert,
+            AttestationStatement::FidoU2F(att) => &att.attestation_cert,
+            AttestationStatement::Tpm(att) => &att.attestation_cert,
+            AttestationStatement::AndroidKey(att) => &att.attestation_cert,
+            AttestationStatement::AppleAnonymous(att) => &att.attestation_cert,
+            AttestationStatement::None
+            | AttestationStatement::AndroidSafetyNet(_)
+            | AttestationStatement::Unparsed(..) => &[],
+        }
+    }
+
+    /// Verifies the certificate chain of the attestation statement: Builds a chain from the
+    /// attestation certificate via the other certificates of the statement to one of `roots`
+    /// (DER encoded trust anchors), and checks the signatures and validity periods along the
+    /// way, including the root's. Only CA certificates can issue others. For packed
+    /// attestation, the AAGUID extension of the attestation certificate (if any) has to match
+    /// the AAGUID of the authenticator data, and the attestation certificate must not be a CA.
+    ///
+    /// This does not verify the attestation signature itself (see `verify()`), nor any
+    /// format specific requirements of the certificates. Certificate signatures have to use
+    /// ECDSA or RSA PKCS #1 v1.5 with SHA-256.
+    pub fn verify_attestation(
+        &self,
+        roots: &[AttestationCertificate],
+    ) -> Result<(), AttestationError> {
+        self.verify_attestation_at(roots, SystemTime::now())
+    }
+
+    /// Like `verify_attestation()`, but checks the validity periods against `time`.
+    pub fn verify_attestation_at(
+        &self,
+        roots: &[AttestationCertificate],
+        time: SystemTime,
+    ) -> Result<(), AttestationError> {
+        self.verify_attestation_with_cache(roots, time, &CertificateCache::new())
+    }
+
+    /// Like `verify_attestation_at()`, but takes the parsed certificates from `cache`.
+    pub fn verify_attestation_with_cache(
+        &self,
+        roots: &[AttestationCertificate],
+        time: SystemTime,
+        cache: &CertificateCache,
+    ) -> Result<(), AttestationError> {
+        let roots = parse_certificates(roots, cache)?;
+        let roots: Vec<x509::Certificate> = roots.iter().map(|cert| cert.certificate()).collect();
+        self.verify_chain(&roots, time, cache)
+    }
+
+    /// Like `verify_attestation_with_cache()`, but with the already parsed roots of
+    /// `trust_store`.
+    pub fn verify_attestation_with_trust_store(
+        &self,
+        trust_store: &TrustStore,
+        time: SystemTime,
+        cache: &CertificateCache,
+    ) -> Result<(), AttestationError> {
+        let roots: Vec<x509::Certificate> = trust_store
+            .roots
+            .iter()
+            .map(|cert| cert.certificate())
+            .collect();
+        self.verify_chain(&roots, time, cache)
+    }
+
+    fn verify_chain(
+        &self,
+        roots: &[x509::Certificate],
+        time: SystemTime,
+        cache: &CertificateCache,
+    ) -> Result<(), AttestationError> {
+        let chain = parse_certificates(self.certificate_chain(), cache)?;
+        let chain: Vec<x509::Certificate> = chain.iter().map(|cert| cert.certificate()).collect();
+        let time = time
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |t| t.as_secs() as i64);
+
+        if let AttestationStatement::Packed(att) = &self.att_stmt {
+            // See https://www.w3.org/TR/webauthn-2/#sctn-packed-attestation-cert-requirements
+            if matches!(chain.first(), Some(cert) if cert.is_ca) {
+                return Err(AttestationError::BasicConstraints);
+            }
+            if let (Some(cert), Some(credential_data)) = (
+                att.attestation_cert.first(),
+                self.auth_data.credential_data.as_ref(),
+            ) {
+                let extension = der::certificate_extension(cert.as_ref(), OID_FIDO_GEN_CE_AAGUID)
+                    .map_err(|_| AttestationError::MalformedCertificate)?;
+                if let Some(extension) = extension {
+                    let (aaguid, _) = der::expect_element(der::TAG_OCTET_STRING, extension)
+                        .map_err(|_| AttestationError::MalformedCertificate)?;
+                    if aaguid != credential_data.aaguid.0 {
+                        return Err(AttestationError::AaguidMismatch);
+                    }
+                }
+            }
+        }
+
+        let mut current = chain.first().ok_or(AttestationError::NoCertificateChain)?;
+        let mut used = vec![false; chain.len()];
+        used[0] = true;
+        let mut chain_error = None;
+        // Every certificate of the statement is used at most once, so this terminates.
+        loop {
+            if !current.is_valid_at(time) {
+                return Err(AttestationError::CertificateNotValid);
+            }
+            for root in roots.iter().filter(|root| root.subject == current.issuer) {
+                if !root.is_ca {
+                    chain_error = Some(AttestationError::BasicConstraints);
+                    continue;
+                }
+                match current.verify_signed_by(root) {
+                    Ok(()) if root.is_valid_at(time) => return Ok(()),
+                    Ok(()) => chain_error = Some(AttestationError::CertificateNotValid),
+                    Err(e) => chain_error = Some(AttestationError::Signature(e)),
+                }
+            }
+            let mut next = None;
+            for (i, cert) in chain.iter().enumerate() {
+                if used[i] || cert.subject != current.issuer {
+                    continue;
+                }
+                if !cert.is_ca {
+                    chain_error = Some(AttestationError::BasicConstraints);
+                    continue;
+                }
+                match current.verify_signed_by(cert) {
+                    Ok(()) => {
+                        next = Some(i);
+                        break;
+                    }
+                    Err(e) => chain_error = Some(AttestationError::Signature(e)),
+                }
+            }
+            match next {
+                Some(i) => {
+                    used[i] = true;
+                    current = &chain[i];
+                }
+                None => break,
+            }
+        }
+        Err(chain_error.unwrap_or(AttestationError::UntrustedChain))
+    }
+
     pub fn anonymize(&mut self) {
         // Remove the attestation statement and the AAGUID from the authenticator data.
         self.att_stmt = AttestationStatement::None;
@@ -491,6 +1367,65 @@ impl AttestationObject {
             credential_data.aaguid = AAGuid::default();
         }
     }
+
+    /// Verifies the attestation signature over authData || clientDataHash.
+    /// Self attestation is verified with the credential public key, packed and android-key
+    /// attestation with the key of the first x5c certificate, which has to be an ES256 (P-256)
+    /// or RS256 (RSA) key. Other attestation types result in
+    /// `CryptoError::UnknownSignatureScheme`. The certificate chain of full attestation is
+    /// never checked here, see `verify_attestation()`.
+    pub fn verify(&self, client_data_hash: &ClientDataHash) -> Result<(), CryptoError> {
+        self.verify_with_cache(client_data_hash, &CertificateCache::new())
+    }
+
+    /// Like `verify()`, but takes the parsed attestation certificate from `cache`.
+    pub fn verify_with_cache(
+        &self,
+        client_data_hash: &ClientDataHash,
+        cache: &CertificateCache,
+    ) -> Result<(), CryptoError> {
+        match &self.att_stmt {
+            AttestationStatement::None => Ok(()),
+            AttestationStatement::Packed(att) if att.is_self_attestation() => {
+                // Self attestation uses the credential private key, so the statement
+                // has to use the algorithm of the credential public key.
+                let credential_data = self
+                    .auth_data
+                    .credential_data
+                    .as_ref()
+                    .ok_or(CryptoError::MalformedInput)?;
+                let key_alg = credential_data.credential_public_key.alg;
+                if att.alg != key_alg {
+                    return Err(CryptoError::AlgorithmMismatch(att.alg, key_alg));
+                }
+                let mut data = self.auth_data.to_vec();
+                data.extend_from_slice(client_data_hash.as_ref());
+                credential_data
+                    .credential_public_key
+                    .verify_signature(att.sig.as_ref(), &data)
+            }
+            AttestationStatement::Packed(AttestationStatementPacked {
+                alg,
+                sig,
+                attestation_cert,
+            })
+            | AttestationStatement::AndroidKey(AttestationStatementAndroidKey {
+                alg,
+                sig,
+                attestation_cert,
+            }) => {
+                let cert = attestation_cert
+                    .first()
+                    .ok_or(CryptoError::MalformedInput)?;
+                let cert = cache.get(cert)?;
+                let mut data = self.auth_data.to_vec();
+                data.extend_from_slice(client_data_hash.as_ref());
+                cert.certificate()
+                    .verify_signature(*alg, sig.as_ref(), &data)
+            }
+            _ => Err(CryptoError::UnknownSignatureScheme),
+        }
+    }
 }
 
 impl Serialize for AttestationObject {
@@ -526,7 +1461,7 @@ impl Serialize for AttestationObject {
                 map.serialize_entry(&"fmt", &"android-safetynet")?; // (1) "fmt"
                 map.serialize_entry(&"attStmt", v)?; // (2) "attStmt"
             }
-            AttestationStatement::Apple(ref v) => {
+            AttestationStatement::AppleAnonymous(ref v) => {
                 map.serialize_entry(&"fmt", &"apple")?; // (1) "fmt"
                 map.serialize_entry(&"attStmt", v)?; // (2) "attStmt"
             }
@@ -534,6 +1469,12 @@ impl Serialize for AttestationObject {
                 map.serialize_entry(&"fmt", &"tpm")?; // (1) "fmt"
                 map.serialize_entry(&"attStmt", v)?; // (2) "attStmt"
             }
+            AttestationStatement::Unparsed(ref fmt, ref raw) => {
+                // Serde has no way to write the encoded statement as it is
+                let v: serde_cbor::Value = serde_cbor::from_slice(raw).map_err(SerError::custom)?;
+                map.serialize_entry(&"fmt", fmt)?; // (1) "fmt"
+                map.serialize_entry(&"attStmt", &v)?; // (2) "attStmt"
+            }
         }
         map.serialize_entry(&"authData", &self.auth_data)?; // (3) "authData"
         map.end()
@@ -545,7 +1486,288 @@ pub mod test {
     use super::super::utils::from_slice_stream;
     use super::*;
     use crate::crypto::{COSEAlgorithm, COSEEC2Key, COSEKey, COSEKeyType, Curve};
-    use serde_cbor::{from_slice, to_vec};
+    use serde_cbor::{from_slice, to_vec, Value};
+
+    // A test chain, as created with openssl:
+    //   Root: RSA 2048, self-signed
+    //   Intermediate: P-256, signed by the root with sha256WithRSAEncryption
+    //   Leaf: P-256, signed by the intermediate with ecdsa-with-SHA256, with the AAGUID
+    //         extension set to the AAGUID of create_attestation_obj()
+    // All are valid from 2026-10-16 15:50:21 UTC to 2126-09-22 15:50:21 UTC.
+    // CN=Test Root CA
+    const TEST_ROOT_CERT: [u8; 789] = [
+        0x30, 0x82, 0x03, 0x11, 0x30, 0x82, 0x01, 0xf9, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x14,
+        0x60, 0x05, 0xf0, 0x9c, 0x64, 0x6a, 0xe3, 0x64, 0x85, 0x1a, 0xbd, 0x7a, 0x39, 0xba, 0xae,
+        0x97, 0x3f, 0x05, 0xc8, 0xd4, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d,
+        0x01, 0x01, 0x0b, 0x05, 0x00, 0x30, 0x17, 0x31, 0x15, 0x30, 0x13, 0x06, 0x03, 0x55, 0x04,
+        0x03, 0x0c, 0x0c, 0x54, 0x65, 0x73, 0x74, 0x20, 0x52, 0x6f, 0x6f, 0x74, 0x20, 0x43, 0x41,
+        0x30, 0x20, 0x17, 0x0d, 0x32, 0x36, 0x31, 0x30, 0x31, 0x36, 0x31, 0x35, 0x35, 0x30, 0x32,
+        0x31, 0x5a, 0x18, 0x0f, 0x32, 0x31, 0x32, 0x36, 0x30, 0x39, 0x32, 0x32, 0x31, 0x35, 0x35,
+        0x30, 0x32, 0x31, 0x5a, 0x30, 0x17, 0x31, 0x15, 0x30, 0x13, 0x06, 0x03, 0x55, 0x04, 0x03,
+        0x0c, 0x0c, 0x54, 0x65, 0x73, 0x74, 0x20, 0x52, 0x6f, 0x6f, 0x74, 0x20, 0x43, 0x41, 0x30,
+        0x82, 0x01, 0x22, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01,
+        0x01, 0x05, 0x00, 0x03, 0x82, 0x01, 0x0f, 0x00, 0x30, 0x82, 0x01, 0x0a, 0x02, 0x82, 0x01,
+        0x01, 0x00, 0xb9, 0xf9, 0x43, 0xcb, 0x8c, 0x4f, 0xdd, 0x40, 0x86, 0x95, 0x54, 0xc1, 0x97,
+        0x37, 0xbc, 0xd6, 0x4d, 0xd6, 0x26, 0xbb, 0x33, 0x22, 0x46, 0xab, 0x10, 0xf5, 0x7d, 0xf1,
+        0x4e, 0xc1, 0xfa, 0xdd, 0x22, 0x5a, 0x11, 0x81, 0x14, 0x27, 0x36, 0xe3, 0x79, 0x30, 0x05,
+        0x73, 0x86, 0xd3, 0x8b, 0x08, 0x32, 0xeb, 0x88, 0x99, 0xda, 0x77, 0x81, 0x23, 0xa5, 0x67,
+        0xa0, 0x36, 0x93, 0x6b, 0x35, 0x8d, 0xbb, 0xad, 0xb6, 0xef, 0xec, 0xf2, 0x81, 0xbc, 0x27,
+        0xff, 0x38, 0xe6, 0xc9, 0x9e, 0x91, 0x37, 0xd8, 0x15, 0xd6, 0x2f, 0x5d, 0xf7, 0xfd, 0x9b,
+        0xd6, 0xdc, 0x1c, 0x02, 0x1c, 0x51, 0x5f, 0x37, 0x36, 0x15, 0x80, 0xe7, 0x79, 0x06, 0xec,
+        0x4a, 0x0f, 0x5f, 0x39, 0x88, 0x66, 0x1e, 0x54, 0x77, 0x61, 0x0f, 0xbe, 0x6b, 0x9c, 0x7c,
+        0x73, 0xf3, 0x15, 0xb9, 0xcd, 0x96, 0x05, 0x9e, 0x3a, 0xb9, 0xbb, 0xdd, 0x6a, 0x2a, 0x70,
+        0xa6, 0x24, 0x63, 0x1d, 0x58, 0xcf, 0x55, 0x57, 0x19, 0xcc, 0x3a, 0x95, 0x6d, 0xc6, 0x5e,
+        0x29, 0x75, 0xa9, 0x46, 0x94, 0x02, 0x4a, 0xab, 0x3c, 0x9b, 0x8f, 0x06, 0x37, 0xc2, 0x86,
+        0x79, 0xaf, 0x99, 0xc9, 0xc1, 0x96, 0x4c, 0x20, 0x90, 0xa7, 0xa4, 0xb8, 0xb1, 0xd2, 0xe7,
+        0xd2, 0xbc, 0x8c, 0x6e, 0xab, 0x18, 0x9c, 0x82, 0xba, 0x5f, 0x31, 0xf2, 0x51, 0x29, 0x1b,
+        0x7d, 0x42, 0xe5, 0xf4, 0x6c, 0xba, 0xe6, 0xbb, 0xd6, 0x0a, 0xac, 0x4b, 0xc4, 0xb6, 0xcd,
+        0x3c, 0x8c, 0xc3, 0x90, 0x28, 0x58, 0x1e, 0x69, 0x32, 0x39, 0xcf, 0xe8, 0xc4, 0x77, 0x49,
+        0xc6, 0xa6, 0x6f, 0xdd, 0x51, 0x98, 0x17, 0x45, 0x66, 0xe1, 0x59, 0x87, 0xf3, 0x65, 0x1a,
+        0xcf, 0xf2, 0x16, 0xc5, 0xc3, 0x6b, 0x17, 0x28, 0x0b, 0x7d, 0x5e, 0xa7, 0x54, 0x3a, 0xb7,
+        0x05, 0x54, 0x3b, 0x02, 0x03, 0x01, 0x00, 0x01, 0xa3, 0x53, 0x30, 0x51, 0x30, 0x1d, 0x06,
+        0x03, 0x55, 0x1d, 0x0e, 0x04, 0x16, 0x04, 0x14, 0xd1, 0xf1, 0xa1, 0x52, 0xe3, 0x17, 0x4c,
+        0xf7, 0xd8, 0xd8, 0xed, 0x48, 0x08, 0x5f, 0x35, 0x03, 0x94, 0xd9, 0x3c, 0x3d, 0x30, 0x1f,
+        0x06, 0x03, 0x55, 0x1d, 0x23, 0x04, 0x18, 0x30, 0x16, 0x80, 0x14, 0xd1, 0xf1, 0xa1, 0x52,
+        0xe3, 0x17, 0x4c, 0xf7, 0xd8, 0xd8, 0xed, 0x48, 0x08, 0x5f, 0x35, 0x03, 0x94, 0xd9, 0x3c,
+        0x3d, 0x30, 0x0f, 0x06, 0x03, 0x55, 0x1d, 0x13, 0x01, 0x01, 0xff, 0x04, 0x05, 0x30, 0x03,
+        0x01, 0x01, 0xff, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01,
+        0x0b, 0x05, 0x00, 0x03, 0x82, 0x01, 0x01, 0x00, 0x21, 0xb0, 0xcd, 0xdb, 0x01, 0xe3, 0x46,
+        0xa6, 0x4b, 0x68, 0x0e, 0x96, 0x8f, 0xf9, 0x38, 0xa4, 0x80, 0xf5, 0xb2, 0x72, 0xdd, 0xa1,
+        0xe8, 0xbb, 0xc9, 0x60, 0x07, 0x56, 0xee, 0x64, 0x3e, 0x34, 0xcf, 0x44, 0x08, 0xd7, 0x66,
+        0x09, 0x26, 0xd1, 0xd0, 0x94, 0x04, 0xe9, 0x98, 0xb7, 0x71, 0x0f, 0xc3, 0xe8, 0xae, 0x12,
+        0x3c, 0x54, 0xc5, 0xf7, 0x59, 0xdf, 0x1f, 0xa8, 0x98, 0x2e, 0xf6, 0x39, 0xd9, 0xaa, 0x2e,
+        0xba, 0x10, 0x8e, 0x23, 0xbc, 0x6a, 0x94, 0xca, 0x5c, 0xa8, 0xde, 0x0b, 0x74, 0x3a, 0xf2,
+        0x23, 0xd3, 0x16, 0x1d, 0x48, 0x44, 0xf2, 0x96, 0xaa, 0x52, 0x9d, 0x74, 0x5d, 0x2e, 0xb0,
+        0x42, 0xdd, 0x3b, 0x7a, 0xcc, 0xdc, 0xff, 0x1b, 0x32, 0x73, 0xcc, 0x17, 0x73, 0x56, 0x59,
+        0x4d, 0x13, 0x3c, 0x97, 0xbb, 0xee, 0x38, 0xb8, 0x75, 0xc1, 0x38, 0xba, 0xee, 0xc9, 0xfc,
+        0xa9, 0x8c, 0x50, 0x19, 0xcd, 0xf5, 0x68, 0x7c, 0xb1, 0x92, 0x02, 0xbe, 0x22, 0xa8, 0x01,
+        0x67, 0x64, 0xb5, 0x11, 0x83, 0x4c, 0xc3, 0x3b, 0x91, 0x2b, 0xad, 0xd5, 0xdd, 0xa9, 0xc7,
+        0xcd, 0x9b, 0x87, 0xfe, 0x95, 0xed, 0x75, 0x66, 0x87, 0xea, 0xa0, 0xc9, 0x60, 0x79, 0x13,
+        0xb4, 0xf5, 0xcb, 0x04, 0x1f, 0x02, 0x7a, 0x5c, 0x7b, 0xa3, 0x27, 0x8c, 0xfb, 0x0d, 0x44,
+        0xc5, 0x9a, 0x15, 0x03, 0x14, 0x0d, 0x2f, 0x8f, 0x1c, 0xcd, 0x1f, 0x98, 0x4c, 0xf4, 0x78,
+        0x02, 0x14, 0x84, 0xa0, 0x92, 0x1e, 0xd7, 0x49, 0x6c, 0x30, 0xf9, 0x24, 0xdf, 0x7d, 0xdf,
+        0x71, 0xff, 0xd2, 0xcf, 0x52, 0x5b, 0x3f, 0x34, 0xd9, 0xc2, 0x26, 0x82, 0x1d, 0x16, 0xf6,
+        0x6d, 0xb6, 0x04, 0x22, 0xca, 0xf4, 0x0d, 0x1c, 0xdf, 0xf5, 0x1b, 0xda, 0x01, 0x46, 0x2f,
+        0x71, 0x95, 0xc8, 0xfb, 0x77, 0x1f, 0xd8, 0x65, 0x0c,
+    ];
+    // CN=Test Intermediate CA
+    const TEST_INTERMEDIATE_CERT: [u8; 575] = [
+        0x30, 0x82, 0x02, 0x3b, 0x30, 0x82, 0x01, 0x23, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x01,
+        0x02, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b, 0x05,
+        0x00, 0x30, 0x17, 0x31, 0x15, 0x30, 0x13, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x0c, 0x54,
+        0x65, 0x73, 0x74, 0x20, 0x52, 0x6f, 0x6f, 0x74, 0x20, 0x43, 0x41, 0x30, 0x20, 0x17, 0x0d,
+        0x32, 0x36, 0x31, 0x30, 0x31, 0x36, 0x31, 0x35, 0x35, 0x30, 0x32, 0x31, 0x5a, 0x18, 0x0f,
+        0x32, 0x31, 0x32, 0x36, 0x30, 0x39, 0x32, 0x32, 0x31, 0x35, 0x35, 0x30, 0x32, 0x31, 0x5a,
+        0x30, 0x1f, 0x31, 0x1d, 0x30, 0x1b, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x14, 0x54, 0x65,
+        0x73, 0x74, 0x20, 0x49, 0x6e, 0x74, 0x65, 0x72, 0x6d, 0x65, 0x64, 0x69, 0x61, 0x74, 0x65,
+        0x20, 0x43, 0x41, 0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02,
+        0x01, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00, 0x04,
+        0x56, 0xd5, 0x10, 0x4b, 0xc1, 0x43, 0x37, 0x1c, 0xe2, 0x2b, 0x87, 0x08, 0x02, 0x6b, 0xde,
+        0x66, 0x2a, 0x9d, 0x27, 0xe9, 0xc0, 0xbb, 0x2d, 0x64, 0xdf, 0x1e, 0x2f, 0xcd, 0x23, 0x3e,
+        0x4a, 0x20, 0xfc, 0xc2, 0x7d, 0x91, 0x20, 0x5c, 0xd5, 0x80, 0x3f, 0x9d, 0x8a, 0x63, 0xeb,
+        0x6f, 0x7c, 0x89, 0x6e, 0xef, 0xdb, 0x18, 0x2b, 0x47, 0xab, 0xda, 0x15, 0x99, 0x18, 0xad,
+        0x1b, 0xbf, 0xb6, 0xb7, 0xa3, 0x53, 0x30, 0x51, 0x30, 0x0f, 0x06, 0x03, 0x55, 0x1d, 0x13,
+        0x01, 0x01, 0xff, 0x04, 0x05, 0x30, 0x03, 0x01, 0x01, 0xff, 0x30, 0x1d, 0x06, 0x03, 0x55,
+        0x1d, 0x0e, 0x04, 0x16, 0x04, 0x14, 0xc6, 0x05, 0x40, 0xcd, 0xde, 0x7d, 0xe7, 0x43, 0x3d,
+        0x0e, 0xf3, 0xa1, 0xf3, 0xf3, 0x04, 0x5b, 0xdf, 0xbc, 0x43, 0x44, 0x30, 0x1f, 0x06, 0x03,
+        0x55, 0x1d, 0x23, 0x04, 0x18, 0x30, 0x16, 0x80, 0x14, 0xd1, 0xf1, 0xa1, 0x52, 0xe3, 0x17,
+        0x4c, 0xf7, 0xd8, 0xd8, 0xed, 0x48, 0x08, 0x5f, 0x35, 0x03, 0x94, 0xd9, 0x3c, 0x3d, 0x30,
+        0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b, 0x05, 0x00, 0x03,
+        0x82, 0x01, 0x01, 0x00, 0x5e, 0x62, 0x0e, 0xaf, 0x92, 0x05, 0x5b, 0x7c, 0x97, 0x07, 0xce,
+        0xd5, 0xa0, 0xcd, 0x91, 0x42, 0x72, 0xb1, 0x5d, 0xfd, 0x0c, 0x77, 0x5e, 0xde, 0x64, 0x53,
+        0xb3, 0xba, 0xca, 0xcd, 0xd1, 0xe0, 0xc2, 0x4a, 0x83, 0xdd, 0xd6, 0xd8, 0xb0, 0x6f, 0x47,
+        0xb6, 0x34, 0x07, 0x7a, 0x46, 0x89, 0x87, 0xaf, 0x4d, 0xdc, 0x62, 0x80, 0x79, 0x15, 0x7b,
+        0x2a, 0x6e, 0x9d, 0x97, 0xa6, 0x93, 0xe4, 0xde, 0x29, 0xed, 0xa5, 0x9c, 0xfb, 0xfe, 0x44,
+        0x5c, 0x71, 0xa5, 0xea, 0x4f, 0x79, 0x7a, 0x67, 0x25, 0xef, 0x98, 0x11, 0xcf, 0xcd, 0x30,
+        0x46, 0xd1, 0x9c, 0xf4, 0x1e, 0xc8, 0x58, 0x1d, 0xb6, 0xce, 0x33, 0x1d, 0x49, 0x77, 0x41,
+        0x07, 0xab, 0x8d, 0xcd, 0x91, 0x8e, 0xe6, 0x4e, 0xef, 0xef, 0xbc, 0xe8, 0x6d, 0x3f, 0x0e,
+        0x8f, 0xfc, 0xf3, 0x13, 0x36, 0xd0, 0xac, 0xfa, 0xd5, 0xee, 0xec, 0x52, 0x73, 0xae, 0x06,
+        0x94, 0xcd, 0x4a, 0x72, 0x2a, 0x09, 0xfa, 0x5e, 0xe3, 0x10, 0x2f, 0xdd, 0xb1, 0x53, 0x53,
+        0x16, 0x74, 0x9e, 0xf9, 0xac, 0x35, 0xa8, 0x81, 0xb7, 0xfe, 0x2e, 0x54, 0xdb, 0x2e, 0xee,
+        0xdd, 0x3a, 0x7f, 0x5a, 0x50, 0xe4, 0x1a, 0xd1, 0x0b, 0x92, 0x4b, 0x50, 0x65, 0x39, 0x2a,
+        0xc8, 0x7f, 0x89, 0xe4, 0x5c, 0x06, 0x2e, 0x04, 0x9c, 0x13, 0xe5, 0xef, 0x53, 0x5c, 0xba,
+        0x4e, 0x72, 0x12, 0x20, 0x28, 0xde, 0xb3, 0xae, 0x48, 0x8d, 0x94, 0x04, 0x71, 0xe3, 0x56,
+        0x0d, 0x9a, 0xc4, 0x2f, 0xd5, 0x6c, 0x51, 0xad, 0xfb, 0x16, 0xfe, 0xec, 0xfd, 0x10, 0x38,
+        0x86, 0x31, 0xe7, 0x5f, 0x93, 0xa2, 0x8d, 0x6d, 0x48, 0xfc, 0x45, 0x1f, 0x76, 0x1d, 0x89,
+        0x09, 0x88, 0x40, 0xd7, 0x31, 0x6e, 0x76, 0x95, 0x23, 0x8f, 0x60, 0xcb, 0xb8, 0xf7, 0xcd,
+        0x75, 0x38, 0x6f, 0xb5, 0xb1,
+    ];
+    // C=US, O=Test, OU=Authenticator Attestation, CN=Test Leaf
+    const TEST_LEAF_CERT: [u8; 475] = [
+        0x30, 0x82, 0x01, 0xd7, 0x30, 0x82, 0x01, 0x7d, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x01,
+        0x03, 0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x30, 0x1f,
+        0x31, 0x1d, 0x30, 0x1b, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x14, 0x54, 0x65, 0x73, 0x74,
+        0x20, 0x49, 0x6e, 0x74, 0x65, 0x72, 0x6d, 0x65, 0x64, 0x69, 0x61, 0x74, 0x65, 0x20, 0x43,
+        0x41, 0x30, 0x20, 0x17, 0x0d, 0x32, 0x36, 0x31, 0x30, 0x31, 0x36, 0x31, 0x35, 0x35, 0x30,
+        0x32, 0x31, 0x5a, 0x18, 0x0f, 0x32, 0x31, 0x32, 0x36, 0x30, 0x39, 0x32, 0x32, 0x31, 0x35,
+        0x35, 0x30, 0x32, 0x31, 0x5a, 0x30, 0x54, 0x31, 0x0b, 0x30, 0x09, 0x06, 0x03, 0x55, 0x04,
+        0x06, 0x13, 0x02, 0x55, 0x53, 0x31, 0x0d, 0x30, 0x0b, 0x06, 0x03, 0x55, 0x04, 0x0a, 0x0c,
+        0x04, 0x54, 0x65, 0x73, 0x74, 0x31, 0x22, 0x30, 0x20, 0x06, 0x03, 0x55, 0x04, 0x0b, 0x0c,
+        0x19, 0x41, 0x75, 0x74, 0x68, 0x65, 0x6e, 0x74, 0x69, 0x63, 0x61, 0x74, 0x6f, 0x72, 0x20,
+        0x41, 0x74, 0x74, 0x65, 0x73, 0x74, 0x61, 0x74, 0x69, 0x6f, 0x6e, 0x31, 0x12, 0x30, 0x10,
+        0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x09, 0x54, 0x65, 0x73, 0x74, 0x20, 0x4c, 0x65, 0x61,
+        0x66, 0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06,
+        0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00, 0x04, 0x60, 0xe9,
+        0x7e, 0x36, 0x93, 0x39, 0x5b, 0x32, 0xbe, 0x65, 0x51, 0x75, 0xb2, 0x08, 0x16, 0x80, 0x5a,
+        0xa3, 0xae, 0x23, 0x19, 0x34, 0xf0, 0x74, 0xe5, 0x1e, 0xf1, 0x22, 0xa5, 0x75, 0xdf, 0x2b,
+        0x30, 0x20, 0x8d, 0x19, 0xaa, 0x71, 0x9b, 0xb7, 0x15, 0x22, 0x89, 0x6c, 0x52, 0x45, 0xaa,
+        0x93, 0xfb, 0xa4, 0x88, 0x0d, 0xbe, 0x22, 0x81, 0xb4, 0x83, 0xf5, 0x4d, 0x33, 0xc8, 0xdb,
+        0x1d, 0xb3, 0xa3, 0x73, 0x30, 0x71, 0x30, 0x0c, 0x06, 0x03, 0x55, 0x1d, 0x13, 0x01, 0x01,
+        0xff, 0x04, 0x02, 0x30, 0x00, 0x30, 0x21, 0x06, 0x0b, 0x2b, 0x06, 0x01, 0x04, 0x01, 0x82,
+        0xe5, 0x1c, 0x01, 0x01, 0x04, 0x04, 0x12, 0x04, 0x10, 0xf8, 0xa0, 0x11, 0xf3, 0x8c, 0x0a,
+        0x4d, 0x15, 0x80, 0x06, 0x17, 0x11, 0x1f, 0x9e, 0xdc, 0x7d, 0x30, 0x1d, 0x06, 0x03, 0x55,
+        0x1d, 0x0e, 0x04, 0x16, 0x04, 0x14, 0x3e, 0x43, 0x31, 0x15, 0xd1, 0xa3, 0xc6, 0x88, 0x40,
+        0x60, 0xfd, 0x1c, 0x29, 0x69, 0x23, 0x0d, 0x82, 0x66, 0x80, 0x25, 0x30, 0x1f, 0x06, 0x03,
+        0x55, 0x1d, 0x23, 0x04, 0x18, 0x30, 0x16, 0x80, 0x14, 0xc6, 0x05, 0x40, 0xcd, 0xde, 0x7d,
+        0xe7, 0x43, 0x3d, 0x0e, 0xf3, 0xa1, 0xf3, 0xf3, 0x04, 0x5b, 0xdf, 0xbc, 0x43, 0x44, 0x30,
+        0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x03, 0x48, 0x00, 0x30,
+        0x45, 0x02, 0x20, 0x1f, 0x56, 0x85, 0xce, 0x79, 0x06, 0x4e, 0x4b, 0x56, 0x59, 0x3d, 0x74,
+        0x5f, 0xb3, 0x01, 0xa2, 0x48, 0x21, 0x29, 0x0d, 0xa7, 0x26, 0xe4, 0x62, 0xf9, 0xbb, 0xa3,
+        0xaf, 0x85, 0x63, 0xd1, 0x44, 0x02, 0x21, 0x00, 0xe4, 0x63, 0x17, 0x38, 0xc8, 0xc0, 0xf7,
+        0xd7, 0x4b, 0x81, 0x0b, 0xcc, 0x81, 0x5d, 0x9e, 0xc5, 0x61, 0x66, 0x0d, 0xa2, 0x76, 0xa3,
+        0x7f, 0x04, 0x52, 0xd5, 0x67, 0xf5, 0x45, 0x29, 0x61, 0x4f,
+    ];
+    // A second chain, with a root that is only valid from 2026-10-16 16:48:03 UTC to
+    // 2028-06-07 16:48:03 UTC, and a leaf that issued another leaf. All keys are P-256, and
+    // the leaves are valid until 2126.
+    // CN=Test Short-lived Root CA
+    const TEST_SHORT_LIVED_ROOT_CERT: [u8; 363] = [
+        0x30, 0x82, 0x01, 0x67, 0x30, 0x82, 0x01, 0x0d, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x01,
+        0x01, 0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x30, 0x23,
+        0x31, 0x21, 0x30, 0x1f, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x18, 0x54, 0x65, 0x73, 0x74,
+        0x20, 0x53, 0x68, 0x6f, 0x72, 0x74, 0x2d, 0x6c, 0x69, 0x76, 0x65, 0x64, 0x20, 0x52, 0x6f,
+        0x6f, 0x74, 0x20, 0x43, 0x41, 0x30, 0x1e, 0x17, 0x0d, 0x32, 0x36, 0x31, 0x30, 0x31, 0x36,
+        0x31, 0x36, 0x34, 0x38, 0x30, 0x33, 0x5a, 0x17, 0x0d, 0x32, 0x38, 0x30, 0x36, 0x30, 0x37,
+        0x31, 0x36, 0x34, 0x38, 0x30, 0x33, 0x5a, 0x30, 0x23, 0x31, 0x21, 0x30, 0x1f, 0x06, 0x03,
+        0x55, 0x04, 0x03, 0x0c, 0x18, 0x54, 0x65, 0x73, 0x74, 0x20, 0x53, 0x68, 0x6f, 0x72, 0x74,
+        0x2d, 0x6c, 0x69, 0x76, 0x65, 0x64, 0x20, 0x52, 0x6f, 0x6f, 0x74, 0x20, 0x43, 0x41, 0x30,
+        0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+        0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00, 0x04, 0x91, 0xe0, 0x61, 0xc8,
+        0x71, 0x1e, 0xb5, 0xd8, 0x3f, 0xd1, 0x16, 0x75, 0x91, 0xa8, 0xdf, 0x4b, 0xbc, 0xe2, 0x1d,
+        0xc1, 0x88, 0x41, 0x02, 0x24, 0x64, 0xcd, 0xe2, 0x38, 0xf9, 0x95, 0x4b, 0xe0, 0x88, 0x84,
+        0x44, 0x5a, 0x0f, 0xd0, 0x01, 0x52, 0x7c, 0x08, 0x7e, 0x1c, 0x69, 0x4a, 0x19, 0x21, 0x9e,
+        0x29, 0x62, 0x87, 0x58, 0xb3, 0x06, 0x61, 0xdf, 0xab, 0x97, 0x3e, 0x22, 0xe2, 0xc1, 0x2f,
+        0xa3, 0x32, 0x30, 0x30, 0x30, 0x0f, 0x06, 0x03, 0x55, 0x1d, 0x13, 0x01, 0x01, 0xff, 0x04,
+        0x05, 0x30, 0x03, 0x01, 0x01, 0xff, 0x30, 0x1d, 0x06, 0x03, 0x55, 0x1d, 0x0e, 0x04, 0x16,
+        0x04, 0x14, 0xdb, 0xf3, 0xe6, 0x8c, 0x92, 0x8d, 0xe6, 0x3e, 0xd6, 0xcf, 0x28, 0xf3, 0xba,
+        0x63, 0x24, 0xe0, 0x4c, 0x01, 0xae, 0xf9, 0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce,
+        0x3d, 0x04, 0x03, 0x02, 0x03, 0x48, 0x00, 0x30, 0x45, 0x02, 0x20, 0x20, 0xac, 0xbb, 0x62,
+        0xdc, 0xf0, 0xd2, 0x91, 0x8d, 0x88, 0x2a, 0x7f, 0xe9, 0x60, 0x85, 0xf3, 0x44, 0xf6, 0xfe,
+        0x3f, 0x17, 0xe1, 0x9f, 0x88, 0xfe, 0x6f, 0x23, 0xc0, 0xaf, 0x2e, 0xbc, 0x3e, 0x02, 0x21,
+        0x00, 0xdb, 0x31, 0x23, 0x87, 0x4d, 0x13, 0x67, 0x54, 0x5a, 0x40, 0x21, 0xa4, 0xf1, 0x63,
+        0x08, 0x2f, 0x54, 0xad, 0x40, 0xae, 0x75, 0x58, 0x6a, 0x93, 0xcf, 0xf4, 0xae, 0xd8, 0x9f,
+        0x05, 0x3b, 0x11,
+    ];
+    // CN=Test Leaf Issuer, basicConstraints cA=FALSE, signed by the short-lived root
+    const TEST_LEAF_ISSUER_CERT: [u8; 388] = [
+        0x30, 0x82, 0x01, 0x80, 0x30, 0x82, 0x01, 0x25, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x01,
+        0x02, 0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x30, 0x23,
+        0x31, 0x21, 0x30, 0x1f, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x18, 0x54, 0x65, 0x73, 0x74,
+        0x20, 0x53, 0x68, 0x6f, 0x72, 0x74, 0x2d, 0x6c, 0x69, 0x76, 0x65, 0x64, 0x20, 0x52, 0x6f,
+        0x6f, 0x74, 0x20, 0x43, 0x41, 0x30, 0x20, 0x17, 0x0d, 0x32, 0x36, 0x31, 0x30, 0x31, 0x36,
+        0x31, 0x36, 0x34, 0x38, 0x30, 0x33, 0x5a, 0x18, 0x0f, 0x32, 0x31, 0x32, 0x36, 0x30, 0x39,
+        0x32, 0x32, 0x31, 0x36, 0x34, 0x38, 0x30, 0x33, 0x5a, 0x30, 0x1b, 0x31, 0x19, 0x30, 0x17,
+        0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x10, 0x54, 0x65, 0x73, 0x74, 0x20, 0x4c, 0x65, 0x61,
+        0x66, 0x20, 0x49, 0x73, 0x73, 0x75, 0x65, 0x72, 0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a,
+        0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01,
+        0x07, 0x03, 0x42, 0x00, 0x04, 0x6d, 0xe5, 0xdd, 0x96, 0xc6, 0x9f, 0x1e, 0xba, 0xda, 0xd3,
+        0x7c, 0x34, 0x18, 0xc0, 0xdc, 0xb9, 0x74, 0xe5, 0x48, 0x29, 0x99, 0x46, 0x06, 0x21, 0x8e,
+        0x09, 0xce, 0xce, 0x4b, 0x86, 0x8c, 0x9a, 0x46, 0xc8, 0xca, 0x4e, 0x1d, 0xd1, 0x62, 0xc2,
+        0x97, 0xc1, 0x63, 0x43, 0xc7, 0x50, 0x9c, 0x2f, 0xd8, 0xbb, 0x66, 0x63, 0xb7, 0xf9, 0xa1,
+        0x5d, 0xcc, 0x49, 0xa1, 0xf4, 0xa5, 0xd2, 0x2b, 0xa0, 0xa3, 0x50, 0x30, 0x4e, 0x30, 0x0c,
+        0x06, 0x03, 0x55, 0x1d, 0x13, 0x01, 0x01, 0xff, 0x04, 0x02, 0x30, 0x00, 0x30, 0x1d, 0x06,
+        0x03, 0x55, 0x1d, 0x0e, 0x04, 0x16, 0x04, 0x14, 0x56, 0x81, 0xa8, 0x4c, 0x14, 0xae, 0x25,
+        0x10, 0xaa, 0xed, 0x52, 0x27, 0x19, 0xcb, 0x6a, 0x78, 0xa6, 0x2c, 0x50, 0xd9, 0x30, 0x1f,
+        0x06, 0x03, 0x55, 0x1d, 0x23, 0x04, 0x18, 0x30, 0x16, 0x80, 0x14, 0xdb, 0xf3, 0xe6, 0x8c,
+        0x92, 0x8d, 0xe6, 0x3e, 0xd6, 0xcf, 0x28, 0xf3, 0xba, 0x63, 0x24, 0xe0, 0x4c, 0x01, 0xae,
+        0xf9, 0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x03, 0x49,
+        0x00, 0x30, 0x46, 0x02, 0x21, 0x00, 0xdf, 0x37, 0xaf, 0x52, 0x5c, 0xdc, 0x22, 0x03, 0x90,
+        0x10, 0x6b, 0x3b, 0xe9, 0xed, 0x3e, 0xa4, 0x16, 0x6a, 0xe3, 0x25, 0xe4, 0x0e, 0x57, 0x32,
+        0xae, 0x0d, 0xa4, 0x43, 0x98, 0x46, 0xe5, 0x67, 0x02, 0x21, 0x00, 0xc4, 0xa4, 0x0a, 0x45,
+        0x57, 0xad, 0xde, 0x7f, 0x5a, 0xa0, 0xb2, 0xe5, 0xe5, 0x12, 0x05, 0x91, 0x78, 0x12, 0x74,
+        0x84, 0x8e, 0xb2, 0x5a, 0x7f, 0xc6, 0x27, 0xd9, 0x2c, 0x5d, 0x2a, 0x40, 0x45,
+    ];
+    // CN=Test Leaf Issued By Leaf
+    const TEST_LEAF_ISSUED_BY_LEAF_CERT: [u8; 387] = [
+        0x30, 0x82, 0x01, 0x7f, 0x30, 0x82, 0x01, 0x25, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x01,
+        0x03, 0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x30, 0x1b,
+        0x31, 0x19, 0x30, 0x17, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x10, 0x54, 0x65, 0x73, 0x74,
+        0x20, 0x4c, 0x65, 0x61, 0x66, 0x20, 0x49, 0x73, 0x73, 0x75, 0x65, 0x72, 0x30, 0x20, 0x17,
+        0x0d, 0x32, 0x36, 0x31, 0x30, 0x31, 0x36, 0x31, 0x36, 0x34, 0x38, 0x30, 0x33, 0x5a, 0x18,
+        0x0f, 0x32, 0x31, 0x32, 0x36, 0x30, 0x39, 0x32, 0x32, 0x31, 0x36, 0x34, 0x38, 0x30, 0x33,
+        0x5a, 0x30, 0x23, 0x31, 0x21, 0x30, 0x1f, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x18, 0x54,
+        0x65, 0x73, 0x74, 0x20, 0x4c, 0x65, 0x61, 0x66, 0x20, 0x49, 0x73, 0x73, 0x75, 0x65, 0x64,
+        0x20, 0x42, 0x79, 0x20, 0x4c, 0x65, 0x61, 0x66, 0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a,
+        0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01,
+        0x07, 0x03, 0x42, 0x00, 0x04, 0xc2, 0xe4, 0x34, 0x9f, 0xbe, 0xec, 0xc5, 0x31, 0x74, 0x37,
+        0x40, 0xe1, 0x1e, 0xbe, 0xec, 0x5e, 0xfb, 0xf0, 0x7b, 0x56, 0xc6, 0x20, 0x4a, 0xa1, 0x0a,
+        0xae, 0x55, 0xb7, 0xae, 0x25, 0x05, 0x3b, 0x87, 0x51, 0xa0, 0x23, 0x4c, 0x36, 0x66, 0xcc,
+        0xd1, 0xdd, 0x77, 0x8a, 0xce, 0x73, 0x16, 0xba, 0xdd, 0x52, 0x97, 0xcb, 0x25, 0xff, 0x22,
+        0xee, 0xc2, 0xe2, 0x1e, 0xda, 0xe4, 0x28, 0x94, 0xc9, 0xa3, 0x50, 0x30, 0x4e, 0x30, 0x0c,
+        0x06, 0x03, 0x55, 0x1d, 0x13, 0x01, 0x01, 0xff, 0x04, 0x02, 0x30, 0x00, 0x30, 0x1d, 0x06,
+        0x03, 0x55, 0x1d, 0x0e, 0x04, 0x16, 0x04, 0x14, 0x00, 0xed, 0x6e, 0x79, 0xa4, 0x5b, 0x53,
+        0x2a, 0x15, 0xb7, 0x13, 0xa7, 0x7d, 0xf0, 0x8d, 0x54, 0x12, 0x66, 0x96, 0x76, 0x30, 0x1f,
+        0x06, 0x03, 0x55, 0x1d, 0x23, 0x04, 0x18, 0x30, 0x16, 0x80, 0x14, 0x56, 0x81, 0xa8, 0x4c,
+        0x14, 0xae, 0x25, 0x10, 0xaa, 0xed, 0x52, 0x27, 0x19, 0xcb, 0x6a, 0x78, 0xa6, 0x2c, 0x50,
+        0xd9, 0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x03, 0x48,
+        0x00, 0x30, 0x45, 0x02, 0x20, 0x37, 0xb3, 0x12, 0x0e, 0xc1, 0x81, 0xfd, 0xcc, 0x42, 0x2e,
+        0x32, 0xf6, 0x59, 0xb0, 0xde, 0xdd, 0x09, 0xbd, 0xa1, 0xaa, 0x6e, 0xc9, 0x30, 0x2f, 0x8a,
+        0xc5, 0x35, 0xf5, 0x3e, 0x3c, 0xbe, 0x7f, 0x02, 0x21, 0x00, 0xf6, 0x53, 0xee, 0x79, 0x0d,
+        0x3b, 0xb0, 0x29, 0xa9, 0xf5, 0x60, 0x87, 0xf2, 0x2a, 0x52, 0x95, 0x85, 0xff, 0xbc, 0x80,
+        0x77, 0xb5, 0x05, 0x60, 0x29, 0x0f, 0x65, 0xb9, 0x3a, 0x4c, 0xef, 0x2e,
+    ];
+
+    // A CBOR map with text keys, like attestation statements
+    pub fn text_map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (Value::Text(key.to_string()), value))
+                .collect(),
+        )
+    }
+
+    // An encoded attestation object of format `fmt`, with the authenticator data of
+    // `create_attestation_obj()`
+    pub fn encode_test_attestation_obj(fmt: &str, att_stmt: Value) -> Vec<u8> {
+        let auth_data = create_attestation_obj().auth_data.to_vec();
+        to_vec(&text_map(vec![
+            ("fmt", Value::Text(fmt.to_string())),
+            ("attStmt", att_stmt),
+            ("authData", Value::Bytes(auth_data)),
+        ]))
+        .unwrap()
+    }
+
+    // A certificate with the given extensions, and nothing else of importance
+    pub fn create_test_certificate(extensions: &[(&[u8], &[u8])]) -> Vec<u8> {
+        let extensions: Vec<Vec<u8>> = extensions
+            .iter()
+            .map(|(oid, value)| {
+                der::sequence(&[
+                    &der::object_id(oid).unwrap(),
+                    &der::octet_string(value).unwrap(),
+                ])
+                .unwrap()
+            })
+            .collect();
+        let extensions: Vec<&[u8]> = extensions.iter().map(Vec::as_slice).collect();
+        let algorithm = der::sequence(&[&der::object_id(der::OID_RS256_BYTES).unwrap()]).unwrap();
+        let empty = der::sequence(&[]).unwrap();
+        let tbs = der::sequence(&[
+            &der::context_specific_explicit_tag(0, &der::integer(&[2]).unwrap()).unwrap(),
+            &der::integer(&[1]).unwrap(), // serialNumber
+            &algorithm,
+            &empty, // issuer
+            &empty, // validity
+            &empty, // subject
+            &empty, // subjectPublicKeyInfo
+            &der::context_specific_explicit_tag(3, &der::sequence(&extensions).unwrap()).unwrap(),
+        ])
+        .unwrap();
+        der::sequence(&[&tbs, &algorithm, &der::bit_string(&[0x00; 8]).unwrap()]).unwrap()
+    }
+    use sha2::{Digest, Sha256};
 
     const SAMPLE_ATTESTATION_STMT_NONE: [u8; 19] = [
         0xa2, // map(2)
@@ -1102,6 +2324,57 @@ pub mod test {
         assert!(with_flag.len() == without_flag.len() + 1);
     }
 
+    #[test]
+    fn test_auth_data_from_bytes() {
+        let raw = &SAMPLE_AUTH_DATA_MAKE_CREDENTIAL[2..];
+        let parsed: AuthenticatorData = from_slice(&SAMPLE_AUTH_DATA_MAKE_CREDENTIAL).unwrap();
+        assert_eq!(AuthenticatorData::from_bytes(raw).unwrap(), parsed);
+
+        let layout = AuthenticatorData::layout(raw).unwrap();
+        assert_eq!(
+            layout,
+            AuthenticatorDataLayout {
+                rp_id_hash: 0..32,
+                flags: 32..33,
+                counter: 33..37,
+                credential_data: Some(37..148),
+                extensions: Some(148..162),
+            }
+        );
+        assert_eq!(raw[layout.rp_id_hash], parsed.rp_id_hash.0);
+        assert_eq!(raw[layout.flags], [parsed.flags.bits()]);
+        assert_eq!(raw[layout.counter], parsed.counter.to_be_bytes());
+
+        // Trailing data
+        let mut trailing = raw.to_vec();
+        trailing.push(0x00);
+        assert!(AuthenticatorData::from_bytes(&trailing).is_err());
+
+        // Truncated
+        assert!(AuthenticatorData::from_bytes(&raw[..147]).is_err());
+        assert!(AuthenticatorData::from_bytes(&raw[..36]).is_err());
+    }
+
+    #[test]
+    fn test_auth_data_backup_flags() {
+        let mut raw = SAMPLE_AUTH_DATA_MAKE_CREDENTIAL[2..].to_vec();
+        let parsed = AuthenticatorData::from_bytes(&raw).unwrap();
+        assert!(!parsed.is_backup_eligible());
+        assert!(!parsed.is_backed_up());
+
+        raw[32] |= 0x08; // BE
+        let parsed = AuthenticatorData::from_bytes(&raw).unwrap();
+        assert!(parsed.is_backup_eligible());
+        assert!(!parsed.is_backed_up());
+
+        raw[32] |= 0x10; // BS
+        let parsed = AuthenticatorData::from_bytes(&raw).unwrap();
+        assert!(parsed.is_backup_eligible());
+        assert!(parsed.is_backed_up());
+        // The flags survive a round trip
+        assert_eq!(parsed.to_vec(), raw);
+    }
+
     /// See: https://github.com/mozilla/authenticator-rs/issues/187
     #[test]
     fn test_aaguid_output() {
@@ -1115,6 +2388,31 @@ pub mod test {
         assert_eq!(expected, &res_str);
     }
 
+    #[cfg(feature = "aaguid_metadata")]
+    #[test]
+    fn test_aaguid_metadata() {
+        // YubiKey 5 (USB-A, firmware 5.1)
+        let aaguid = AAGuid([
+            0xee, 0x88, 0x28, 0x79, 0x72, 0x1c, 0x49, 0x13, 0x97, 0x75, 0x3d, 0xfc, 0xce, 0x97,
+            0x07, 0x2a,
+        ]);
+        let metadata = aaguid.metadata().expect("Unknown AAGUID");
+        assert_eq!(metadata.name, "YubiKey 5 Series");
+        assert_eq!(metadata.icon, None);
+        assert_eq!(metadata.transports, vec![Transport::USB]);
+
+        let metadata = AAGuid([
+            0xfa, 0x2b, 0x99, 0xdc, 0x9e, 0x39, 0x42, 0x57, 0x8f, 0x92, 0x4a, 0x30, 0xd2, 0x3c,
+            0x41, 0x18,
+        ])
+        .metadata()
+        .expect("Unknown AAGUID");
+        assert_eq!(metadata.name, "YubiKey 5 Series with NFC");
+        assert_eq!(metadata.transports, vec![Transport::NFC, Transport::USB]);
+
+        assert_eq!(AAGuid::default().metadata(), None);
+    }
+
     #[test]
     fn test_ad_flags_from_bits() {
         // Check that AuthenticatorDataFlags is defined on the entire u8 range and that
@@ -1126,4 +2424,1314 @@ pub mod test {
             );
         }
     }
+
+    #[test]
+    fn test_verify_ed25519_self_attestation() {
+        // Generated with pyca/cryptography, using the key of RFC 8032, test 1
+        //   sk = Ed25519PrivateKey.from_private_bytes(bytes.fromhex("9d61b1...ae7f60"))
+        //   sig = sk.sign(auth_data + sha256(b"client data"))
+        let auth_data = AuthenticatorData::from_bytes(&[
+            0xa3, 0x79, 0xa6, 0xf6, 0xee, 0xaf, 0xb9, 0xa5, 0x5e, 0x37, 0x8c, 0x11, 0x80, 0x34,
+            0xe2, 0x75, 0x1e, 0x68, 0x2f, 0xab, 0x9f, 0x2d, 0x30, 0xab, 0x13, 0xd2, 0x12, 0x55,
+            0x86, 0xce, 0x19, 0x47, // sha256("example.com")
+            0x41, // UP | AT
+            0x00, 0x00, 0x00, 0x00, // counter
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, // aaguid
+            0x00, 0x04, // credential id length
+            0x01, 0x02, 0x03, 0x04, // credential id
+            0xa4, // map(4)
+            0x01, 0x01, // kty: OKP
+            0x03, 0x27, // alg: EdDSA
+            0x20, 0x06, // crv: Ed25519
+            0x21, 0x58, 0x20, // x: bytes(32)
+            0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7, 0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64,
+            0x07, 0x3a, 0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25, 0xaf, 0x02, 0x1a, 0x68,
+            0xf7, 0x07, 0x51, 0x1a,
+        ])
+        .expect("Failed to parse authenticator data");
+        let sig = [
+            0xec, 0xa2, 0xc8, 0xf2, 0x1e, 0xf0, 0x10, 0x2e, 0x28, 0x44, 0xcd, 0xc2, 0x5d, 0x6b,
+            0xe6, 0xc5, 0x15, 0x27, 0x9f, 0x17, 0x4d, 0xa2, 0xc6, 0x58, 0x98, 0x3f, 0x3d, 0xdb,
+            0x8b, 0x0e, 0x67, 0x7a, 0x8f, 0x1a, 0xe5, 0x67, 0x98, 0x97, 0xab, 0x87, 0xc6, 0x8c,
+            0xb0, 0x03, 0x6b, 0x60, 0xb2, 0x94, 0xb3, 0xae, 0x67, 0xf7, 0x13, 0x2c, 0x80, 0x30,
+            0xac, 0x22, 0x03, 0x17, 0xbd, 0x1a, 0x0f, 0x03,
+        ];
+        let client_data_hash = ClientDataHash(Sha256::digest(b"client data").into());
+
+        let mut att_obj = AttestationObject {
+            auth_data,
+            att_stmt: AttestationStatement::Packed(AttestationStatementPacked {
+                alg: COSEAlgorithm::EDDSA,
+                sig: Signature(sig.to_vec()),
+                attestation_cert: vec![],
+            }),
+        };
+        assert!(att_obj.verify(&client_data_hash).is_ok());
+
+        if let AttestationStatement::Packed(ref mut att) = att_obj.att_stmt {
+            assert!(att.is_self_attestation());
+            att.alg = COSEAlgorithm::ES256;
+        }
+        assert_matches!(
+            att_obj.verify(&client_data_hash),
+            Err(CryptoError::AlgorithmMismatch(
+                COSEAlgorithm::ES256,
+                COSEAlgorithm::EDDSA
+            ))
+        );
+        if let AttestationStatement::Packed(ref mut att) = att_obj.att_stmt {
+            att.alg = COSEAlgorithm::EDDSA;
+        }
+
+        // Ed25519 signs the data itself, not a hash of it
+        let wrong_hash = ClientDataHash(Sha256::digest(b"other client data").into());
+        assert_matches!(
+            att_obj.verify(&wrong_hash),
+            Err(CryptoError::VerificationFailed)
+        );
+
+        if let AttestationStatement::Packed(ref mut att) = att_obj.att_stmt {
+            att.sig.0[0] ^= 0x01;
+        }
+        assert_matches!(
+            att_obj.verify(&client_data_hash),
+            Err(CryptoError::VerificationFailed)
+        );
+    }
+
+    // Authenticator data for example.com, with UP and AT set, a zero AAGUID, and the credential
+    // id 01020304, followed by the given COSE encoded credential public key.
+    fn self_attestation_auth_data(credential_public_key: &[u8]) -> AuthenticatorData {
+        let mut auth_data = vec![
+            0xa3, 0x79, 0xa6, 0xf6, 0xee, 0xaf, 0xb9, 0xa5, 0x5e, 0x37, 0x8c, 0x11, 0x80, 0x34,
+            0xe2, 0x75, 0x1e, 0x68, 0x2f, 0xab, 0x9f, 0x2d, 0x30, 0xab, 0x13, 0xd2, 0x12, 0x55,
+            0x86, 0xce, 0x19, 0x47, // sha256("example.com")
+            0x41, // UP | AT
+            0x00, 0x00, 0x00, 0x00, // counter
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, // aaguid
+            0x00, 0x04, // credential id length
+            0x01, 0x02, 0x03, 0x04, // credential id
+        ];
+        auth_data.extend_from_slice(credential_public_key);
+        AuthenticatorData::from_bytes(&auth_data).expect("Failed to parse authenticator data")
+    }
+
+    fn self_attestation_obj(
+        credential_public_key: &[u8],
+        alg: COSEAlgorithm,
+        sig: &[u8],
+    ) -> AttestationObject {
+        AttestationObject {
+            auth_data: self_attestation_auth_data(credential_public_key),
+            att_stmt: AttestationStatement::Packed(AttestationStatementPacked {
+                alg,
+                sig: Signature(sig.to_vec()),
+                attestation_cert: vec![],
+            }),
+        }
+    }
+
+    #[test]
+    fn test_verify_es256_self_attestation() {
+        // Generated with pyca/cryptography
+        //   sk = ec.generate_private_key(ec.SECP256R1())
+        //   sig = sk.sign(auth_data + sha256(b"client data"), ec.ECDSA(hashes.SHA256()))
+        // kty: EC2, alg: ES256, crv: P-256, x, y
+        let credential_public_key = [
+            0xa5, 0x01, 0x02, 0x03, 0x26, 0x20, 0x01, 0x21, 0x58, 0x20, 0xbc, 0xb8, 0xf2, 0x06,
+            0x12, 0x3c, 0xf3, 0xbd, 0x67, 0x65, 0x27, 0x0d, 0x92, 0xf9, 0x28, 0x68, 0x4f, 0xf3,
+            0x1f, 0xd2, 0xc3, 0x15, 0x65, 0xfe, 0x60, 0x3e, 0x78, 0x53, 0xfc, 0x87, 0x8f, 0x34,
+            0x22, 0x58, 0x20, 0x9f, 0x7c, 0xeb, 0x72, 0x95, 0xb9, 0xd8, 0xb1, 0x41, 0x8b, 0xbe,
+            0x77, 0x8f, 0xce, 0x5a, 0xc5, 0x6e, 0x83, 0xee, 0x19, 0x8b, 0xae, 0x80, 0xea, 0x47,
+            0xb3, 0x92, 0xa4, 0x77, 0xc4, 0x9c, 0x66,
+        ];
+        let sig = [
+            0x30, 0x44, 0x02, 0x20, 0x77, 0xde, 0x5f, 0xbe, 0x1c, 0xbc, 0xe4, 0x73, 0x43, 0xc1,
+            0x43, 0xca, 0x52, 0xb2, 0xce, 0xd2, 0xf3, 0xd4, 0x98, 0xbc, 0x6a, 0xdf, 0x18, 0x86,
+            0xad, 0x32, 0x2a, 0xdb, 0x96, 0x7a, 0x4a, 0xa9, 0x02, 0x20, 0x67, 0x56, 0xae, 0x58,
+            0x6e, 0x0f, 0x75, 0x79, 0x77, 0xd2, 0x5f, 0x74, 0xb0, 0x1d, 0x34, 0xa5, 0x99, 0x71,
+            0x53, 0xeb, 0x1d, 0xde, 0x74, 0xc4, 0x82, 0xa5, 0x7f, 0x27, 0x7f, 0x9c, 0x48, 0xcb,
+        ];
+        let client_data_hash = ClientDataHash(Sha256::digest(b"client data").into());
+        let att_obj = self_attestation_obj(&credential_public_key, COSEAlgorithm::ES256, &sig);
+        assert!(att_obj.verify(&client_data_hash).is_ok());
+
+        let wrong_hash = ClientDataHash(Sha256::digest(b"other client data").into());
+        assert_matches!(
+            att_obj.verify(&wrong_hash),
+            Err(CryptoError::VerificationFailed)
+        );
+        let att_obj = self_attestation_obj(&credential_public_key, COSEAlgorithm::RS256, &sig);
+        assert_matches!(
+            att_obj.verify(&client_data_hash),
+            Err(CryptoError::AlgorithmMismatch(
+                COSEAlgorithm::RS256,
+                COSEAlgorithm::ES256
+            ))
+        );
+    }
+
+    #[test]
+    fn test_verify_rs256_self_attestation() {
+        // Generated with pyca/cryptography
+        //   sk = rsa.generate_private_key(65537, 2048)
+        //   sig = sk.sign(auth_data + sha256(b"client data"), padding.PKCS1v15(), hashes.SHA256())
+        // kty: RSA, alg: RS256, n, e: 65537
+        let credential_public_key = [
+            0xa4, 0x01, 0x03, 0x03, 0x39, 0x01, 0x00, 0x20, 0x59, 0x01, 0x00, 0xc2, 0xbc, 0x36,
+            0x6a, 0x2f, 0x50, 0xe3, 0xb3, 0xf6, 0x96, 0x68, 0x47, 0x69, 0xc9, 0xb3, 0x81, 0x0f,
+            0x04, 0xc2, 0xd2, 0x3e, 0x8f, 0x36, 0xa0, 0x86, 0x09, 0x81, 0xac, 0x8c, 0x60, 0x49,
+            0x03, 0xef, 0x32, 0x46, 0x26, 0x9d, 0x96, 0x66, 0x25, 0xcf, 0x2f, 0x46, 0x12, 0xcc,
+            0xce, 0x0d, 0x47, 0xd0, 0x23, 0xb1, 0xc6, 0x18, 0x0e, 0xa1, 0x7e, 0xc3, 0xe1, 0xc3,
+            0x57, 0xa9, 0x1e, 0x8c, 0xb2, 0xdc, 0xed, 0xff, 0x3b, 0x89, 0x6f, 0xd3, 0x5f, 0x6f,
+            0x11, 0x9b, 0x06, 0x26, 0xa9, 0xf7, 0x1b, 0x5f, 0x1c, 0xe9, 0x44, 0x4a, 0xca, 0x4f,
+            0x7e, 0x68, 0xb4, 0x89, 0x20, 0x1e, 0xf1, 0xda, 0x6c, 0xb5, 0xbb, 0xfe, 0xc3, 0xfd,
+            0x0b, 0x08, 0x3f, 0x30, 0x38, 0xcf, 0x61, 0x70, 0xce, 0x5d, 0xf8, 0x0a, 0xcc, 0x1f,
+            0xb5, 0x62, 0x27, 0xed, 0xc0, 0x92, 0xf8, 0x05, 0xd1, 0xcf, 0x0d, 0x60, 0x4c, 0x25,
+            0x61, 0xec, 0x62, 0xf0, 0xc3, 0xf9, 0x39, 0x55, 0xe1, 0x53, 0x2e, 0xd8, 0x12, 0x22,
+            0xea, 0xa4, 0x53, 0x32, 0x26, 0x44, 0x54, 0x3b, 0x44, 0xef, 0xcb, 0x8b, 0xcb, 0xdb,
+            0x37, 0x8a, 0x6c, 0xb4, 0x02, 0x5c, 0xdb, 0xe2, 0xf6, 0x05, 0x40, 0x84, 0x0f, 0x94,
+            0x7b, 0x9a, 0xf0, 0xaf, 0x7a, 0xb7, 0x37, 0xdd, 0xeb, 0x38, 0x10, 0x08, 0x1e, 0x47,
+            0xbc, 0x1b, 0xc1, 0x25, 0x97, 0x5e, 0xbb, 0x4c, 0xcb, 0x57, 0x34, 0xfa, 0x61, 0xa7,
+            0xf4, 0x5a, 0x05, 0x21, 0xe5, 0xab, 0x0a, 0x38, 0x43, 0xb1, 0x1d, 0x47, 0x77, 0xd8,
+            0x30, 0xeb, 0x5f, 0x84, 0x01, 0x7d, 0xbd, 0x12, 0x54, 0x7d, 0x2c, 0x14, 0xd0, 0x35,
+            0xa1, 0xa0, 0x79, 0x2c, 0xde, 0xb0, 0x9b, 0x03, 0x3e, 0xa5, 0x86, 0x1d, 0x94, 0x5e,
+            0x76, 0x68, 0xc4, 0x59, 0x45, 0x4a, 0xb6, 0x44, 0x50, 0x2d, 0x55, 0x22, 0xdc, 0xfc,
+            0xf3, 0x21, 0x43, 0x01, 0x00, 0x01,
+        ];
+        let mut sig = [
+            0xa4, 0x17, 0x73, 0xcd, 0x94, 0xaf, 0xf4, 0x1d, 0x94, 0x80, 0x5c, 0x0c, 0x63, 0x1a,
+            0x13, 0x39, 0x9a, 0x10, 0xea, 0x2c, 0xa3, 0x2e, 0xa9, 0xb9, 0x6f, 0x45, 0x72, 0xcb,
+            0x99, 0x78, 0x4e, 0x46, 0xcc, 0x1f, 0xd3, 0x8d, 0x1c, 0xaf, 0xc2, 0x1e, 0xcc, 0x6d,
+            0xf2, 0x9c, 0xf7, 0xf4, 0x87, 0x6c, 0x53, 0xf0, 0xa3, 0x49, 0x30, 0x02, 0xd3, 0xa3,
+            0x4c, 0xdf, 0x08, 0x64, 0x4c, 0x1e, 0x52, 0x96, 0x5f, 0x0e, 0xfa, 0xb7, 0x9f, 0x6b,
+            0xb9, 0xf5, 0x32, 0x48, 0xcc, 0x4a, 0xd9, 0x2c, 0x20, 0x37, 0x1a, 0xd2, 0x27, 0xa9,
+            0x2b, 0x59, 0x29, 0xe2, 0x95, 0x0e, 0xb5, 0x5c, 0x39, 0xc0, 0x6d, 0x37, 0x6d, 0x97,
+            0x7f, 0x64, 0x7e, 0x57, 0x70, 0xda, 0x07, 0x0c, 0xbe, 0xd1, 0x66, 0xa9, 0x48, 0x8f,
+            0xc5, 0xa1, 0x13, 0xa5, 0x56, 0x6c, 0x9b, 0xe1, 0x58, 0xb2, 0xb7, 0x39, 0xb4, 0x36,
+            0x42, 0x25, 0x87, 0x51, 0xfe, 0xd7, 0x5c, 0x93, 0x8b, 0x66, 0x9d, 0xb6, 0x6f, 0x45,
+            0xb6, 0x3a, 0x05, 0x19, 0xbd, 0x10, 0x2a, 0x36, 0x95, 0xe0, 0x8e, 0xca, 0x13, 0x81,
+            0x38, 0xaf, 0x44, 0xd2, 0x55, 0x0a, 0xdb, 0x24, 0xb2, 0xa0, 0xa7, 0xec, 0x51, 0xf7,
+            0x24, 0xb5, 0x89, 0xf0, 0xca, 0xf4, 0x9d, 0xd2, 0x2d, 0xb1, 0x4a, 0xab, 0x80, 0x6a,
+            0xca, 0x1f, 0xf2, 0x4f, 0x67, 0x81, 0x06, 0x0c, 0x73, 0xce, 0x3a, 0x07, 0x31, 0x7a,
+            0xc8, 0x71, 0x46, 0xdd, 0x5f, 0xb1, 0x37, 0xa1, 0x40, 0xe1, 0x0c, 0x5b, 0x4c, 0xa7,
+            0xa3, 0x39, 0xc7, 0xe9, 0xd0, 0x40, 0x25, 0x92, 0x5e, 0x70, 0x11, 0xae, 0x3e, 0x57,
+            0xdd, 0x93, 0x8d, 0xdc, 0xf4, 0x4e, 0xc4, 0x30, 0xb9, 0xa3, 0xb8, 0xf5, 0x6b, 0x77,
+            0x89, 0x54, 0x3d, 0x0f, 0x5b, 0xbe, 0x51, 0x19, 0x0b, 0x37, 0x4b, 0xc8, 0xa3, 0x63,
+            0x23, 0xda, 0xe2, 0xce,
+        ];
+        let client_data_hash = ClientDataHash(Sha256::digest(b"client data").into());
+        let att_obj = self_attestation_obj(&credential_public_key, COSEAlgorithm::RS256, &sig);
+        assert!(att_obj.verify(&client_data_hash).is_ok());
+
+        let wrong_hash = ClientDataHash(Sha256::digest(b"other client data").into());
+        assert_matches!(
+            att_obj.verify(&wrong_hash),
+            Err(CryptoError::VerificationFailed)
+        );
+        sig[0] ^= 0x01;
+        let att_obj = self_attestation_obj(&credential_public_key, COSEAlgorithm::RS256, &sig);
+        assert_matches!(
+            att_obj.verify(&client_data_hash),
+            Err(CryptoError::VerificationFailed)
+        );
+    }
+
+    // A TPM attestation object, as Windows Hello creates it: an RSA 2048 credential key in
+    // the TPM, certified with RS1 by an attestation identity key (AIK). The AIK certificate has
+    // an empty subject, the TCG subjectAltName and the tcg-kp-AIKCertificate EKU, and is issued
+    // by an intermediate of the TPM vendor. The object was created with OpenSSL and a separate
+    // CBOR encoder, so it does not depend on the encoders of this crate.
+    // All certificates are valid from 2024-06-01 to 2044-06-01.
+    pub const SAMPLE_ATTESTATION_OBJECT_TPM: [u8; 2807] = [
+        0xa3, 0x63, 0x66, 0x6d, 0x74, 0x63, 0x74, 0x70, 0x6d, 0x67, 0x61, 0x74, 0x74, 0x53, 0x74,
+        0x6d, 0x74, 0xa6, 0x63, 0x61, 0x6c, 0x67, 0x39, 0xff, 0xfe, 0x63, 0x73, 0x69, 0x67, 0x59,
+        0x01, 0x00, 0x4e, 0x7b, 0x55, 0x8e, 0xd9, 0x0c, 0x76, 0x97, 0x95, 0xb1, 0x80, 0x49, 0x9c,
+        0xd5, 0x91, 0x8a, 0x83, 0x4b, 0x78, 0xb1, 0xc2, 0xea, 0xaf, 0xc7, 0x2c, 0xe9, 0xa8, 0xd3,
+        0x0f, 0xcb, 0xce, 0x78, 0x79, 0xf9, 0xcf, 0x16, 0xce, 0x3d, 0x95, 0x5e, 0x3d, 0x62, 0xde,
+        0x37, 0xce, 0x45, 0x32, 0xb1, 0x5e, 0x67, 0x0c, 0x0b, 0x86, 0xee, 0x87, 0x7f, 0x02, 0x97,
+        0xd0, 0x7a, 0x56, 0xce, 0xbb, 0x38, 0x54, 0x21, 0x2e, 0xce, 0x56, 0xde, 0xb1, 0x9b, 0x1d,
+        0x33, 0x2c, 0xef, 0xa5, 0xc8, 0xe4, 0xbc, 0x2a, 0x52, 0x88, 0xee, 0xc1, 0x64, 0x12, 0xbe,
+        0xb9, 0x68, 0xe9, 0xb7, 0xa7, 0xca, 0x75, 0xd6, 0x8f, 0x97, 0x75, 0xca, 0x3b, 0x06, 0x2d,
+        0xef, 0x4a, 0x18, 0xad, 0x99, 0x2c, 0x77, 0x25, 0x88, 0xef, 0x99, 0x27, 0x18, 0x58, 0xd4,
+        0xbf, 0x57, 0x7d, 0xdf, 0xaa, 0x3a, 0x6a, 0xce, 0x64, 0xa2, 0xfd, 0x4f, 0x56, 0xff, 0x3b,
+        0x2b, 0x61, 0x80, 0x57, 0xcb, 0xcb, 0xb7, 0x26, 0x24, 0xe8, 0x57, 0xb1, 0xba, 0xe9, 0xd6,
+        0xfd, 0xb0, 0xb8, 0x20, 0x4d, 0x03, 0x2e, 0x20, 0x39, 0x33, 0xe7, 0xed, 0xea, 0xab, 0x41,
+        0x46, 0x9e, 0xfb, 0xdd, 0xc1, 0x37, 0x42, 0x8a, 0x60, 0xd1, 0x23, 0xec, 0x55, 0x83, 0x9c,
+        0xeb, 0xb1, 0x35, 0x84, 0xb6, 0x6e, 0x56, 0x7e, 0xb2, 0x8f, 0xec, 0x4b, 0x73, 0xa2, 0x39,
+        0xa0, 0x68, 0x43, 0xbc, 0x55, 0x1b, 0xe9, 0xa4, 0xf6, 0xe3, 0x22, 0xb3, 0xdc, 0xa0, 0xca,
+        0xb9, 0xa1, 0x16, 0xe8, 0x1f, 0x20, 0xe2, 0x3a, 0x8d, 0x7c, 0xe3, 0x00, 0x4f, 0x40, 0x7b,
+        0xfb, 0x73, 0x41, 0x7d, 0xf3, 0xa2, 0x70, 0x4c, 0xff, 0xf6, 0x94, 0x7d, 0x1b, 0xa0, 0xb5,
+        0x08, 0x2d, 0xe1, 0x49, 0x53, 0x8c, 0x15, 0x0c, 0xaa, 0x4e, 0x94, 0x96, 0x2f, 0xc9, 0xc4,
+        0xe4, 0x65, 0x2f, 0x63, 0x76, 0x65, 0x72, 0x63, 0x32, 0x2e, 0x30, 0x63, 0x78, 0x35, 0x63,
+        0x82, 0x59, 0x03, 0x33, 0x30, 0x82, 0x03, 0x2f, 0x30, 0x82, 0x02, 0x17, 0xa0, 0x03, 0x02,
+        0x01, 0x02, 0x02, 0x08, 0x1f, 0x2e, 0x3d, 0x4c, 0x5b, 0x6a, 0x79, 0x88, 0x30, 0x0d, 0x06,
+        0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b, 0x05, 0x00, 0x30, 0x2f, 0x31,
+        0x2d, 0x30, 0x2b, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x24, 0x45, 0x55, 0x53, 0x2d, 0x45,
+        0x58, 0x4d, 0x50, 0x4c, 0x2d, 0x4b, 0x45, 0x59, 0x49, 0x44, 0x2d, 0x39, 0x46, 0x34, 0x41,
+        0x37, 0x43, 0x32, 0x42, 0x31, 0x45, 0x30, 0x44, 0x38, 0x46, 0x36, 0x41, 0x35, 0x42, 0x33,
+        0x43, 0x30, 0x1e, 0x17, 0x0d, 0x32, 0x34, 0x30, 0x36, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30,
+        0x30, 0x30, 0x5a, 0x17, 0x0d, 0x34, 0x34, 0x30, 0x36, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30,
+        0x30, 0x30, 0x5a, 0x30, 0x00, 0x30, 0x82, 0x01, 0x22, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86,
+        0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01, 0x05, 0x00, 0x03, 0x82, 0x01, 0x0f, 0x00, 0x30,
+        0x82, 0x01, 0x0a, 0x02, 0x82, 0x01, 0x01, 0x00, 0xc5, 0x69, 0x17, 0xcf, 0x1e, 0xa5, 0xcc,
+        0x82, 0x3c, 0x49, 0x02, 0xbe, 0xd1, 0xfc, 0x1e, 0x91, 0xa1, 0x71, 0x9f, 0xbc, 0x30, 0x35,
+        0xb1, 0x27, 0x5f, 0xa2, 0xca, 0xdd, 0x98, 0x0e, 0xa4, 0xf6, 0xa6, 0x1a, 0x40, 0xe5, 0x27,
+        0xbf, 0x58, 0x96, 0xd8, 0x62, 0x6c, 0xb6, 0x12, 0x6c, 0x13, 0x31, 0x28, 0x81, 0xab, 0x94,
+        0xd7, 0x02, 0x51, 0x79, 0xa1, 0x09, 0x93, 0xcf, 0x5f, 0x81, 0xe2, 0x33, 0x02, 0xc9, 0x7f,
+        0xa9, 0xb6, 0x19, 0xc4, 0x80, 0x26, 0xc9, 0x26, 0x48, 0x08, 0xc8, 0x6a, 0x8e, 0x28, 0xf0,
+        0xca, 0x8b, 0xdf, 0xa1, 0x12, 0x97, 0xc6, 0x88, 0x71, 0xda, 0xba, 0x33, 0x9c, 0xd5, 0x73,
+        0x95, 0xa3, 0xf8, 0x0d, 0x5c, 0x72, 0xf5, 0x47, 0x12, 0x23, 0xd0, 0x95, 0xc2, 0x65, 0xf7,
+        0x60, 0xfd, 0xea, 0x25, 0x93, 0xe0, 0xb0, 0x8f, 0x4a, 0xd7, 0xcb, 0x57, 0xef, 0x21, 0xb1,
+        0xd7, 0xf3, 0x9b, 0x21, 0x54, 0xbc, 0x9b, 0x25, 0x19, 0x6a, 0xa9, 0x68, 0xbe, 0x14, 0x94,
+        0x79, 0xf9, 0x9d, 0xf5, 0x3a, 0xb2, 0x09, 0x57, 0x9f, 0x87, 0x96, 0xcd, 0xbe, 0x0c, 0xc8,
+        0x99, 0x00, 0x57, 0x63, 0xd8, 0x46, 0x70, 0x14, 0xad, 0x14, 0x24, 0xb7, 0xef, 0x1c, 0x62,
+        0x21, 0x64, 0x8a, 0xaf, 0x39, 0x7a, 0x40, 0x9b, 0x01, 0xd5, 0x4e, 0xb0, 0x94, 0x2f, 0x5c,
+        0x3d, 0xc4, 0x85, 0x61, 0x84, 0xdd, 0x71, 0xb2, 0x14, 0xf2, 0x8c, 0xe1, 0x08, 0xf8, 0x6e,
+        0x1f, 0x0e, 0x19, 0x14, 0x19, 0x9d, 0x14, 0x2e, 0x1f, 0x49, 0x67, 0x64, 0x1a, 0xed, 0xc6,
+        0xe8, 0x83, 0xe1, 0xcb, 0xf4, 0xab, 0x0f, 0xdb, 0x75, 0xe2, 0x2e, 0x1f, 0x2c, 0x0c, 0x1c,
+        0xc7, 0x89, 0x8e, 0x2b, 0x95, 0x29, 0x69, 0x2e, 0x6c, 0xbe, 0xa1, 0x86, 0x4d, 0xbb, 0x9c,
+        0x15, 0x99, 0x59, 0x3f, 0x25, 0x9e, 0xea, 0x3d, 0xbb, 0x02, 0x03, 0x01, 0x00, 0x01, 0xa3,
+        0x7e, 0x30, 0x7c, 0x30, 0x0c, 0x06, 0x03, 0x55, 0x1d, 0x13, 0x01, 0x01, 0xff, 0x04, 0x02,
+        0x30, 0x00, 0x30, 0x0e, 0x06, 0x03, 0x55, 0x1d, 0x0f, 0x01, 0x01, 0xff, 0x04, 0x04, 0x03,
+        0x02, 0x07, 0x80, 0x30, 0x4a, 0x06, 0x03, 0x55, 0x1d, 0x11, 0x01, 0x01, 0xff, 0x04, 0x40,
+        0x30, 0x3e, 0xa4, 0x3c, 0x30, 0x3a, 0x31, 0x38, 0x30, 0x0e, 0x06, 0x05, 0x67, 0x81, 0x05,
+        0x02, 0x03, 0x0c, 0x05, 0x69, 0x64, 0x3a, 0x31, 0x33, 0x30, 0x10, 0x06, 0x05, 0x67, 0x81,
+        0x05, 0x02, 0x02, 0x0c, 0x07, 0x4e, 0x50, 0x43, 0x54, 0x37, 0x35, 0x78, 0x30, 0x14, 0x06,
+        0x05, 0x67, 0x81, 0x05, 0x02, 0x01, 0x0c, 0x0b, 0x69, 0x64, 0x3a, 0x34, 0x45, 0x35, 0x34,
+        0x34, 0x33, 0x30, 0x30, 0x30, 0x10, 0x06, 0x03, 0x55, 0x1d, 0x25, 0x04, 0x09, 0x30, 0x07,
+        0x06, 0x05, 0x67, 0x81, 0x05, 0x08, 0x03, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86,
+        0xf7, 0x0d, 0x01, 0x01, 0x0b, 0x05, 0x00, 0x03, 0x82, 0x01, 0x01, 0x00, 0x9a, 0x9e, 0xe6,
+        0xf1, 0xf6, 0xab, 0x51, 0x7f, 0x8e, 0x70, 0x2e, 0x04, 0xea, 0xb7, 0x97, 0x9d, 0x90, 0x69,
+        0x6e, 0x01, 0x61, 0x3c, 0x1d, 0x0e, 0xd3, 0x9e, 0x15, 0x5c, 0x6a, 0xb3, 0xcd, 0xa7, 0x55,
+        0xf5, 0xdf, 0xb4, 0x12, 0x27, 0xd9, 0x44, 0xbe, 0xe2, 0xe8, 0x11, 0x9b, 0x79, 0x8e, 0x34,
+        0x06, 0x01, 0x68, 0xd7, 0x8e, 0xd9, 0x1b, 0x69, 0x44, 0x94, 0x44, 0x9b, 0xae, 0xac, 0x55,
+        0x1a, 0x33, 0xa7, 0x7f, 0x3f, 0xe9, 0x7c, 0x23, 0x87, 0x2d, 0x60, 0xc5, 0x0a, 0x54, 0xa0,
+        0xd7, 0x8a, 0x3d, 0x1d, 0x3f, 0xa3, 0xd7, 0xfd, 0x03, 0x54, 0xb1, 0xf9, 0x49, 0xa7, 0xd1,
+        0x97, 0x72, 0x2f, 0x0d, 0x81, 0x09, 0xd9, 0x0f, 0x57, 0x94, 0x50, 0xb6, 0x6c, 0xe6, 0xf3,
+        0x3c, 0xe5, 0x0c, 0x16, 0xde, 0x27, 0xde, 0x10, 0xc1, 0xfc, 0x50, 0x36, 0xb2, 0xfb, 0x5a,
+        0xaf, 0x1e, 0x45, 0x7c, 0x62, 0xe2, 0x7d, 0x15, 0x92, 0x7f, 0x07, 0xa6, 0x08, 0x11, 0xea,
+        0xcc, 0x5c, 0x9d, 0xf1, 0x11, 0x7c, 0x2d, 0xd5, 0x21, 0xb4, 0x76, 0xdc, 0xde, 0x53, 0xed,
+        0x9b, 0x14, 0xd0, 0xce, 0x13, 0x34, 0x96, 0x93, 0xf2, 0xb3, 0xd8, 0x89, 0x29, 0x99, 0x65,
+        0xd0, 0x68, 0xe7, 0x0a, 0xa9, 0x96, 0xe1, 0xb6, 0xda, 0xf8, 0x9f, 0xc0, 0xdc, 0xd5, 0x11,
+        0xc2, 0x69, 0x41, 0x63, 0x2d, 0x9f, 0xfa, 0x9f, 0xb5, 0xe9, 0xf9, 0xaf, 0xbf, 0xe8, 0xb3,
+        0xee, 0x0d, 0x21, 0xbb, 0x46, 0x7f, 0x1c, 0xbd, 0x76, 0x0e, 0x03, 0x04, 0xcd, 0x26, 0xab,
+        0x73, 0xe8, 0x8b, 0xbd, 0x23, 0x01, 0xb0, 0x2e, 0x15, 0xef, 0xf6, 0x03, 0x91, 0x32, 0x7b,
+        0x1b, 0xfe, 0x90, 0xd5, 0x99, 0x88, 0x9d, 0xc3, 0x65, 0x55, 0xf9, 0x2a, 0xfe, 0x5f, 0x08,
+        0x22, 0x88, 0x3a, 0xf0, 0x83, 0x65, 0x95, 0xd7, 0x15, 0x43, 0x78, 0xd3, 0x4a, 0x59, 0x03,
+        0x41, 0x30, 0x82, 0x03, 0x3d, 0x30, 0x82, 0x02, 0x25, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02,
+        0x04, 0x33, 0x00, 0x00, 0x01, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d,
+        0x01, 0x01, 0x0b, 0x05, 0x00, 0x30, 0x60, 0x31, 0x0b, 0x30, 0x09, 0x06, 0x03, 0x55, 0x04,
+        0x06, 0x13, 0x02, 0x55, 0x53, 0x31, 0x1b, 0x30, 0x19, 0x06, 0x03, 0x55, 0x04, 0x0a, 0x0c,
+        0x12, 0x45, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x20, 0x54, 0x50, 0x4d, 0x20, 0x56, 0x65,
+        0x6e, 0x64, 0x6f, 0x72, 0x31, 0x34, 0x30, 0x32, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x2b,
+        0x45, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x20, 0x54, 0x50, 0x4d, 0x20, 0x52, 0x6f, 0x6f,
+        0x74, 0x20, 0x43, 0x65, 0x72, 0x74, 0x69, 0x66, 0x69, 0x63, 0x61, 0x74, 0x65, 0x20, 0x41,
+        0x75, 0x74, 0x68, 0x6f, 0x72, 0x69, 0x74, 0x79, 0x20, 0x32, 0x30, 0x31, 0x34, 0x30, 0x1e,
+        0x17, 0x0d, 0x32, 0x34, 0x30, 0x36, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x5a,
+        0x17, 0x0d, 0x34, 0x34, 0x30, 0x36, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x5a,
+        0x30, 0x2f, 0x31, 0x2d, 0x30, 0x2b, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x24, 0x45, 0x55,
+        0x53, 0x2d, 0x45, 0x58, 0x4d, 0x50, 0x4c, 0x2d, 0x4b, 0x45, 0x59, 0x49, 0x44, 0x2d, 0x39,
+        0x46, 0x34, 0x41, 0x37, 0x43, 0x32, 0x42, 0x31, 0x45, 0x30, 0x44, 0x38, 0x46, 0x36, 0x41,
+        0x35, 0x42, 0x33, 0x43, 0x30, 0x82, 0x01, 0x22, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48,
+        0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01, 0x05, 0x00, 0x03, 0x82, 0x01, 0x0f, 0x00, 0x30, 0x82,
+        0x01, 0x0a, 0x02, 0x82, 0x01, 0x01, 0x00, 0xc4, 0x41, 0xb4, 0x27, 0xe5, 0x7c, 0xf2, 0x9b,
+        0x3a, 0xce, 0xd2, 0x7e, 0x06, 0xb2, 0xe5, 0xbb, 0x64, 0x69, 0x98, 0xec, 0x07, 0xc0, 0xc6,
+        0xcc, 0x46, 0xec, 0x56, 0xa8, 0x68, 0x8d, 0xc9, 0xe9, 0x65, 0x72, 0x6e, 0x5e, 0xa7, 0x86,
+        0xb8, 0x66, 0xbb, 0x81, 0x56, 0xcf, 0x7c, 0xbf, 0x6e, 0x24, 0xe3, 0xcd, 0x09, 0x4c, 0xf3,
+        0xe0, 0xba, 0xb1, 0x7e, 0x18, 0x10, 0x77, 0xbc, 0x4c, 0xf3, 0x41, 0xe7, 0xeb, 0xfc, 0x13,
+        0x0c, 0x34, 0x75, 0xdb, 0xca, 0x4c, 0x81, 0x5d, 0xc7, 0x76, 0x9a, 0xc8, 0xb8, 0xb5, 0x00,
+        0x10, 0x54, 0x90, 0xdc, 0xf6, 0xde, 0xff, 0xcc, 0x1b, 0x9c, 0xde, 0x26, 0xcb, 0xd9, 0x67,
+        0xee, 0x08, 0xca, 0x18, 0x72, 0x4c, 0x5f, 0x86, 0x36, 0x8d, 0x85, 0x9a, 0xfa, 0x0b, 0xfe,
+        0x50, 0x94, 0x02, 0x34, 0xb1, 0x33, 0x33, 0xc2, 0x2d, 0x18, 0x55, 0x74, 0x7b, 0xf9, 0xd5,
+        0x2f, 0xba, 0x94, 0x8f, 0x8d, 0xdd, 0x7c, 0x38, 0xfa, 0x6b, 0x3a, 0x52, 0xa9, 0x00, 0xc2,
+        0xd4, 0xda, 0xfd, 0x2d, 0x5a, 0xed, 0x21, 0xd3, 0xd2, 0xaa, 0xd8, 0x80, 0x97, 0xf9, 0x4c,
+        0x9b, 0x79, 0x93, 0x0b, 0xeb, 0x2b, 0x02, 0x64, 0xaa, 0x66, 0xbe, 0xc5, 0x1e, 0xe8, 0xd4,
+        0xe6, 0x64, 0x18, 0xb2, 0x0b, 0x9c, 0x14, 0xb3, 0x56, 0xf9, 0x19, 0x17, 0x89, 0x78, 0x0a,
+        0x12, 0xff, 0x28, 0x87, 0xbe, 0x9f, 0xb1, 0x24, 0x04, 0xc0, 0xa7, 0x68, 0x80, 0x67, 0x16,
+        0x1b, 0x5b, 0x14, 0xb4, 0x68, 0x82, 0xa7, 0x5a, 0x7c, 0x5c, 0x94, 0x0e, 0xa9, 0x51, 0xa7,
+        0xfc, 0xbb, 0xa6, 0x96, 0xe3, 0x7b, 0x3f, 0x39, 0xaf, 0x17, 0x43, 0x4b, 0xf7, 0xf5, 0x4a,
+        0x75, 0x7d, 0x91, 0x6a, 0x30, 0x3a, 0xe2, 0xb2, 0x4a, 0xe9, 0x74, 0x70, 0x5d, 0x6f, 0xa5,
+        0xdd, 0x51, 0x15, 0xec, 0x43, 0x6a, 0x06, 0x15, 0x02, 0x03, 0x01, 0x00, 0x01, 0xa3, 0x30,
+        0x30, 0x2e, 0x30, 0x0f, 0x06, 0x03, 0x55, 0x1d, 0x13, 0x01, 0x01, 0xff, 0x04, 0x05, 0x30,
+        0x03, 0x01, 0x01, 0xff, 0x30, 0x1b, 0x06, 0x03, 0x55, 0x1d, 0x25, 0x04, 0x14, 0x30, 0x12,
+        0x06, 0x05, 0x67, 0x81, 0x05, 0x08, 0x03, 0x06, 0x09, 0x2b, 0x06, 0x01, 0x04, 0x01, 0x82,
+        0x37, 0x15, 0x24, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01,
+        0x0b, 0x05, 0x00, 0x03, 0x82, 0x01, 0x01, 0x00, 0x4a, 0x23, 0x14, 0x6a, 0x6b, 0x90, 0xc1,
+        0xc9, 0x6f, 0x0f, 0x48, 0x75, 0x76, 0x26, 0xde, 0x8a, 0x82, 0xcf, 0xc2, 0x5f, 0x26, 0xd3,
+        0xf6, 0xdf, 0x25, 0xbe, 0x3e, 0x3f, 0x97, 0x89, 0x8a, 0xe3, 0x42, 0x86, 0xd1, 0xa1, 0x36,
+        0x17, 0x1c, 0x65, 0x77, 0x91, 0x44, 0xd3, 0xfd, 0x6e, 0x36, 0x3b, 0x16, 0x7a, 0x3b, 0x87,
+        0xc6, 0x32, 0x9d, 0x88, 0x6c, 0x44, 0xa4, 0x9a, 0x85, 0x18, 0x96, 0x1f, 0x31, 0x70, 0xc3,
+        0xaf, 0x76, 0xee, 0x48, 0x85, 0x1b, 0xf1, 0x77, 0x65, 0xdc, 0xc9, 0xb1, 0xf2, 0x7b, 0x3b,
+        0x35, 0xfa, 0x9e, 0x1e, 0x3b, 0xfb, 0x1c, 0x4f, 0x00, 0x70, 0x38, 0x2c, 0x18, 0xa9, 0x75,
+        0x5c, 0xc3, 0x63, 0x8a, 0x5e, 0x7a, 0x94, 0x68, 0x22, 0x2a, 0xa3, 0x03, 0xf0, 0x88, 0x97,
+        0x83, 0xa5, 0xd8, 0x00, 0xd7, 0x1c, 0xcc, 0x8f, 0x43, 0x7a, 0x33, 0xc0, 0x59, 0x8e, 0x31,
+        0x23, 0xed, 0xab, 0x6d, 0x63, 0x1d, 0xca, 0xca, 0xfb, 0x07, 0xea, 0x17, 0xf7, 0xd0, 0xfd,
+        0x97, 0x98, 0x22, 0xb8, 0x43, 0x95, 0x0a, 0xd4, 0xb6, 0x6d, 0x83, 0x23, 0xcc, 0x17, 0x64,
+        0x4a, 0xc7, 0x48, 0xac, 0xc6, 0x51, 0x0e, 0x0b, 0x67, 0x2a, 0xa7, 0x63, 0xb5, 0xd4, 0x33,
+        0x62, 0xfe, 0x56, 0x68, 0x45, 0xfd, 0xcb, 0x9d, 0xd7, 0x8f, 0xa5, 0xc0, 0x9a, 0x26, 0x2c,
+        0x37, 0xb7, 0x98, 0x60, 0x33, 0x68, 0x6d, 0xca, 0xd4, 0x23, 0x9e, 0x30, 0xbf, 0xff, 0x0e,
+        0xef, 0xa4, 0x4e, 0xae, 0x0b, 0x27, 0xa1, 0x9b, 0x3e, 0x87, 0x0d, 0x9a, 0x0e, 0x72, 0x62,
+        0xf6, 0xef, 0x09, 0x5c, 0x3b, 0x1d, 0x53, 0xfe, 0xfb, 0x5b, 0x25, 0x63, 0x85, 0x4f, 0x59,
+        0x69, 0xe5, 0xbd, 0xf9, 0xa7, 0xfa, 0x0e, 0x84, 0x26, 0x9c, 0x7d, 0xa2, 0xd4, 0x47, 0x21,
+        0xae, 0xd3, 0xfe, 0x89, 0xb7, 0x72, 0xb9, 0x1f, 0xca, 0x67, 0x70, 0x75, 0x62, 0x41, 0x72,
+        0x65, 0x61, 0x59, 0x01, 0x36, 0x00, 0x01, 0x00, 0x0b, 0x00, 0x06, 0x04, 0x72, 0x00, 0x20,
+        0x9d, 0xff, 0xcb, 0xf3, 0x6c, 0x38, 0x3a, 0xe6, 0x99, 0xfb, 0x98, 0x68, 0xdc, 0x6d, 0xcb,
+        0x89, 0xd7, 0x15, 0x38, 0x84, 0xbe, 0x28, 0x03, 0x92, 0x2c, 0x12, 0x41, 0x58, 0xbf, 0xad,
+        0x22, 0xae, 0x00, 0x10, 0x00, 0x10, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0xc6,
+        0xe6, 0x9a, 0xc3, 0x53, 0xc1, 0xc1, 0xd9, 0x4e, 0x5c, 0x20, 0xc3, 0xc2, 0xbd, 0x02, 0x8b,
+        0x20, 0xae, 0x14, 0x40, 0xb8, 0xb9, 0x6b, 0x5f, 0xde, 0x5d, 0x73, 0x74, 0x81, 0x9a, 0x57,
+        0x8a, 0xa5, 0x95, 0x54, 0xe1, 0xad, 0xe3, 0x79, 0x29, 0x76, 0x56, 0x91, 0xc7, 0xd3, 0xb3,
+        0x4b, 0xcc, 0x1a, 0xf7, 0xe6, 0x98, 0x91, 0x62, 0x12, 0xda, 0xc7, 0xe1, 0xb1, 0xaa, 0xb8,
+        0xc4, 0xaa, 0x24, 0x5b, 0x92, 0x48, 0xa9, 0xc8, 0x29, 0xdc, 0xbc, 0xbb, 0x82, 0xdb, 0x5e,
+        0xad, 0xe3, 0x21, 0x4b, 0x7a, 0xd4, 0xca, 0xec, 0x47, 0xe0, 0xb5, 0xc5, 0xc6, 0xf3, 0x58,
+        0x0a, 0xb8, 0xa6, 0x0a, 0x8b, 0xd5, 0xbf, 0xd8, 0x4a, 0xae, 0x7e, 0x78, 0x17, 0x63, 0x3f,
+        0x72, 0x8b, 0xd6, 0x86, 0xe3, 0xda, 0x06, 0xd0, 0x9b, 0x2a, 0xdc, 0x4d, 0x9b, 0xc7, 0x4b,
+        0xf0, 0x9b, 0xeb, 0x2d, 0x24, 0x7e, 0x4e, 0x3f, 0x7d, 0xb2, 0x03, 0xb4, 0x2a, 0x2d, 0x66,
+        0x0c, 0x6f, 0xc5, 0xe5, 0xbb, 0xbd, 0x2c, 0x00, 0x83, 0xf7, 0x46, 0xe0, 0xdd, 0x2b, 0x37,
+        0xce, 0x06, 0xf7, 0x6d, 0x53, 0x85, 0x32, 0x05, 0x56, 0x06, 0xd8, 0xf3, 0xe4, 0xca, 0x62,
+        0x42, 0x93, 0x60, 0x27, 0x45, 0xf0, 0x48, 0x76, 0x72, 0x4b, 0x02, 0xd0, 0x7c, 0x75, 0x6e,
+        0x6d, 0x1d, 0x41, 0x8c, 0xfa, 0xef, 0xde, 0x31, 0x55, 0x9b, 0xe0, 0xd1, 0xc0, 0xa1, 0xdb,
+        0x0e, 0x0c, 0x04, 0x9c, 0xd4, 0x2c, 0x4c, 0x5e, 0x26, 0x60, 0x53, 0x12, 0x00, 0xd1, 0xe5,
+        0x51, 0x66, 0x22, 0x8d, 0x1a, 0x79, 0x52, 0x6c, 0xf1, 0xa2, 0x6c, 0xa1, 0x4f, 0x29, 0xe6,
+        0x2f, 0xa8, 0x20, 0x50, 0x2d, 0xb9, 0x54, 0x9a, 0x60, 0xd4, 0x4f, 0x9e, 0x46, 0x88, 0x81,
+        0xdc, 0x56, 0xcf, 0xdf, 0x03, 0x23, 0xe4, 0x33, 0x5d, 0xea, 0xfc, 0xcb, 0x70, 0x10, 0x85,
+        0x68, 0x63, 0x65, 0x72, 0x74, 0x49, 0x6e, 0x66, 0x6f, 0x58, 0xa1, 0xff, 0x54, 0x43, 0x47,
+        0x80, 0x17, 0x00, 0x22, 0x00, 0x0b, 0x2a, 0x7d, 0x5c, 0x9e, 0x01, 0xb3, 0x8f, 0x46, 0xe7,
+        0xd2, 0x1c, 0x0a, 0x95, 0xb3, 0xf8, 0xe4, 0xd6, 0xc1, 0x7a, 0x20, 0xb4, 0xe3, 0x9f, 0x58,
+        0xc2, 0xd6, 0x01, 0xa7, 0xe3, 0xb9, 0x8f, 0x45, 0x00, 0x14, 0x50, 0xfd, 0x5b, 0xad, 0xaa,
+        0xc3, 0x9a, 0xa5, 0xeb, 0xac, 0x9d, 0x8c, 0x95, 0x26, 0x85, 0x9b, 0xc2, 0x36, 0xf3, 0xbb,
+        0x00, 0x00, 0x00, 0x01, 0x58, 0xa2, 0xb6, 0xc0, 0x00, 0x00, 0x00, 0x2d, 0x00, 0x00, 0x00,
+        0x00, 0x01, 0x00, 0x07, 0x00, 0x02, 0x00, 0x03, 0x00, 0x01, 0x00, 0x22, 0x00, 0x0b, 0xfc,
+        0x46, 0x32, 0x35, 0xcc, 0x3e, 0x25, 0x41, 0xa7, 0xad, 0xf7, 0xac, 0x2e, 0xfa, 0x5f, 0xe0,
+        0xb5, 0xe1, 0xfc, 0x26, 0x2b, 0xb0, 0x90, 0x4e, 0x46, 0x13, 0x5a, 0xad, 0x8e, 0x70, 0x0b,
+        0xcf, 0x00, 0x22, 0x00, 0x0b, 0x2a, 0x7d, 0x5c, 0x9e, 0x01, 0xb3, 0x8f, 0x46, 0xe7, 0xd2,
+        0x1c, 0x0a, 0x95, 0xb3, 0xf8, 0xe4, 0xd6, 0xc1, 0x7a, 0x20, 0xb4, 0xe3, 0x9f, 0x58, 0xc2,
+        0xd6, 0x01, 0xa7, 0xe3, 0xb9, 0x8f, 0x45, 0x68, 0x61, 0x75, 0x74, 0x68, 0x44, 0x61, 0x74,
+        0x61, 0x59, 0x01, 0x57, 0xa3, 0x79, 0xa6, 0xf6, 0xee, 0xaf, 0xb9, 0xa5, 0x5e, 0x37, 0x8c,
+        0x11, 0x80, 0x34, 0xe2, 0x75, 0x1e, 0x68, 0x2f, 0xab, 0x9f, 0x2d, 0x30, 0xab, 0x13, 0xd2,
+        0x12, 0x55, 0x86, 0xce, 0x19, 0x47, 0x45, 0x00, 0x00, 0x00, 0x00, 0x08, 0x98, 0x70, 0x58,
+        0xca, 0xdc, 0x4b, 0x81, 0xb6, 0xe1, 0x30, 0xde, 0x50, 0xdc, 0xbe, 0x96, 0x00, 0x10, 0x5f,
+        0x3c, 0x2b, 0x9a, 0x7e, 0x14, 0xd0, 0x6c, 0x81, 0xa3, 0xf9, 0xe2, 0xb7, 0xc5, 0x4d, 0x18,
+        0xa4, 0x01, 0x03, 0x03, 0x39, 0x01, 0x00, 0x20, 0x59, 0x01, 0x00, 0xc6, 0xe6, 0x9a, 0xc3,
+        0x53, 0xc1, 0xc1, 0xd9, 0x4e, 0x5c, 0x20, 0xc3, 0xc2, 0xbd, 0x02, 0x8b, 0x20, 0xae, 0x14,
+        0x40, 0xb8, 0xb9, 0x6b, 0x5f, 0xde, 0x5d, 0x73, 0x74, 0x81, 0x9a, 0x57, 0x8a, 0xa5, 0x95,
+        0x54, 0xe1, 0xad, 0xe3, 0x79, 0x29, 0x76, 0x56, 0x91, 0xc7, 0xd3, 0xb3, 0x4b, 0xcc, 0x1a,
+        0xf7, 0xe6, 0x98, 0x91, 0x62, 0x12, 0xda, 0xc7, 0xe1, 0xb1, 0xaa, 0xb8, 0xc4, 0xaa, 0x24,
+        0x5b, 0x92, 0x48, 0xa9, 0xc8, 0x29, 0xdc, 0xbc, 0xbb, 0x82, 0xdb, 0x5e, 0xad, 0xe3, 0x21,
+        0x4b, 0x7a, 0xd4, 0xca, 0xec, 0x47, 0xe0, 0xb5, 0xc5, 0xc6, 0xf3, 0x58, 0x0a, 0xb8, 0xa6,
+        0x0a, 0x8b, 0xd5, 0xbf, 0xd8, 0x4a, 0xae, 0x7e, 0x78, 0x17, 0x63, 0x3f, 0x72, 0x8b, 0xd6,
+        0x86, 0xe3, 0xda, 0x06, 0xd0, 0x9b, 0x2a, 0xdc, 0x4d, 0x9b, 0xc7, 0x4b, 0xf0, 0x9b, 0xeb,
+        0x2d, 0x24, 0x7e, 0x4e, 0x3f, 0x7d, 0xb2, 0x03, 0xb4, 0x2a, 0x2d, 0x66, 0x0c, 0x6f, 0xc5,
+        0xe5, 0xbb, 0xbd, 0x2c, 0x00, 0x83, 0xf7, 0x46, 0xe0, 0xdd, 0x2b, 0x37, 0xce, 0x06, 0xf7,
+        0x6d, 0x53, 0x85, 0x32, 0x05, 0x56, 0x06, 0xd8, 0xf3, 0xe4, 0xca, 0x62, 0x42, 0x93, 0x60,
+        0x27, 0x45, 0xf0, 0x48, 0x76, 0x72, 0x4b, 0x02, 0xd0, 0x7c, 0x75, 0x6e, 0x6d, 0x1d, 0x41,
+        0x8c, 0xfa, 0xef, 0xde, 0x31, 0x55, 0x9b, 0xe0, 0xd1, 0xc0, 0xa1, 0xdb, 0x0e, 0x0c, 0x04,
+        0x9c, 0xd4, 0x2c, 0x4c, 0x5e, 0x26, 0x60, 0x53, 0x12, 0x00, 0xd1, 0xe5, 0x51, 0x66, 0x22,
+        0x8d, 0x1a, 0x79, 0x52, 0x6c, 0xf1, 0xa2, 0x6c, 0xa1, 0x4f, 0x29, 0xe6, 0x2f, 0xa8, 0x20,
+        0x50, 0x2d, 0xb9, 0x54, 0x9a, 0x60, 0xd4, 0x4f, 0x9e, 0x46, 0x88, 0x81, 0xdc, 0x56, 0xcf,
+        0xdf, 0x03, 0x23, 0xe4, 0x33, 0x5d, 0xea, 0xfc, 0xcb, 0x70, 0x10, 0x85, 0x21, 0x43, 0x01,
+        0x00, 0x01,
+    ];
+    // The self-signed root of the chain in SAMPLE_ATTESTATION_OBJECT_TPM
+    pub const SAMPLE_TPM_ROOT_CERT: [u8; 851] = [
+        0x30, 0x82, 0x03, 0x4f, 0x30, 0x82, 0x02, 0x37, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x02,
+        0x5c, 0x1e, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b,
+        0x05, 0x00, 0x30, 0x60, 0x31, 0x0b, 0x30, 0x09, 0x06, 0x03, 0x55, 0x04, 0x06, 0x13, 0x02,
+        0x55, 0x53, 0x31, 0x1b, 0x30, 0x19, 0x06, 0x03, 0x55, 0x04, 0x0a, 0x0c, 0x12, 0x45, 0x78,
+        0x61, 0x6d, 0x70, 0x6c, 0x65, 0x20, 0x54, 0x50, 0x4d, 0x20, 0x56, 0x65, 0x6e, 0x64, 0x6f,
+        0x72, 0x31, 0x34, 0x30, 0x32, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x2b, 0x45, 0x78, 0x61,
+        0x6d, 0x70, 0x6c, 0x65, 0x20, 0x54, 0x50, 0x4d, 0x20, 0x52, 0x6f, 0x6f, 0x74, 0x20, 0x43,
+        0x65, 0x72, 0x74, 0x69, 0x66, 0x69, 0x63, 0x61, 0x74, 0x65, 0x20, 0x41, 0x75, 0x74, 0x68,
+        0x6f, 0x72, 0x69, 0x74, 0x79, 0x20, 0x32, 0x30, 0x31, 0x34, 0x30, 0x1e, 0x17, 0x0d, 0x32,
+        0x34, 0x30, 0x36, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x5a, 0x17, 0x0d, 0x34,
+        0x34, 0x30, 0x36, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x5a, 0x30, 0x60, 0x31,
+        0x0b, 0x30, 0x09, 0x06, 0x03, 0x55, 0x04, 0x06, 0x13, 0x02, 0x55, 0x53, 0x31, 0x1b, 0x30,
+        0x19, 0x06, 0x03, 0x55, 0x04, 0x0a, 0x0c, 0x12, 0x45, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65,
+        0x20, 0x54, 0x50, 0x4d, 0x20, 0x56, 0x65, 0x6e, 0x64, 0x6f, 0x72, 0x31, 0x34, 0x30, 0x32,
+        0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x2b, 0x45, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x20,
+        0x54, 0x50, 0x4d, 0x20, 0x52, 0x6f, 0x6f, 0x74, 0x20, 0x43, 0x65, 0x72, 0x74, 0x69, 0x66,
+        0x69, 0x63, 0x61, 0x74, 0x65, 0x20, 0x41, 0x75, 0x74, 0x68, 0x6f, 0x72, 0x69, 0x74, 0x79,
+        0x20, 0x32, 0x30, 0x31, 0x34, 0x30, 0x82, 0x01, 0x22, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86,
+        0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01, 0x05, 0x00, 0x03, 0x82, 0x01, 0x0f, 0x00, 0x30,
+        0x82, 0x01, 0x0a, 0x02, 0x82, 0x01, 0x01, 0x00, 0xb6, 0xd5, 0xc1, 0x9a, 0x57, 0x3f, 0x90,
+        0x76, 0x91, 0xd8, 0x39, 0x4c, 0xc2, 0xa8, 0x7f, 0x84, 0x08, 0x92, 0x4c, 0x78, 0x4a, 0x94,
+        0xab, 0xdb, 0x04, 0x86, 0x83, 0x07, 0x73, 0x26, 0xa2, 0xa7, 0xd6, 0x28, 0x9d, 0x75, 0xc3,
+        0x8c, 0xca, 0xb8, 0x08, 0xd8, 0xd8, 0x6b, 0x88, 0x2c, 0xb9, 0x86, 0x5b, 0x17, 0xcb, 0x33,
+        0x02, 0x7e, 0xea, 0x34, 0x1e, 0xb7, 0x74, 0xba, 0x64, 0xe0, 0x62, 0xe9, 0x83, 0x29, 0x57,
+        0xcf, 0x15, 0x8b, 0x83, 0x2c, 0x1d, 0x96, 0x51, 0xb8, 0xf5, 0x21, 0x18, 0x4c, 0xf2, 0x9f,
+        0x6e, 0xbb, 0xe5, 0xc9, 0xb2, 0x2c, 0x63, 0xba, 0x32, 0xe9, 0x2f, 0xae, 0xa1, 0xd1, 0x30,
+        0xaf, 0x99, 0x60, 0x67, 0x53, 0x28, 0xe2, 0x64, 0xe3, 0xc0, 0xd9, 0x51, 0x4b, 0x03, 0x17,
+        0x3f, 0x93, 0x88, 0xc1, 0x5a, 0x44, 0x34, 0x03, 0xe3, 0x9a, 0xbd, 0x67, 0xb9, 0x76, 0x3f,
+        0x11, 0x8f, 0xaa, 0xf6, 0x6b, 0xc8, 0xb3, 0xea, 0xfb, 0xf8, 0x4f, 0xca, 0x8a, 0x5a, 0xbf,
+        0x72, 0xef, 0x58, 0x5d, 0x96, 0xd0, 0x5a, 0x2f, 0x82, 0x0a, 0xa4, 0xc0, 0x2b, 0x54, 0x6f,
+        0x4d, 0xf8, 0x4b, 0x59, 0x14, 0x87, 0x28, 0x19, 0x29, 0x88, 0x90, 0x25, 0xaf, 0x66, 0xa1,
+        0xd5, 0x51, 0x64, 0xd7, 0xf5, 0xf5, 0xb5, 0x45, 0x2d, 0x61, 0x79, 0xf8, 0x33, 0x48, 0x6f,
+        0xd7, 0x5c, 0xbd, 0x89, 0x40, 0x34, 0x3e, 0x86, 0xb5, 0x6c, 0xba, 0x24, 0x9a, 0x01, 0x98,
+        0x05, 0x5e, 0x05, 0x50, 0x0e, 0x76, 0x55, 0x31, 0xa0, 0x7e, 0x5c, 0xe4, 0x83, 0x82, 0xd2,
+        0x60, 0x5d, 0x43, 0xb1, 0x5f, 0x16, 0x3e, 0x2d, 0x22, 0x08, 0x24, 0x50, 0x74, 0x5e, 0x68,
+        0x9b, 0xa9, 0x6f, 0x4f, 0x28, 0xe0, 0x14, 0x97, 0x43, 0x0e, 0xa2, 0x3a, 0x1b, 0xd3, 0x07,
+        0xd1, 0xa9, 0x83, 0x9a, 0x39, 0xcf, 0xd3, 0x02, 0x07, 0x02, 0x03, 0x01, 0x00, 0x01, 0xa3,
+        0x13, 0x30, 0x11, 0x30, 0x0f, 0x06, 0x03, 0x55, 0x1d, 0x13, 0x01, 0x01, 0xff, 0x04, 0x05,
+        0x30, 0x03, 0x01, 0x01, 0xff, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d,
+        0x01, 0x01, 0x0b, 0x05, 0x00, 0x03, 0x82, 0x01, 0x01, 0x00, 0x8d, 0x3b, 0xd9, 0x1f, 0xf3,
+        0xe2, 0x82, 0x36, 0xe2, 0xa4, 0x45, 0x5f, 0x54, 0x35, 0x7e, 0xc3, 0x89, 0x5e, 0x10, 0xac,
+        0xfc, 0x12, 0xec, 0xdd, 0x93, 0x77, 0xc7, 0xf9, 0x3a, 0x01, 0x9f, 0xa5, 0x1d, 0xf8, 0x71,
+        0x67, 0x41, 0x41, 0x3c, 0x64, 0xdc, 0xc9, 0xa4, 0x20, 0x2e, 0xbd, 0xd5, 0xcf, 0xef, 0xa7,
+        0x25, 0xd5, 0x02, 0x25, 0x83, 0x2d, 0x82, 0xfc, 0x07, 0x40, 0x53, 0x56, 0xa7, 0xa7, 0xaf,
+        0x8e, 0x3b, 0x3f, 0x69, 0xfd, 0xa0, 0x66, 0x65, 0x0a, 0xb1, 0xd2, 0xa6, 0x1a, 0xe1, 0x75,
+        0x6f, 0xd9, 0x2c, 0x8c, 0xfe, 0x24, 0xe2, 0xd5, 0x25, 0x73, 0x74, 0x2a, 0xad, 0xf3, 0x82,
+        0x15, 0xf4, 0xab, 0x91, 0x43, 0xb5, 0x1a, 0xf0, 0x2c, 0x6f, 0xd0, 0x78, 0xc3, 0x35, 0x07,
+        0xe3, 0x2f, 0x2b, 0xa7, 0x2f, 0x48, 0x82, 0x5f, 0xaf, 0x4a, 0x50, 0xf6, 0x0c, 0x3a, 0x95,
+        0x66, 0xb2, 0xba, 0x80, 0x0d, 0x82, 0xd9, 0xe3, 0x64, 0x6a, 0x93, 0x04, 0xde, 0x03, 0x72,
+        0xe2, 0xc7, 0xa9, 0x90, 0x84, 0xd7, 0x1a, 0x86, 0x6e, 0xd9, 0x78, 0x35, 0x40, 0x2f, 0x85,
+        0x81, 0x71, 0xac, 0x84, 0xe7, 0xff, 0xf2, 0x5a, 0xb2, 0x08, 0x34, 0x22, 0xff, 0x9b, 0x4b,
+        0x98, 0xbe, 0xd4, 0xb5, 0xb0, 0x3a, 0xfb, 0x5e, 0x43, 0x67, 0x77, 0x94, 0xf2, 0x5a, 0x9d,
+        0x65, 0x57, 0x1a, 0x18, 0xff, 0xfa, 0xe6, 0x22, 0xe3, 0x30, 0x19, 0x4d, 0x0c, 0x53, 0xed,
+        0x93, 0x9d, 0x56, 0x19, 0x4d, 0x38, 0xf2, 0xbc, 0xc0, 0x42, 0xcc, 0x05, 0xae, 0x98, 0xdc,
+        0x68, 0x2c, 0x74, 0x20, 0x19, 0x74, 0xd6, 0x49, 0xf6, 0xe8, 0xe8, 0xa6, 0x51, 0xa8, 0x00,
+        0x58, 0x50, 0x62, 0x3f, 0xa9, 0x1d, 0x27, 0x2e, 0xdc, 0x2c, 0xb8, 0x24, 0xfe, 0xa9, 0x33,
+        0x40, 0xf6, 0xc1, 0x5e, 0x5a, 0x7c, 0xeb, 0x85, 0x24, 0xd7, 0xa8,
+    ];
+    // The clientDataHash used for the sample attestation objects
+    pub const SAMPLE_CLIENT_DATA_HASH: [u8; 32] = [
+        0xae, 0xe4, 0xf3, 0x75, 0x67, 0x46, 0xa8, 0x94, 0xac, 0x42, 0x86, 0xd4, 0xa3, 0x73, 0x2e,
+        0x5e, 0xfd, 0xc4, 0xf2, 0x10, 0x85, 0x84, 0xeb, 0x12, 0xd9, 0x86, 0x5c, 0x9f, 0xaf, 0xbc,
+        0x13, 0xfd,
+    ];
+
+    // 2025-01-01 00:00:00 UTC, when all sample certificates are valid
+    pub const SAMPLE_VERIFICATION_TIME: u64 = 1_735_689_600;
+
+    // The attestation statement of SAMPLE_ATTESTATION_OBJECT_TPM
+    pub fn sample_tpm_att_stmt() -> Value {
+        match from_slice(&SAMPLE_ATTESTATION_OBJECT_TPM).unwrap() {
+            Value::Map(mut map) => map.remove(&Value::Text("attStmt".to_string())).unwrap(),
+            other => panic!("Unexpected attestation object {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tpm_attestation_statement() {
+        let parsed: AttestationObject =
+            from_slice(&SAMPLE_ATTESTATION_OBJECT_TPM).expect("Failed to parse");
+        assert_eq!(
+            to_vec(&parsed).unwrap(),
+            SAMPLE_ATTESTATION_OBJECT_TPM.to_vec()
+        );
+        let att = match &parsed.att_stmt {
+            AttestationStatement::Tpm(att) => att,
+            other => panic!("Unexpected attestation statement {:?}", other),
+        };
+        assert_eq!(att.ver, "2.0");
+        assert_eq!(att.alg, COSEAlgorithm::INSECURE_RS1);
+        assert_eq!(att.sig.as_ref().len(), 256);
+        assert_eq!(att.attestation_cert.len(), 2);
+
+        // certInfo certifies pubArea, bound to this registration by extraData, which is
+        // SHA-1(authData || clientDataHash) for RS1
+        assert_eq!(att.cert_info.magic, TPM_GENERATED_VALUE);
+        assert_eq!(att.cert_info.attest_type, TPM_ST_ATTEST_CERTIFY);
+        assert_eq!(
+            att.cert_info.extra_data,
+            vec![
+                0x50, 0xfd, 0x5b, 0xad, 0xaa, 0xc3, 0x9a, 0xa5, 0xeb, 0xac, 0x9d, 0x8c, 0x95, 0x26,
+                0x85, 0x9b, 0xc2, 0x36, 0xf3, 0xbb,
+            ]
+        );
+        assert_eq!(att.cert_info.qualified_signer.len(), 34);
+        assert_eq!(att.cert_info.clock, 0x0001_58a2_b6c0);
+        assert_eq!(att.cert_info.reset_count, 0x2d);
+        assert_eq!(att.cert_info.restart_count, 0);
+        assert!(att.cert_info.safe);
+        assert_eq!(att.cert_info.firmware_version, 0x0007_0002_0003_0001);
+        assert_eq!(
+            att.cert_info.attested_qualified_name,
+            att.cert_info.qualified_signer
+        );
+        assert!(att.is_name_attested());
+
+        // pubArea is the credential public key of authData
+        assert_eq!(att.pub_area.name_alg, TPM_ALG_SHA256);
+        assert_eq!(att.pub_area.object_attributes, 0x0006_0472);
+        assert_eq!(att.pub_area.auth_policy.len(), 32);
+        let credential_key = &parsed
+            .auth_data
+            .credential_data
+            .as_ref()
+            .expect("No credential data")
+            .credential_public_key;
+        assert_eq!(credential_key.alg, COSEAlgorithm::RS256);
+        let modulus = match &credential_key.key {
+            COSEKeyType::RSA(key) => key.n.clone(),
+            other => panic!("Unexpected credential key {:?}", other),
+        };
+        assert_eq!(
+            att.pub_area.key,
+            TpmPublicKey::Rsa {
+                key_bits: 2048,
+                exponent: 0,
+                modulus,
+            }
+        );
+
+        // The AIK certificate chains up to the root
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(SAMPLE_VERIFICATION_TIME);
+        let root = AttestationCertificate(SAMPLE_TPM_ROOT_CERT.to_vec());
+        assert!(parsed.verify_attestation_at(&[root], time).is_ok());
+        assert_matches!(
+            parsed.verify_attestation_at(&[], time),
+            Err(AttestationError::UntrustedChain)
+        );
+
+        let bytes = |data: &[u8]| to_vec(&Value::Bytes(data.to_vec())).unwrap();
+        // Trailing data
+        let mut long_cert_info = att.cert_info.as_ref().to_vec();
+        long_cert_info.push(0x00);
+        assert!(from_slice::<TpmsAttest>(&bytes(&long_cert_info)).is_err());
+        // Truncated
+        assert!(from_slice::<TpmtPublic>(&bytes(&att.pub_area.as_ref()[..40])).is_err());
+        assert!(from_slice::<TpmsAttest>(&bytes(&att.cert_info.as_ref()[..40])).is_err());
+    }
+
+    // An android-key attestation object of a StrongBox backed key, created with OpenSSL and a
+    // separate CBOR encoder. The chain is the leaf for the credential key ("Android Keystore
+    // Key", valid from 1970-01-01 to 2048-01-01 as KeyMint issues it), a P-256 StrongBox
+    // intermediate and an RSA root. The KeyDescription of the leaf has the authorizations
+    // of a KeyMint 200 key: creationDateTime and attestationApplicationId in softwareEnforced,
+    // and purpose, algorithm, keySize, digest, ecCurve, noAuthRequired, origin, rootOfTrust,
+    // and the OS and patch levels in hardwareEnforced.
+    pub const SAMPLE_ATTESTATION_OBJECT_ANDROID_KEY: [u8; 1572] = [
+        0xa3, 0x63, 0x66, 0x6d, 0x74, 0x6b, 0x61, 0x6e, 0x64, 0x72, 0x6f, 0x69, 0x64, 0x2d, 0x6b,
+        0x65, 0x79, 0x67, 0x61, 0x74, 0x74, 0x53, 0x74, 0x6d, 0x74, 0xa3, 0x63, 0x61, 0x6c, 0x67,
+        0x26, 0x63, 0x73, 0x69, 0x67, 0x58, 0x46, 0x30, 0x44, 0x02, 0x20, 0x3b, 0x60, 0xa7, 0x02,
+        0xff, 0x64, 0x4d, 0xe5, 0x5e, 0x49, 0xb4, 0x54, 0x74, 0xd8, 0xb9, 0x1a, 0x81, 0x14, 0xc1,
+        0xef, 0x48, 0xa0, 0x6d, 0xca, 0x55, 0x5a, 0x5c, 0x08, 0xec, 0x88, 0x9d, 0x6d, 0x02, 0x20,
+        0x7a, 0xe3, 0x72, 0xad, 0x6d, 0x2a, 0x5a, 0x91, 0x17, 0x9e, 0xa4, 0x88, 0x5c, 0x23, 0x45,
+        0xfb, 0xc3, 0x5d, 0x58, 0x80, 0x10, 0x19, 0x9f, 0x4c, 0xf9, 0x95, 0xcb, 0x1e, 0x84, 0x86,
+        0xc6, 0x6a, 0x63, 0x78, 0x35, 0x63, 0x82, 0x59, 0x02, 0xbf, 0x30, 0x82, 0x02, 0xbb, 0x30,
+        0x82, 0x02, 0x61, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x01, 0x01, 0x30, 0x0a, 0x06, 0x08,
+        0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x30, 0x3f, 0x31, 0x12, 0x30, 0x10, 0x06,
+        0x03, 0x55, 0x04, 0x0c, 0x0c, 0x09, 0x53, 0x74, 0x72, 0x6f, 0x6e, 0x67, 0x42, 0x6f, 0x78,
+        0x31, 0x29, 0x30, 0x27, 0x06, 0x03, 0x55, 0x04, 0x05, 0x13, 0x20, 0x36, 0x62, 0x32, 0x61,
+        0x31, 0x63, 0x38, 0x39, 0x65, 0x33, 0x64, 0x34, 0x66, 0x30, 0x35, 0x61, 0x37, 0x63, 0x31,
+        0x62, 0x39, 0x65, 0x38, 0x64, 0x32, 0x66, 0x33, 0x61, 0x34, 0x62, 0x35, 0x63, 0x30, 0x1e,
+        0x17, 0x0d, 0x37, 0x30, 0x30, 0x31, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x5a,
+        0x17, 0x0d, 0x34, 0x38, 0x30, 0x31, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x5a,
+        0x30, 0x1f, 0x31, 0x1d, 0x30, 0x1b, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x14, 0x41, 0x6e,
+        0x64, 0x72, 0x6f, 0x69, 0x64, 0x20, 0x4b, 0x65, 0x79, 0x73, 0x74, 0x6f, 0x72, 0x65, 0x20,
+        0x4b, 0x65, 0x79, 0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02,
+        0x01, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00, 0x04,
+        0x6d, 0x6c, 0x6c, 0x37, 0x7e, 0x11, 0x32, 0xbd, 0xa3, 0xda, 0x9f, 0x2a, 0xc6, 0xc2, 0xba,
+        0x30, 0xc9, 0x04, 0x11, 0xc6, 0x8c, 0x90, 0x14, 0x5d, 0x8b, 0xc2, 0xe3, 0x77, 0x1a, 0x01,
+        0x33, 0xaa, 0xa0, 0x58, 0x8b, 0x60, 0x8e, 0x0d, 0xda, 0x3c, 0x79, 0x0e, 0x6e, 0xd5, 0x14,
+        0xa4, 0xca, 0xa1, 0x56, 0xf1, 0xf9, 0x92, 0x3f, 0x24, 0xe0, 0xd3, 0xc7, 0x0c, 0x56, 0x18,
+        0xf7, 0xa0, 0x9d, 0xa9, 0xa3, 0x82, 0x01, 0x6c, 0x30, 0x82, 0x01, 0x68, 0x30, 0x0c, 0x06,
+        0x03, 0x55, 0x1d, 0x13, 0x01, 0x01, 0xff, 0x04, 0x02, 0x30, 0x00, 0x30, 0x0e, 0x06, 0x03,
+        0x55, 0x1d, 0x0f, 0x01, 0x01, 0xff, 0x04, 0x04, 0x03, 0x02, 0x07, 0x80, 0x30, 0x82, 0x01,
+        0x46, 0x06, 0x0a, 0x2b, 0x06, 0x01, 0x04, 0x01, 0xd6, 0x79, 0x02, 0x01, 0x11, 0x04, 0x82,
+        0x01, 0x36, 0x30, 0x82, 0x01, 0x32, 0x02, 0x02, 0x00, 0xc8, 0x0a, 0x01, 0x02, 0x02, 0x02,
+        0x00, 0xc8, 0x0a, 0x01, 0x02, 0x04, 0x20, 0xae, 0xe4, 0xf3, 0x75, 0x67, 0x46, 0xa8, 0x94,
+        0xac, 0x42, 0x86, 0xd4, 0xa3, 0x73, 0x2e, 0x5e, 0xfd, 0xc4, 0xf2, 0x10, 0x85, 0x84, 0xeb,
+        0x12, 0xd9, 0x86, 0x5c, 0x9f, 0xaf, 0xbc, 0x13, 0xfd, 0x04, 0x00, 0x30, 0x5a, 0xbf, 0x85,
+        0x3d, 0x08, 0x02, 0x06, 0x01, 0x90, 0x00, 0xc7, 0x9c, 0x7b, 0xbf, 0x85, 0x45, 0x4a, 0x04,
+        0x48, 0x30, 0x46, 0x31, 0x20, 0x30, 0x1e, 0x04, 0x16, 0x63, 0x6f, 0x6d, 0x2e, 0x67, 0x6f,
+        0x6f, 0x67, 0x6c, 0x65, 0x2e, 0x61, 0x6e, 0x64, 0x72, 0x6f, 0x69, 0x64, 0x2e, 0x67, 0x6d,
+        0x73, 0x02, 0x04, 0x0f, 0x02, 0xaa, 0xcb, 0x31, 0x22, 0x04, 0x20, 0x4e, 0xd9, 0x82, 0x3a,
+        0xa4, 0x7f, 0xd1, 0x4b, 0x3a, 0x4a, 0x0d, 0x8f, 0xdd, 0x42, 0x88, 0x5c, 0xa2, 0x3f, 0x2e,
+        0x08, 0x78, 0xda, 0xf4, 0x7d, 0xff, 0x5e, 0xb4, 0x48, 0x9a, 0x7a, 0x64, 0x74, 0x30, 0x81,
+        0xa1, 0xa1, 0x05, 0x31, 0x03, 0x02, 0x01, 0x02, 0xa2, 0x03, 0x02, 0x01, 0x03, 0xa3, 0x04,
+        0x02, 0x02, 0x01, 0x00, 0xa5, 0x05, 0x31, 0x03, 0x02, 0x01, 0x04, 0xaa, 0x03, 0x02, 0x01,
+        0x01, 0xbf, 0x83, 0x77, 0x02, 0x05, 0x00, 0xbf, 0x85, 0x3e, 0x03, 0x02, 0x01, 0x00, 0xbf,
+        0x85, 0x40, 0x4c, 0x30, 0x4a, 0x04, 0x20, 0xf4, 0xda, 0x8e, 0x19, 0x22, 0x93, 0x12, 0x38,
+        0x26, 0x33, 0xb3, 0xea, 0x53, 0xff, 0x51, 0xe7, 0x53, 0x96, 0x05, 0x20, 0xf8, 0xc6, 0xe2,
+        0x7b, 0x23, 0xd6, 0xe7, 0xaa, 0x13, 0xad, 0xd7, 0xcd, 0x01, 0x01, 0xff, 0x0a, 0x01, 0x00,
+        0x04, 0x20, 0x2d, 0xc6, 0xd0, 0x0b, 0x7f, 0x74, 0x33, 0x96, 0x03, 0xa4, 0xb5, 0xe9, 0x2b,
+        0x25, 0x98, 0x1d, 0x13, 0x20, 0x7e, 0x41, 0xca, 0x3b, 0x2d, 0x12, 0x2c, 0x54, 0xd1, 0xfe,
+        0xa1, 0x92, 0xcd, 0xf4, 0xbf, 0x85, 0x41, 0x05, 0x02, 0x03, 0x02, 0x22, 0xe0, 0xbf, 0x85,
+        0x42, 0x05, 0x02, 0x03, 0x03, 0x16, 0xa6, 0xbf, 0x85, 0x4e, 0x06, 0x02, 0x04, 0x01, 0x34,
+        0xd8, 0xd9, 0xbf, 0x85, 0x4f, 0x06, 0x02, 0x04, 0x01, 0x34, 0xd8, 0xd9, 0x30, 0x0a, 0x06,
+        0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x03, 0x48, 0x00, 0x30, 0x45, 0x02,
+        0x20, 0x35, 0x24, 0x11, 0xbe, 0x97, 0x42, 0x86, 0x4b, 0xd3, 0x8c, 0x5c, 0xc9, 0xde, 0x0e,
+        0x26, 0xe6, 0x6e, 0x0d, 0xd0, 0x07, 0xff, 0x4c, 0xf9, 0xb8, 0xfc, 0xc6, 0x09, 0xeb, 0xd1,
+        0x2f, 0xc0, 0xd1, 0x02, 0x21, 0x00, 0xf4, 0x04, 0xc5, 0x8b, 0xad, 0x3d, 0x57, 0xcf, 0x1a,
+        0xdf, 0x47, 0xf2, 0xdb, 0x2f, 0x94, 0x80, 0x9c, 0x1c, 0xb2, 0x5e, 0x43, 0x07, 0x41, 0x5e,
+        0xbe, 0xd1, 0x94, 0x38, 0x6a, 0xd1, 0xf2, 0x8f, 0x59, 0x02, 0x40, 0x30, 0x82, 0x02, 0x3c,
+        0x30, 0x82, 0x01, 0x24, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x10, 0x2b, 0x7c, 0x3d, 0x4e,
+        0x5f, 0x60, 0x71, 0x82, 0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x01, 0x30, 0x0d, 0x06,
+        0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b, 0x05, 0x00, 0x30, 0x1b, 0x31,
+        0x19, 0x30, 0x17, 0x06, 0x03, 0x55, 0x04, 0x05, 0x13, 0x10, 0x66, 0x39, 0x32, 0x30, 0x30,
+        0x39, 0x65, 0x38, 0x35, 0x33, 0x62, 0x36, 0x62, 0x30, 0x34, 0x35, 0x30, 0x1e, 0x17, 0x0d,
+        0x32, 0x34, 0x30, 0x36, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x5a, 0x17, 0x0d,
+        0x34, 0x34, 0x30, 0x36, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x5a, 0x30, 0x3f,
+        0x31, 0x12, 0x30, 0x10, 0x06, 0x03, 0x55, 0x04, 0x0c, 0x0c, 0x09, 0x53, 0x74, 0x72, 0x6f,
+        0x6e, 0x67, 0x42, 0x6f, 0x78, 0x31, 0x29, 0x30, 0x27, 0x06, 0x03, 0x55, 0x04, 0x05, 0x13,
+        0x20, 0x36, 0x62, 0x32, 0x61, 0x31, 0x63, 0x38, 0x39, 0x65, 0x33, 0x64, 0x34, 0x66, 0x30,
+        0x35, 0x61, 0x37, 0x63, 0x31, 0x62, 0x39, 0x65, 0x38, 0x64, 0x32, 0x66, 0x33, 0x61, 0x34,
+        0x62, 0x35, 0x63, 0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02,
+        0x01, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00, 0x04,
+        0xe3, 0xef, 0xff, 0x1f, 0x40, 0x88, 0x87, 0x49, 0xb1, 0x64, 0xdf, 0xa3, 0xd4, 0x65, 0x90,
+        0x3a, 0x23, 0x3e, 0x4c, 0xd8, 0x0f, 0x14, 0x48, 0x7b, 0x24, 0xe2, 0xde, 0x9c, 0x4e, 0x37,
+        0xa0, 0x62, 0x01, 0x2d, 0xf2, 0x82, 0x89, 0xbd, 0x0d, 0xc8, 0x22, 0xd2, 0x40, 0x97, 0x0b,
+        0x54, 0xfd, 0x28, 0xa1, 0x22, 0xca, 0x98, 0xd2, 0x03, 0xb4, 0x2e, 0x30, 0x21, 0x6f, 0x5b,
+        0x73, 0x6f, 0x35, 0x3b, 0xa3, 0x23, 0x30, 0x21, 0x30, 0x0f, 0x06, 0x03, 0x55, 0x1d, 0x13,
+        0x01, 0x01, 0xff, 0x04, 0x05, 0x30, 0x03, 0x01, 0x01, 0xff, 0x30, 0x0e, 0x06, 0x03, 0x55,
+        0x1d, 0x0f, 0x01, 0x01, 0xff, 0x04, 0x04, 0x03, 0x02, 0x02, 0x04, 0x30, 0x0d, 0x06, 0x09,
+        0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b, 0x05, 0x00, 0x03, 0x82, 0x01, 0x01,
+        0x00, 0x64, 0x85, 0xd6, 0x43, 0x92, 0x1d, 0xbf, 0x87, 0x9b, 0x6e, 0x95, 0x5d, 0xab, 0xbd,
+        0x29, 0xd5, 0x18, 0x55, 0x7f, 0x47, 0xcd, 0x4d, 0x21, 0x47, 0xb0, 0x17, 0xcd, 0x37, 0x56,
+        0xa5, 0x2e, 0x40, 0xcc, 0x2f, 0x9d, 0x30, 0x0f, 0x77, 0x01, 0x03, 0x5d, 0xeb, 0x2c, 0xb4,
+        0x17, 0x5d, 0xbb, 0xc9, 0x03, 0x10, 0xe3, 0xed, 0x72, 0xdb, 0xf3, 0x1d, 0x1e, 0xa9, 0x7a,
+        0x8a, 0x14, 0xf4, 0xd2, 0x6d, 0xaf, 0x8a, 0x9e, 0x4d, 0xbf, 0x53, 0xf8, 0xc8, 0xb9, 0x0e,
+        0xae, 0x57, 0xb8, 0x3e, 0x2c, 0xf7, 0xb2, 0xb3, 0x94, 0x6d, 0xe8, 0x6f, 0x43, 0x7b, 0x28,
+        0xc4, 0x2f, 0xa9, 0x3f, 0x85, 0x49, 0xf5, 0x3c, 0xdd, 0xfd, 0xc4, 0xda, 0x61, 0x29, 0x4b,
+        0x5f, 0x8a, 0xdd, 0x27, 0xb8, 0xc2, 0x8e, 0x69, 0xd1, 0x17, 0x90, 0x6a, 0xa7, 0x81, 0x91,
+        0x6b, 0xc1, 0xfb, 0xdd, 0xce, 0x1c, 0x17, 0xa0, 0x6f, 0x87, 0x70, 0x18, 0xb0, 0x7f, 0xd3,
+        0xb5, 0x31, 0xdf, 0x73, 0x3c, 0x0a, 0x1a, 0xc2, 0xd2, 0x95, 0x4d, 0x78, 0xcd, 0xeb, 0x4d,
+        0xad, 0xd8, 0x9f, 0xe1, 0x64, 0x56, 0x6a, 0x56, 0x6c, 0x35, 0xca, 0x6d, 0xd2, 0xec, 0x4a,
+        0x20, 0x5c, 0x11, 0x68, 0x34, 0xfc, 0x99, 0xa8, 0x80, 0xbe, 0xb3, 0x85, 0x1e, 0xb9, 0x2b,
+        0xe4, 0x21, 0x3f, 0x24, 0x40, 0x28, 0x43, 0x5d, 0xf1, 0x2d, 0xf6, 0x80, 0xb7, 0xca, 0x95,
+        0x43, 0x06, 0x94, 0x2f, 0xda, 0x87, 0x7f, 0x33, 0x70, 0x13, 0x27, 0x4a, 0xe3, 0xdf, 0x0b,
+        0xc0, 0x3e, 0x9d, 0x79, 0x60, 0xf7, 0xa9, 0xd1, 0xa9, 0x1c, 0x56, 0xec, 0x54, 0x10, 0xab,
+        0x30, 0x24, 0x69, 0x41, 0xaf, 0xf4, 0xa2, 0x16, 0x90, 0xcd, 0xd9, 0x1f, 0x72, 0x75, 0x81,
+        0x3f, 0x2b, 0x58, 0x84, 0x7f, 0x56, 0x44, 0xb3, 0xea, 0xc6, 0xc6, 0xfd, 0x55, 0xa1, 0xea,
+        0xdb, 0xde, 0x68, 0x61, 0x75, 0x74, 0x68, 0x44, 0x61, 0x74, 0x61, 0x58, 0xa4, 0xa3, 0x79,
+        0xa6, 0xf6, 0xee, 0xaf, 0xb9, 0xa5, 0x5e, 0x37, 0x8c, 0x11, 0x80, 0x34, 0xe2, 0x75, 0x1e,
+        0x68, 0x2f, 0xab, 0x9f, 0x2d, 0x30, 0xab, 0x13, 0xd2, 0x12, 0x55, 0x86, 0xce, 0x19, 0x47,
+        0x45, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0xc4, 0xa1, 0xe0, 0x9b, 0x6f, 0x2d, 0x73,
+        0x58, 0xa0, 0xe4, 0xb9, 0xc1, 0xd2, 0x6f, 0x8e, 0x3a, 0x7b, 0x05, 0xc9, 0xd1, 0xe2, 0xf3,
+        0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0xa0, 0xb1, 0xc2, 0xd3, 0xa5, 0x01, 0x02, 0x03, 0x26,
+        0x20, 0x01, 0x21, 0x58, 0x20, 0x6d, 0x6c, 0x6c, 0x37, 0x7e, 0x11, 0x32, 0xbd, 0xa3, 0xda,
+        0x9f, 0x2a, 0xc6, 0xc2, 0xba, 0x30, 0xc9, 0x04, 0x11, 0xc6, 0x8c, 0x90, 0x14, 0x5d, 0x8b,
+        0xc2, 0xe3, 0x77, 0x1a, 0x01, 0x33, 0xaa, 0x22, 0x58, 0x20, 0xa0, 0x58, 0x8b, 0x60, 0x8e,
+        0x0d, 0xda, 0x3c, 0x79, 0x0e, 0x6e, 0xd5, 0x14, 0xa4, 0xca, 0xa1, 0x56, 0xf1, 0xf9, 0x92,
+        0x3f, 0x24, 0xe0, 0xd3, 0xc7, 0x0c, 0x56, 0x18, 0xf7, 0xa0, 0x9d, 0xa9,
+    ];
+    // The self-signed root of the chain in SAMPLE_ATTESTATION_OBJECT_ANDROID_KEY
+    pub const SAMPLE_ANDROID_KEY_ROOT_CERT: [u8; 720] = [
+        0x30, 0x82, 0x02, 0xcc, 0x30, 0x82, 0x01, 0xb4, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x09,
+        0x00, 0xe8, 0xfa, 0x19, 0x63, 0x14, 0xd2, 0xfa, 0x18, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86,
+        0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b, 0x05, 0x00, 0x30, 0x1b, 0x31, 0x19, 0x30, 0x17,
+        0x06, 0x03, 0x55, 0x04, 0x05, 0x13, 0x10, 0x66, 0x39, 0x32, 0x30, 0x30, 0x39, 0x65, 0x38,
+        0x35, 0x33, 0x62, 0x36, 0x62, 0x30, 0x34, 0x35, 0x30, 0x1e, 0x17, 0x0d, 0x32, 0x34, 0x30,
+        0x36, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x5a, 0x17, 0x0d, 0x34, 0x34, 0x30,
+        0x36, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x5a, 0x30, 0x1b, 0x31, 0x19, 0x30,
+        0x17, 0x06, 0x03, 0x55, 0x04, 0x05, 0x13, 0x10, 0x66, 0x39, 0x32, 0x30, 0x30, 0x39, 0x65,
+        0x38, 0x35, 0x33, 0x62, 0x36, 0x62, 0x30, 0x34, 0x35, 0x30, 0x82, 0x01, 0x22, 0x30, 0x0d,
+        0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01, 0x05, 0x00, 0x03, 0x82,
+        0x01, 0x0f, 0x00, 0x30, 0x82, 0x01, 0x0a, 0x02, 0x82, 0x01, 0x01, 0x00, 0x98, 0x11, 0xd4,
+        0x70, 0x13, 0xea, 0xa9, 0x64, 0x9d, 0x12, 0x12, 0x7e, 0xbc, 0xa9, 0x20, 0xe8, 0xf8, 0xf7,
+        0xb6, 0x7e, 0xa4, 0x09, 0x62, 0x00, 0xbc, 0xb3, 0xa0, 0xe0, 0x5a, 0xdb, 0x63, 0xd4, 0xf9,
+        0x37, 0x86, 0x14, 0x77, 0x85, 0x98, 0xeb, 0x60, 0x18, 0x55, 0x97, 0x41, 0x31, 0x89, 0x72,
+        0x84, 0xdc, 0xd5, 0x8b, 0xa1, 0xd6, 0x2e, 0x0e, 0x44, 0x40, 0x62, 0xc2, 0xd5, 0x7a, 0x35,
+        0xa6, 0x26, 0x3d, 0x23, 0x68, 0x83, 0xd9, 0x81, 0xbe, 0x79, 0x35, 0x66, 0x7d, 0x3c, 0x44,
+        0x38, 0x02, 0xdd, 0x23, 0x32, 0x8e, 0x01, 0xba, 0xf0, 0xf8, 0xf3, 0x9c, 0x93, 0x84, 0xaa,
+        0x05, 0x3d, 0x77, 0x6f, 0x0a, 0xea, 0xce, 0xa2, 0xd7, 0x27, 0x9d, 0x22, 0x18, 0x37, 0x0c,
+        0x15, 0x59, 0x6a, 0x18, 0x48, 0xb3, 0x68, 0xa4, 0x32, 0xcc, 0x1c, 0x76, 0x71, 0xe2, 0x32,
+        0x71, 0x40, 0x95, 0xdb, 0xd7, 0x97, 0x65, 0x5a, 0x4a, 0x6e, 0xd2, 0x91, 0xf2, 0x2f, 0x9b,
+        0x0d, 0x4d, 0x0b, 0xc2, 0xad, 0x6d, 0x25, 0xb5, 0x56, 0xf2, 0xb6, 0x0d, 0x9e, 0x3e, 0xfb,
+        0xb8, 0x59, 0xab, 0xa3, 0xfd, 0x84, 0x4b, 0x30, 0x15, 0xad, 0x6f, 0x24, 0x91, 0x47, 0x55,
+        0x7b, 0x3d, 0x92, 0xe4, 0x38, 0x26, 0x9e, 0x44, 0xdc, 0x0f, 0x4a, 0x35, 0x3b, 0x8d, 0x6d,
+        0xa8, 0xd7, 0x05, 0x59, 0x88, 0x7f, 0xbe, 0xfa, 0xf4, 0x36, 0x25, 0x9a, 0x70, 0xbc, 0x3f,
+        0x51, 0xf2, 0xcf, 0x89, 0x93, 0x25, 0x5b, 0x54, 0x03, 0x44, 0xf7, 0xf1, 0x7e, 0xd3, 0x33,
+        0x88, 0x99, 0x32, 0x26, 0x90, 0x21, 0xd1, 0x73, 0x78, 0x38, 0x08, 0xc4, 0x0f, 0xcd, 0x8b,
+        0x24, 0x27, 0x3c, 0xd1, 0xd0, 0x29, 0x19, 0x3a, 0x89, 0x2b, 0x0e, 0x6f, 0x31, 0x21, 0xaf,
+        0x82, 0x20, 0x8a, 0x47, 0x2e, 0xa8, 0x89, 0x00, 0xbe, 0xb0, 0xca, 0xa5, 0x61, 0x02, 0x03,
+        0x01, 0x00, 0x01, 0xa3, 0x13, 0x30, 0x11, 0x30, 0x0f, 0x06, 0x03, 0x55, 0x1d, 0x13, 0x01,
+        0x01, 0xff, 0x04, 0x05, 0x30, 0x03, 0x01, 0x01, 0xff, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86,
+        0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b, 0x05, 0x00, 0x03, 0x82, 0x01, 0x01, 0x00, 0x4e,
+        0x84, 0x87, 0x95, 0x86, 0x71, 0x77, 0x3b, 0xc6, 0xbb, 0x4c, 0xe1, 0x1a, 0x34, 0xba, 0xde,
+        0xde, 0x54, 0x25, 0x17, 0x4b, 0xb5, 0x2b, 0xf8, 0x58, 0xcc, 0xac, 0xff, 0x63, 0x84, 0x60,
+        0x6e, 0xbf, 0x5b, 0x87, 0x23, 0xf5, 0x3b, 0x8b, 0x90, 0x44, 0xb0, 0x63, 0x33, 0x36, 0x0a,
+        0xbb, 0x7b, 0xc9, 0x81, 0x93, 0xca, 0x71, 0xb8, 0x3c, 0x47, 0xe8, 0xf7, 0x86, 0x58, 0x4b,
+        0xb3, 0x92, 0xa0, 0x76, 0xb4, 0x93, 0x90, 0x2f, 0x93, 0x0a, 0x6e, 0xc4, 0x0c, 0xb5, 0x4e,
+        0x43, 0xe8, 0x78, 0xb7, 0x70, 0xe6, 0x5e, 0x09, 0x5d, 0x43, 0x59, 0x85, 0xfa, 0x57, 0x1b,
+        0xdf, 0x60, 0xd3, 0x8c, 0xc6, 0xd4, 0x9e, 0x59, 0x0d, 0x70, 0x87, 0x44, 0x04, 0xe8, 0x7a,
+        0x93, 0x6b, 0x84, 0x0a, 0xab, 0xe6, 0xf1, 0x2c, 0x76, 0x25, 0x36, 0x27, 0xce, 0x75, 0x02,
+        0x06, 0xfb, 0xe7, 0xd7, 0x76, 0xf5, 0xd6, 0x4c, 0x32, 0xf7, 0xfa, 0x6c, 0x28, 0x7a, 0x46,
+        0x9e, 0x93, 0xee, 0x2b, 0xb7, 0xf8, 0xd4, 0x9c, 0x66, 0x2b, 0x0b, 0xb6, 0x47, 0xc7, 0x88,
+        0xf2, 0x9f, 0xc2, 0x3c, 0xfa, 0xf7, 0x4c, 0x63, 0x14, 0xda, 0xdd, 0x78, 0x55, 0xbc, 0x60,
+        0xd1, 0x9c, 0x70, 0xb8, 0x1c, 0xef, 0xd1, 0x2a, 0x52, 0xbb, 0x04, 0x54, 0x13, 0x8a, 0xc2,
+        0x4d, 0x88, 0x98, 0x77, 0x67, 0x8a, 0x1b, 0x1e, 0xf2, 0xb6, 0x74, 0xa3, 0xe6, 0xd2, 0xec,
+        0x6a, 0x3c, 0x28, 0xe6, 0x90, 0x5a, 0xed, 0x6f, 0xad, 0x8e, 0xbc, 0x33, 0x48, 0x65, 0xd1,
+        0x91, 0xfc, 0x25, 0xd9, 0xae, 0x3b, 0xaa, 0xec, 0x14, 0x0f, 0x9c, 0x33, 0x3a, 0x93, 0x75,
+        0x78, 0x27, 0x62, 0x09, 0x0c, 0x82, 0xf9, 0xcc, 0x98, 0x8f, 0xa5, 0x74, 0x06, 0xa6, 0x9e,
+        0x22, 0x6d, 0xa6, 0x3f, 0xb9, 0xe2, 0x40, 0x64, 0x6f, 0x2a, 0xaf, 0x81, 0x58, 0x81, 0x01,
+    ];
+
+    #[test]
+    fn test_android_key_attestation_statement() {
+        let parsed: AttestationObject =
+            from_slice(&SAMPLE_ATTESTATION_OBJECT_ANDROID_KEY).expect("Failed to parse");
+        assert_eq!(
+            to_vec(&parsed).unwrap(),
+            SAMPLE_ATTESTATION_OBJECT_ANDROID_KEY.to_vec()
+        );
+        let att = match &parsed.att_stmt {
+            AttestationStatement::AndroidKey(att) => att,
+            other => panic!("Unexpected attestation statement {:?}", other),
+        };
+        assert_eq!(att.alg, COSEAlgorithm::ES256);
+        assert_eq!(att.attestation_cert.len(), 2);
+        let description = att
+            .key_description()
+            .expect("Failed to parse key description")
+            .expect("No key description");
+        assert_eq!(
+            description,
+            AndroidKeyDescription {
+                attestation_version: 200,
+                attestation_security_level: AndroidSecurityLevel::StrongBox,
+                keymaster_version: 200,
+                keymaster_security_level: AndroidSecurityLevel::StrongBox,
+                attestation_challenge: SAMPLE_CLIENT_DATA_HASH.to_vec(),
+                unique_id: vec![],
+                software_enforced: AndroidAuthorizationList::default(),
+                tee_enforced: AndroidAuthorizationList {
+                    purpose: vec![ANDROID_KEY_PURPOSE_SIGN],
+                    all_applications: false,
+                    origin: Some(ANDROID_KEY_ORIGIN_GENERATED),
+                },
+            }
+        );
+
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(SAMPLE_VERIFICATION_TIME);
+        let root = AttestationCertificate(SAMPLE_ANDROID_KEY_ROOT_CERT.to_vec());
+        assert!(parsed.verify_attestation_at(&[root], time).is_ok());
+
+        let att = AttestationStatementAndroidKey {
+            alg: COSEAlgorithm::ES256,
+            sig: Signature(vec![0x44; 8]),
+            attestation_cert: vec![AttestationCertificate(create_test_certificate(&[]))],
+        };
+        assert_matches!(att.key_description(), Ok(None));
+        let att = AttestationStatementAndroidKey {
+            attestation_cert: vec![AttestationCertificate(vec![0x30, 0x03, 0x02])],
+            ..att
+        };
+        assert_matches!(att.key_description(), Err(CryptoError::MalformedInput));
+    }
+
+    // An Apple anonymous attestation object, created with OpenSSL and a separate CBOR encoder
+    // in the layout of Apple devices: the credential certificate (P-256, valid for three days,
+    // with the nonce extension) and a P-384 CA certificate, which signs with ecdsa-with-SHA384.
+    const SAMPLE_ATTESTATION_OBJECT_APPLE: [u8; 1262] = [
+        0xa3, 0x63, 0x66, 0x6d, 0x74, 0x65, 0x61, 0x70, 0x70, 0x6c, 0x65, 0x67, 0x61, 0x74, 0x74,
+        0x53, 0x74, 0x6d, 0x74, 0xa1, 0x63, 0x78, 0x35, 0x63, 0x82, 0x59, 0x02, 0x3c, 0x30, 0x82,
+        0x02, 0x38, 0x30, 0x82, 0x01, 0xbf, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x06, 0x01, 0x73,
+        0xb5, 0xe2, 0xc8, 0xa9, 0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03,
+        0x02, 0x30, 0x4c, 0x31, 0x1e, 0x30, 0x1c, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x15, 0x45,
+        0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x20, 0x57, 0x65, 0x62, 0x41, 0x75, 0x74, 0x68, 0x6e,
+        0x20, 0x43, 0x41, 0x20, 0x31, 0x31, 0x15, 0x30, 0x13, 0x06, 0x03, 0x55, 0x04, 0x0a, 0x0c,
+        0x0c, 0x45, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x20, 0x49, 0x6e, 0x63, 0x2e, 0x31, 0x13,
+        0x30, 0x11, 0x06, 0x03, 0x55, 0x04, 0x08, 0x0c, 0x0a, 0x43, 0x61, 0x6c, 0x69, 0x66, 0x6f,
+        0x72, 0x6e, 0x69, 0x61, 0x30, 0x1e, 0x17, 0x0d, 0x32, 0x34, 0x30, 0x36, 0x31, 0x30, 0x31,
+        0x32, 0x30, 0x30, 0x30, 0x30, 0x5a, 0x17, 0x0d, 0x32, 0x34, 0x30, 0x36, 0x31, 0x33, 0x31,
+        0x32, 0x30, 0x30, 0x30, 0x30, 0x5a, 0x30, 0x81, 0x93, 0x31, 0x49, 0x30, 0x47, 0x06, 0x03,
+        0x55, 0x04, 0x03, 0x0c, 0x40, 0x33, 0x66, 0x31, 0x61, 0x39, 0x63, 0x35, 0x65, 0x37, 0x62,
+        0x32, 0x64, 0x34, 0x66, 0x36, 0x30, 0x61, 0x38, 0x63, 0x31, 0x65, 0x33, 0x62, 0x35, 0x64,
+        0x37, 0x66, 0x39, 0x30, 0x61, 0x32, 0x63, 0x34, 0x65, 0x36, 0x62, 0x38, 0x64, 0x30, 0x66,
+        0x31, 0x61, 0x33, 0x63, 0x35, 0x65, 0x37, 0x62, 0x39, 0x64, 0x31, 0x66, 0x33, 0x61, 0x35,
+        0x63, 0x37, 0x65, 0x39, 0x62, 0x31, 0x64, 0x33, 0x66, 0x31, 0x1a, 0x30, 0x18, 0x06, 0x03,
+        0x55, 0x04, 0x0b, 0x0c, 0x11, 0x41, 0x41, 0x41, 0x20, 0x43, 0x65, 0x72, 0x74, 0x69, 0x66,
+        0x69, 0x63, 0x61, 0x74, 0x69, 0x6f, 0x6e, 0x31, 0x15, 0x30, 0x13, 0x06, 0x03, 0x55, 0x04,
+        0x0a, 0x0c, 0x0c, 0x45, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x20, 0x49, 0x6e, 0x63, 0x2e,
+        0x31, 0x13, 0x30, 0x11, 0x06, 0x03, 0x55, 0x04, 0x08, 0x0c, 0x0a, 0x43, 0x61, 0x6c, 0x69,
+        0x66, 0x6f, 0x72, 0x6e, 0x69, 0x61, 0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48,
+        0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03,
+        0x42, 0x00, 0x04, 0x7e, 0x51, 0xe3, 0xf3, 0x2b, 0x4c, 0x78, 0xa8, 0xda, 0xc9, 0x5e, 0xca,
+        0x2d, 0x3b, 0xa9, 0xed, 0x09, 0x17, 0x84, 0xa7, 0xbc, 0x26, 0x75, 0x5a, 0x11, 0x3a, 0x2c,
+        0xa1, 0x95, 0x0a, 0xb5, 0x01, 0x40, 0xbd, 0x32, 0x68, 0xac, 0xe9, 0x7a, 0x0c, 0x8a, 0xf8,
+        0x4c, 0x02, 0xbc, 0xa4, 0xcb, 0x8c, 0xb0, 0x63, 0x00, 0x1f, 0x18, 0x87, 0x6c, 0x5b, 0x6c,
+        0x6f, 0x26, 0x71, 0x76, 0xf9, 0x93, 0xe0, 0xa3, 0x45, 0x30, 0x43, 0x30, 0x0c, 0x06, 0x03,
+        0x55, 0x1d, 0x13, 0x01, 0x01, 0xff, 0x04, 0x02, 0x30, 0x00, 0x30, 0x33, 0x06, 0x09, 0x2a,
+        0x86, 0x48, 0x86, 0xf7, 0x63, 0x64, 0x08, 0x02, 0x04, 0x26, 0x30, 0x24, 0xa1, 0x22, 0x04,
+        0x20, 0xa6, 0x4c, 0x7e, 0x03, 0xfd, 0x08, 0x0f, 0x0a, 0xb2, 0xa6, 0x1b, 0xc8, 0x8c, 0x44,
+        0xb5, 0xef, 0x9d, 0x90, 0x2e, 0xb0, 0x89, 0xac, 0xee, 0x7c, 0x8e, 0x13, 0x2a, 0x92, 0xf3,
+        0x79, 0xc3, 0x81, 0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02,
+        0x03, 0x67, 0x00, 0x30, 0x64, 0x02, 0x30, 0x63, 0x90, 0xbd, 0x1a, 0x06, 0xb1, 0x77, 0x47,
+        0x86, 0x00, 0xb8, 0xe0, 0x00, 0x89, 0x91, 0x09, 0xfc, 0x2d, 0xcc, 0x7a, 0xfc, 0x7b, 0x70,
+        0x9e, 0x72, 0x66, 0x7c, 0x53, 0x38, 0x26, 0x40, 0x51, 0xf1, 0x37, 0xfa, 0xcd, 0x77, 0xf3,
+        0xfa, 0x7d, 0x86, 0x4d, 0x77, 0x6c, 0xf0, 0x76, 0x31, 0xb1, 0x02, 0x30, 0x09, 0x54, 0x5f,
+        0x2e, 0x3e, 0xf7, 0x84, 0x61, 0x4b, 0x77, 0x49, 0x7a, 0x75, 0x6c, 0x1a, 0x34, 0x73, 0xbb,
+        0xf1, 0xaa, 0x90, 0x57, 0x40, 0x02, 0x4d, 0x02, 0x23, 0xb5, 0xde, 0xc4, 0xf5, 0x63, 0x29,
+        0x2a, 0x75, 0x0e, 0x7f, 0x20, 0x07, 0x8c, 0x0a, 0x50, 0xef, 0x2a, 0xfd, 0xb1, 0x82, 0x72,
+        0x59, 0x01, 0xe4, 0x30, 0x82, 0x01, 0xe0, 0x30, 0x82, 0x01, 0x67, 0xa0, 0x03, 0x02, 0x01,
+        0x02, 0x02, 0x08, 0x5f, 0x5c, 0x4e, 0x3d, 0x2a, 0x1b, 0x0c, 0x9d, 0x30, 0x0a, 0x06, 0x08,
+        0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03, 0x30, 0x4f, 0x31, 0x21, 0x30, 0x1f, 0x06,
+        0x03, 0x55, 0x04, 0x03, 0x0c, 0x18, 0x45, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x20, 0x57,
+        0x65, 0x62, 0x41, 0x75, 0x74, 0x68, 0x6e, 0x20, 0x52, 0x6f, 0x6f, 0x74, 0x20, 0x43, 0x41,
+        0x31, 0x15, 0x30, 0x13, 0x06, 0x03, 0x55, 0x04, 0x0a, 0x0c, 0x0c, 0x45, 0x78, 0x61, 0x6d,
+        0x70, 0x6c, 0x65, 0x20, 0x49, 0x6e, 0x63, 0x2e, 0x31, 0x13, 0x30, 0x11, 0x06, 0x03, 0x55,
+        0x04, 0x08, 0x0c, 0x0a, 0x43, 0x61, 0x6c, 0x69, 0x66, 0x6f, 0x72, 0x6e, 0x69, 0x61, 0x30,
+        0x1e, 0x17, 0x0d, 0x32, 0x34, 0x30, 0x36, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30,
+        0x5a, 0x17, 0x0d, 0x34, 0x34, 0x30, 0x36, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30,
+        0x5a, 0x30, 0x4c, 0x31, 0x1e, 0x30, 0x1c, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x15, 0x45,
+        0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x20, 0x57, 0x65, 0x62, 0x41, 0x75, 0x74, 0x68, 0x6e,
+        0x20, 0x43, 0x41, 0x20, 0x31, 0x31, 0x15, 0x30, 0x13, 0x06, 0x03, 0x55, 0x04, 0x0a, 0x0c,
+        0x0c, 0x45, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x20, 0x49, 0x6e, 0x63, 0x2e, 0x31, 0x13,
+        0x30, 0x11, 0x06, 0x03, 0x55, 0x04, 0x08, 0x0c, 0x0a, 0x43, 0x61, 0x6c, 0x69, 0x66, 0x6f,
+        0x72, 0x6e, 0x69, 0x61, 0x30, 0x76, 0x30, 0x10, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d,
+        0x02, 0x01, 0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22, 0x03, 0x62, 0x00, 0x04, 0xb4, 0x6c,
+        0x1a, 0x7c, 0x18, 0xb6, 0xb1, 0x3d, 0x3c, 0xa7, 0x01, 0x49, 0xb0, 0xc4, 0x4b, 0x60, 0x51,
+        0xb8, 0xbc, 0x12, 0xae, 0xd4, 0xa6, 0xfb, 0x75, 0x29, 0x63, 0xbd, 0x44, 0x2c, 0xc7, 0x2d,
+        0xd3, 0xbf, 0xb5, 0xd8, 0x6b, 0x4d, 0x9d, 0x6e, 0x83, 0xe0, 0xec, 0x83, 0x10, 0x07, 0xce,
+        0x78, 0xe4, 0x76, 0x24, 0x9c, 0xb5, 0xf8, 0x84, 0x1d, 0x53, 0x0b, 0xa4, 0x64, 0xde, 0xad,
+        0x10, 0x43, 0x03, 0x90, 0x0e, 0x91, 0x72, 0x90, 0xf6, 0xca, 0x8d, 0x16, 0x7d, 0x61, 0xdf,
+        0x3b, 0x5b, 0xf6, 0xd9, 0xe7, 0x95, 0x6d, 0x82, 0xc0, 0x62, 0x98, 0x7a, 0xe6, 0xf2, 0x5e,
+        0x8e, 0x4d, 0xdc, 0x50, 0xa3, 0x13, 0x30, 0x11, 0x30, 0x0f, 0x06, 0x03, 0x55, 0x1d, 0x13,
+        0x01, 0x01, 0xff, 0x04, 0x05, 0x30, 0x03, 0x01, 0x01, 0xff, 0x30, 0x0a, 0x06, 0x08, 0x2a,
+        0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03, 0x03, 0x67, 0x00, 0x30, 0x64, 0x02, 0x30, 0x42,
+        0xb1, 0xf0, 0x22, 0x97, 0x4c, 0xd5, 0x04, 0x70, 0xca, 0xcd, 0xea, 0x01, 0xf7, 0x6a, 0x11,
+        0x35, 0xc0, 0x46, 0x32, 0x2e, 0x44, 0xff, 0x31, 0xb2, 0x91, 0x06, 0xa0, 0x44, 0xeb, 0x48,
+        0xb6, 0x65, 0x01, 0x41, 0x4c, 0x24, 0x2c, 0x3b, 0x10, 0x73, 0x25, 0xc8, 0x07, 0x9c, 0x15,
+        0xfc, 0x9c, 0x02, 0x30, 0x07, 0x57, 0x6d, 0xe1, 0xf7, 0xe5, 0xc8, 0xd0, 0x7e, 0x0f, 0x68,
+        0xf4, 0x1d, 0xe2, 0x7e, 0xf3, 0x8d, 0x99, 0x98, 0x78, 0xcd, 0x8d, 0x12, 0x73, 0x89, 0x9d,
+        0x27, 0x25, 0x73, 0x24, 0x29, 0xeb, 0x85, 0x2d, 0xd4, 0x1b, 0x8b, 0xaa, 0x4e, 0x48, 0xa4,
+        0xb4, 0xe9, 0x2f, 0xd8, 0x6d, 0x41, 0xc6, 0x68, 0x61, 0x75, 0x74, 0x68, 0x44, 0x61, 0x74,
+        0x61, 0x58, 0xa4, 0xa3, 0x79, 0xa6, 0xf6, 0xee, 0xaf, 0xb9, 0xa5, 0x5e, 0x37, 0x8c, 0x11,
+        0x80, 0x34, 0xe2, 0x75, 0x1e, 0x68, 0x2f, 0xab, 0x9f, 0x2d, 0x30, 0xab, 0x13, 0xd2, 0x12,
+        0x55, 0x86, 0xce, 0x19, 0x47, 0x45, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x1d, 0x9e,
+        0x8c, 0x7b, 0x6a, 0x5f, 0x4e, 0x3d, 0x2c, 0x1b, 0x0a, 0x99, 0x88, 0x77, 0x66, 0x55, 0x44,
+        0x33, 0x22, 0x11, 0x00, 0xff, 0xee, 0xdd, 0xcc, 0xbb, 0xaa, 0x99, 0x88, 0x77, 0x66, 0x55,
+        0xa5, 0x01, 0x02, 0x03, 0x26, 0x20, 0x01, 0x21, 0x58, 0x20, 0x7e, 0x51, 0xe3, 0xf3, 0x2b,
+        0x4c, 0x78, 0xa8, 0xda, 0xc9, 0x5e, 0xca, 0x2d, 0x3b, 0xa9, 0xed, 0x09, 0x17, 0x84, 0xa7,
+        0xbc, 0x26, 0x75, 0x5a, 0x11, 0x3a, 0x2c, 0xa1, 0x95, 0x0a, 0xb5, 0x01, 0x22, 0x58, 0x20,
+        0x40, 0xbd, 0x32, 0x68, 0xac, 0xe9, 0x7a, 0x0c, 0x8a, 0xf8, 0x4c, 0x02, 0xbc, 0xa4, 0xcb,
+        0x8c, 0xb0, 0x63, 0x00, 0x1f, 0x18, 0x87, 0x6c, 0x5b, 0x6c, 0x6f, 0x26, 0x71, 0x76, 0xf9,
+        0x93, 0xe0,
+    ];
+
+    // A packed attestation object with an RSA 2048 attestation key, as some enterprise
+    // authenticators have, and a P-256 credential key. The signature is over
+    // SAMPLE_CLIENT_DATA_HASH. Created with OpenSSL and a separate CBOR encoder.
+    const SAMPLE_ATTESTATION_OBJECT_PACKED_RSA: [u8; 1310] = [
+        0xa3, 0x63, 0x66, 0x6d, 0x74, 0x66, 0x70, 0x61, 0x63, 0x6b, 0x65, 0x64, 0x67, 0x61, 0x74,
+        0x74, 0x53, 0x74, 0x6d, 0x74, 0xa3, 0x63, 0x61, 0x6c, 0x67, 0x39, 0x01, 0x00, 0x63, 0x73,
+        0x69, 0x67, 0x59, 0x01, 0x00, 0x6b, 0x2d, 0x8c, 0xe0, 0xa7, 0x17, 0x7a, 0x9f, 0x75, 0x6b,
+        0xd1, 0xb4, 0x65, 0xae, 0x0f, 0x56, 0xff, 0x7c, 0x95, 0xe7, 0x80, 0x9f, 0xc6, 0x0c, 0x6e,
+        0x37, 0x3b, 0x21, 0xcd, 0xf1, 0x94, 0x6c, 0x3e, 0x8c, 0x11, 0x54, 0x0d, 0xad, 0x04, 0x39,
+        0x6c, 0xe8, 0xfc, 0x6c, 0x2a, 0x9d, 0x93, 0xf1, 0xfc, 0xae, 0xa8, 0xb4, 0x1c, 0x04, 0x6a,
+        0x34, 0x8b, 0x20, 0x7e, 0x86, 0x10, 0xf0, 0x8a, 0x70, 0x7c, 0x14, 0x28, 0x14, 0x0b, 0x86,
+        0x5d, 0x07, 0x42, 0xef, 0xd5, 0x39, 0xb4, 0x64, 0xfd, 0xa4, 0x5d, 0x29, 0x00, 0xd5, 0x6b,
+        0x87, 0xac, 0xbd, 0xd6, 0x10, 0x39, 0x85, 0x41, 0xb5, 0x0e, 0x0f, 0x4f, 0xeb, 0x39, 0x17,
+        0x10, 0x6e, 0x46, 0x7a, 0x7f, 0xaf, 0x01, 0x69, 0x08, 0x28, 0x55, 0x4c, 0x19, 0x5f, 0x4c,
+        0x45, 0x49, 0x65, 0xb2, 0xc2, 0xfb, 0xf5, 0x7d, 0x44, 0x24, 0xf2, 0xbe, 0x95, 0xcb, 0x78,
+        0x02, 0x52, 0xdb, 0x48, 0xab, 0x56, 0xcd, 0xd7, 0x6a, 0x9e, 0xc1, 0x00, 0x8f, 0x61, 0x5b,
+        0x30, 0xbd, 0xa4, 0xf6, 0x3b, 0xd3, 0x76, 0x0c, 0x47, 0x89, 0xdc, 0x38, 0xcf, 0x24, 0xb4,
+        0x21, 0x16, 0x9c, 0x3e, 0x94, 0xf1, 0x0a, 0xc6, 0x77, 0xf4, 0x3d, 0xec, 0x4b, 0x8f, 0x42,
+        0x6e, 0x23, 0xef, 0x1b, 0x07, 0xe6, 0xb7, 0x3c, 0x45, 0x43, 0x08, 0x02, 0x1d, 0x7f, 0x50,
+        0x49, 0x44, 0x85, 0x8c, 0x10, 0x1a, 0x78, 0x70, 0x6b, 0xef, 0x1f, 0x80, 0x12, 0x1f, 0x79,
+        0xfc, 0x79, 0x81, 0xc7, 0x0f, 0xfb, 0x0b, 0x9d, 0xac, 0xa5, 0xb7, 0x46, 0x86, 0x4d, 0x04,
+        0x9c, 0x19, 0x4c, 0x22, 0x8d, 0x5c, 0xe6, 0xd2, 0xe0, 0xf0, 0x8f, 0x0c, 0xfe, 0x49, 0x39,
+        0xdc, 0xba, 0x5b, 0xf3, 0x73, 0xc3, 0x97, 0xd4, 0xd1, 0xde, 0x6a, 0x76, 0x87, 0x36, 0xd0,
+        0x7e, 0x5d, 0xde, 0x08, 0xc1, 0x96, 0x63, 0x78, 0x35, 0x63, 0x81, 0x59, 0x03, 0x54, 0x30,
+        0x82, 0x03, 0x50, 0x30, 0x82, 0x02, 0x38, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x02, 0x4a,
+        0x2f, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b, 0x05,
+        0x00, 0x30, 0x38, 0x31, 0x1f, 0x30, 0x1d, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x16, 0x45,
+        0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x20, 0x41, 0x74, 0x74, 0x65, 0x73, 0x74, 0x61, 0x74,
+        0x69, 0x6f, 0x6e, 0x20, 0x43, 0x41, 0x31, 0x15, 0x30, 0x13, 0x06, 0x03, 0x55, 0x04, 0x0a,
+        0x0c, 0x0c, 0x45, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x20, 0x43, 0x6f, 0x72, 0x70, 0x30,
+        0x1e, 0x17, 0x0d, 0x32, 0x34, 0x30, 0x36, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30,
+        0x5a, 0x17, 0x0d, 0x34, 0x34, 0x30, 0x36, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30,
+        0x5a, 0x30, 0x69, 0x31, 0x0b, 0x30, 0x09, 0x06, 0x03, 0x55, 0x04, 0x06, 0x13, 0x02, 0x55,
+        0x53, 0x31, 0x15, 0x30, 0x13, 0x06, 0x03, 0x55, 0x04, 0x0a, 0x0c, 0x0c, 0x45, 0x78, 0x61,
+        0x6d, 0x70, 0x6c, 0x65, 0x20, 0x43, 0x6f, 0x72, 0x70, 0x31, 0x22, 0x30, 0x20, 0x06, 0x03,
+        0x55, 0x04, 0x0b, 0x0c, 0x19, 0x41, 0x75, 0x74, 0x68, 0x65, 0x6e, 0x74, 0x69, 0x63, 0x61,
+        0x74, 0x6f, 0x72, 0x20, 0x41, 0x74, 0x74, 0x65, 0x73, 0x74, 0x61, 0x74, 0x69, 0x6f, 0x6e,
+        0x31, 0x1f, 0x30, 0x1d, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x16, 0x45, 0x78, 0x61, 0x6d,
+        0x70, 0x6c, 0x65, 0x20, 0x45, 0x6e, 0x74, 0x65, 0x72, 0x70, 0x72, 0x69, 0x73, 0x65, 0x20,
+        0x4b, 0x65, 0x79, 0x30, 0x82, 0x01, 0x22, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86,
+        0xf7, 0x0d, 0x01, 0x01, 0x01, 0x05, 0x00, 0x03, 0x82, 0x01, 0x0f, 0x00, 0x30, 0x82, 0x01,
+        0x0a, 0x02, 0x82, 0x01, 0x01, 0x00, 0xca, 0xc7, 0xb7, 0xcd, 0xcf, 0xc3, 0x8a, 0xc5, 0xef,
+        0x7e, 0x26, 0x1c, 0xa7, 0x63, 0x94, 0x9e, 0x43, 0xef, 0x88, 0xb4, 0x2e, 0x86, 0x16, 0xf1,
+        0xd8, 0xe0, 0x2e, 0xb8, 0xba, 0x12, 0x4d, 0x42, 0xcd, 0x35, 0xdf, 0x4a, 0x00, 0x2c, 0xc7,
+        0x68, 0x48, 0x69, 0xa2, 0xbd, 0xa3, 0x3c, 0x18, 0xb0, 0x67, 0xca, 0xb0, 0xda, 0xe2, 0x5f,
+        0x09, 0xfb, 0x30, 0xf8, 0x6f, 0x35, 0x21, 0x08, 0xf1, 0x4b, 0x70, 0x8d, 0xed, 0x97, 0x85,
+        0x1a, 0x13, 0xaf, 0xeb, 0x85, 0x03, 0xe3, 0x9b, 0x38, 0x12, 0x14, 0x6d, 0x0c, 0xe3, 0x03,
+        0x91, 0xf8, 0x1a, 0xcc, 0xe9, 0xf8, 0xa2, 0xa8, 0x89, 0x4c, 0x98, 0xa5, 0x78, 0x5d, 0xd6,
+        0x74, 0xd9, 0xe9, 0xf7, 0xc1, 0x2b, 0x59, 0x16, 0x35, 0xf7, 0xc3, 0x09, 0xd8, 0xf8, 0x69,
+        0xfa, 0xf6, 0xdb, 0xf4, 0x61, 0x53, 0x5d, 0xf4, 0x3a, 0x56, 0x00, 0xa8, 0x42, 0x8d, 0x95,
+        0x0f, 0x93, 0x56, 0x7b, 0x60, 0xba, 0x01, 0x36, 0xfe, 0x63, 0xd9, 0xa4, 0xe4, 0xac, 0x9f,
+        0x94, 0x01, 0x9b, 0x23, 0x60, 0x74, 0x4b, 0x65, 0x3f, 0x47, 0xf1, 0x8f, 0xb0, 0x50, 0x6a,
+        0x8b, 0x49, 0x3f, 0xb6, 0x82, 0x81, 0x13, 0xd8, 0x78, 0xda, 0x3b, 0xab, 0xa7, 0x31, 0x01,
+        0x49, 0x28, 0xa7, 0x08, 0xf6, 0x9d, 0x4b, 0x71, 0x1e, 0x79, 0xb8, 0x5e, 0x92, 0x0a, 0x89,
+        0xfa, 0xd8, 0xb5, 0x42, 0x86, 0x31, 0x72, 0x6a, 0x46, 0xfc, 0xd8, 0x90, 0xb0, 0x6e, 0xa7,
+        0x5c, 0x2f, 0x89, 0x69, 0x85, 0xb7, 0xc6, 0xb4, 0x8f, 0xf1, 0x7a, 0xbf, 0x22, 0x8c, 0x4c,
+        0xad, 0x12, 0xf4, 0x18, 0x2d, 0x5b, 0x10, 0x72, 0x78, 0xeb, 0xdf, 0x87, 0x5c, 0x4f, 0xbe,
+        0x91, 0x3d, 0x55, 0x98, 0xfe, 0x35, 0xa6, 0xa0, 0x52, 0xeb, 0xde, 0xd2, 0xa6, 0x5e, 0x9f,
+        0x44, 0x63, 0x6b, 0x03, 0xf8, 0xfa, 0x27, 0x02, 0x03, 0x01, 0x00, 0x01, 0xa3, 0x33, 0x30,
+        0x31, 0x30, 0x0c, 0x06, 0x03, 0x55, 0x1d, 0x13, 0x01, 0x01, 0xff, 0x04, 0x02, 0x30, 0x00,
+        0x30, 0x21, 0x06, 0x0b, 0x2b, 0x06, 0x01, 0x04, 0x01, 0x82, 0xe5, 0x1c, 0x01, 0x01, 0x04,
+        0x04, 0x12, 0x04, 0x10, 0x60, 0x28, 0xb0, 0x17, 0xb1, 0xd4, 0x4c, 0x02, 0xb4, 0xb3, 0xaf,
+        0xcd, 0xaf, 0xc9, 0x6b, 0xb2, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d,
+        0x01, 0x01, 0x0b, 0x05, 0x00, 0x03, 0x82, 0x01, 0x01, 0x00, 0x92, 0x24, 0x58, 0x9c, 0xfb,
+        0xaa, 0xe8, 0xcc, 0xed, 0x78, 0xbd, 0x39, 0xfa, 0xca, 0x4f, 0xd6, 0x56, 0xa1, 0x76, 0x7a,
+        0x7a, 0xd9, 0xa5, 0xa4, 0xd6, 0x30, 0xe6, 0xf9, 0xdf, 0x1e, 0x3c, 0x11, 0x0e, 0x73, 0x7f,
+        0xdd, 0x9f, 0x54, 0x45, 0x18, 0x98, 0xa9, 0x1b, 0x88, 0x45, 0x93, 0x48, 0xb9, 0x0c, 0xea,
+        0x31, 0x20, 0x6c, 0x4d, 0x1c, 0x9f, 0x6a, 0x45, 0xe3, 0x7b, 0x6b, 0x0e, 0xaa, 0x36, 0xbd,
+        0x5b, 0x05, 0xeb, 0x56, 0x29, 0x61, 0xe7, 0x4b, 0xbc, 0x24, 0x02, 0x6a, 0xf6, 0x35, 0x8c,
+        0xf6, 0x32, 0xea, 0x69, 0xce, 0x7a, 0x5a, 0xed, 0x91, 0x99, 0x93, 0x61, 0x24, 0x7f, 0xc2,
+        0xd1, 0x4c, 0xdd, 0x8e, 0x22, 0xc4, 0xca, 0x22, 0x72, 0x1e, 0xc8, 0x0f, 0x88, 0xcb, 0x2a,
+        0xb0, 0x4e, 0x5f, 0x26, 0x3b, 0x39, 0x95, 0x85, 0x15, 0x53, 0xeb, 0x2e, 0x8b, 0x80, 0x10,
+        0x69, 0x99, 0x1c, 0x00, 0x3b, 0x76, 0xea, 0x8f, 0x30, 0xb1, 0x8e, 0x3f, 0xe2, 0xb9, 0x77,
+        0x16, 0xaa, 0x3e, 0x67, 0x14, 0x0a, 0xa5, 0xfd, 0x4c, 0x29, 0xee, 0x82, 0x79, 0xc2, 0xdc,
+        0xc7, 0x82, 0x41, 0x21, 0x10, 0xfd, 0x36, 0x32, 0xf9, 0x44, 0xce, 0xb7, 0x3a, 0x5f, 0x27,
+        0x49, 0x94, 0x9a, 0x56, 0xdf, 0xd7, 0x87, 0xf0, 0xb0, 0xe9, 0x0d, 0x59, 0xa2, 0xfa, 0x4d,
+        0x56, 0xd6, 0x3b, 0x61, 0xc2, 0x03, 0xdb, 0x24, 0xe7, 0x76, 0xb1, 0x2d, 0x97, 0xa2, 0x89,
+        0xfe, 0xb3, 0x2a, 0x34, 0xda, 0xef, 0xfb, 0xc8, 0x9f, 0x5b, 0x48, 0xd3, 0xb4, 0x16, 0x6c,
+        0x66, 0x49, 0x3e, 0x16, 0xe4, 0xbf, 0xb6, 0x4e, 0xf9, 0x4d, 0x5c, 0xab, 0xdd, 0x58, 0x58,
+        0x28, 0x92, 0x1b, 0x30, 0xa4, 0x86, 0xd8, 0xe3, 0x04, 0x65, 0x33, 0x32, 0xa0, 0x31, 0x97,
+        0x4c, 0x80, 0x0c, 0x9a, 0xfb, 0xe8, 0x6e, 0xaf, 0x89, 0x40, 0x51, 0x68, 0x61, 0x75, 0x74,
+        0x68, 0x44, 0x61, 0x74, 0x61, 0x58, 0x94, 0xa3, 0x79, 0xa6, 0xf6, 0xee, 0xaf, 0xb9, 0xa5,
+        0x5e, 0x37, 0x8c, 0x11, 0x80, 0x34, 0xe2, 0x75, 0x1e, 0x68, 0x2f, 0xab, 0x9f, 0x2d, 0x30,
+        0xab, 0x13, 0xd2, 0x12, 0x55, 0x86, 0xce, 0x19, 0x47, 0x45, 0x00, 0x00, 0x00, 0x00, 0x60,
+        0x28, 0xb0, 0x17, 0xb1, 0xd4, 0x4c, 0x02, 0xb4, 0xb3, 0xaf, 0xcd, 0xaf, 0xc9, 0x6b, 0xb2,
+        0x00, 0x10, 0x8e, 0x2b, 0x7d, 0x4c, 0x1a, 0x09, 0xf3, 0xe6, 0xb5, 0xd8, 0xc7, 0xa2, 0xf1,
+        0xe0, 0x4b, 0x39, 0xa5, 0x01, 0x02, 0x03, 0x26, 0x20, 0x01, 0x21, 0x58, 0x20, 0x14, 0x88,
+        0xea, 0x33, 0x4c, 0x61, 0x32, 0x33, 0xae, 0xca, 0x06, 0xcd, 0x6a, 0xd5, 0x66, 0x61, 0x39,
+        0x4c, 0xe5, 0xbe, 0x16, 0xbc, 0xae, 0xdc, 0xcc, 0x1c, 0xdc, 0x2e, 0xb8, 0x75, 0x76, 0xd7,
+        0x22, 0x58, 0x20, 0xd3, 0xfc, 0x78, 0x9a, 0xa7, 0x70, 0xd1, 0xab, 0xf5, 0x43, 0xec, 0xd0,
+        0x77, 0xd9, 0xb0, 0x7b, 0xa0, 0x25, 0xc8, 0xb5, 0x94, 0x39, 0xe6, 0x01, 0x3f, 0xc6, 0x1a,
+        0x6a, 0xc9, 0xce, 0xd0, 0xad,
+    ];
+
+    #[test]
+    fn test_verify_full_attestation() {
+        let client_data_hash = ClientDataHash(SAMPLE_CLIENT_DATA_HASH);
+        let wrong_hash = ClientDataHash(Sha256::digest(b"other client data").into());
+        for sample in [
+            &SAMPLE_ATTESTATION_OBJECT_PACKED_RSA[..],
+            &SAMPLE_ATTESTATION_OBJECT_ANDROID_KEY[..],
+        ] {
+            let att_obj: AttestationObject = from_slice(sample).expect("Failed to parse");
+            assert!(att_obj.verify(&client_data_hash).is_ok());
+            assert_matches!(
+                att_obj.verify(&wrong_hash),
+                Err(CryptoError::VerificationFailed)
+            );
+        }
+
+        // The statement has to use the algorithm of the attestation key
+        let mut att_obj: AttestationObject =
+            from_slice(&SAMPLE_ATTESTATION_OBJECT_PACKED_RSA).expect("Failed to parse");
+        match &mut att_obj.att_stmt {
+            AttestationStatement::Packed(att) => att.alg = COSEAlgorithm::ES256,
+            other => panic!("Unexpected attestation statement {:?}", other),
+        }
+        assert_matches!(
+            att_obj.verify(&client_data_hash),
+            Err(CryptoError::AlgorithmMismatch(
+                COSEAlgorithm::ES256,
+                COSEAlgorithm::RS256
+            ))
+        );
+
+        // The CA of the apple sample has a P-384 key, which we can't verify with
+        let apple: AttestationObject =
+            from_slice(&SAMPLE_ATTESTATION_OBJECT_APPLE).expect("Failed to parse");
+        assert_matches!(
+            apple.verify(&client_data_hash),
+            Err(CryptoError::UnknownSignatureScheme)
+        );
+        let p384_cert = match apple.att_stmt {
+            AttestationStatement::AppleAnonymous(mut att) => att.attestation_cert.remove(1),
+            other => panic!("Unexpected attestation statement {:?}", other),
+        };
+        match &mut att_obj.att_stmt {
+            AttestationStatement::Packed(att) => att.attestation_cert = vec![p384_cert],
+            other => panic!("Unexpected attestation statement {:?}", other),
+        }
+        assert_matches!(
+            att_obj.verify(&client_data_hash),
+            Err(CryptoError::UnsupportedKeyType)
+        );
+    }
+
+    #[test]
+    fn test_certificate_cache() {
+        let cache = CertificateCache::new();
+        let client_data_hash = ClientDataHash(SAMPLE_CLIENT_DATA_HASH);
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(SAMPLE_VERIFICATION_TIME);
+        let tpm: AttestationObject =
+            from_slice(&SAMPLE_ATTESTATION_OBJECT_TPM).expect("Failed to parse");
+        let tpm_root = || AttestationCertificate(SAMPLE_TPM_ROOT_CERT.to_vec());
+        let android: AttestationObject =
+            from_slice(&SAMPLE_ATTESTATION_OBJECT_ANDROID_KEY).expect("Failed to parse");
+        let android_root = || AttestationCertificate(SAMPLE_ANDROID_KEY_ROOT_CERT.to_vec());
+
+        // The cached certificate is the same as a fresh parse
+        let cached = cache.get(&tpm_root()).expect("Failed to parse");
+        assert_eq!(cached.der(), &SAMPLE_TPM_ROOT_CERT[..]);
+        assert_eq!(
+            cached.certificate(),
+            x509::Certificate::from_der(&SAMPLE_TPM_ROOT_CERT).expect("Failed to parse")
+        );
+        assert!(Arc::ptr_eq(
+            &cached,
+            &cache.get(&tpm_root()).expect("Failed to parse")
+        ));
+        assert_eq!(cache.len(), 1);
+
+        // Malformed certificates are not cached
+        assert_matches!(
+            cache.get(&AttestationCertificate(vec![0x30, 0x03, 0x02])),
+            Err(_)
+        );
+        assert_eq!(cache.len(), 1);
+
+        // Two chain certificates and the root of each sample
+        for _ in 0..2 {
+            assert!(tpm
+                .verify_attestation_with_cache(&[tpm_root()], time, &cache)
+                .is_ok());
+            assert!(android
+                .verify_attestation_with_cache(&[android_root()], time, &cache)
+                .is_ok());
+            assert!(android.verify_with_cache(&client_data_hash, &cache).is_ok());
+            assert_eq!(cache.len(), 6);
+        }
+        assert_matches!(
+            android.verify_attestation_with_cache(&[tpm_root()], time, &cache),
+            Err(AttestationError::UntrustedChain)
+        );
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert!(android
+            .verify_attestation_with_cache(&[android_root()], time, &cache)
+            .is_ok());
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn test_apple_anonymous_attestation_statement() {
+        let parsed: AttestationObject =
+            from_slice(&SAMPLE_ATTESTATION_OBJECT_APPLE).expect("Failed to parse");
+        assert_eq!(
+            to_vec(&parsed).unwrap(),
+            SAMPLE_ATTESTATION_OBJECT_APPLE.to_vec()
+        );
+        let att = match &parsed.att_stmt {
+            AttestationStatement::AppleAnonymous(att) => att,
+            other => panic!("Unexpected attestation statement {:?}", other),
+        };
+        assert_eq!(att.attestation_cert.len(), 2);
+        // SHA-256(authData || clientDataHash)
+        assert_eq!(
+            att.nonce().unwrap(),
+            vec![
+                0xa6, 0x4c, 0x7e, 0x03, 0xfd, 0x08, 0x0f, 0x0a, 0xb2, 0xa6, 0x1b, 0xc8, 0x8c, 0x44,
+                0xb5, 0xef, 0x9d, 0x90, 0x2e, 0xb0, 0x89, 0xac, 0xee, 0x7c, 0x8e, 0x13, 0x2a, 0x92,
+                0xf3, 0x79, 0xc3, 0x81,
+            ]
+        );
+        let client_data_hash = ClientDataHash(SAMPLE_CLIENT_DATA_HASH);
+        assert!(att
+            .verify_nonce(&parsed.auth_data, &client_data_hash)
+            .is_ok());
+        let wrong_hash = ClientDataHash(Sha256::digest(b"other client data").into());
+        assert_matches!(
+            att.verify_nonce(&parsed.auth_data, &wrong_hash),
+            Err(CryptoError::VerificationFailed)
+        );
+        let mut other: AttestationObject =
+            from_slice(&SAMPLE_ATTESTATION_OBJECT_APPLE).expect("Failed to parse");
+        other.auth_data.counter += 1;
+        assert_matches!(
+            att.verify_nonce(&other.auth_data, &client_data_hash),
+            Err(CryptoError::VerificationFailed)
+        );
+
+        let att = AttestationStatementAppleAnonymous {
+            attestation_cert: vec![AttestationCertificate(create_test_certificate(&[]))],
+        };
+        assert_matches!(att.nonce(), Err(CryptoError::MalformedInput));
+    }
+
+    #[test]
+    fn test_android_safetynet_attestation_statement() {
+        let b64 = |data: &[u8]| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(data);
+        let nonce = base64::engine::general_purpose::STANDARD.encode([0x99; 32]);
+        let header = br#"{"alg":"RS256","x5c":["MIIB"]}"#;
+        let payload = format!(r#"{{"nonce":"{nonce}","ctsProfileMatch":true}}"#);
+        let response = format!(
+            "{}.{}.{}",
+            b64(header),
+            b64(payload.as_bytes()),
+            b64(&[1, 2, 3])
+        );
+
+        let att_stmt = text_map(vec![
+            ("ver", Value::Text("231313000".to_string())),
+            ("response", Value::Bytes(response.into_bytes())),
+        ]);
+        let encoded = encode_test_attestation_obj("android-safetynet", att_stmt);
+
+        let parsed: AttestationObject = from_slice(&encoded).expect("Failed to parse");
+        assert_eq!(to_vec(&parsed).unwrap(), encoded);
+        let att = match &parsed.att_stmt {
+            AttestationStatement::AndroidSafetyNet(att) => att,
+            other => panic!("Unexpected attestation statement {:?}", other),
+        };
+        assert_eq!(att.ver, "231313000");
+        let segments = att.jws_segments().expect("Failed to split JWS");
+        assert_eq!(segments.header, header.to_vec());
+        assert_eq!(segments.signature, vec![1, 2, 3]);
+        assert_eq!(
+            segments.signing_input,
+            format!("{}.{}", b64(header), b64(payload.as_bytes())).into_bytes()
+        );
+        let payload: serde_json::Value =
+            serde_json::from_slice(&segments.payload).expect("Failed to parse payload");
+        assert_eq!(payload["nonce"], nonce);
+
+        let att = AttestationStatementAndroidSafetyNet {
+            ver: "1".to_string(),
+            response: b"a.b".to_vec(),
+        };
+        assert_matches!(att.jws_segments(), Err(CryptoError::MalformedInput));
+        let att = AttestationStatementAndroidSafetyNet {
+            ver: "1".to_string(),
+            response: b"a.b!.c".to_vec(),
+        };
+        assert_matches!(att.jws_segments(), Err(CryptoError::MalformedInput));
+    }
+
+    #[test]
+    fn test_verify_attestation_chain() {
+        let cert = |der: &[u8]| AttestationCertificate(der.to_vec());
+        let att_obj = |chain: Vec<AttestationCertificate>| AttestationObject {
+            auth_data: create_attestation_obj().auth_data,
+            att_stmt: AttestationStatement::Packed(AttestationStatementPacked {
+                alg: COSEAlgorithm::ES256,
+                sig: Signature(vec![0x00; 8]),
+                attestation_cert: chain,
+            }),
+        };
+        let roots = [cert(&TEST_ROOT_CERT)];
+        let valid = UNIX_EPOCH + std::time::Duration::from_secs(1_800_000_000);
+
+        let chain = vec![cert(&TEST_LEAF_CERT), cert(&TEST_INTERMEDIATE_CERT)];
+        assert!(att_obj(chain).verify_attestation_at(&roots, valid).is_ok());
+        // The order of the intermediates doesn't matter, and the root may be included
+        let chain = vec![
+            cert(&TEST_LEAF_CERT),
+            cert(&TEST_ROOT_CERT),
+            cert(&TEST_INTERMEDIATE_CERT),
+        ];
+        assert!(att_obj(chain).verify_attestation_at(&roots, valid).is_ok());
+
+        let chain = || vec![cert(&TEST_LEAF_CERT), cert(&TEST_INTERMEDIATE_CERT)];
+        // Missing intermediate
+        assert_matches!(
+            att_obj(vec![cert(&TEST_LEAF_CERT)]).verify_attestation_at(&roots, valid),
+            Err(AttestationError::UntrustedChain)
+        );
+        // Intermediates can be trust anchors as well
+        assert!(att_obj(chain())
+            .verify_attestation_at(&[cert(&TEST_INTERMEDIATE_CERT)], valid)
+            .is_ok());
+        // Other roots
+        assert_matches!(
+            att_obj(chain()).verify_attestation_at(&[cert(&TEST_LEAF_CERT)], valid),
+            Err(AttestationError::UntrustedChain)
+        );
+        assert_matches!(
+            att_obj(chain()).verify_attestation_at(&[], valid),
+            Err(AttestationError::UntrustedChain)
+        );
+        // Expired, and not yet valid
+        let expired = UNIX_EPOCH + std::time::Duration::from_secs(5_000_000_000);
+        assert_matches!(
+            att_obj(chain()).verify_attestation_at(&roots, expired),
+            Err(AttestationError::CertificateNotValid)
+        );
+        assert_matches!(
+            att_obj(chain()).verify_attestation_at(&roots, UNIX_EPOCH),
+            Err(AttestationError::CertificateNotValid)
+        );
+
+        // Tampered signature of the leaf
+        let mut leaf = TEST_LEAF_CERT.to_vec();
+        let last = leaf.len() - 1;
+        leaf[last] ^= 0x01;
+        let chain = vec![cert(&leaf), cert(&TEST_INTERMEDIATE_CERT)];
+        assert_matches!(
+            att_obj(chain).verify_attestation_at(&roots, valid),
+            Err(AttestationError::Signature(_))
+        );
+        // Tampered contents of the intermediate (the last byte of its public key)
+        let mut intermediate = TEST_INTERMEDIATE_CERT.to_vec();
+        let spki = x509::Certificate::from_der(&TEST_INTERMEDIATE_CERT)
+            .unwrap()
+            .subject_public_key_info
+            .to_vec();
+        let offset = intermediate
+            .windows(spki.len())
+            .position(|w| w == spki.as_slice())
+            .unwrap();
+        intermediate[offset + spki.len() - 1] ^= 0x01;
+        let chain = vec![cert(&TEST_LEAF_CERT), cert(&intermediate)];
+        assert_matches!(
+            att_obj(chain).verify_attestation_at(&roots, valid),
+            Err(AttestationError::Signature(_))
+        );
+
+        // AAGUID mismatch
+        let chain = vec![cert(&TEST_LEAF_CERT), cert(&TEST_INTERMEDIATE_CERT)];
+        let mut obj = att_obj(chain);
+        if let Some(credential_data) = obj.auth_data.credential_data.as_mut() {
+            credential_data.aaguid = AAGuid::default();
+        }
+        assert_matches!(
+            obj.verify_attestation_at(&roots, valid),
+            Err(AttestationError::AaguidMismatch)
+        );
+
+        // Only CAs can issue certificates
+        let short_lived_roots = [cert(&TEST_SHORT_LIVED_ROOT_CERT)];
+        assert!(att_obj(vec![cert(&TEST_LEAF_ISSUER_CERT)])
+            .verify_attestation_at(&short_lived_roots, valid)
+            .is_ok());
+        let chain = vec![
+            cert(&TEST_LEAF_ISSUED_BY_LEAF_CERT),
+            cert(&TEST_LEAF_ISSUER_CERT),
+        ];
+        assert_matches!(
+            att_obj(chain).verify_attestation_at(&short_lived_roots, valid),
+            Err(AttestationError::BasicConstraints)
+        );
+        assert_matches!(
+            att_obj(vec![cert(&TEST_LEAF_ISSUED_BY_LEAF_CERT)])
+                .verify_attestation_at(&[cert(&TEST_LEAF_ISSUER_CERT)], valid),
+            Err(AttestationError::BasicConstraints)
+        );
+        // The attestation certificate of packed attestation must not be a CA
+        assert_matches!(
+            att_obj(vec![cert(&TEST_INTERMEDIATE_CERT)]).verify_attestation_at(&roots, valid),
+            Err(AttestationError::BasicConstraints)
+        );
+        // The root has to be valid, too
+        let root_expired = UNIX_EPOCH + std::time::Duration::from_secs(1_900_000_000);
+        assert_matches!(
+            att_obj(vec![cert(&TEST_LEAF_ISSUER_CERT)])
+                .verify_attestation_at(&short_lived_roots, root_expired),
+            Err(AttestationError::CertificateNotValid)
+        );
+
+        // No chain
+        assert_matches!(
+            att_obj(vec![]).verify_attestation_at(&roots, valid),
+            Err(AttestationError::NoCertificateChain)
+        );
+        assert_matches!(
+            att_obj(vec![cert(&[0x30, 0x00])]).verify_attestation_at(&roots, valid),
+            Err(AttestationError::MalformedCertificate)
+        );
+    }
 }
@@ -1,4 +1,5 @@
 use super::commands::CommandError;
+use crate::crypto::CryptoError;
 use crate::transport::errors::HIDError;
 use base64::Engine;
 use serde::de::{self, Deserializer, Error as SerdeError, MapAccess, Visitor};
@@ -6,6 +7,7 @@ use serde::ser::SerializeMap;
 use serde::{Deserialize, Serialize, Serializer};
 use serde_json as json;
 use sha2::{Digest, Sha256};
+use std::convert::TryFrom;
 use std::fmt;
 
 /// https://w3c.github.io/webauthn/#dom-collectedclientdata-tokenbinding
@@ -216,21 +218,40 @@ pub struct CollectedClientData {
 }
 
 impl CollectedClientData {
+    /// The SHA-256 hash of the JSON serialized client data, as WebAuthn defines it.
     pub fn hash(&self) -> Result<ClientDataHash, HIDError> {
+        self.hash_with(|json| Sha256::digest(json).to_vec())
+    }
+
+    /// Like `hash()`, but with another hash function, e.g. for clients that don't follow
+    /// WebAuthn. CTAP requires the result to be 32 bytes long.
+    pub fn hash_with<F>(&self, hasher: F) -> Result<ClientDataHash, HIDError>
+    where
+        F: FnOnce(&[u8]) -> Vec<u8>,
+    {
         // WebIDL's dictionary definition specifies that the order of the struct
         // is exactly as the WebIDL specification declares it, with an algorithm
         // for partial dictionaries, so that's how interop works for these
         // things.
         // See: https://heycam.github.io/webidl/#dfn-dictionary
         let json = json::to_vec(&self).map_err(CommandError::Json)?;
-        let digest = Sha256::digest(json);
-        Ok(ClientDataHash(digest.into()))
+        let digest = hasher(&json);
+        let digest = <[u8; 32]>::try_from(digest.as_slice())
+            .map_err(|_| CommandError::Crypto(CryptoError::MalformedInput))?;
+        Ok(ClientDataHash::from_raw(digest))
     }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct ClientDataHash(pub [u8; 32]);
 
+impl ClientDataHash {
+    /// A hash computed by the caller. WebAuthn clients should use `CollectedClientData::hash()`.
+    pub fn from_raw(bytes: [u8; 32]) -> Self {
+        ClientDataHash(bytes)
+    }
+}
+
 impl PartialEq<[u8]> for ClientDataHash {
     fn eq(&self, other: &[u8]) -> bool {
         self.0.eq(other)
@@ -256,6 +277,7 @@ impl Serialize for ClientDataHash {
 mod test {
     use super::{Challenge, ClientDataHash, CollectedClientData, TokenBinding, WebauthnType};
     use serde_json as json;
+    use sha2::{Digest, Sha256, Sha512};
 
     #[test]
     fn test_token_binding_status() {
@@ -336,4 +358,37 @@ mod test {
             ])
         );
     }
+
+    #[test]
+    fn test_collected_client_data_hash_with() {
+        let client_data = CollectedClientData {
+            webauthn_type: WebauthnType::Get,
+            challenge: Challenge::new(vec![0x00, 0x01, 0x02, 0x03]),
+            origin: String::from("example.com"),
+            cross_origin: false,
+            token_binding: None,
+        };
+        let sha256 = client_data.hash().expect("failed to serialize client data");
+        assert_eq!(
+            client_data
+                .hash_with(|json| Sha256::digest(json).to_vec())
+                .expect("failed to hash client data"),
+            sha256
+        );
+
+        let sha512 = Sha512::digest(json::to_vec(&client_data).unwrap());
+        let truncated = client_data
+            .hash_with(|json| Sha512::digest(json)[..32].to_vec())
+            .expect("failed to hash client data");
+        assert_ne!(truncated, sha256);
+        assert_eq!(truncated, sha512[..32]);
+
+        // CTAP needs 32 bytes
+        assert!(client_data
+            .hash_with(|json| Sha512::digest(json).to_vec())
+            .is_err());
+        assert!(client_data.hash_with(|_| vec![]).is_err());
+
+        assert_eq!(ClientDataHash::from_raw([7; 32]), ClientDataHash([7; 32]));
+    }
 }
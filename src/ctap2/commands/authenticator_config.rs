@@ -1,6 +1,7 @@
-use super::{Command, CommandError, PinUvAuthCommand, RequestCtap2, StatusCode};
+use super::{Command, CommandError, PinUvAuthCommand, RequestCtap2, StatusCode, UvReason};
 use crate::{
     crypto::{PinUvAuthParam, PinUvAuthToken},
+    ctap2::commands::client_pin::PinUvAuthTokenPermission,
     ctap2::server::UserVerificationRequirement,
     errors::AuthenticatorError,
     transport::errors::HIDError,
@@ -8,6 +9,7 @@ use crate::{
 };
 use serde::{ser::SerializeMap, Deserialize, Serialize, Serializer};
 use serde_cbor::{de::from_slice, to_vec, Value};
+use std::fmt;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct SetMinPINLength {
@@ -67,6 +69,79 @@ impl AuthConfigCommand {
     }
 }
 
+impl AuthConfigCommand {
+    /// Checks the subcommand parameters against the limits the authenticator reported
+    /// in getInfo, so that we can bail out before sending a request it will reject.
+    pub fn check_limits(&self, info: &AuthenticatorInfo) -> Result<(), AuthConfigError> {
+        let params = match self {
+            AuthConfigCommand::SetMinPINLength(params) => params,
+            _ => return Ok(()),
+        };
+        if info.options.set_min_pin_length != Some(true) {
+            return Err(AuthConfigError::SetMinPINLengthUnsupported);
+        }
+        if let Some(new_min_pin_length) = params.new_min_pin_length {
+            // The spec defaults to 4, if the authenticator doesn't report a minimum.
+            let current = info.min_pin_length.unwrap_or(4);
+            if new_min_pin_length < current {
+                return Err(AuthConfigError::MinPINLengthDecreased(
+                    new_min_pin_length,
+                    current,
+                ));
+            }
+        }
+        if let Some(rpids) = params
+            .min_pin_length_rpids
+            .as_ref()
+            .filter(|r| !r.is_empty())
+        {
+            if !info.supports_extension("minPinLength") {
+                return Err(AuthConfigError::MinPINLengthExtensionUnsupported);
+            }
+            // An absent maxRPIDsForSetMinPINLength means no RP IDs can be added.
+            let max = info.max_rpids_for_set_min_pin_length.unwrap_or(0);
+            if rpids.len() as u64 > max {
+                return Err(AuthConfigError::TooManyMinPINLengthRPIDs(rpids.len(), max));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuthConfigError {
+    /// The authenticator does not support the setMinPINLength subcommand
+    SetMinPINLengthUnsupported,
+    /// The minimum PIN length can only be increased (requested, current)
+    MinPINLengthDecreased(u64, u64),
+    /// minPinLengthRPIDs were given, but the minPinLength extension is not supported
+    MinPINLengthExtensionUnsupported,
+    /// More minPinLengthRPIDs than maxRPIDsForSetMinPINLength (requested, maximum)
+    TooManyMinPINLengthRPIDs(usize, u64),
+}
+
+impl fmt::Display for AuthConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AuthConfigError::SetMinPINLengthUnsupported => {
+                write!(f, "setMinPINLength is not supported by the authenticator")
+            }
+            AuthConfigError::MinPINLengthDecreased(requested, current) => write!(
+                f,
+                "minimum PIN length can't be decreased from {current} to {requested}"
+            ),
+            AuthConfigError::MinPINLengthExtensionUnsupported => write!(
+                f,
+                "minPinLengthRPIDs given, but minPinLength extension is not supported"
+            ),
+            AuthConfigError::TooManyMinPINLengthRPIDs(requested, max) => write!(
+                f,
+                "too many minPinLengthRPIDs ({requested}), authenticator accepts at most {max}"
+            ),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub enum AuthConfigResult {
     Success(AuthenticatorInfo),
@@ -74,7 +149,7 @@ pub enum AuthConfigResult {
 
 #[derive(Debug)]
 pub struct AuthenticatorConfig {
-    subcommand: AuthConfigCommand, // subCommand currently being requested
+    pub(crate) subcommand: AuthConfigCommand, // subCommand currently being requested
     pin_uv_auth_param: Option<PinUvAuthParam>, // First 16 bytes of HMAC-SHA-256 of contents using pinUvAuthToken.
 }
 
@@ -148,7 +223,7 @@ impl RequestCtap2 for AuthenticatorConfig {
         Dev: FidoDevice,
     {
         if input.is_empty() {
-            return Err(CommandError::InputTooSmall.into());
+            return Err(CommandError::EmptyResponse.into());
         }
 
         let status: StatusCode = input[0].into();
@@ -181,6 +256,7 @@ impl PinUvAuthCommand for AuthenticatorConfig {
     ) -> Result<(), AuthenticatorError> {
         let mut param = None;
         if let Some(token) = pin_uv_auth_token {
+            self.check_permissions(&token)?;
             // pinUvAuthParam (0x04): the result of calling
             // authenticate(pinUvAuthToken, 32×0xff || 0x0d || uint8(subCommand) || subCommandParams).
             let mut data = vec![0xff; 32];
@@ -203,12 +279,17 @@ impl PinUvAuthCommand for AuthenticatorConfig {
         Ok(())
     }
 
-    fn can_skip_user_verification(
-        &mut self,
+    fn user_verification_requirement_reason(
+        &self,
         authinfo: &AuthenticatorInfo,
         _uv_req: UserVerificationRequirement,
-    ) -> bool {
-        !authinfo.device_is_protected()
+    ) -> UvReason {
+        // Unprotected devices can be configured, e.g. before handing them out
+        if authinfo.device_is_protected() {
+            UvReason::DeviceProtected
+        } else {
+            UvReason::NotRequired
+        }
     }
 
     fn set_uv_option(&mut self, _uv: Option<bool>) {
@@ -222,4 +303,8 @@ impl PinUvAuthCommand for AuthenticatorConfig {
     fn get_rp_id(&self) -> Option<&String> {
         None
     }
+
+    fn required_permissions(&self) -> PinUvAuthTokenPermission {
+        PinUvAuthTokenPermission::AuthenticatorConfiguration
+    }
 }
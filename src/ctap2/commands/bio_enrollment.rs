@@ -1,5 +1,6 @@
 use crate::{
     crypto::{PinUvAuthParam, PinUvAuthToken},
+    ctap2::commands::client_pin::PinUvAuthTokenPermission,
     ctap2::server::UserVerificationRequirement,
     errors::AuthenticatorError,
     transport::errors::HIDError,
@@ -14,7 +15,9 @@ use serde_bytes::ByteBuf;
 use serde_cbor::{from_slice, to_vec, Value};
 use std::fmt;
 
-use super::{Command, CommandError, CtapResponse, PinUvAuthCommand, RequestCtap2, StatusCode};
+use super::{
+    Command, CommandError, CtapResponse, PinUvAuthCommand, RequestCtap2, StatusCode, UvReason,
+};
 
 #[derive(Debug, Clone, Copy)]
 pub enum BioEnrollmentModality {
@@ -221,12 +224,17 @@ impl PinUvAuthCommand for BioEnrollment {
         None
     }
 
+    fn required_permissions(&self) -> PinUvAuthTokenPermission {
+        PinUvAuthTokenPermission::BioEnrollment
+    }
+
     fn set_pin_uv_auth_param(
         &mut self,
         pin_uv_auth_token: Option<PinUvAuthToken>,
     ) -> Result<(), AuthenticatorError> {
         let mut param = None;
         if let Some(token) = pin_uv_auth_token {
+            self.check_permissions(&token)?;
             // pinUvAuthParam (0x04): the result of calling
             // authenticate(pinUvAuthToken, fingerprint (0x01) || uint8(subCommand) || subCommandParams).
             let (id, params) = self.subcommand.to_id_and_param();
@@ -241,13 +249,13 @@ impl PinUvAuthCommand for BioEnrollment {
         Ok(())
     }
 
-    fn can_skip_user_verification(
-        &mut self,
+    fn user_verification_requirement_reason(
+        &self,
         _info: &crate::AuthenticatorInfo,
         _uv: UserVerificationRequirement,
-    ) -> bool {
+    ) -> UvReason {
         // "discouraged" does not exist for BioEnrollment
-        false
+        UvReason::ManagementCommand
     }
 
     fn set_uv_option(&mut self, _uv: Option<bool>) {
@@ -285,7 +293,7 @@ impl RequestCtap2 for BioEnrollment {
         Dev: FidoDevice,
     {
         if input.is_empty() {
-            return Err(CommandError::InputTooSmall.into());
+            return Err(CommandError::EmptyResponse.into());
         }
 
         let status: StatusCode = input[0].into();
@@ -293,7 +301,7 @@ impl RequestCtap2 for BioEnrollment {
             if input.len() > 1 {
                 trace!("parsing bio enrollment response data: {:#04X?}", &input);
                 let bio_enrollment =
-                    from_slice(&input[1..]).map_err(CommandError::Deserializing)?;
+                    from_slice(&input[1..]).map_err(CommandError::from_response_body)?;
                 Ok(bio_enrollment)
             } else {
                 // Some subcommands return only an OK-status without any data
@@ -658,3 +666,56 @@ pub enum BioEnrollmentResult {
     FingerprintSensorInfo(FingerprintSensorInfo),
     SampleStatus(LastEnrollmentSampleStatus, u64),
 }
+
+#[cfg(test)]
+mod test {
+    use super::{BioEnrollment, BioEnrollmentCommand};
+    use crate::crypto::{PinUvAuthProtocol, PinUvAuthToken};
+    use crate::ctap2::commands::client_pin::PinUvAuthTokenPermission;
+    use crate::ctap2::commands::{PinUvAuthCommand, RequestCtap2};
+    use crate::AuthenticatorInfo;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_bio_enrollment_pin_uv_auth_param() {
+        let info = AuthenticatorInfo {
+            pin_protocols: Some(vec![1]),
+            ..Default::default()
+        };
+        let token = PinUvAuthToken::new_test_token(
+            PinUvAuthProtocol::try_from(&info).unwrap(),
+            (0x00..0x20).collect(),
+            PinUvAuthTokenPermission::BioEnrollment,
+        );
+
+        let mut cmd = BioEnrollment::new(
+            BioEnrollmentCommand::SetFriendlyName((vec![0x01, 0x02], String::from("finger"))),
+            false,
+        );
+        // authenticate(pinUvAuthToken, modality || subCommand || subCommandParams), i.e.
+        // HMAC-SHA-256 over 0x01 0x05 a2 01 42 0102 02 66 "finger", truncated to 16 bytes
+        cmd.set_pin_uv_auth_param(Some(token)).unwrap();
+        assert_eq!(
+            cmd.wire_format().unwrap(),
+            vec![
+                0xa5, // map(5)
+                0x01, // modality
+                0x01, // fingerprint
+                0x02, // subCommand
+                0x05, // setFriendlyName
+                0x03, // subCommandParams
+                0xa2, // map(2)
+                0x01, // templateId
+                0x42, 0x01, 0x02, // bytes(2)
+                0x02, // templateFriendlyName
+                0x66, 0x66, 0x69, 0x6e, 0x67, 0x65, 0x72, // "finger"
+                0x04, // pinUvAuthProtocol
+                0x01, // 1
+                0x05, // pinUvAuthParam
+                0x50, // bytes(16)
+                0xd6, 0x2a, 0x95, 0x63, 0x51, 0xfc, 0xc3, 0xe8, 0x80, 0xad, 0x8f, 0x7f, 0xda, 0x25,
+                0xe7, 0x24,
+            ]
+        );
+    }
+}
@@ -158,6 +158,45 @@ pub struct ClientPinResponse {
 
 impl CtapResponse for ClientPinResponse {}
 
+/// Number of consecutive wrong PINs after which authenticators refuse further attempts,
+/// until they are power cycled.
+pub const MAX_PIN_ATTEMPTS_PER_BOOT: u8 = 3;
+
+/// The PIN retry state of an authenticator, as returned by getPINRetries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PinRetries {
+    /// Remaining PIN attempts before the PIN is blocked, and the authenticator has to be reset.
+    pub retries: u8,
+    /// If true, the authenticator has to be power cycled (e.g. reinserted) before it accepts
+    /// another PIN. Only CTAP 2.1 authenticators report this.
+    pub power_cycle_state: Option<bool>,
+}
+
+impl PinRetries {
+    /// Remaining PIN attempts before the authenticator has to be power cycled. Authenticators
+    /// don't report how many wrong PINs were entered since they were powered on, so this
+    /// assumes none, unless a power cycle is required already.
+    pub fn attempts_before_power_cycle(&self) -> u8 {
+        if self.power_cycle_state == Some(true) {
+            return 0;
+        }
+        self.retries.min(MAX_PIN_ATTEMPTS_PER_BOOT)
+    }
+}
+
+impl TryFrom<&ClientPinResponse> for PinRetries {
+    type Error = CommandError;
+
+    fn try_from(resp: &ClientPinResponse) -> Result<Self, Self::Error> {
+        Ok(PinRetries {
+            retries: resp
+                .pin_retries
+                .ok_or(CommandError::MissingRequiredField("pin_retries"))?,
+            power_cycle_state: resp.power_cycle_state,
+        })
+    }
+}
+
 impl<'de> Deserialize<'de> for ClientPinResponse {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -592,7 +631,7 @@ where
     ) -> Result<Self::Output, HIDError> {
         trace!("Client pin subcomand response:{:04X?}", &input);
         if input.is_empty() {
-            return Err(CommandError::InputTooSmall.into());
+            return Err(CommandError::EmptyResponse.into());
         }
 
         let status: StatusCode = input[0].into();
@@ -668,6 +707,8 @@ pub enum PinError {
     UvBlocked,
     /// Used for CTAP2.0 UV (fingerprints)
     PinAuthInvalid,
+    /// The pinUvAuthToken was not granted the permissions needed for the command
+    MissingPermissions,
     Crypto(CryptoError),
 }
 
@@ -698,6 +739,12 @@ impl fmt::Display for PinError {
             PinError::PinNotSet => write!(f, "Pin needed but not set on device."),
             PinError::UvBlocked => write!(f, "No retries left. Uv blocked. Device needs reset."),
             PinError::PinAuthInvalid => write!(f, "PinAuth invalid."),
+            PinError::MissingPermissions => {
+                write!(
+                    f,
+                    "PinUvAuthToken lacks the permissions for this operation."
+                )
+            }
             PinError::Crypto(ref e) => write!(f, "Crypto backend error: {e:?}"),
         }
     }
@@ -712,19 +759,42 @@ impl From<CryptoError> for PinError {
 }
 
 #[cfg(test)]
-mod test {
-    use super::ClientPinResponse;
-    use crate::crypto::{COSEAlgorithm, COSEEC2Key, COSEKey, COSEKeyType, Curve};
+pub mod test {
+    use super::{ClientPinResponse, PinError, PinRetries, PinUvAuthTokenPermission};
+    use crate::crypto::{COSEAlgorithm, COSEEC2Key, COSEKey, COSEKeyType, Curve, SharedSecret};
+    use crate::ctap2::commands::get_info::AuthenticatorInfo;
+    use crate::ctap2::commands::{repackage_pin_errors, Command};
+    use crate::errors::AuthenticatorError;
+    use crate::transport::platform::recording::{RecordedRequest, RecordingDevice};
+    use crate::transport::{FidoDevice, FidoProtocol};
     use serde_cbor::de::from_slice;
+    use serde_cbor::Value;
+    use std::collections::BTreeMap;
+
+    pub const KEY_AGREEMENT_RESPONSE: [u8; 80] = [
+        161, 1, 165, 1, 2, 3, 56, 24, 32, 1, 33, 88, 32, 115, 222, 167, 5, 88, 238, 119, 202, 121,
+        23, 241, 150, 9, 48, 197, 136, 174, 0, 17, 90, 190, 83, 65, 103, 237, 97, 41, 213, 128,
+        111, 7, 106, 34, 88, 32, 248, 204, 9, 26, 82, 96, 25, 72, 5, 82, 251, 185, 22, 39, 246,
+        149, 54, 246, 255, 225, 52, 102, 67, 221, 113, 194, 236, 213, 199, 147, 180, 81,
+    ];
+
+    fn device_with_shared_secret() -> (RecordingDevice, SharedSecret) {
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.set_authenticator_info(AuthenticatorInfo {
+            pin_protocols: Some(vec![1]),
+            ..Default::default()
+        });
+        device.add_response(&[&[0x00][..], &KEY_AGREEMENT_RESPONSE[..]].concat());
+        let shared_secret = device
+            .establish_shared_secret(&|| true)
+            .expect("Failed to establish shared secret");
+        device.requests.clear();
+        (device, shared_secret)
+    }
 
     #[test]
     fn test_get_key_agreement() {
-        let reference = [
-            161, 1, 165, 1, 2, 3, 56, 24, 32, 1, 33, 88, 32, 115, 222, 167, 5, 88, 238, 119, 202,
-            121, 23, 241, 150, 9, 48, 197, 136, 174, 0, 17, 90, 190, 83, 65, 103, 237, 97, 41, 213,
-            128, 111, 7, 106, 34, 88, 32, 248, 204, 9, 26, 82, 96, 25, 72, 5, 82, 251, 185, 22, 39,
-            246, 149, 54, 246, 255, 225, 52, 102, 67, 221, 113, 194, 236, 213, 199, 147, 180, 81,
-        ];
+        let reference = KEY_AGREEMENT_RESPONSE;
         let expected = ClientPinResponse {
             key_agreement: Some(COSEKey {
                 alg: COSEAlgorithm::ECDH_ES_HKDF256,
@@ -765,6 +835,34 @@ mod test {
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn test_pin_retries_power_cycle_state() {
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        // pinRetries: 7
+        device.add_response(&[0x00, 0xa1, 0x03, 0x07]);
+        // pinRetries: 5, powerCycleState: true
+        device.add_response(&[0x00, 0xa2, 0x03, 0x05, 0x04, 0xf5]);
+        // pinRetries: 2, powerCycleState: false
+        device.add_response(&[0x00, 0xa2, 0x03, 0x02, 0x04, 0xf4]);
+
+        let retries = device.get_pin_retries().expect("Failed to get PIN retries");
+        assert_eq!(
+            retries,
+            PinRetries {
+                retries: 7,
+                power_cycle_state: None
+            }
+        );
+        assert_eq!(retries.attempts_before_power_cycle(), 3);
+
+        let retries = device.get_pin_retries().expect("Failed to get PIN retries");
+        assert_eq!(retries.power_cycle_state, Some(true));
+        assert_eq!(retries.attempts_before_power_cycle(), 0);
+
+        let retries = device.get_pin_retries().expect("Failed to get PIN retries");
+        assert_eq!(retries.attempts_before_power_cycle(), 2);
+    }
+
     #[test]
     fn test_get_uv_retries() {
         let reference = [161, 5, 2];
@@ -848,4 +946,76 @@ mod test {
             from_slice(&reference).expect("could not deserialize reference");
         assert_eq!(expected, result);
     }
+
+    #[test]
+    fn test_get_puat_using_uv_with_permissions() {
+        let (mut device, shared_secret) = device_with_shared_secret();
+        let pin_token = shared_secret.encrypt(&[0x01; 32]).unwrap();
+        let mut response = vec![0x00, 0xa1, 0x02, 0x58, pin_token.len() as u8];
+        response.extend(pin_token);
+        device.add_response(&response);
+
+        let rp_id = String::from("example.com");
+        let token = device
+            .get_pin_uv_auth_token_using_uv_with_permissions(
+                PinUvAuthTokenPermission::GetAssertion,
+                Some(&rp_id),
+                &|| true,
+            )
+            .expect("Failed to get pinUvAuthToken using UV");
+        assert_eq!(token.permissions, PinUvAuthTokenPermission::GetAssertion);
+        assert_eq!(token.pin_protocol.id(), 1);
+
+        let request = match &device.requests[..] {
+            [RecordedRequest::Ctap2(Command::ClientPin, payload)] => {
+                from_slice::<BTreeMap<u8, Value>>(payload).unwrap()
+            }
+            requests => panic!("Unexpected requests {:?}", requests),
+        };
+        assert_eq!(request.get(&0x02), Some(&Value::Integer(0x06)));
+        assert!(request.contains_key(&0x03));
+        // No PIN involved
+        assert!(!request.contains_key(&0x06));
+        assert_eq!(request.get(&0x09), Some(&Value::Integer(0x02)));
+        assert_eq!(request.get(&0x0A), Some(&Value::Text(rp_id)));
+    }
+
+    #[test]
+    fn test_get_puat_using_uv_errors() {
+        let (mut device, _) = device_with_shared_secret();
+        // CTAP2_ERR_UV_INVALID, followed by getUVRetries (uvRetries: 2)
+        device.add_response(&[0x3F]);
+        device.add_response(&[0x00, 0xa1, 0x05, 0x02]);
+        let err = device
+            .get_pin_uv_auth_token_using_uv_with_permissions(
+                PinUvAuthTokenPermission::MakeCredential,
+                None,
+                &|| true,
+            )
+            .expect_err("UV should have failed");
+        assert_matches!(
+            repackage_pin_errors(&mut device, err),
+            AuthenticatorError::PinError(PinError::InvalidUv(Some(2)))
+        );
+
+        // CTAP2_ERR_UV_BLOCKED
+        device.add_response(&[0x3C]);
+        let err = device
+            .get_pin_uv_auth_token_using_uv_with_permissions(
+                PinUvAuthTokenPermission::MakeCredential,
+                None,
+                &|| true,
+            )
+            .expect_err("UV should be blocked");
+        assert_matches!(
+            repackage_pin_errors(&mut device, err),
+            AuthenticatorError::PinError(PinError::UvBlocked)
+        );
+
+        device.add_response(&[0x00, 0xa1, 0x05, 0x00]);
+        assert_eq!(
+            device.get_uv_retries().expect("Failed to get UV retries"),
+            0
+        );
+    }
 }
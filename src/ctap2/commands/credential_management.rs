@@ -1,6 +1,9 @@
-use super::{Command, CommandError, CtapResponse, PinUvAuthCommand, RequestCtap2, StatusCode};
+use super::{
+    Command, CommandError, CtapResponse, PinUvAuthCommand, RequestCtap2, StatusCode, UvReason,
+};
 use crate::{
     crypto::{COSEKey, PinUvAuthParam, PinUvAuthToken},
+    ctap2::commands::client_pin::PinUvAuthTokenPermission,
     ctap2::server::{
         PublicKeyCredentialDescriptor, PublicKeyCredentialUserEntity, RelyingParty, RpIdHash,
         UserVerificationRequirement,
@@ -16,6 +19,7 @@ use serde::{
 };
 use serde_bytes::ByteBuf;
 use serde_cbor::{de::from_slice, to_vec, Value};
+use std::collections::HashSet;
 use std::fmt;
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -201,6 +205,10 @@ pub enum CredentialManagementResult {
     CredentialList(CredentialList),
     DeleteSucess,
     UpdateSuccess,
+    /// Sent while enumerating the credentials of `CredManagementCmd::GetCredentials`, with the
+    /// number of discoverable credentials that are still to be fetched. It starts with the
+    /// count of `getCredsMetadata`, and the `CredentialList` follows once it reaches zero.
+    EnumerationProgress(u64),
 }
 
 #[derive(Debug, Default, Serialize)]
@@ -219,6 +227,33 @@ impl CredentialList {
     }
 }
 
+/// Compares the discoverable credentials found on a device (e.g. from
+/// `CredentialList::credential_list`) with the ones a server knows about. Returns the
+/// credentials only present on the device (orphaned, candidates for deletion) and the ones
+/// only known to the server (missing on the device), each in their original order.
+/// Credentials are matched by ID only, transports are ignored.
+pub fn diff_credentials(
+    device_creds: &[PublicKeyCredentialDescriptor],
+    server_creds: &[PublicKeyCredentialDescriptor],
+) -> (
+    Vec<PublicKeyCredentialDescriptor>,
+    Vec<PublicKeyCredentialDescriptor>,
+) {
+    let device_ids: HashSet<&[u8]> = device_creds.iter().map(|c| c.id.as_slice()).collect();
+    let server_ids: HashSet<&[u8]> = server_creds.iter().map(|c| c.id.as_slice()).collect();
+    let orphaned = device_creds
+        .iter()
+        .filter(|c| !server_ids.contains(c.id.as_slice()))
+        .cloned()
+        .collect();
+    let missing = server_creds
+        .iter()
+        .filter(|c| !device_ids.contains(c.id.as_slice()))
+        .cloned()
+        .collect();
+    (orphaned, missing)
+}
+
 impl<'de> Deserialize<'de> for CredentialManagementResponse {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -381,7 +416,7 @@ impl RequestCtap2 for CredentialManagement {
         Dev: FidoDevice,
     {
         if input.is_empty() {
-            return Err(CommandError::InputTooSmall.into());
+            return Err(CommandError::EmptyResponse.into());
         }
 
         let status: StatusCode = input[0].into();
@@ -390,7 +425,7 @@ impl RequestCtap2 for CredentialManagement {
             if input.len() > 1 {
                 trace!("parsing credential management data: {:#04X?}", &input);
                 let credential_management =
-                    from_slice(&input[1..]).map_err(CommandError::Deserializing)?;
+                    from_slice(&input[1..]).map_err(CommandError::from_response_body)?;
                 Ok(credential_management)
             } else {
                 // Some subcommands return only an OK-status without any data
@@ -419,12 +454,17 @@ impl PinUvAuthCommand for CredentialManagement {
         None
     }
 
+    fn required_permissions(&self) -> PinUvAuthTokenPermission {
+        PinUvAuthTokenPermission::CredentialManagement
+    }
+
     fn set_pin_uv_auth_param(
         &mut self,
         pin_uv_auth_token: Option<PinUvAuthToken>,
     ) -> Result<(), AuthenticatorError> {
         let mut param = None;
         if let Some(token) = pin_uv_auth_token {
+            self.check_permissions(&token)?;
             // pinUvAuthParam (0x04): the result of calling
             // authenticate(pinUvAuthToken, uint8(subCommand) || subCommandParams).
             let (id, params) = self.subcommand.to_id_and_param();
@@ -438,13 +478,13 @@ impl PinUvAuthCommand for CredentialManagement {
         Ok(())
     }
 
-    fn can_skip_user_verification(
-        &mut self,
+    fn user_verification_requirement_reason(
+        &self,
         _info: &crate::AuthenticatorInfo,
         _uv: UserVerificationRequirement,
-    ) -> bool {
+    ) -> UvReason {
         // "discouraged" does not exist for AuthenticatorConfig
-        false
+        UvReason::ManagementCommand
     }
 
     fn set_uv_option(&mut self, _uv: Option<bool>) {
@@ -455,3 +495,37 @@ impl PinUvAuthCommand for CredentialManagement {
         self.pin_uv_auth_param.as_ref()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::diff_credentials;
+    use crate::ctap2::server::{PublicKeyCredentialDescriptor, Transport};
+
+    fn cred(id: u8, transports: Vec<Transport>) -> PublicKeyCredentialDescriptor {
+        PublicKeyCredentialDescriptor {
+            id: vec![id; 16],
+            transports,
+        }
+    }
+
+    #[test]
+    fn test_diff_credentials() {
+        let device = [cred(1, vec![]), cred(2, vec![]), cred(3, vec![])];
+        let server = [
+            cred(4, vec![]),
+            cred(2, vec![Transport::USB]),
+            cred(5, vec![]),
+        ];
+        let (orphaned, missing) = diff_credentials(&device, &server);
+        assert_eq!(orphaned, vec![cred(1, vec![]), cred(3, vec![])]);
+        assert_eq!(missing, vec![cred(4, vec![]), cred(5, vec![])]);
+
+        let (orphaned, missing) = diff_credentials(&device, &device);
+        assert!(orphaned.is_empty());
+        assert!(missing.is_empty());
+
+        let (orphaned, missing) = diff_credentials(&[], &server);
+        assert!(orphaned.is_empty());
+        assert_eq!(missing, server.to_vec());
+    }
+}
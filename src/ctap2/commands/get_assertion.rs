@@ -1,7 +1,7 @@
 use super::get_info::AuthenticatorInfo;
 use super::{
     Command, CommandError, CtapResponse, PinUvAuthCommand, RequestCtap1, RequestCtap2, Retryable,
-    StatusCode,
+    StatusCode, UvReason,
 };
 use crate::consts::{
     PARAMETER_SIZE, U2F_AUTHENTICATE, U2F_DONT_ENFORCE_USER_PRESENCE_AND_SIGN,
@@ -10,17 +10,19 @@ use crate::consts::{
 use crate::crypto::{COSEKey, CryptoError, PinUvAuthParam, PinUvAuthToken, SharedSecret};
 use crate::ctap2::attestation::{AuthenticatorData, AuthenticatorDataFlags};
 use crate::ctap2::client_data::ClientDataHash;
+use crate::ctap2::commands::client_pin::PinUvAuthTokenPermission;
 use crate::ctap2::commands::get_next_assertion::GetNextAssertion;
 use crate::ctap2::commands::make_credentials::UserVerification;
 use crate::ctap2::server::{
     AuthenticationExtensionsClientInputs, AuthenticationExtensionsClientOutputs,
-    AuthenticatorAttachment, PublicKeyCredentialDescriptor, PublicKeyCredentialUserEntity,
-    RelyingParty, RpIdHash, UserVerificationRequirement,
+    AuthenticatorAttachment, CredentialProperties, HmacGetSecretInput, HmacGetSecretOutput,
+    PublicKeyCredentialDescriptor, PublicKeyCredentialUserEntity, RelyingParty, RpIdHash,
+    UserVerificationRequirement,
 };
-use crate::ctap2::utils::{read_be_u32, read_byte};
+use crate::ctap2::utils::{read_be_u32, read_byte, ExtensionsMap};
 use crate::errors::AuthenticatorError;
 use crate::transport::errors::{ApduErrorStatus, HIDError};
-use crate::transport::{FidoDevice, VirtualFidoDevice};
+use crate::transport::{FidoDevice, FidoProtocol, VirtualFidoDevice};
 use crate::u2ftypes::CTAP1RequestAPDU;
 use serde::{
     de::{Error as DesError, MapAccess, Visitor},
@@ -29,6 +31,7 @@ use serde::{
 };
 use serde_bytes::ByteBuf;
 use serde_cbor::{de::from_slice, ser, Value};
+use std::collections::BTreeMap;
 use std::fmt;
 use std::io::Cursor;
 
@@ -37,10 +40,15 @@ use std::io::Cursor;
 pub struct GetAssertionOptions {
     #[serde(rename = "uv", skip_serializing_if = "Option::is_none")]
     pub user_verification: Option<bool>,
-    #[serde(rename = "up", skip_serializing_if = "Option::is_none")]
+    // "up" defaults to true, so only an explicit `false` is sent to the authenticator.
+    #[serde(rename = "up", skip_serializing_if = "is_default_user_presence")]
     pub user_presence: Option<bool>,
 }
 
+fn is_default_user_presence(user_presence: &Option<bool>) -> bool {
+    user_presence.unwrap_or(true)
+}
+
 impl Default for GetAssertionOptions {
     fn default() -> Self {
         Self {
@@ -52,7 +60,7 @@ impl Default for GetAssertionOptions {
 
 impl GetAssertionOptions {
     pub(crate) fn has_some(&self) -> bool {
-        self.user_presence.is_some() || self.user_verification.is_some()
+        !is_default_user_presence(&self.user_presence) || self.user_verification.is_some()
     }
 }
 
@@ -71,6 +79,7 @@ pub struct CalculatedHmacSecretExtension {
     pub public_key: COSEKey,
     pub salt_enc: Vec<u8>,
     pub salt_auth: Vec<u8>,
+    pub pin_protocol: u64,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -109,10 +118,42 @@ impl HmacSecretExtension {
             public_key,
             salt_enc,
             salt_auth,
+            pin_protocol: secret.pin_protocol.id(),
         });
 
         Ok(())
     }
+
+    /// Decrypts the output of the authenticator, which is
+    /// AES256-CBC(shared_secret, output1 || output2), or just output1 if no salt2 was given.
+    pub fn decrypt_output(
+        &self,
+        secret: &SharedSecret,
+        output: &[u8],
+    ) -> Result<HmacGetSecretOutput, CryptoError> {
+        let plaintext = secret.decrypt(output)?;
+        let mut result = HmacGetSecretOutput::default();
+        match (&self.salt2, plaintext.len()) {
+            (None, 32) => result.output1.copy_from_slice(&plaintext),
+            (Some(_), 64) => {
+                let mut output2 = [0u8; 32];
+                result.output1.copy_from_slice(&plaintext[..32]);
+                output2.copy_from_slice(&plaintext[32..]);
+                result.output2 = Some(output2);
+            }
+            _ => return Err(CryptoError::MalformedInput),
+        }
+        Ok(result)
+    }
+}
+
+impl From<&HmacGetSecretInput> for HmacSecretExtension {
+    fn from(input: &HmacGetSecretInput) -> Self {
+        HmacSecretExtension::new(
+            input.salt1.to_vec(),
+            input.salt2.map(|salt2| salt2.to_vec()),
+        )
+    }
 }
 
 impl Serialize for HmacSecretExtension {
@@ -121,10 +162,15 @@ impl Serialize for HmacSecretExtension {
         S: Serializer,
     {
         if let Some(calc) = &self.calculated_hmac {
-            let mut map = serializer.serialize_map(Some(3))?;
+            // pinUvAuthProtocol is optional and defaults to 1
+            let send_protocol = calc.pin_protocol != 1;
+            let mut map = serializer.serialize_map(Some(3 + send_protocol as usize))?;
             map.serialize_entry(&1, &calc.public_key)?;
             map.serialize_entry(&2, serde_bytes::Bytes::new(&calc.salt_enc))?;
             map.serialize_entry(&3, serde_bytes::Bytes::new(&calc.salt_auth))?;
+            if send_protocol {
+                map.serialize_entry(&4, &calc.pin_protocol)?;
+            }
             map.end()
         } else {
             Err(SerError::custom(
@@ -134,18 +180,32 @@ impl Serialize for HmacSecretExtension {
     }
 }
 
-#[derive(Debug, Default, Clone, Serialize)]
+#[derive(Debug, Default, Clone)]
 pub struct GetAssertionExtensions {
-    #[serde(skip_serializing)]
     pub app_id: Option<String>,
-    #[serde(rename = "hmac-secret", skip_serializing_if = "Option::is_none")]
+    pub cred_props: Option<bool>,
     pub hmac_secret: Option<HmacSecretExtension>,
+    /// Inputs of extensions that are not modelled here, sent as they are.
+    pub raw_extensions: BTreeMap<String, Value>,
+}
+
+impl Serialize for GetAssertionExtensions {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = ExtensionsMap::default();
+        map.insert("hmac-secret", self.hmac_secret.as_ref());
+        map.insert_raw(&self.raw_extensions);
+        map.serialize(serializer)
+    }
 }
 
 impl From<AuthenticationExtensionsClientInputs> for GetAssertionExtensions {
     fn from(input: AuthenticationExtensionsClientInputs) -> Self {
         Self {
             app_id: input.app_id,
+            cred_props: input.cred_props,
             ..Default::default()
         }
     }
@@ -153,7 +213,7 @@ impl From<AuthenticationExtensionsClientInputs> for GetAssertionExtensions {
 
 impl GetAssertionExtensions {
     fn has_content(&self) -> bool {
-        self.hmac_secret.is_some()
+        self.hmac_secret.is_some() || !self.raw_extensions.is_empty()
     }
 }
 
@@ -192,12 +252,30 @@ impl GetAssertion {
         }
     }
 
-    pub fn finalize_result<Dev: FidoDevice>(&self, dev: &Dev, result: &mut GetAssertionResult) {
+    pub fn finalize_result<Dev: FidoDevice>(
+        &self,
+        dev: &Dev,
+        result: &mut GetAssertionResult,
+    ) -> Result<(), CommandError> {
+        // The credential has to be scoped to the RP we asked for. (When the appid extension
+        // is used, `self.rp` has already been replaced by the appid.)
+        if result.assertion.auth_data.rp_id_hash != self.rp.hash() {
+            return Err(CommandError::RpIdHashMismatch);
+        }
+
         result.attachment = match dev.get_authenticator_info() {
             Some(info) if info.options.platform_device => AuthenticatorAttachment::Platform,
             Some(_) => AuthenticatorAttachment::CrossPlatform,
             None => AuthenticatorAttachment::Unknown,
         };
+        // CTAP1 requests don't carry the pinUvAuthParam
+        result.pin_uv_auth_protocol = match dev.get_protocol() {
+            FidoProtocol::CTAP2 => self
+                .pin_uv_auth_param
+                .as_ref()
+                .map(|param| param.pin_protocol.id()),
+            FidoProtocol::CTAP1 => None,
+        };
 
         // Handle extensions whose outputs are not encoded in the authenticator data.
         // 1. appId
@@ -205,6 +283,116 @@ impl GetAssertion {
             result.extensions.app_id =
                 Some(result.assertion.auth_data.rp_id_hash == RelyingParty::from(app_id).hash());
         }
+
+        // 2. credProps
+        // The authenticator does not tell us whether the credential is discoverable, but it
+        // must be if it was found without an allow list, or if the authenticator stored a
+        // user entity along with it. Otherwise we can't tell, and report nothing.
+        if self.extensions.cred_props == Some(true)
+            && (self.allow_list.is_empty() || result.assertion.user.is_some())
+        {
+            result.extensions.cred_props = Some(CredentialProperties {
+                rk: true,
+                ..Default::default()
+            });
+        }
+        Ok(())
+    }
+}
+
+impl GetAssertion {
+    /// Rejects option combinations that the authenticator would refuse with some generic
+    /// error, so that callers get an actionable one instead.
+    pub(crate) fn validate_options(&self) -> Result<(), AuthenticatorError> {
+        let uv = self.options.user_verification == Some(true);
+        if uv && self.pin_uv_auth_param.is_some() {
+            // CTAP 2.1, Section 6.2.2, Step 5: "If the pinUvAuthParam is present, [...] let
+            // the "uv" option be treated as being present with the value false."
+            // A platform must not ask for both.
+            return Err(AuthenticatorError::InvalidOptionCombination(
+                "\"uv\" together with a pinUvAuthParam",
+            ));
+        }
+        if uv && self.options.user_presence == Some(false) {
+            // Built-in user verification always involves the user.
+            return Err(AuthenticatorError::InvalidOptionCombination(
+                "\"uv\" without \"up\"",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Assembles a `GetAssertion` request from named parts, instead of the positional arguments
+/// of `GetAssertion::new()`. `build()` checks that the request is complete and consistent.
+#[derive(Debug, Default)]
+pub struct GetAssertionBuilder {
+    rp_id: Option<String>,
+    client_data_hash: Option<ClientDataHash>,
+    allow_list: Vec<PublicKeyCredentialDescriptor>,
+    options: GetAssertionOptions,
+    extensions: GetAssertionExtensions,
+}
+
+impl GetAssertionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn rp_id(mut self, rp_id: &str) -> Self {
+        self.rp_id = Some(rp_id.to_string());
+        self
+    }
+
+    pub fn client_data_hash(mut self, client_data_hash: ClientDataHash) -> Self {
+        self.client_data_hash = Some(client_data_hash);
+        self
+    }
+
+    pub fn allow_list(mut self, allow_list: Vec<PublicKeyCredentialDescriptor>) -> Self {
+        self.allow_list = allow_list;
+        self
+    }
+
+    pub fn user_verification(mut self, user_verification: bool) -> Self {
+        self.options.user_verification = Some(user_verification);
+        self
+    }
+
+    pub fn user_presence(mut self, user_presence: bool) -> Self {
+        self.options.user_presence = Some(user_presence);
+        self
+    }
+
+    pub fn app_id(mut self, app_id: &str) -> Self {
+        self.extensions.app_id = Some(app_id.to_string());
+        self
+    }
+
+    /// Salts for the hmac-secret extension. They are encrypted with the shared secret of the
+    /// device, once it has been established.
+    pub fn hmac_secret(mut self, input: &HmacGetSecretInput) -> Self {
+        self.extensions.hmac_secret = Some(input.into());
+        self
+    }
+
+    pub fn build(self) -> Result<GetAssertion, AuthenticatorError> {
+        let rp_id = self
+            .rp_id
+            .filter(|rp_id| !rp_id.is_empty())
+            .ok_or(AuthenticatorError::InvalidRelyingPartyInput)?;
+        let client_data_hash = self
+            .client_data_hash
+            .ok_or(AuthenticatorError::InvalidRelyingPartyInput)?;
+        let get_assertion = GetAssertion::new(
+            client_data_hash,
+            RelyingParty::from(rp_id.as_str()),
+            self.allow_list,
+            self.options,
+            self.extensions,
+        );
+        get_assertion.validate_options()?;
+        Ok(get_assertion)
     }
 }
 
@@ -215,6 +403,7 @@ impl PinUvAuthCommand for GetAssertion {
     ) -> Result<(), AuthenticatorError> {
         let mut param = None;
         if let Some(token) = pin_uv_auth_token {
+            self.check_permissions(&token)?;
             param = Some(
                 token
                     .derive(self.client_data_hash.as_ref())
@@ -233,18 +422,27 @@ impl PinUvAuthCommand for GetAssertion {
         Some(&self.rp.id)
     }
 
-    fn can_skip_user_verification(
-        &mut self,
+    fn required_permissions(&self) -> PinUvAuthTokenPermission {
+        PinUvAuthTokenPermission::GetAssertion
+    }
+
+    fn user_verification_requirement_reason(
+        &self,
         info: &AuthenticatorInfo,
         uv_req: UserVerificationRequirement,
-    ) -> bool {
+    ) -> UvReason {
         let supports_uv = info.options.user_verification == Some(true);
         let pin_configured = info.options.client_pin == Some(true);
         let device_protected = supports_uv || pin_configured;
-        let uv_discouraged = uv_req == UserVerificationRequirement::Discouraged;
         let always_uv = info.options.always_uv == Some(true);
 
-        !always_uv && (!device_protected || uv_discouraged)
+        match uv_req {
+            _ if always_uv => UvReason::AlwaysUv,
+            _ if !device_protected => UvReason::NotRequired,
+            UserVerificationRequirement::Discouraged => UvReason::NotRequired,
+            UserVerificationRequirement::Preferred => UvReason::DeviceProtected,
+            UserVerificationRequirement::Required => UvReason::RelyingPartyRequirement,
+        }
     }
 
     fn get_pin_uv_auth_param(&self) -> Option<&PinUvAuthParam> {
@@ -351,7 +549,8 @@ impl RequestCtap1 for GetAssertion {
 
         let mut result = GetAssertionResult::from_ctap1(input, &self.rp.hash(), add_info)
             .map_err(|e| Retryable::Error(HIDError::Command(e)))?;
-        self.finalize_result(dev, &mut result);
+        self.finalize_result(dev, &mut result)
+            .map_err(|e| Retryable::Error(HIDError::Command(e)))?;
         // Although there's only one result, we return a vector for consistency with CTAP2.
         Ok(vec![result])
     }
@@ -362,7 +561,7 @@ impl RequestCtap1 for GetAssertion {
     ) -> Result<Self::Output, HIDError> {
         let mut results = dev.get_assertion(self)?;
         for result in results.iter_mut() {
-            self.finalize_result(dev, result);
+            self.finalize_result(dev, result)?;
         }
         Ok(results)
     }
@@ -385,7 +584,7 @@ impl RequestCtap2 for GetAssertion {
         input: &[u8],
     ) -> Result<Self::Output, HIDError> {
         if input.is_empty() {
-            return Err(CommandError::InputTooSmall.into());
+            return Err(CommandError::EmptyResponse.into());
         }
 
         let status: StatusCode = input[0].into();
@@ -396,36 +595,46 @@ impl RequestCtap2 for GetAssertion {
         );
         if input.len() == 1 {
             if status.is_ok() {
-                return Err(CommandError::InputTooSmall.into());
+                return Err(CommandError::EmptyResponse.into());
             }
             return Err(CommandError::StatusCode(status, None).into());
         }
 
         if status.is_ok() {
             let assertion: GetAssertionResponse =
-                from_slice(&input[1..]).map_err(CommandError::Deserializing)?;
-            let number_of_credentials = assertion.number_of_credentials.unwrap_or(1);
-
-            let mut results = Vec::with_capacity(number_of_credentials);
+                from_slice(&input[1..]).map_err(CommandError::from_response_body)?;
+            let number_of_credentials = match assertion.number_of_credentials {
+                Some(0) => return Err(CommandError::InvalidNumberOfCredentials.into()),
+                Some(n) => n,
+                None => 1,
+            };
+
+            let mut results = Vec::new();
             results.push(GetAssertionResult {
                 assertion: assertion.into(),
                 attachment: AuthenticatorAttachment::Unknown,
                 extensions: Default::default(),
+                pin_uv_auth_protocol: None,
             });
 
             let msg = GetNextAssertion;
             // We already have one, so skipping 0
             for _ in 1..number_of_credentials {
                 let assertion = dev.send_cbor(&msg)?;
+                // Only the first response may carry numberOfCredentials
+                if assertion.number_of_credentials.is_some() {
+                    return Err(CommandError::InvalidNumberOfCredentials.into());
+                }
                 results.push(GetAssertionResult {
                     assertion: assertion.into(),
                     attachment: AuthenticatorAttachment::Unknown,
                     extensions: Default::default(),
+                    pin_uv_auth_protocol: None,
                 });
             }
 
             for result in results.iter_mut() {
-                self.finalize_result(dev, result);
+                self.finalize_result(dev, result)?;
             }
             Ok(results)
         } else {
@@ -439,8 +648,15 @@ impl RequestCtap2 for GetAssertion {
         dev: &mut Dev,
     ) -> Result<Self::Output, HIDError> {
         let mut results = dev.get_assertion(self)?;
+        let promised = results
+            .first()
+            .map(|r| r.assertion.number_of_credentials.unwrap_or(1))
+            .unwrap_or(0);
+        if results.len() > promised {
+            return Err(CommandError::InvalidNumberOfCredentials.into());
+        }
         for result in results.iter_mut() {
-            self.finalize_result(dev, result);
+            self.finalize_result(dev, result)?;
         }
         Ok(results)
     }
@@ -453,6 +669,12 @@ pub struct Assertion {
     pub auth_data: AuthenticatorData,
     pub signature: Vec<u8>,
     pub user: Option<PublicKeyCredentialUserEntity>,
+    /// Total number of assertions the authenticator has for this request. Only set in the
+    /// first assertion, and only if the authenticator told us.
+    pub number_of_credentials: Option<usize>,
+    /// Whether the user selected the credential on the authenticator itself (CTAP 2.1), so
+    /// that the platform does not need to ask again. None, if the authenticator did not say.
+    pub user_selected: Option<bool>,
 }
 
 impl From<GetAssertionResponse> for Assertion {
@@ -462,6 +684,8 @@ impl From<GetAssertionResponse> for Assertion {
             auth_data: r.auth_data,
             signature: r.signature,
             user: r.user,
+            number_of_credentials: r.number_of_credentials,
+            user_selected: r.user_selected,
         }
     }
 }
@@ -471,9 +695,20 @@ pub struct GetAssertionResult {
     pub assertion: Assertion,
     pub attachment: AuthenticatorAttachment,
     pub extensions: AuthenticationExtensionsClientOutputs,
+    /// The PIN/UV auth protocol (1 or 2) of the pinUvAuthParam sent with the request, or None
+    /// if the request had none.
+    pub pin_uv_auth_protocol: Option<u64>,
 }
 
 impl GetAssertionResult {
+    pub fn is_backup_eligible(&self) -> bool {
+        self.assertion.auth_data.is_backup_eligible()
+    }
+
+    pub fn is_backed_up(&self) -> bool {
+        self.assertion.auth_data.is_backed_up()
+    }
+
     pub fn from_ctap1(
         input: &[u8],
         rp_id_hash: &RpIdHash,
@@ -502,12 +737,15 @@ impl GetAssertionResult {
             signature,
             user: None,
             auth_data,
+            number_of_credentials: None,
+            user_selected: None,
         };
 
         Ok(GetAssertionResult {
             assertion,
             attachment: AuthenticatorAttachment::Unknown,
             extensions: Default::default(),
+            pin_uv_auth_protocol: None,
         })
     }
 }
@@ -519,6 +757,7 @@ pub struct GetAssertionResponse {
     pub signature: Vec<u8>,
     pub user: Option<PublicKeyCredentialUserEntity>,
     pub number_of_credentials: Option<usize>,
+    pub user_selected: Option<bool>,
 }
 
 impl CtapResponse for GetAssertionResponse {}
@@ -546,6 +785,7 @@ impl<'de> Deserialize<'de> for GetAssertionResponse {
                 let mut signature = None;
                 let mut user = None;
                 let mut number_of_credentials = None;
+                let mut user_selected = None;
 
                 while let Some(key) = map.next_key()? {
                     match key {
@@ -559,7 +799,8 @@ impl<'de> Deserialize<'de> for GetAssertionResponse {
                             if auth_data.is_some() {
                                 return Err(M::Error::duplicate_field("auth_data"));
                             }
-                            auth_data = Some(map.next_value()?);
+                            let raw: ByteBuf = map.next_value()?;
+                            auth_data = Some(parse_assertion_auth_data(raw.as_slice())?);
                         }
                         3 => {
                             if signature.is_some() {
@@ -581,6 +822,12 @@ impl<'de> Deserialize<'de> for GetAssertionResponse {
                             }
                             number_of_credentials = Some(map.next_value()?);
                         }
+                        6 => {
+                            if user_selected.is_some() {
+                                return Err(M::Error::duplicate_field("user_selected"));
+                            }
+                            user_selected = Some(map.next_value()?);
+                        }
                         k => return Err(M::Error::custom(format!("unexpected key: {k:?}"))),
                     }
                 }
@@ -594,6 +841,7 @@ impl<'de> Deserialize<'de> for GetAssertionResponse {
                     signature,
                     user,
                     number_of_credentials,
+                    user_selected,
                 })
             }
         }
@@ -602,35 +850,71 @@ impl<'de> Deserialize<'de> for GetAssertionResponse {
     }
 }
 
+// Assertions never carry attested credential data, so the AT flag has to be clear. Without
+// it, the authenticator data is the fixed-size header followed by optional extensions only.
+fn parse_assertion_auth_data<E: DesError>(raw: &[u8]) -> Result<AuthenticatorData, E> {
+    // Check the flags before parsing, so we report the actual problem instead of failing
+    // on the (bogus) attested credential data.
+    if let Some(flags) = raw.get(32) {
+        let flags = AuthenticatorDataFlags::from_bits_truncate(*flags);
+        if flags.contains(AuthenticatorDataFlags::ATTESTED) {
+            return Err(E::custom("attested credential data flag set in assertion"));
+        }
+    }
+    let auth_data =
+        AuthenticatorData::deserialize(serde::de::value::BytesDeserializer::<E>::new(raw))?;
+    // rpIdHash (32) + flags (1) + signCount (4)
+    if !auth_data
+        .flags
+        .contains(AuthenticatorDataFlags::EXTENSION_DATA)
+        && raw.len() != 37
+    {
+        return Err(E::invalid_length(raw.len(), &"37"));
+    }
+    Ok(auth_data)
+}
+
 #[cfg(test)]
 pub mod test {
     use super::{
-        Assertion, CommandError, GetAssertion, GetAssertionOptions, GetAssertionResult, HIDError,
-        StatusCode,
+        Assertion, CommandError, GetAssertion, GetAssertionBuilder, GetAssertionExtensions,
+        GetAssertionOptions, GetAssertionResponse, GetAssertionResult, HIDError,
+        HmacGetSecretInput, HmacSecretExtension, StatusCode,
     };
     use crate::consts::{
         Capability, HIDCmd, SW_CONDITIONS_NOT_SATISFIED, SW_NO_ERROR, U2F_CHECK_IS_REGISTERED,
         U2F_REQUEST_USER_PRESENCE,
     };
+    use crate::crypto::{PinUvAuthParam, PinUvAuthProtocol};
     use crate::ctap2::attestation::{AAGuid, AuthenticatorData, AuthenticatorDataFlags};
-    use crate::ctap2::client_data::{Challenge, CollectedClientData, TokenBinding, WebauthnType};
+    use crate::ctap2::client_data::{
+        Challenge, ClientDataHash, CollectedClientData, TokenBinding, WebauthnType,
+    };
     use crate::ctap2::commands::get_info::tests::AAGUID_RAW;
     use crate::ctap2::commands::get_info::{
         AuthenticatorInfo, AuthenticatorOptions, AuthenticatorVersion,
     };
-    use crate::ctap2::commands::RequestCtap1;
+    use crate::ctap2::commands::make_credentials::test::peer_key;
+    use crate::ctap2::commands::{Command, PinUvAuthCommand, UvReason};
+    use crate::ctap2::commands::{RequestCtap1, RequestCtap2};
     use crate::ctap2::preflight::{
         do_credential_list_filtering_ctap1, do_credential_list_filtering_ctap2,
     };
     use crate::ctap2::server::{
-        AuthenticatorAttachment, PublicKeyCredentialDescriptor, PublicKeyCredentialUserEntity,
-        RelyingParty, RpIdHash, Transport,
+        AuthenticatorAttachment, CredentialProperties, PublicKeyCredentialDescriptor,
+        PublicKeyCredentialUserEntity, RelyingParty, RpIdHash, Transport,
+        UserVerificationRequirement,
     };
+    use crate::errors::AuthenticatorError;
     use crate::transport::device_selector::Device;
     use crate::transport::hid::HIDDevice;
+    use crate::transport::platform::recording::{RecordedRequest, RecordingDevice};
     use crate::transport::{FidoDevice, FidoDeviceIO, FidoProtocol};
     use crate::u2ftypes::U2FDeviceInfo;
     use rand::{thread_rng, RngCore};
+    use serde_cbor::de::from_slice;
+    use serde_cbor::Value;
+    use std::convert::TryFrom;
 
     #[test]
     fn test_get_assertion_ctap2() {
@@ -667,10 +951,10 @@ pub mod test {
         device.set_cid(cid);
 
         let mut msg = cid.to_vec();
-        msg.extend(vec![HIDCmd::Cbor.into(), 0x00, 0x90]);
+        msg.extend(vec![HIDCmd::Cbor.into(), 0x00, 0x8a]);
         msg.extend(vec![0x2]); // u2f command
         msg.extend(vec![
-            0xa4, // map(4)
+            0xa3, // map(3)
             0x1,  // rpid
             0x6b, // text(11)
             101, 120, 97, 109, 112, 108, 101, 46, 99, 111, 109, // example.com
@@ -702,11 +986,6 @@ pub mod test {
             0x74, 0x79, 0x70, 0x65, // type
             0x6a, // text(10)
             0x70, 0x75, 0x62, 0x6C, 0x69, 0x63, 0x2D, 0x6B, 0x65, 0x79, // public-key
-            0x5,  // options
-            0xa1, // map(1)
-            0x62, // text(2)
-            0x75, 0x70, // up
-            0xf5, // true
         ]);
         device.add_write(&msg, 0);
 
@@ -743,9 +1022,9 @@ pub mod test {
         // Check if response is correct
         let expected_auth_data = AuthenticatorData {
             rp_id_hash: RpIdHash([
-                0x62, 0x5d, 0xda, 0xdf, 0x74, 0x3f, 0x57, 0x27, 0xe6, 0x6b, 0xba, 0x8c, 0x2e, 0x38,
-                0x79, 0x22, 0xd1, 0xaf, 0x43, 0xc5, 0x03, 0xd9, 0x11, 0x4a, 0x8f, 0xba, 0x10, 0x4d,
-                0x84, 0xd0, 0x2b, 0xfa,
+                0xa3, 0x79, 0xa6, 0xf6, 0xee, 0xaf, 0xb9, 0xa5, 0x5e, 0x37, 0x8c, 0x11, 0x80, 0x34,
+                0xe2, 0x75, 0x1e, 0x68, 0x2f, 0xab, 0x9f, 0x2d, 0x30, 0xab, 0x13, 0xd2, 0x12, 0x55,
+                0x86, 0xce, 0x19, 0x47,
             ]),
             flags: AuthenticatorDataFlags::USER_PRESENT,
             counter: 0x11,
@@ -781,12 +1060,15 @@ pub mod test {
                 display_name: Some("John P. Smith".to_string()),
             }),
             auth_data: expected_auth_data,
+            number_of_credentials: Some(1),
+            user_selected: None,
         };
 
         let expected = vec![GetAssertionResult {
             assertion: expected_assertion,
             attachment: AuthenticatorAttachment::Unknown,
             extensions: Default::default(),
+            pin_uv_auth_protocol: None,
         }];
         let response = device.send_cbor(&assertion).unwrap();
         assert_eq!(response, expected);
@@ -914,12 +1196,15 @@ pub mod test {
             ],
             user: None,
             auth_data: expected_auth_data,
+            number_of_credentials: None,
+            user_selected: None,
         };
 
         let expected = vec![GetAssertionResult {
             assertion: expected_assertion,
             attachment: AuthenticatorAttachment::Unknown,
             extensions: Default::default(),
+            pin_uv_auth_protocol: None,
         }];
         assert_eq!(response, expected);
     }
@@ -1056,12 +1341,15 @@ pub mod test {
             ],
             user: None,
             auth_data: expected_auth_data,
+            number_of_credentials: None,
+            user_selected: None,
         };
 
         let expected = vec![GetAssertionResult {
             assertion: expected_assertion,
             attachment: AuthenticatorAttachment::Unknown,
             extensions: Default::default(),
+            pin_uv_auth_protocol: None,
         }];
         assert_eq!(response, expected);
     }
@@ -1310,6 +1598,202 @@ pub mod test {
         );
     }
 
+    #[test]
+    fn test_get_assertion_empty_allow_list() {
+        // An empty allowList must be omitted, not sent as an empty array
+        let assertion = GetAssertion::new(
+            ClientDataHash([0x42; 32]),
+            RelyingParty::from("example.com"),
+            vec![],
+            GetAssertionOptions::default(),
+            Default::default(),
+        );
+        let mut expected = vec![
+            0xa2, // map(2)
+            0x01, // rpid
+            0x6b, // text(11)
+            101, 120, 97, 109, 112, 108, 101, 46, 99, 111, 109,  // example.com
+            0x02, // clientDataHash
+            0x58, 0x20, // bytes(32)
+        ];
+        expected.extend_from_slice(&[0x42; 32]);
+        assert_eq!(
+            assertion
+                .wire_format()
+                .expect("Failed to serialize request"),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_get_assertion_options_user_presence() {
+        // "up" defaults to true and is omitted
+        let options = GetAssertionOptions::default();
+        assert!(!options.has_some());
+
+        let options = GetAssertionOptions {
+            user_presence: Some(true),
+            user_verification: Some(true),
+        };
+        assert!(options.has_some());
+        assert_eq!(
+            serde_cbor::to_vec(&options).unwrap(),
+            vec![
+                0xa1, // map(1)
+                0x62, // text(2)
+                0x75, 0x76, // uv
+                0xf5, // true
+            ]
+        );
+
+        // A silent request always sends "up" explicitly
+        let options = GetAssertionOptions {
+            user_presence: Some(false),
+            user_verification: None,
+        };
+        assert!(options.has_some());
+        assert_eq!(
+            serde_cbor::to_vec(&options).unwrap(),
+            vec![
+                0xa1, // map(1)
+                0x62, // text(2)
+                0x75, 0x70, // up
+                0xf4, // false
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_assertion_validate_options() {
+        let mut get_assertion = GetAssertion::new(
+            ClientDataHash([0; 32]),
+            RelyingParty::from("example.com"),
+            vec![],
+            GetAssertionOptions::default(),
+            Default::default(),
+        );
+        assert!(get_assertion.validate_options().is_ok());
+
+        get_assertion.options.user_verification = Some(true);
+        assert!(get_assertion.validate_options().is_ok());
+
+        get_assertion.pin_uv_auth_param = Some(PinUvAuthParam::create_empty());
+        assert!(matches!(
+            get_assertion.validate_options(),
+            Err(AuthenticatorError::InvalidOptionCombination(_))
+        ));
+        // A pinUvAuthParam with "uv" unset or false is fine
+        get_assertion.options.user_verification = Some(false);
+        assert!(get_assertion.validate_options().is_ok());
+        get_assertion.options.user_verification = None;
+        assert!(get_assertion.validate_options().is_ok());
+
+        get_assertion.pin_uv_auth_param = None;
+        get_assertion.options.user_presence = Some(false);
+        assert!(get_assertion.validate_options().is_ok());
+        get_assertion.options.user_verification = Some(true);
+        assert!(matches!(
+            get_assertion.validate_options(),
+            Err(AuthenticatorError::InvalidOptionCombination(_))
+        ));
+    }
+
+    #[test]
+    fn test_get_assertion_cred_props() {
+        let sample_result = || {
+            let response: GetAssertionResponse =
+                from_slice(&GET_ASSERTION_SAMPLE_RESPONSE_CTAP2[1..]).unwrap();
+            GetAssertionResult {
+                assertion: response.into(),
+                attachment: AuthenticatorAttachment::Unknown,
+                extensions: Default::default(),
+                pin_uv_auth_protocol: None,
+            }
+        };
+        let device = Device::new("commands/get_assertion").unwrap();
+        let allowed_key = PublicKeyCredentialDescriptor {
+            id: vec![0x01, 0x02, 0x03],
+            transports: vec![],
+        };
+        let get_assertion = |allow_list, cred_props| {
+            GetAssertion::new(
+                ClientDataHash([0u8; 32]),
+                RelyingParty::from("example.com"),
+                allow_list,
+                GetAssertionOptions::default(),
+                GetAssertionExtensions {
+                    cred_props,
+                    ..Default::default()
+                },
+            )
+        };
+
+        // Not requested
+        let mut result = sample_result();
+        get_assertion(vec![], None)
+            .finalize_result(&device, &mut result)
+            .unwrap();
+        assert_eq!(result.extensions.cred_props, None);
+
+        // Discoverable login
+        let mut result = sample_result();
+        get_assertion(vec![], Some(true))
+            .finalize_result(&device, &mut result)
+            .unwrap();
+        assert_eq!(
+            result.extensions.cred_props,
+            Some(CredentialProperties {
+                rk: true,
+                ..Default::default()
+            })
+        );
+
+        // The authenticator returned a user entity
+        let mut result = sample_result();
+        get_assertion(vec![allowed_key.clone()], Some(true))
+            .finalize_result(&device, &mut result)
+            .unwrap();
+        assert_eq!(
+            result.extensions.cred_props,
+            Some(CredentialProperties {
+                rk: true,
+                ..Default::default()
+            })
+        );
+
+        // No user entity, so we can't tell
+        let mut result = sample_result();
+        result.assertion.user = None;
+        get_assertion(vec![allowed_key], Some(true))
+            .finalize_result(&device, &mut result)
+            .unwrap();
+        assert_eq!(result.extensions.cred_props, None);
+    }
+
+    #[test]
+    fn test_get_assertion_rp_id_hash_mismatch() {
+        let response: GetAssertionResponse =
+            from_slice(&GET_ASSERTION_SAMPLE_RESPONSE_CTAP2[1..]).unwrap();
+        let mut result = GetAssertionResult {
+            assertion: response.into(),
+            attachment: AuthenticatorAttachment::Unknown,
+            extensions: Default::default(),
+            pin_uv_auth_protocol: None,
+        };
+        let device = Device::new("commands/get_assertion").unwrap();
+        let get_assertion = GetAssertion::new(
+            ClientDataHash([0u8; 32]),
+            RelyingParty::from("example.org"),
+            vec![],
+            GetAssertionOptions::default(),
+            Default::default(),
+        );
+        assert!(matches!(
+            get_assertion.finalize_result(&device, &mut result),
+            Err(CommandError::RpIdHashMismatch)
+        ));
+    }
+
     #[test]
     fn test_get_assertion_ctap1_flags() {
         // Ensure that only the two low bits of flags are preserved when repackaging a
@@ -1329,6 +1813,226 @@ pub mod test {
         );
     }
 
+    #[test]
+    fn test_get_assertion_attested_flag() {
+        // Sanity check: the unmodified sample parses
+        let sample = GET_ASSERTION_SAMPLE_RESPONSE_CTAP2.to_vec();
+        assert!(from_slice::<GetAssertionResponse>(&sample[1..]).is_ok());
+
+        // AT flag set, even though no credential data follows
+        let mut sample = GET_ASSERTION_SAMPLE_RESPONSE_CTAP2.to_vec();
+        sample[92 + 32] |= AuthenticatorDataFlags::ATTESTED.bits();
+        assert!(from_slice::<GetAssertionResponse>(&sample[1..]).is_err());
+
+        // AT flag clear, but trailing data after the counter
+        let mut sample = GET_ASSERTION_SAMPLE_RESPONSE_CTAP2.to_vec();
+        sample[91] += 1; // auth_data length
+        sample.insert(92 + 37, 0x00);
+        assert!(from_slice::<GetAssertionResponse>(&sample[1..]).is_err());
+    }
+
+    #[test]
+    fn test_get_assertion_builder() {
+        let allowed_key = PublicKeyCredentialDescriptor {
+            id: vec![0x01, 0x02, 0x03],
+            transports: vec![Transport::USB],
+        };
+        let get_assertion = GetAssertionBuilder::new()
+            .rp_id("example.com")
+            .client_data_hash(ClientDataHash(CLIENT_DATA_HASH))
+            .allow_list(vec![allowed_key.clone()])
+            .user_verification(true)
+            .hmac_secret(&HmacGetSecretInput {
+                salt1: [0x01; 32],
+                salt2: None,
+            })
+            .build()
+            .expect("Failed to build GetAssertion");
+        assert_eq!(get_assertion.rp, RelyingParty::from("example.com"));
+        assert_eq!(
+            get_assertion.client_data_hash,
+            ClientDataHash(CLIENT_DATA_HASH)
+        );
+        assert_eq!(get_assertion.allow_list, vec![allowed_key]);
+        assert_eq!(get_assertion.options.user_verification, Some(true));
+        assert_eq!(
+            get_assertion.extensions.hmac_secret.map(|ext| ext.salt1),
+            Some(vec![0x01; 32])
+        );
+
+        assert!(matches!(
+            GetAssertionBuilder::new()
+                .client_data_hash(ClientDataHash(CLIENT_DATA_HASH))
+                .build(),
+            Err(AuthenticatorError::InvalidRelyingPartyInput)
+        ));
+        assert!(matches!(
+            GetAssertionBuilder::new().rp_id("example.com").build(),
+            Err(AuthenticatorError::InvalidRelyingPartyInput)
+        ));
+        assert!(matches!(
+            GetAssertionBuilder::new()
+                .rp_id("example.com")
+                .client_data_hash(ClientDataHash(CLIENT_DATA_HASH))
+                .user_verification(true)
+                .user_presence(false)
+                .build(),
+            Err(AuthenticatorError::InvalidOptionCombination(_))
+        ));
+    }
+
+    #[test]
+    fn test_hmac_secret_pin_protocol() {
+        for protocol in [1, 2] {
+            let info = AuthenticatorInfo {
+                extensions: vec!["hmac-secret".to_string()],
+                pin_protocols: Some(vec![protocol]),
+                ..Default::default()
+            };
+            let secret = PinUvAuthProtocol::try_from(&info)
+                .unwrap()
+                .encapsulate(&peer_key())
+                .unwrap();
+            let mut hmac_secret = HmacSecretExtension::new(vec![0x01; 32], None);
+            hmac_secret.calculate(&secret).unwrap();
+            let serialized = serde_cbor::to_vec(&hmac_secret).unwrap();
+            let input = match from_slice(&serialized).unwrap() {
+                Value::Map(m) => m,
+                v => panic!("Unexpected hmac-secret input: {:?}", v),
+            };
+            // pinUvAuthProtocol (0x04) is only sent if it is not the default of 1
+            let pin_protocol = input.get(&Value::Integer(0x04));
+            if protocol == 1 {
+                assert_eq!(input.len(), 3);
+                assert_eq!(pin_protocol, None);
+            } else {
+                assert_eq!(input.len(), 4);
+                assert_eq!(pin_protocol, Some(&Value::Integer(protocol.into())));
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_assertion_user_selected() {
+        let get_assertion = GetAssertion::new(
+            ClientDataHash([0u8; 32]),
+            RelyingParty::from("example.com"),
+            vec![],
+            GetAssertionOptions::default(),
+            Default::default(),
+        );
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        let results = get_assertion
+            .handle_response_ctap2(&mut device, &GET_ASSERTION_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle response");
+        assert_eq!(results[0].assertion.user_selected, None);
+
+        let mut response = GET_ASSERTION_SAMPLE_RESPONSE_CTAP2.to_vec();
+        response[1] = 0xA6; // map(6)
+        response.extend([
+            0x06, // unsigned(6), userSelected
+            0xF5, // true
+        ]);
+        let results = get_assertion
+            .handle_response_ctap2(&mut device, &response)
+            .expect("Failed to handle response");
+        assert_eq!(results[0].assertion.user_selected, Some(true));
+    }
+
+    #[test]
+    fn test_get_assertion_number_of_credentials() {
+        let get_assertion = GetAssertion::new(
+            ClientDataHash([0u8; 32]),
+            RelyingParty::from("example.com"),
+            vec![],
+            GetAssertionOptions::default(),
+            Default::default(),
+        );
+        // The sample ends with numberOfCredentials = 1
+        let mut first = GET_ASSERTION_SAMPLE_RESPONSE_CTAP2.to_vec();
+        *first.last_mut().unwrap() = 0x02;
+        // Subsequent responses carry no numberOfCredentials
+        let mut next = GET_ASSERTION_SAMPLE_RESPONSE_CTAP2.to_vec();
+        next.truncate(next.len() - 2);
+        next[1] = 0xA4; // map(4)
+
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.add_response(&next);
+        let results = get_assertion
+            .handle_response_ctap2(&mut device, &first)
+            .expect("Failed to handle response");
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].assertion.number_of_credentials, Some(2));
+        assert_eq!(results[1].assertion.number_of_credentials, None);
+        assert_eq!(
+            device.requests,
+            vec![RecordedRequest::Ctap2(Command::GetNextAssertion, vec![])]
+        );
+
+        // The second response claims to be a first one
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.add_response(&first);
+        assert!(matches!(
+            get_assertion.handle_response_ctap2(&mut device, &first),
+            Err(HIDError::Command(CommandError::InvalidNumberOfCredentials))
+        ));
+
+        let mut zero = GET_ASSERTION_SAMPLE_RESPONSE_CTAP2.to_vec();
+        *zero.last_mut().unwrap() = 0x00;
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        assert!(matches!(
+            get_assertion.handle_response_ctap2(&mut device, &zero),
+            Err(HIDError::Command(CommandError::InvalidNumberOfCredentials))
+        ));
+    }
+
+    #[test]
+    fn test_get_assertion_uv_reason() {
+        let get_assertion = GetAssertion::new(
+            ClientDataHash([0u8; 32]),
+            RelyingParty::from("example.com"),
+            vec![],
+            GetAssertionOptions::default(),
+            Default::default(),
+        );
+        let mut info = AuthenticatorInfo::default();
+        let reason = |info: &AuthenticatorInfo, uv_req| {
+            get_assertion.user_verification_requirement_reason(info, uv_req)
+        };
+
+        // Unprotected device
+        assert_eq!(
+            reason(&info, UserVerificationRequirement::Required),
+            UvReason::NotRequired
+        );
+        assert!(
+            get_assertion.can_skip_user_verification(&info, UserVerificationRequirement::Required)
+        );
+
+        info.options.client_pin = Some(true);
+        assert_eq!(
+            reason(&info, UserVerificationRequirement::Discouraged),
+            UvReason::NotRequired
+        );
+        assert_eq!(
+            reason(&info, UserVerificationRequirement::Preferred),
+            UvReason::DeviceProtected
+        );
+        assert_eq!(
+            reason(&info, UserVerificationRequirement::Required),
+            UvReason::RelyingPartyRequirement
+        );
+        assert!(!get_assertion
+            .can_skip_user_verification(&info, UserVerificationRequirement::Preferred));
+
+        info.options.client_pin = None;
+        info.options.always_uv = Some(true);
+        assert_eq!(
+            reason(&info, UserVerificationRequirement::Discouraged),
+            UvReason::AlwaysUv
+        );
+    }
+
     // Manually assembled according to https://www.w3.org/TR/webauthn-2/#clientdatajson-serialization
     const CLIENT_DATA_VEC: [u8; 140] = [
         0x7b, 0x22, 0x74, 0x79, 0x70, 0x65, 0x22, 0x3a, // {"type":
@@ -1441,7 +2145,7 @@ pub mod test {
         0xF5, 0xF6, 0xAF, 0xA3, 0x5A, 0xAD, 0x53, 0x73, 0x85, 0x8E,
     ];
 
-    const GET_ASSERTION_SAMPLE_RESPONSE_CTAP2: [u8; 298] = [
+    pub const GET_ASSERTION_SAMPLE_RESPONSE_CTAP2: [u8; 298] = [
         0x00, // status == success
         0xA5, // map(5)
         0x01, // unsigned(1)
@@ -1460,9 +2164,9 @@ pub mod test {
         0x70, 0x75, 0x62, 0x6C, 0x69, 0x63, 0x2D, 0x6B, 0x65, 0x79, // "public-key"
         0x02, // unsigned(2)
         0x58, 0x25, // bytes(0x37, ) auth_data
-        0x62, 0x5D, 0xDA, 0xDF, 0x74, 0x3F, 0x57, 0x27, 0xE6, 0x6B, 0xBA, 0x8C, 0x2E, 0x38, 0x79,
-        0x22, 0xD1, 0xAF, 0x43, 0xC5, 0x03, 0xD9, 0x11, 0x4A, 0x8F, 0xBA, 0x10, 0x4D, 0x84, 0xD0,
-        0x2B, 0xFA, 0x01, 0x00, 0x00, 0x00, 0x11, // end: auth_data
+        0xA3, 0x79, 0xA6, 0xF6, 0xEE, 0xAF, 0xB9, 0xA5, 0x5E, 0x37, 0x8C, 0x11, 0x80, 0x34, 0xE2,
+        0x75, 0x1E, 0x68, 0x2F, 0xAB, 0x9F, 0x2D, 0x30, 0xAB, 0x13, 0xD2, 0x12, 0x55, 0x86, 0xCE,
+        0x19, 0x47, 0x01, 0x00, 0x00, 0x00, 0x11, // end: auth_data
         0x03, // unsigned(3)
         0x58, 0x47, // bytes(0x71, ) signature
         0x30, 0x45, 0x02, 0x20, 0x4A, 0x5A, 0x9D, 0xD3, 0x92, 0x98, 0x14, 0x9D, 0x90, 0x47, 0x69,
@@ -1,6 +1,6 @@
 use super::{Command, CommandError, CtapResponse, RequestCtap2, StatusCode};
 use crate::ctap2::attestation::AAGuid;
-use crate::ctap2::server::PublicKeyCredentialParameters;
+use crate::ctap2::server::{PublicKeyCredentialParameters, Transport};
 use crate::transport::errors::HIDError;
 use crate::transport::{FidoDevice, VirtualFidoDevice};
 use serde::{
@@ -8,6 +8,7 @@ use serde::{
     Deserialize, Deserializer, Serialize,
 };
 use serde_cbor::{de::from_slice, Value};
+use serde_json::Value as JsonValue;
 use std::collections::BTreeMap;
 use std::fmt;
 
@@ -31,7 +32,7 @@ impl RequestCtap2 for GetInfo {
         input: &[u8],
     ) -> Result<Self::Output, HIDError> {
         if input.is_empty() {
-            return Err(CommandError::InputTooSmall.into());
+            return Err(CommandError::EmptyResponse.into());
         }
 
         let status: StatusCode = input[0].into();
@@ -40,14 +41,17 @@ impl RequestCtap2 for GetInfo {
             if status.is_ok() {
                 trace!("parsing authenticator info data: {:#04X?}", &input);
                 let authenticator_info =
-                    from_slice(&input[1..]).map_err(CommandError::Deserializing)?;
+                    from_slice(&input[1..]).map_err(CommandError::from_response_body)?;
                 Ok(authenticator_info)
             } else {
                 let data: Value = from_slice(&input[1..]).map_err(CommandError::Deserializing)?;
                 Err(CommandError::StatusCode(status, Some(data)).into())
             }
+        } else if !status.is_ok() {
+            // Errors like CTAP1_ERR_CHANNEL_BUSY come without a payload
+            Err(CommandError::StatusCode(status, None).into())
         } else {
-            Err(CommandError::InputTooSmall.into())
+            Err(CommandError::EmptyResponse.into())
         }
     }
 
@@ -324,7 +328,7 @@ pub struct AuthenticatorInfo {
     // CTAP 2.1
     pub max_credential_count_in_list: Option<usize>,
     pub max_credential_id_length: Option<usize>,
-    pub transports: Option<Vec<String>>,
+    pub transports: Option<Vec<Transport>>,
     pub algorithms: Option<Vec<PublicKeyCredentialParameters>>,
     pub max_ser_large_blob_array: Option<u64>,
     pub force_pin_change: Option<bool>,
@@ -340,12 +344,33 @@ pub struct AuthenticatorInfo {
 }
 
 impl AuthenticatorInfo {
+    /// Whether the authenticator lists the extension identifier `name` in getInfo.
+    pub fn supports_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|extension| extension == name)
+    }
+
     pub fn supports_cred_protect(&self) -> bool {
-        self.extensions.contains(&"credProtect".to_string())
+        self.supports_extension("credProtect")
     }
 
     pub fn supports_hmac_secret(&self) -> bool {
-        self.extensions.contains(&"hmac-secret".to_string())
+        self.supports_extension("hmac-secret")
+    }
+
+    pub fn supports_hmac_secret_mc(&self) -> bool {
+        self.supports_extension("hmac-secret-mc")
+    }
+
+    /// The level of certification `name` (e.g. "FIDO", "FIPS-CMVP-2" or "CC-EAL") the
+    /// authenticator claims in getInfo. Unknown certification names are kept as they are.
+    pub fn certification_level(&self, name: &str) -> Option<u64> {
+        self.certifications.as_ref()?.get(name).copied()
+    }
+
+    /// Whether the authenticator claims certification `name` with at least `min_level`.
+    pub fn has_certification(&self, name: &str, min_level: u64) -> bool {
+        self.certification_level(name)
+            .map_or(false, |level| level >= min_level)
     }
 
     pub fn max_supported_version(&self) -> AuthenticatorVersion {
@@ -366,6 +391,87 @@ impl AuthenticatorInfo {
     pub fn device_is_protected(&self) -> bool {
         self.options.client_pin == Some(true) || self.options.user_verification == Some(true)
     }
+
+    /// The minimum PIN length (in Unicode code points) the authenticator currently enforces,
+    /// as reported in getInfo (0x0D). Absent for devices that don't report it, in which
+    /// case the spec-mandated default of 4 applies.
+    pub fn min_pin_length(&self) -> Option<u64> {
+        self.min_pin_length
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            max_supported_version: self.max_supported_version(),
+            pin_configured: self.options.client_pin == Some(true),
+            user_verification: self.options.user_verification == Some(true),
+            resident_key: self.options.resident_key,
+            cred_protect: self.supports_cred_protect(),
+            hmac_secret: self.supports_hmac_secret(),
+            min_pin_length: self.min_pin_length(),
+        }
+    }
+
+    /// Compares this (e.g. the info from before an authenticatorConfig command) with `other`
+    /// (e.g. the refreshed info afterwards) and lists everything that changed.
+    pub fn diff(&self, other: &AuthenticatorInfo) -> InfoDiff {
+        // Going through the serialized form, so new fields are picked up automatically.
+        fn to_map<T: Serialize>(value: &T) -> serde_json::Map<String, JsonValue> {
+            match serde_json::to_value(value) {
+                Ok(JsonValue::Object(map)) => map,
+                _ => Default::default(),
+            }
+        }
+        let mut old = to_map(self);
+        let mut new = to_map(other);
+        let old_options = to_map(&self.options);
+        let new_options = to_map(&other.options);
+        old.remove("options");
+        new.remove("options");
+
+        let mut diff = InfoDiff::default();
+        for (id, old_value) in old_options.iter() {
+            let new_value = new_options.get(id).unwrap_or(&JsonValue::Null);
+            if old_value != new_value {
+                diff.options
+                    .insert(id.clone(), (old_value.as_bool(), new_value.as_bool()));
+            }
+        }
+        for (name, old_value) in old.iter() {
+            if Some(old_value) != new.get(name) {
+                diff.fields.push(name.clone());
+            }
+        }
+        diff
+    }
+}
+
+/// Changes between two `AuthenticatorInfo`s, see `AuthenticatorInfo::diff()`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct InfoDiff {
+    /// Changed options by their option ID (e.g. "alwaysUv"), with the old and the new value.
+    /// `None` means the option is absent.
+    pub options: BTreeMap<String, (Option<bool>, Option<bool>)>,
+    /// Names of all other fields that changed (e.g. "min_pin_length").
+    pub fields: Vec<String>,
+}
+
+impl InfoDiff {
+    pub fn is_empty(&self) -> bool {
+        self.options.is_empty() && self.fields.is_empty()
+    }
+}
+
+/// Condensed view of the features an authenticator reports in getInfo, for UIs that need
+/// to tell users what a device can do (and what it requires of them).
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Capabilities {
+    pub max_supported_version: AuthenticatorVersion,
+    pub pin_configured: bool,
+    pub user_verification: bool,
+    pub resident_key: bool,
+    pub cred_protect: bool,
+    pub hmac_secret: bool,
+    pub min_pin_length: Option<u64>,
 }
 
 impl CtapResponse for AuthenticatorInfo {}
@@ -549,13 +655,15 @@ impl<'de> Deserialize<'de> for AuthenticatorInfo {
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::consts::{Capability, HIDCmd, CID_BROADCAST};
+    use crate::consts::{Capability, HIDCmd, CID_BROADCAST, STATUS_PROCESSING, STATUS_UPNEEDED};
     use crate::crypto::COSEAlgorithm;
     use crate::transport::device_selector::Device;
     use crate::transport::platform::device::IN_HID_RPT_SIZE;
     use crate::transport::{hid::HIDDevice, FidoDevice, FidoProtocol};
+    use crate::StatusUpdate;
     use rand::{thread_rng, RngCore};
     use serde_cbor::de::from_slice;
+    use std::sync::mpsc::channel;
 
     // Raw data take from https://github.com/Yubico/python-fido2/blob/master/test/test_ctap2.py
     pub const AAGUID_RAW: [u8; 16] = [
@@ -795,6 +903,46 @@ pub mod tests {
         assert_eq!(authenticator_info, expected);
     }
 
+    #[test]
+    fn parse_authenticator_info_certifications() {
+        let mut payload = AUTHENTICATOR_INFO_PAYLOAD.to_vec();
+        payload[0] += 1;
+        payload.extend_from_slice(&[
+            0x13, // unsigned(19), certifications
+            0xA3, //   map(3)
+            0x64, //     text(4)
+            0x46, 0x49, 0x44, 0x4F, // "FIDO"
+            0x02, //     unsigned(2)
+            0x68, //     text(8)
+            0x58, 0x2D, 0x56, 0x45, 0x4E, 0x44, 0x4F, 0x52, // "X-VENDOR"
+            0x07, //     unsigned(7)
+            0x6B, //     text(11)
+            0x46, 0x49, 0x50, 0x53, 0x2D, 0x43, 0x4D, 0x56, 0x50, 0x2D, 0x32, // "FIPS-CMVP-2"
+            0x01, //     unsigned(1)
+        ]);
+
+        let authenticator_info: AuthenticatorInfo = from_slice(&payload).unwrap();
+        let certifications = authenticator_info.certifications.as_ref().unwrap();
+        assert_eq!(certifications.len(), 3);
+        assert_eq!(authenticator_info.certification_level("FIDO"), Some(2));
+        assert_eq!(
+            authenticator_info.certification_level("FIPS-CMVP-2"),
+            Some(1)
+        );
+        // Unknown certifications are preserved
+        assert_eq!(authenticator_info.certification_level("X-VENDOR"), Some(7));
+        assert_eq!(authenticator_info.certification_level("CC-EAL"), None);
+
+        assert!(authenticator_info.has_certification("FIDO", 1));
+        assert!(authenticator_info.has_certification("FIDO", 2));
+        assert!(!authenticator_info.has_certification("FIDO", 3));
+        assert!(!authenticator_info.has_certification("CC-EAL", 0));
+
+        let authenticator_info: AuthenticatorInfo =
+            from_slice(&AUTHENTICATOR_INFO_PAYLOAD).unwrap();
+        assert_eq!(authenticator_info.certification_level("FIDO"), None);
+    }
+
     #[test]
     fn parse_authenticator_info_yk_bio_5c() {
         let authenticator_info: AuthenticatorInfo =
@@ -843,7 +991,7 @@ pub mod tests {
             pin_protocols: Some(vec![2, 1]),
             max_credential_count_in_list: Some(8),
             max_credential_id_length: Some(128),
-            transports: Some(vec!["usb".to_string()]),
+            transports: Some(vec![Transport::USB]),
             algorithms: Some(vec![
                 PublicKeyCredentialParameters {
                     alg: COSEAlgorithm::ES256,
@@ -866,6 +1014,52 @@ pub mod tests {
         };
 
         assert_eq!(authenticator_info, expected);
+        assert_eq!(authenticator_info.min_pin_length(), Some(4));
+        assert_eq!(
+            authenticator_info.capabilities(),
+            Capabilities {
+                max_supported_version: AuthenticatorVersion::FIDO_2_1,
+                pin_configured: true,
+                user_verification: true,
+                resident_key: true,
+                cred_protect: true,
+                hmac_secret: true,
+                min_pin_length: Some(4),
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_info_empty_and_truncated_response() {
+        let mut device = Device::new("commands/get_info").unwrap(); // not really used
+        let mut handle =
+            |input: &[u8]| GetInfo::default().handle_response_ctap2(&mut device, input);
+
+        assert!(matches!(
+            handle(&[]),
+            Err(HIDError::Command(CommandError::EmptyResponse))
+        ));
+        assert!(matches!(
+            handle(&[0x00]),
+            Err(HIDError::Command(CommandError::EmptyResponse))
+        ));
+
+        let mut input = vec![0x00];
+        input.extend_from_slice(&AUTHENTICATOR_INFO_PAYLOAD[..40]);
+        assert!(matches!(
+            handle(&input),
+            Err(HIDError::Command(CommandError::TruncatedResponse))
+        ));
+
+        // Complete, but not a map
+        assert!(matches!(
+            handle(&[0x00, 0x01]),
+            Err(HIDError::Command(CommandError::Deserializing(_)))
+        ));
+
+        let mut input = vec![0x00];
+        input.extend_from_slice(&AUTHENTICATOR_INFO_PAYLOAD);
+        assert!(handle(&input).is_ok());
     }
 
     #[test]
@@ -961,6 +1155,143 @@ pub mod tests {
         assert_eq!(result, &expected);
     }
 
+    #[test]
+    fn test_get_info_keepalive_status() {
+        // Only keep-alive messages asking for user presence prompt the user
+        for (status, prompted) in [(STATUS_PROCESSING, false), (STATUS_UPNEEDED, true)] {
+            let mut device = Device::new("commands/get_info").unwrap();
+            let nonce = [0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01];
+
+            // channel id
+            let mut cid = [0u8; 4];
+            thread_rng().fill_bytes(&mut cid);
+
+            // init packet
+            let mut msg = CID_BROADCAST.to_vec();
+            msg.extend(vec![HIDCmd::Init.into(), 0x00, 0x08]); // cmd + bcnt
+            msg.extend_from_slice(&nonce);
+            device.add_write(&msg, 0);
+
+            // init_resp packet
+            let mut msg = CID_BROADCAST.to_vec();
+            msg.extend(vec![
+                0x06, /* HIDCmd::Init without TYPE_INIT */
+                0x00, 0x11,
+            ]); // cmd + bcnt
+            msg.extend_from_slice(&nonce);
+            msg.extend_from_slice(&cid); // new channel id
+            msg.extend(vec![0x02, 0x04, 0x01, 0x08, 0x01 | 0x04 | 0x08]); // versions + flags (wink+cbor+nmsg)
+            device.add_read(&msg, 0);
+
+            // ctap2 request
+            let mut msg = cid.to_vec();
+            msg.extend(vec![HIDCmd::Cbor.into(), 0x00, 0x1]); // cmd + bcnt
+            msg.extend(vec![0x04]); // authenticatorGetInfo
+            device.add_write(&msg, 0);
+
+            // keep-alive packets
+            for _ in 0..2 {
+                let mut msg = cid.to_vec();
+                msg.extend(vec![HIDCmd::Keepalive.into(), 0x00, 0x01]); // cmd + bcnt
+                msg.push(status);
+                device.add_read(&msg, 0);
+            }
+
+            // ctap2 response
+            let mut msg = cid.to_vec();
+            msg.extend(vec![HIDCmd::Cbor.into(), 0x00, 0x5A]); // cmd + bcnt
+            msg.extend(vec![0]); // Status code: Success
+            msg.extend(&AUTHENTICATOR_INFO_PAYLOAD[0..(IN_HID_RPT_SIZE - 8)]);
+            device.add_read(&msg, 0);
+            // Continuation package
+            let mut msg = cid.to_vec();
+            msg.extend(vec![0x00]); // SEQ
+            msg.extend(&AUTHENTICATOR_INFO_PAYLOAD[(IN_HID_RPT_SIZE - 8)..]);
+            device.add_read(&msg, 0);
+
+            let (tx, rx) = channel();
+            device
+                .init_cancellable(Some(&tx), &|| true)
+                .expect("Failed to init device");
+            assert!(device.get_authenticator_info().is_some());
+            let prompts = rx
+                .try_iter()
+                .filter(|update| matches!(update, StatusUpdate::PresenceRequired))
+                .count();
+            assert_eq!(prompts, usize::from(prompted));
+        }
+    }
+
+    #[test]
+    fn test_get_info_fragmented() {
+        let mut device = Device::new("commands/get_info").unwrap();
+        let nonce = [0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01];
+
+        // channel id
+        let mut cid = [0u8; 4];
+        thread_rng().fill_bytes(&mut cid);
+        let other_cid = cid.map(|b| !b);
+
+        // init packet
+        let mut msg = CID_BROADCAST.to_vec();
+        msg.extend(vec![HIDCmd::Init.into(), 0x00, 0x08]); // cmd + bcnt
+        msg.extend_from_slice(&nonce);
+        device.add_write(&msg, 0);
+
+        // init_resp packet
+        let mut msg = CID_BROADCAST.to_vec();
+        msg.extend(vec![
+            0x06, /* HIDCmd::Init without TYPE_INIT */
+            0x00, 0x11,
+        ]); // cmd + bcnt
+        msg.extend_from_slice(&nonce);
+        msg.extend_from_slice(&cid); // new channel id
+        msg.extend(vec![0x02, 0x04, 0x01, 0x08, 0x01 | 0x04 | 0x08]); // versions + flags (wink+cbor+nmsg)
+        device.add_read(&msg, 0);
+
+        // ctap2 request
+        let mut msg = cid.to_vec();
+        msg.extend(vec![HIDCmd::Cbor.into(), 0x00, 0x1]); // cmd + bcnt
+        msg.extend(vec![0x04]); // authenticatorGetInfo
+        device.add_write(&msg, 0);
+
+        // ctap2 response, which needs an init packet and six continuation packets
+        let mut response = vec![0]; // Status code: Success
+        response.extend_from_slice(&AUTHENTICATOR_INFO_PAYLOAD_YK_BIO_5C);
+        let (init, mut rest) = response.split_at(IN_HID_RPT_SIZE - 7);
+        let mut msg = cid.to_vec();
+        msg.extend(vec![
+            HIDCmd::Cbor.into(),
+            (response.len() >> 8) as u8,
+            response.len() as u8,
+        ]); // cmd + bcnt
+        msg.extend(init);
+        device.add_read(&msg, 0);
+        let mut seq = 0u8;
+        while !rest.is_empty() {
+            if seq == 2 {
+                // Packets for other channels are skipped
+                let mut msg = other_cid.to_vec();
+                msg.extend(vec![seq]); // SEQ
+                msg.extend(vec![0xff; IN_HID_RPT_SIZE - 5]);
+                device.add_read(&msg, 0);
+            }
+            let (chunk, remainder) = rest.split_at(rest.len().min(IN_HID_RPT_SIZE - 5));
+            let mut msg = cid.to_vec();
+            msg.extend(vec![seq]); // SEQ
+            msg.extend(chunk);
+            device.add_read(&msg, 0);
+            rest = remainder;
+            seq += 1;
+        }
+        assert_eq!(seq, 6);
+        device.init().expect("Failed to init device");
+
+        let expected: AuthenticatorInfo =
+            from_slice(&AUTHENTICATOR_INFO_PAYLOAD_YK_BIO_5C).unwrap();
+        assert_eq!(device.get_authenticator_info(), Some(&expected));
+    }
+
     #[test]
     fn test_authenticator_info_max_version() {
         let fido2_0 = AuthenticatorInfo {
@@ -999,6 +1330,39 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_authenticator_info_diff() {
+        let before = AuthenticatorInfo {
+            options: AuthenticatorOptions {
+                client_pin: Some(true),
+                always_uv: Some(false),
+                ..Default::default()
+            },
+            min_pin_length: Some(4),
+            force_pin_change: Some(false),
+            ..Default::default()
+        };
+        assert!(before.diff(&before.clone()).is_empty());
+
+        let mut after = before.clone();
+        after.options.always_uv = Some(true);
+        after.options.ep = Some(false);
+        after.options.resident_key = true;
+        after.min_pin_length = Some(8);
+        after.force_pin_change = Some(true);
+
+        let diff = before.diff(&after);
+        assert_eq!(
+            diff.options,
+            BTreeMap::from([
+                ("alwaysUv".to_string(), (Some(false), Some(true))),
+                ("ep".to_string(), (None, Some(false))),
+                ("rk".to_string(), (Some(false), Some(true))),
+            ])
+        );
+        assert_eq!(diff.fields, vec!["force_pin_change", "min_pin_length"]);
+    }
+
     #[test]
     fn parse_authenticator_info_protocol_versions() {
         let mut expected = AuthenticatorInfo {
@@ -24,22 +24,22 @@ impl RequestCtap2 for GetNextAssertion {
         input: &[u8],
     ) -> Result<Self::Output, HIDError> {
         if input.is_empty() {
-            return Err(CommandError::InputTooSmall.into());
+            return Err(CommandError::EmptyResponse.into());
         }
 
         let status: StatusCode = input[0].into();
         debug!("response status code: {:?}", status);
         if input.len() > 1 {
             if status.is_ok() {
-                let assertion = from_slice(&input[1..]).map_err(CommandError::Deserializing)?;
-                // TODO(baloo): check assertion response does not have numberOfCredentials
+                let assertion =
+                    from_slice(&input[1..]).map_err(CommandError::from_response_body)?;
                 Ok(assertion)
             } else {
                 let data: Value = from_slice(&input[1..]).map_err(CommandError::Deserializing)?;
                 Err(CommandError::StatusCode(status, Some(data)).into())
             }
         } else if status.is_ok() {
-            Err(CommandError::InputTooSmall.into())
+            Err(CommandError::EmptyResponse.into())
         } else {
             Err(CommandError::StatusCode(status, None).into())
         }
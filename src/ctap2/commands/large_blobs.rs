@@ -0,0 +1,449 @@
+use super::{Command, CommandError, CtapResponse, RequestCtap2, StatusCode};
+use crate::ctap2::utils::{from_slice_stream, read_be_u16, read_be_u32, read_byte};
+use crate::transport::errors::HIDError;
+use crate::transport::{FidoDevice, VirtualFidoDevice};
+use serde::{
+    de::{Error as DesError, MapAccess, Visitor},
+    ser::SerializeMap,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_bytes::ByteBuf;
+use serde_cbor::{de::from_slice, error::Error as CborError, ser::to_vec, Value};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::Cursor;
+
+/// Length of the truncated SHA-256 hash at the end of the serialized large-blob array.
+const LARGE_BLOB_ARRAY_HASH_LEN: usize = 16;
+
+/// Reads a fragment of the serialized large-blob array (authenticatorLargeBlobs with `get`).
+/// Writing is not supported yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LargeBlobs {
+    /// Number of bytes to read
+    pub get: u64,
+    /// Byte offset at which to start reading
+    pub offset: u64,
+}
+
+impl LargeBlobs {
+    pub fn new(get: u64, offset: u64) -> Self {
+        LargeBlobs { get, offset }
+    }
+}
+
+impl Serialize for LargeBlobs {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry(&0x01, &self.get)?;
+        map.serialize_entry(&0x03, &self.offset)?;
+        map.end()
+    }
+}
+
+impl RequestCtap2 for LargeBlobs {
+    type Output = LargeBlobsResponse;
+
+    fn command(&self) -> Command {
+        Command::LargeBlobs
+    }
+
+    fn wire_format(&self) -> Result<Vec<u8>, HIDError> {
+        let output = to_vec(&self).map_err(CommandError::Serializing)?;
+        trace!("client subcommmand: {:04X?}", &output);
+        Ok(output)
+    }
+
+    fn handle_response_ctap2<Dev: FidoDevice>(
+        &self,
+        _dev: &mut Dev,
+        input: &[u8],
+    ) -> Result<Self::Output, HIDError> {
+        if input.is_empty() {
+            return Err(CommandError::EmptyResponse.into());
+        }
+
+        let status: StatusCode = input[0].into();
+
+        if status.is_ok() {
+            if input.len() == 1 {
+                return Err(CommandError::EmptyResponse.into());
+            }
+            trace!("parsing large blobs data: {:#04X?}", &input);
+            let response = from_slice(&input[1..]).map_err(CommandError::from_response_body)?;
+            Ok(response)
+        } else {
+            let data: Option<Value> = if input.len() > 1 {
+                Some(from_slice(&input[1..]).map_err(CommandError::Deserializing)?)
+            } else {
+                None
+            };
+            Err(CommandError::StatusCode(status, data).into())
+        }
+    }
+
+    fn send_to_virtual_device<Dev: VirtualFidoDevice>(
+        &self,
+        _dev: &mut Dev,
+    ) -> Result<Self::Output, HIDError> {
+        unimplemented!()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LargeBlobsResponse {
+    /// The requested fragment of the serialized large-blob array
+    pub config: Vec<u8>,
+}
+
+impl CtapResponse for LargeBlobsResponse {}
+
+impl<'de> Deserialize<'de> for LargeBlobsResponse {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct LargeBlobsResponseVisitor;
+
+        impl<'de> Visitor<'de> for LargeBlobsResponseVisitor {
+            type Value = LargeBlobsResponse;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map")
+            }
+
+            fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+            where
+                M: MapAccess<'de>,
+            {
+                let mut config = None; // (0x01)
+
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        0x01 => {
+                            if config.is_some() {
+                                return Err(DesError::duplicate_field("config"));
+                            }
+                            config = Some(map.next_value::<ByteBuf>()?.into_vec());
+                        }
+                        _ => {
+                            // Ignore unknown keys
+                            map.next_value::<serde_cbor::Value>()?;
+                        }
+                    }
+                }
+
+                Ok(LargeBlobsResponse {
+                    config: config.ok_or_else(|| M::Error::missing_field("config"))?,
+                })
+            }
+        }
+
+        deserializer.deserialize_map(LargeBlobsResponseVisitor)
+    }
+}
+
+/// One element of the large-blob array, i.e. a large blob encrypted with the largeBlobKey of
+/// some credential.
+/// See https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-20210615.html#large-blob
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LargeBlobArrayEntry {
+    /// AEAD_AES_256_GCM ciphertext of the DEFLATE-compressed blob, including the 16 byte tag
+    pub ciphertext: Vec<u8>,
+    /// The nonce used for encryption
+    pub nonce: [u8; 12],
+    /// Length of the blob before compression
+    pub orig_size: u64,
+}
+
+impl<'de> Deserialize<'de> for LargeBlobArrayEntry {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct LargeBlobArrayEntryVisitor;
+
+        impl<'de> Visitor<'de> for LargeBlobArrayEntryVisitor {
+            type Value = LargeBlobArrayEntry;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map")
+            }
+
+            fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+            where
+                M: MapAccess<'de>,
+            {
+                let mut ciphertext = None; // (0x01)
+                let mut nonce = None; // (0x02)
+                let mut orig_size = None; // (0x03)
+
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        0x01 => {
+                            if ciphertext.is_some() {
+                                return Err(DesError::duplicate_field("ciphertext"));
+                            }
+                            ciphertext = Some(map.next_value::<ByteBuf>()?.into_vec());
+                        }
+                        0x02 => {
+                            if nonce.is_some() {
+                                return Err(DesError::duplicate_field("nonce"));
+                            }
+                            let raw = map.next_value::<ByteBuf>()?;
+                            let mut buf = [0u8; 12];
+                            if raw.len() != buf.len() {
+                                return Err(DesError::invalid_length(raw.len(), &"12"));
+                            }
+                            buf.copy_from_slice(&raw);
+                            nonce = Some(buf);
+                        }
+                        0x03 => {
+                            if orig_size.is_some() {
+                                return Err(DesError::duplicate_field("origSize"));
+                            }
+                            orig_size = Some(map.next_value()?);
+                        }
+                        _ => {
+                            // Ignore unknown keys
+                            map.next_value::<serde_cbor::Value>()?;
+                        }
+                    }
+                }
+
+                Ok(LargeBlobArrayEntry {
+                    ciphertext: ciphertext.ok_or_else(|| M::Error::missing_field("ciphertext"))?,
+                    nonce: nonce.ok_or_else(|| M::Error::missing_field("nonce"))?,
+                    orig_size: orig_size.ok_or_else(|| M::Error::missing_field("origSize"))?,
+                })
+            }
+        }
+
+        deserializer.deserialize_map(LargeBlobArrayEntryVisitor)
+    }
+}
+
+/// The serialized large-blob array as stored on the authenticator: A CBOR array of
+/// `LargeBlobArrayEntry`s, followed by the first 16 bytes of its SHA-256 hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LargeBlobArray(pub Vec<u8>);
+
+impl LargeBlobArray {
+    /// Checks the integrity of the array and returns the CBOR array without the trailing hash.
+    pub fn cbor_array(&self) -> Result<&[u8], CommandError> {
+        if self.0.len() < LARGE_BLOB_ARRAY_HASH_LEN {
+            return Err(CommandError::InputTooSmall);
+        }
+        let (array, hash) = self.0.split_at(self.0.len() - LARGE_BLOB_ARRAY_HASH_LEN);
+        if Sha256::digest(array)[..LARGE_BLOB_ARRAY_HASH_LEN] != *hash {
+            return Err(CommandError::Deserializing(CborError::custom(
+                "large blob array integrity check failed",
+            )));
+        }
+        Ok(array)
+    }
+
+    /// Checks the integrity of the array and returns an iterator over its entries. Entries
+    /// are parsed one at a time, and the iterator stops after the first one that can't be
+    /// parsed.
+    pub fn entries(&self) -> Result<LargeBlobArrayEntries<'_>, CommandError> {
+        let mut cursor = Cursor::new(self.cbor_array()?);
+        let remaining = read_array_len(&mut cursor).map_err(CommandError::Deserializing)?;
+        Ok(LargeBlobArrayEntries { cursor, remaining })
+    }
+}
+
+// Definite length arrays only, as required for CTAP2 canonical CBOR.
+fn read_array_len(cursor: &mut Cursor<&[u8]>) -> Result<u64, CborError> {
+    let first = read_byte::<_, CborError>(cursor)?;
+    if first & 0xe0 != 0x80 {
+        return Err(CborError::custom("large blob array is not an array"));
+    }
+    match first & 0x1f {
+        len @ 0..=23 => Ok(u64::from(len)),
+        24 => Ok(u64::from(read_byte::<_, CborError>(cursor)?)),
+        25 => Ok(u64::from(read_be_u16::<_, CborError>(cursor)?)),
+        26 => Ok(u64::from(read_be_u32::<_, CborError>(cursor)?)),
+        _ => Err(CborError::custom("unsupported large blob array length")),
+    }
+}
+
+pub struct LargeBlobArrayEntries<'a> {
+    cursor: Cursor<&'a [u8]>,
+    remaining: u64,
+}
+
+impl<'a> Iterator for LargeBlobArrayEntries<'a> {
+    type Item = Result<LargeBlobArrayEntry, CommandError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        match from_slice_stream(&mut self.cursor) {
+            Ok(entry) => {
+                self.remaining -= 1;
+                Some(Ok(entry))
+            }
+            Err(e) => {
+                // We can't know where the next entry starts
+                self.remaining = 0;
+                Some(Err(CommandError::Deserializing(e)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LargeBlobArray, LargeBlobArrayEntry, LargeBlobs, LargeBlobsResponse};
+    use crate::ctap2::commands::get_info::AuthenticatorInfo;
+    use crate::ctap2::commands::{CommandError, RequestCtap2, StatusCode};
+    use crate::transport::device_selector::Device;
+    use crate::transport::errors::HIDError;
+    use crate::transport::FidoDevice;
+    use sha2::{Digest, Sha256};
+
+    // The initial serialized large-blob array, as given in the spec
+    const EMPTY_LARGE_BLOB_ARRAY: [u8; 17] = [
+        0x80, // array(0)
+        0x76, 0xbe, 0x8b, 0x52, 0x8d, 0x00, 0x75, 0xf7, 0xaa, 0xe9, 0x8d, 0x6f, 0xa5, 0x7a, 0x6d,
+        0x3c, // truncated SHA-256
+    ];
+
+    fn with_hash(array: &[u8]) -> Vec<u8> {
+        let mut data = array.to_vec();
+        data.extend_from_slice(&Sha256::digest(array)[..16]);
+        data
+    }
+
+    #[test]
+    fn test_empty_large_blob_array() {
+        let array = LargeBlobArray(EMPTY_LARGE_BLOB_ARRAY.to_vec());
+        assert_eq!(array.entries().expect("Integrity check failed").count(), 0);
+    }
+
+    #[test]
+    fn test_large_blob_array_entries() {
+        let array = LargeBlobArray(with_hash(&[
+            0x82, // array(2)
+            0xa3, // map(3)
+            0x01, // unsigned(1)
+            0x42, 0x01, 0x02, // bytes(2), ciphertext
+            0x02, // unsigned(2)
+            0x4c, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a,
+            0x0b, // bytes(12), nonce
+            0x03, // unsigned(3)
+            0x18, 0x20, // unsigned(32), origSize
+            0xa2, // map(2), missing origSize
+            0x01, // unsigned(1)
+            0x41, 0x03, // bytes(1), ciphertext
+            0x02, // unsigned(2)
+            0x4c, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a,
+            0x0b, // bytes(12), nonce
+        ]));
+
+        let mut entries = array.entries().expect("Integrity check failed");
+        assert_eq!(
+            entries.next().unwrap().unwrap(),
+            LargeBlobArrayEntry {
+                ciphertext: vec![0x01, 0x02],
+                nonce: [0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b],
+                orig_size: 32,
+            }
+        );
+        assert!(entries.next().unwrap().is_err());
+        assert!(entries.next().is_none());
+    }
+
+    #[test]
+    fn test_large_blob_array_integrity() {
+        let mut data = EMPTY_LARGE_BLOB_ARRAY.to_vec();
+        data[16] ^= 0x01;
+        assert!(LargeBlobArray(data).entries().is_err());
+
+        // Too short to even hold the hash
+        assert!(LargeBlobArray(vec![0x80]).entries().is_err());
+
+        // Valid hash, but not an array
+        assert!(LargeBlobArray(with_hash(&[0xa0])).entries().is_err());
+    }
+
+    #[test]
+    fn test_large_blobs_wire_format() {
+        let cmd = LargeBlobs::new(960, 0);
+        assert_eq!(
+            cmd.wire_format().unwrap(),
+            vec![
+                0xa2, // map(2)
+                0x01, // unsigned(1), get
+                0x19, 0x03, 0xc0, // unsigned(960)
+                0x03, // unsigned(3), offset
+                0x00, // unsigned(0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_large_blob_array() {
+        let mut dev = Device::new_skipping_serialization("commands/large_blobs").unwrap();
+        // maxFragmentLength = 80 - 64 = 16, so the empty array needs two reads
+        dev.set_authenticator_info(AuthenticatorInfo {
+            max_msg_size: Some(80),
+            ..Default::default()
+        });
+        dev.add_upcoming_ctap2_request(&LargeBlobs::new(16, 0));
+        dev.add_upcoming_ctap_response(LargeBlobsResponse {
+            config: EMPTY_LARGE_BLOB_ARRAY[..16].to_vec(),
+        });
+        dev.add_upcoming_ctap2_request(&LargeBlobs::new(16, 16));
+        dev.add_upcoming_ctap_response(LargeBlobsResponse {
+            config: EMPTY_LARGE_BLOB_ARRAY[16..].to_vec(),
+        });
+
+        let array = dev
+            .read_large_blob_array(&|| true)
+            .expect("Failed to read array");
+        assert_eq!(array, LargeBlobArray(EMPTY_LARGE_BLOB_ARRAY.to_vec()));
+        assert_eq!(array.cbor_array().unwrap(), &[0x80]);
+
+        // A corrupted array is rejected
+        let mut corrupted = EMPTY_LARGE_BLOB_ARRAY.to_vec();
+        corrupted[1] ^= 0x01;
+        dev.add_upcoming_ctap2_request(&LargeBlobs::new(16, 0));
+        dev.add_upcoming_ctap_response(LargeBlobsResponse {
+            config: corrupted[..16].to_vec(),
+        });
+        dev.add_upcoming_ctap2_request(&LargeBlobs::new(16, 16));
+        dev.add_upcoming_ctap_response(LargeBlobsResponse {
+            config: corrupted[16..].to_vec(),
+        });
+        assert!(dev.read_large_blob_array(&|| true).is_err());
+
+        // Reading stops once the array exceeds maxSerializedLargeBlobArray
+        dev.set_authenticator_info(AuthenticatorInfo {
+            max_msg_size: Some(80),
+            max_ser_large_blob_array: Some(32),
+            ..Default::default()
+        });
+        for offset in &[0, 16, 32] {
+            dev.add_upcoming_ctap2_request(&LargeBlobs::new(16, *offset));
+            dev.add_upcoming_ctap_response(LargeBlobsResponse {
+                config: vec![0x00; 16],
+            });
+        }
+        assert!(dev.read_large_blob_array(&|| true).is_err());
+
+        // Nothing is sent once the operation has been cancelled
+        assert_matches!(
+            dev.read_large_blob_array(&|| false),
+            Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::KeepaliveCancel,
+                None
+            )))
+        );
+    }
+}
@@ -1,46 +1,357 @@
 use super::get_info::{AuthenticatorInfo, AuthenticatorVersion};
 use super::{
     Command, CommandError, CtapResponse, PinUvAuthCommand, RequestCtap1, RequestCtap2, Retryable,
-    StatusCode,
+    StatusCode, UvReason,
 };
 use crate::consts::{PARAMETER_SIZE, U2F_REGISTER, U2F_REQUEST_USER_PRESENCE};
 use crate::crypto::{
-    parse_u2f_der_certificate, COSEAlgorithm, COSEEC2Key, COSEKey, COSEKeyType, Curve,
+    parse_u2f_der_certificate, COSEAlgorithm, COSEEC2Key, COSEKey, COSEKeyType, CryptoError, Curve,
     PinUvAuthParam, PinUvAuthToken,
 };
 use crate::ctap2::attestation::{
-    AAGuid, AttestationObject, AttestationStatement, AttestationStatementFidoU2F,
-    AttestedCredentialData, AuthenticatorData, AuthenticatorDataFlags, HmacSecretResponse,
+    AAGuid, AttestationError, AttestationObject, AttestationStatement, AttestationStatementFidoU2F,
+    AttestedCredentialData, AuthenticatorData, AuthenticatorDataFlags, CertificateCache,
+    HmacSecretResponse, TrustStore,
+};
+use crate::ctap2::client_data::{ClientDataHash, CollectedClientData, WebauthnType};
+use crate::ctap2::commands::client_pin::PinUvAuthTokenPermission;
+use crate::ctap2::commands::get_assertion::HmacSecretExtension;
+use crate::ctap2::preflight::{
+    do_credential_list_filtering_ctap1, do_credential_list_filtering_ctap2,
 };
-use crate::ctap2::client_data::ClientDataHash;
 use crate::ctap2::server::{
     AuthenticationExtensionsClientInputs, AuthenticationExtensionsClientOutputs,
-    AuthenticatorAttachment, CredentialProtectionPolicy, PublicKeyCredentialDescriptor,
-    PublicKeyCredentialParameters, PublicKeyCredentialUserEntity, RelyingParty, RpIdHash,
-    UserVerificationRequirement,
+    AuthenticatorAttachment, CredentialProperties, CredentialProtectionPolicy,
+    PublicKeyCredentialDescriptor, PublicKeyCredentialParameters, PublicKeyCredentialUserEntity,
+    RelyingParty, RpIdHash, Transport, UserVerificationRequirement,
 };
-use crate::ctap2::utils::{read_byte, serde_parse_err};
+use crate::ctap2::utils::{read_byte, serde_parse_err, ExtensionsMap};
 use crate::errors::AuthenticatorError;
 use crate::transport::errors::{ApduErrorStatus, HIDError};
-use crate::transport::{FidoDevice, VirtualFidoDevice};
+use crate::transport::{FidoDevice, FidoProtocol, VirtualFidoDevice};
 use crate::u2ftypes::CTAP1RequestAPDU;
+use base64::Engine;
 use serde::{
-    de::{Error as DesError, MapAccess, Unexpected, Visitor},
+    de::{Error as DesError, IgnoredAny, MapAccess, Unexpected, Visitor},
     ser::SerializeMap,
     Deserialize, Deserializer, Serialize, Serializer,
 };
+use serde_bytes::{ByteBuf, Bytes};
 use serde_cbor::{self, de::from_slice, ser, Value};
+use serde_json as json;
+use std::collections::BTreeMap;
+use std::convert::TryInto;
 use std::fmt;
 use std::io::{Cursor, Read};
+use std::time::SystemTime;
+
+/// The two kinds of enterprise attestation (`enterpriseAttestation` request parameter 1 and 2).
+/// See https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-20210615.html#sctn-feature-descriptions-enterp-attstn
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EnterpriseAttestationMode {
+    /// The authenticator vendor decides which RP IDs get enterprise attestation.
+    VendorFacilitated,
+    /// The platform decides which RP IDs get enterprise attestation.
+    PlatformManaged,
+}
+
+/// The kind of attestation a `RegistrationPolicy` requires. Mirrors WebAuthn's
+/// `AttestationConveyancePreference`, but as a minimum the result has to meet.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum AttestationConveyance {
+    /// Any attestation statement, including none.
+    #[default]
+    None,
+    /// Any attestation statement, including self attestation.
+    Indirect,
+    /// An attestation statement signed by an attestation key, not by the credential itself.
+    Direct,
+    /// An enterprise attestation statement.
+    Enterprise,
+}
+
+/// Requirements of a relying party on new credentials, see
+/// `MakeCredentialsResult::check_policy()`. Empty lists allow anything.
+#[derive(Clone, Debug, Default)]
+pub struct RegistrationPolicy {
+    pub attestation: AttestationConveyance,
+    pub allowed_algorithms: Vec<COSEAlgorithm>,
+    pub allowed_aaguids: Vec<AAGuid>,
+    /// Needs the credProps extension, as the authenticator does not tell us otherwise.
+    pub require_resident_key: bool,
+    pub require_user_verification: bool,
+    pub reject_backup_eligible: bool,
+}
+
+#[derive(Debug)]
+pub enum PolicyViolation {
+    /// The client data is not the one of a registration.
+    ClientData,
+    /// The authenticator data holds no attested credential data.
+    MissingCredentialData,
+    /// The attestation statement does not verify against the client data.
+    Signature(CryptoError),
+    /// The certificate chain of the attestation statement does not verify against the trust
+    /// store, see `verify_batch()`.
+    Chain(AttestationError),
+    Attestation(AttestationConveyance),
+    Algorithm(COSEAlgorithm),
+    Aaguid(AAGuid),
+    ResidentKey,
+    UserVerification,
+    BackupEligible,
+}
+
+/// The checks of `MakeCredentialsResult::check_policy()` that only need the attestation object.
+fn check_attestation_object<'a>(
+    att_obj: &'a AttestationObject,
+    client_data_hash: &ClientDataHash,
+    policy: &RegistrationPolicy,
+    enterprise_attestation: bool,
+    cache: &CertificateCache,
+) -> Result<&'a AttestedCredentialData, PolicyViolation> {
+    let auth_data = &att_obj.auth_data;
+    let credential_data = auth_data
+        .credential_data
+        .as_ref()
+        .ok_or(PolicyViolation::MissingCredentialData)?;
+
+    match att_obj.verify_with_cache(client_data_hash, cache) {
+        // Attestation we can't verify, e.g. with a P-384 attestation key
+        Ok(())
+        | Err(CryptoError::UnknownSignatureScheme)
+        | Err(CryptoError::UnsupportedKeyType) => {}
+        Err(e) => return Err(PolicyViolation::Signature(e)),
+    }
+
+    let att_stmt = &att_obj.att_stmt;
+    let attestation_met = match policy.attestation {
+        AttestationConveyance::None => true,
+        AttestationConveyance::Indirect => !matches!(att_stmt, AttestationStatement::None),
+        AttestationConveyance::Direct => match att_stmt {
+            AttestationStatement::None => false,
+            AttestationStatement::Packed(att) => !att.is_self_attestation(),
+            AttestationStatement::Unparsed(fmt, _) => fmt != "none",
+            _ => true,
+        },
+        AttestationConveyance::Enterprise => enterprise_attestation,
+    };
+    if !attestation_met {
+        return Err(PolicyViolation::Attestation(policy.attestation));
+    }
+
+    let alg = credential_data.credential_public_key.alg;
+    if !policy.allowed_algorithms.is_empty() && !policy.allowed_algorithms.contains(&alg) {
+        return Err(PolicyViolation::Algorithm(alg));
+    }
+    if !policy.allowed_aaguids.is_empty()
+        && !policy.allowed_aaguids.contains(&credential_data.aaguid)
+    {
+        return Err(PolicyViolation::Aaguid(credential_data.aaguid.clone()));
+    }
+    if policy.require_user_verification
+        && !auth_data
+            .flags
+            .contains(AuthenticatorDataFlags::USER_VERIFIED)
+    {
+        return Err(PolicyViolation::UserVerification);
+    }
+    if policy.reject_backup_eligible && auth_data.is_backup_eligible() {
+        return Err(PolicyViolation::BackupEligible);
+    }
+    Ok(credential_data)
+}
+
+/// A registration that passed `verify_batch()`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct VerifiedRegistration {
+    pub aaguid: AAGuid,
+    pub credential_id: Vec<u8>,
+    pub credential_public_key: COSEKey,
+    pub counter: u32,
+    pub flags: AuthenticatorDataFlags,
+    /// Whether the certificate chain of the attestation statement was verified against the
+    /// trust store. Always set for `AttestationConveyance::Direct`.
+    pub trusted: bool,
+}
+
+/// Verifies many registrations at once, e.g. when enrolling a batch of security keys, against
+/// `policy` (see `MakeCredentialsResult::check_policy()`). Certificate chains of attestation
+/// statements are verified against `trust_store` where there are any, and have to be for
+/// direct attestation. Certificates are parsed once for the whole batch.
+///
+/// The attestation objects come without the rest of the authenticator's response, so a
+/// policy requiring enterprise attestation or resident keys can't be met.
+pub fn verify_batch(
+    items: &[(AttestationObject, ClientDataHash)],
+    policy: &RegistrationPolicy,
+    trust_store: &TrustStore,
+) -> Vec<Result<VerifiedRegistration, PolicyViolation>> {
+    verify_batch_at(items, policy, trust_store, SystemTime::now())
+}
+
+/// Like `verify_batch()`, but checks the validity periods of certificates against `time`.
+pub fn verify_batch_at(
+    items: &[(AttestationObject, ClientDataHash)],
+    policy: &RegistrationPolicy,
+    trust_store: &TrustStore,
+    time: SystemTime,
+) -> Vec<Result<VerifiedRegistration, PolicyViolation>> {
+    let cache = CertificateCache::new();
+    items
+        .iter()
+        .map(|(att_obj, client_data_hash)| {
+            let credential_data =
+                check_attestation_object(att_obj, client_data_hash, policy, false, &cache)?;
+            if policy.require_resident_key {
+                return Err(PolicyViolation::ResidentKey);
+            }
+            let trusted =
+                match att_obj.verify_attestation_with_trust_store(trust_store, time, &cache) {
+                    Ok(()) => true,
+                    Err(AttestationError::NoCertificateChain)
+                        if policy.attestation != AttestationConveyance::Direct =>
+                    {
+                        false
+                    }
+                    Err(e) => return Err(PolicyViolation::Chain(e)),
+                };
+            Ok(VerifiedRegistration {
+                aaguid: credential_data.aaguid.clone(),
+                credential_id: credential_data.credential_id.clone(),
+                credential_public_key: credential_data.credential_public_key.clone(),
+                counter: att_obj.auth_data.counter,
+                flags: att_obj.auth_data.flags,
+                trusted,
+            })
+        })
+        .collect()
+}
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct MakeCredentialsResult {
     pub att_obj: AttestationObject,
     pub attachment: AuthenticatorAttachment,
     pub extensions: AuthenticationExtensionsClientOutputs,
+    /// Set if enterprise attestation was requested and the authenticator returned an
+    /// attestation statement for it.
+    pub enterprise_attestation: Option<EnterpriseAttestationMode>,
+    /// The `epAtt` member of the response (CTAP 2.1), telling whether the authenticator
+    /// returned an enterprise attestation. None if the authenticator did not say.
+    pub ep_att: Option<bool>,
+    /// The `largeBlobKey` member of the response (CTAP 2.1), see
+    /// `AuthenticationExtensionsClientOutputs::large_blob_key`.
+    pub large_blob_key: Option<[u8; 32]>,
+    /// The PIN/UV auth protocol (1 or 2) of the pinUvAuthParam sent with the request, or None
+    /// if the request had none.
+    pub pin_uv_auth_protocol: Option<u64>,
 }
 
 impl MakeCredentialsResult {
+    pub fn is_backup_eligible(&self) -> bool {
+        self.att_obj.auth_data.is_backup_eligible()
+    }
+
+    pub fn is_backed_up(&self) -> bool {
+        self.att_obj.auth_data.is_backed_up()
+    }
+
+    /// Checks the result against all requirements of `policy`, and returns the first one it
+    /// violates. The attestation statement is verified where we can (see
+    /// `AttestationObject::verify()`), its certificate chain is not checked.
+    pub fn check_policy(
+        &self,
+        policy: &RegistrationPolicy,
+        client_data: &CollectedClientData,
+    ) -> Result<(), PolicyViolation> {
+        if client_data.webauthn_type != WebauthnType::Create {
+            return Err(PolicyViolation::ClientData);
+        }
+        let client_data_hash = client_data
+            .hash()
+            .map_err(|_| PolicyViolation::ClientData)?;
+        check_attestation_object(
+            &self.att_obj,
+            &client_data_hash,
+            policy,
+            self.enterprise_attestation.is_some(),
+            &CertificateCache::new(),
+        )?;
+        if policy.require_resident_key
+            && !matches!(
+                self.extensions.cred_props,
+                Some(CredentialProperties { rk: true, .. })
+            )
+        {
+            return Err(PolicyViolation::ResidentKey);
+        }
+        Ok(())
+    }
+
+    /// The result in the shape of a browser's `PublicKeyCredential.toJSON()` (a
+    /// `RegistrationResponseJSON`, see https://w3c.github.io/webauthn/#dictdef-registrationresponsejson),
+    /// as expected by most WebAuthn servers. `client_data_json` has to be the exact
+    /// serialization the client data hash was computed from.
+    ///
+    /// We don't know over which transport we talked to the authenticator, so
+    /// `response.transports` is only derived from the attachment: "internal" for platform
+    /// authenticators, "usb" for cross-platform ones (i.e. HID devices), and empty otherwise.
+    pub fn to_registration_json(
+        &self,
+        credential_id: &[u8],
+        client_data_json: &[u8],
+    ) -> Result<json::Value, CommandError> {
+        let b64 = |data: &[u8]| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(data);
+        let att_obj = ser::to_vec(&self.att_obj).map_err(CommandError::Serializing)?;
+        let (attachment, transports) = match self.attachment {
+            AuthenticatorAttachment::Platform => (Some("platform"), vec![Transport::Internal]),
+            AuthenticatorAttachment::CrossPlatform => {
+                (Some("cross-platform"), vec![Transport::USB])
+            }
+            AuthenticatorAttachment::Unknown => (None, vec![]),
+        };
+
+        let mut extensions = json::Map::new();
+        if let Some(cred_props) = &self.extensions.cred_props {
+            let mut output = json::json!({ "rk": cred_props.rk });
+            if let Some(name) = &cred_props.authenticator_display_name {
+                output["authenticatorDisplayName"] = name.as_str().into();
+            }
+            extensions.insert("credProps".into(), output);
+        }
+        if let Some(hmac_create_secret) = self.extensions.hmac_create_secret {
+            extensions.insert("hmacCreateSecret".into(), hmac_create_secret.into());
+        }
+        if let Some(cred_blob) = self.extensions.cred_blob {
+            extensions.insert("credBlob".into(), cred_blob.into());
+        }
+
+        let mut response = json::Map::new();
+        response.insert("clientDataJSON".into(), b64(client_data_json).into());
+        response.insert("attestationObject".into(), b64(&att_obj).into());
+        response.insert(
+            "transports".into(),
+            transports
+                .iter()
+                .map(|t| json::Value::from(t.as_str()))
+                .collect(),
+        );
+
+        let mut credential = json::Map::new();
+        credential.insert("id".into(), b64(credential_id).into());
+        credential.insert("rawId".into(), b64(credential_id).into());
+        credential.insert("type".into(), "public-key".into());
+        credential.insert("response".into(), json::Value::Object(response));
+        if let Some(attachment) = attachment {
+            credential.insert("authenticatorAttachment".into(), attachment.into());
+        }
+        credential.insert(
+            "clientExtensionResults".into(),
+            json::Value::Object(extensions),
+        );
+        Ok(json::Value::Object(credential))
+    }
+
     pub fn from_ctap1(input: &[u8], rp_id_hash: &RpIdHash) -> Result<Self, CommandError> {
         let mut data = Cursor::new(input);
         let magic_num = read_byte(&mut data).map_err(CommandError::Deserializing)?;
@@ -107,96 +418,180 @@ impl MakeCredentialsResult {
             att_obj,
             attachment: AuthenticatorAttachment::Unknown,
             extensions: Default::default(),
+            enterprise_attestation: None,
+            ep_att: None,
+            large_blob_key: None,
+            pin_uv_auth_protocol: None,
         })
     }
 }
 
-impl<'de> Deserialize<'de> for MakeCredentialsResult {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
+/// The name of the authenticator model, if `aaguid` is in the bundled metadata (see the
+/// `aaguid_metadata` feature).
+pub(crate) fn authenticator_display_name(aaguid: &AAGuid) -> Option<String> {
+    #[cfg(feature = "aaguid_metadata")]
     {
-        struct MakeCredentialsResultVisitor;
+        aaguid.metadata().map(|metadata| metadata.name.to_string())
+    }
+    #[cfg(not(feature = "aaguid_metadata"))]
+    {
+        let _ = aaguid;
+        None
+    }
+}
+
+/// The attestation statement (0x03) of an authenticatorMakeCredential response as it is
+/// encoded in `response`, if there is one. Only the keys of the response are decoded.
+fn raw_att_stmt(response: &[u8]) -> Result<Option<&[u8]>, serde_cbor::Error> {
+    // Responses are maps with less than 24 members, so the size is part of the first byte
+    let size = match response.first() {
+        Some(byte @ 0xa0..=0xb7) => byte & 0x1f,
+        _ => {
+            return Err(DesError::invalid_type(
+                Unexpected::Other("CBOR item"),
+                &"a map",
+            ))
+        }
+    };
+    let mut deserializer = serde_cbor::Deserializer::from_slice(&response[1..]);
+    for _ in 0..size {
+        let key = i64::deserialize(&mut deserializer)?;
+        let start = deserializer.byte_offset();
+        IgnoredAny::deserialize(&mut deserializer)?;
+        if key == 3 {
+            return Ok(Some(&response[1 + start..1 + deserializer.byte_offset()]));
+        }
+    }
+    Ok(None)
+}
 
-        impl<'de> Visitor<'de> for MakeCredentialsResultVisitor {
-            type Value = MakeCredentialsResult;
+struct MakeCredentialsResultVisitor<'a> {
+    /// See `raw_att_stmt()`. If set, the attestation statement is kept as it is instead of
+    /// being parsed (see `MakeCredentials::skip_attestation_statement`).
+    raw_att_stmt: Option<&'a [u8]>,
+}
 
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("a cbor map")
-            }
+impl<'de, 'a> Visitor<'de> for MakeCredentialsResultVisitor<'a> {
+    type Value = MakeCredentialsResult;
 
-            fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
-            where
-                M: MapAccess<'de>,
-            {
-                let mut format: Option<&str> = None;
-                let mut auth_data: Option<AuthenticatorData> = None;
-                let mut att_stmt: Option<AttestationStatement> = None;
-
-                while let Some(key) = map.next_key()? {
-                    match key {
-                        1 => {
-                            if format.is_some() {
-                                return Err(DesError::duplicate_field("fmt (0x01)"));
-                            }
-                            format = Some(map.next_value()?);
-                        }
-                        2 => {
-                            if auth_data.is_some() {
-                                return Err(DesError::duplicate_field("authData (0x02)"));
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a cbor map")
+    }
+
+    fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut format: Option<&str> = None;
+        let mut auth_data: Option<AuthenticatorData> = None;
+        let mut att_stmt: Option<AttestationStatement> = None;
+        let mut ep_att: Option<bool> = None;
+        let mut large_blob_key: Option<[u8; 32]> = None;
+
+        while let Some(key) = map.next_key()? {
+            match key {
+                1 => {
+                    if format.is_some() {
+                        return Err(DesError::duplicate_field("fmt (0x01)"));
+                    }
+                    format = Some(map.next_value()?);
+                }
+                2 => {
+                    if auth_data.is_some() {
+                        return Err(DesError::duplicate_field("authData (0x02)"));
+                    }
+                    auth_data = Some(map.next_value()?);
+                }
+                3 => {
+                    let format = format.ok_or_else(|| DesError::missing_field("fmt (0x01)"))?;
+                    if att_stmt.is_some() {
+                        return Err(DesError::duplicate_field("attStmt (0x03)"));
+                    }
+                    if let Some(raw) = self.raw_att_stmt.filter(|_| format != "none") {
+                        // Keep the encoded attestation statement, without looking at it
+                        map.next_value::<IgnoredAny>()?;
+                        att_stmt = Some(AttestationStatement::Unparsed(
+                            format.to_string(),
+                            raw.to_vec(),
+                        ));
+                        continue;
+                    }
+                    att_stmt = match format {
+                        "none" => {
+                            let map: std::collections::BTreeMap<(), ()> = map.next_value()?;
+                            if !map.is_empty() {
+                                return Err(DesError::invalid_value(
+                                    Unexpected::Map,
+                                    &"the empty map",
+                                ));
                             }
-                            auth_data = Some(map.next_value()?);
+                            Some(AttestationStatement::None)
                         }
-                        3 => {
-                            let format =
-                                format.ok_or_else(|| DesError::missing_field("fmt (0x01)"))?;
-                            if att_stmt.is_some() {
-                                return Err(DesError::duplicate_field("attStmt (0x03)"));
-                            }
-                            att_stmt = match format {
-                                "none" => {
-                                    let map: std::collections::BTreeMap<(), ()> =
-                                        map.next_value()?;
-                                    if !map.is_empty() {
-                                        return Err(DesError::invalid_value(
-                                            Unexpected::Map,
-                                            &"the empty map",
-                                        ));
-                                    }
-                                    Some(AttestationStatement::None)
-                                }
-                                "packed" => Some(AttestationStatement::Packed(map.next_value()?)),
-                                "fido-u2f" => {
-                                    Some(AttestationStatement::FidoU2F(map.next_value()?))
-                                }
-                                _ => {
-                                    return Err(DesError::custom(
-                                        "unknown attestation statement format",
-                                    ))
-                                }
-                            }
+                        "packed" => Some(AttestationStatement::Packed(map.next_value()?)),
+                        "fido-u2f" => Some(AttestationStatement::FidoU2F(map.next_value()?)),
+                        "tpm" => Some(AttestationStatement::Tpm(map.next_value()?)),
+                        "android-key" => Some(AttestationStatement::AndroidKey(map.next_value()?)),
+                        "android-safetynet" => {
+                            Some(AttestationStatement::AndroidSafetyNet(map.next_value()?))
                         }
-                        _ => continue,
+                        "apple" => Some(AttestationStatement::AppleAnonymous(map.next_value()?)),
+                        _ => return Err(DesError::custom("unknown attestation statement format")),
                     }
                 }
-
-                let auth_data = auth_data
-                    .ok_or_else(|| M::Error::custom("found no authData (0x02)".to_string()))?;
-                let att_stmt = att_stmt
-                    .ok_or_else(|| M::Error::custom("found no attStmt (0x03)".to_string()))?;
-
-                Ok(MakeCredentialsResult {
-                    att_obj: AttestationObject {
-                        auth_data,
-                        att_stmt,
-                    },
-                    attachment: AuthenticatorAttachment::Unknown,
-                    extensions: Default::default(),
-                })
+                4 => {
+                    if ep_att.is_some() {
+                        return Err(DesError::duplicate_field("epAtt (0x04)"));
+                    }
+                    ep_att = Some(map.next_value()?);
+                }
+                5 => {
+                    if large_blob_key.is_some() {
+                        return Err(DesError::duplicate_field("largeBlobKey (0x05)"));
+                    }
+                    let key: ByteBuf = map.next_value()?;
+                    large_blob_key = Some(
+                        key.as_slice()
+                            .try_into()
+                            .map_err(|_| DesError::invalid_length(key.len(), &"32"))?,
+                    );
+                }
+                _ => continue,
             }
         }
 
-        deserializer.deserialize_bytes(MakeCredentialsResultVisitor)
+        let auth_data =
+            auth_data.ok_or_else(|| M::Error::custom("found no authData (0x02)".to_string()))?;
+        let att_stmt = match (att_stmt, format) {
+            (Some(att_stmt), _) => att_stmt,
+            // Some devices omit the attestation statement entirely for "none" attestation.
+            // Browsers accept this, so we do too, but only for this format.
+            (None, Some("none")) => AttestationStatement::None,
+            (None, _) => {
+                return Err(M::Error::custom("found no attStmt (0x03)".to_string()));
+            }
+        };
+
+        Ok(MakeCredentialsResult {
+            att_obj: AttestationObject {
+                auth_data,
+                att_stmt,
+            },
+            attachment: AuthenticatorAttachment::Unknown,
+            extensions: Default::default(),
+            enterprise_attestation: None,
+            ep_att,
+            large_blob_key,
+            pin_uv_auth_protocol: None,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for MakeCredentialsResult {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(MakeCredentialsResultVisitor { raw_att_stmt: None })
     }
 }
 
@@ -209,13 +604,17 @@ pub struct MakeCredentialsOptions {
     pub resident_key: Option<bool>,
     #[serde(rename = "uv", skip_serializing_if = "Option::is_none")]
     pub user_verification: Option<bool>,
-    // TODO(MS): ctap2.1 supports user_presence, but ctap2.0 does not and tokens will error out
-    //           Commands need a version-flag to know what to de/serialize and what to ignore.
+    /// Only understood by CTAP 2.1 authenticators, CTAP 2.0 tokens error out. It is dropped
+    /// for them in `MakeCredentials::normalize_for()`.
+    #[serde(rename = "up", skip_serializing_if = "Option::is_none")]
+    pub user_presence: Option<bool>,
 }
 
 impl MakeCredentialsOptions {
     pub(crate) fn has_some(&self) -> bool {
-        self.resident_key.is_some() || self.user_verification.is_some()
+        self.resident_key.is_some()
+            || self.user_verification.is_some()
+            || self.user_presence.is_some()
     }
 }
 
@@ -233,31 +632,92 @@ impl UserVerification for MakeCredentialsOptions {
     }
 }
 
-#[derive(Debug, Default, Clone, Serialize)]
+#[derive(Debug, Default, Clone)]
 pub struct MakeCredentialsExtensions {
-    #[serde(skip_serializing)]
+    /// An opaque blob to store with the credential. It may not be longer than the
+    /// maxCredBlobLength of the authenticator.
+    pub cred_blob: Option<Vec<u8>>,
     pub cred_props: Option<bool>,
-    #[serde(rename = "credProtect", skip_serializing_if = "Option::is_none")]
     pub cred_protect: Option<CredentialProtectionPolicy>,
-    #[serde(rename = "hmac-secret", skip_serializing_if = "Option::is_none")]
     pub hmac_secret: Option<bool>,
-    #[serde(rename = "minPinLength", skip_serializing_if = "Option::is_none")]
+    pub hmac_secret_mc: Option<HmacSecretExtension>,
     pub min_pin_length: Option<bool>,
+    pub large_blob_key: Option<bool>,
+    /// Inputs of extensions that are not modelled here, sent as they are. They are neither
+    /// checked against getInfo, nor is their output processed.
+    pub raw_extensions: BTreeMap<String, Value>,
+}
+
+impl Serialize for MakeCredentialsExtensions {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = ExtensionsMap::default();
+        map.insert("credBlob", self.cred_blob.as_deref().map(Bytes::new));
+        map.insert("credProtect", self.cred_protect.as_ref());
+        map.insert("hmac-secret", self.hmac_secret.as_ref());
+        map.insert("hmac-secret-mc", self.hmac_secret_mc.as_ref());
+        map.insert("minPinLength", self.min_pin_length.as_ref());
+        map.insert("largeBlobKey", self.large_blob_key.as_ref());
+        map.insert_raw(&self.raw_extensions);
+        map.serialize(serializer)
+    }
 }
 
 impl MakeCredentialsExtensions {
+    /// Drops the authenticator extensions that the device does not list in getInfo, instead
+    /// of sending them blindly. Enforced extensions need to be checked before this.
+    pub(crate) fn remove_unsupported(&mut self, info: &AuthenticatorInfo) {
+        if !info.supports_cred_protect() {
+            self.cred_protect = None;
+        }
+        if !info.supports_hmac_secret() {
+            self.hmac_secret = None;
+        }
+        // Only ask for hmac-secret outputs at creation time, if the authenticator knows how
+        // to produce them. Otherwise the request would fail.
+        if !info.supports_hmac_secret() || !info.supports_hmac_secret_mc() {
+            self.hmac_secret_mc = None;
+        }
+        if !info.supports_extension("minPinLength") {
+            self.min_pin_length = None;
+        }
+        if !info.supports_extension("credBlob") {
+            self.cred_blob = None;
+        }
+        // largeBlobKey is only listed in getInfo, if the authenticator supports largeBlobs
+        if info.options.large_blobs != Some(true) {
+            self.large_blob_key = None;
+        }
+    }
+
     fn has_content(&self) -> bool {
-        self.cred_protect.is_some() || self.hmac_secret.is_some() || self.min_pin_length.is_some()
+        self.cred_blob.is_some()
+            || self.cred_protect.is_some()
+            || self.hmac_secret.is_some()
+            || self.hmac_secret_mc.is_some()
+            || self.min_pin_length.is_some()
+            || self.large_blob_key.is_some()
+            || !self.raw_extensions.is_empty()
     }
 }
 
 impl From<AuthenticationExtensionsClientInputs> for MakeCredentialsExtensions {
     fn from(input: AuthenticationExtensionsClientInputs) -> Self {
         Self {
+            cred_blob: input.cred_blob,
             cred_props: input.cred_props,
             cred_protect: input.credential_protection_policy,
             hmac_secret: input.hmac_create_secret,
+            // hmac-secret-mc requires hmac-secret to be requested as well
+            hmac_secret_mc: match input.hmac_create_secret {
+                Some(true) => input.hmac_get_secret.as_ref().map(Into::into),
+                _ => None,
+            },
             min_pin_length: input.min_pin_length,
+            large_blob_key: input.large_blob_key,
+            raw_extensions: BTreeMap::new(),
         }
     }
 }
@@ -281,6 +741,9 @@ pub struct MakeCredentials {
     pub options: MakeCredentialsOptions,
     pub pin_uv_auth_param: Option<PinUvAuthParam>,
     pub enterprise_attestation: Option<u64>,
+    /// Don't parse the attestation statement of the response, but keep it as
+    /// `AttestationStatement::Unparsed`. Useful for callers that only need the credential.
+    pub skip_attestation_statement: bool,
 }
 
 impl MakeCredentials {
@@ -304,9 +767,114 @@ impl MakeCredentials {
             options,
             pin_uv_auth_param: None,
             enterprise_attestation: None,
+            skip_attestation_statement: false,
+        }
+    }
+
+    /// For vendor-facilitated enterprise attestation (mode 1), the client decides which RPs
+    /// may receive it. If `self.rp` is not one of them, the request is dropped rather than
+    /// failing the registration.
+    pub(crate) fn restrict_enterprise_attestation(&mut self, allowed_rp_ids: &[String]) {
+        if self.enterprise_attestation == Some(1) && !allowed_rp_ids.contains(&self.rp.id) {
+            debug!(
+                "Dropping enterprise attestation request, {} is not allowed to receive it",
+                self.rp.id
+            );
+            self.enterprise_attestation = None;
+        }
+    }
+
+    /// Authenticators reject enterprise attestation requests, unless the feature is enabled
+    /// (ep == true). A vendor-facilitated request (mode 1) is dropped in that case, so the
+    /// registration proceeds with regular attestation. Platform-managed requests (mode 2) are
+    /// refused beforehand, see `check_compatibility()`.
+    pub(crate) fn drop_disabled_enterprise_attestation(
+        &mut self,
+        info: Option<&AuthenticatorInfo>,
+    ) {
+        let ep_enabled = info.map_or(false, |info| info.options.ep == Some(true));
+        if self.enterprise_attestation == Some(1) && !ep_enabled {
+            debug!("Dropping enterprise attestation request, it is not enabled on the device");
+            self.enterprise_attestation = None;
+        }
+    }
+
+    /// Adjusts the request to what the authenticator described by `info` understands, so that
+    /// serializing it yields a payload for the version of the authenticator:
+    ///  * Extensions that the authenticator does not list are dropped.
+    ///  * Vendor-facilitated enterprise attestation is dropped, unless enabled.
+    ///  * Options set to their default value (false) are omitted for CTAP 2.0 authenticators.
+    ///    Some of them reject "uv", even if it is false, when they have no built-in UV.
+    ///  * "up" is omitted for authenticators that don't support CTAP 2.1.
+    ///
+    /// Everything the caller insisted on (e.g. enforced extensions) has to be checked before
+    /// this. Normalizing a request again is a no-op, so this can be called after options
+    /// changed, e.g. after the PIN/UV handling set "uv".
+    pub fn normalize_for(&mut self, info: &AuthenticatorInfo) {
+        self.extensions.remove_unsupported(info);
+        self.drop_disabled_enterprise_attestation(Some(info));
+        if info.max_supported_version() == AuthenticatorVersion::FIDO_2_0 {
+            if self.options.resident_key == Some(false) {
+                self.options.resident_key = None;
+            }
+            if self.options.user_verification == Some(false) {
+                self.options.user_verification = None;
+            }
+        }
+        if info.max_supported_version() != AuthenticatorVersion::FIDO_2_1 {
+            self.options.user_presence = None;
+        }
+    }
+
+    /// "pre-flight" for the exclude list: Asks the authenticator with silent (up=false)
+    /// requests, whether it holds any of the excluded credentials. The list is sent in batches
+    /// that respect maxCredentialCountInList, and credential IDs longer than
+    /// maxCredentialIdLength are skipped, as they can't be from this authenticator.
+    ///
+    /// Afterwards, the exclude list only contains the credentials the authenticator holds, so
+    /// it is never too long for the authenticator. If there are any, this returns
+    /// `AuthenticatorError::CredentialExcluded`. Sending the request anyway makes a CTAP2
+    /// authenticator wait for a touch, before it returns the error.
+    pub fn preflight_exclude_list<Dev: FidoDevice>(
+        &mut self,
+        dev: &mut Dev,
+        pin_uv_auth_token: Option<PinUvAuthToken>,
+    ) -> Result<(), AuthenticatorError> {
+        if self.exclude_list.is_empty() {
+            return Ok(());
+        }
+        self.exclude_list = match dev.get_protocol() {
+            FidoProtocol::CTAP2 => do_credential_list_filtering_ctap2(
+                dev,
+                &self.exclude_list,
+                &self.rp,
+                pin_uv_auth_token,
+            )?,
+            FidoProtocol::CTAP1 => do_credential_list_filtering_ctap1(
+                dev,
+                &self.exclude_list,
+                &self.rp,
+                &self.client_data_hash,
+            )
+            .into_iter()
+            .collect(),
+        };
+        if self.exclude_list.is_empty() {
+            Ok(())
+        } else {
+            Err(AuthenticatorError::CredentialExcluded)
         }
     }
 
+    /// The CBOR payload of the request, without the fields that the authenticator described
+    /// by `info` does not understand (see `normalize_for()`). The request itself is left as
+    /// it is. `wire_format()` serializes everything.
+    pub fn wire_format_for(&self, info: &AuthenticatorInfo) -> Result<Vec<u8>, HIDError> {
+        let mut normalized = self.clone();
+        normalized.normalize_for(info);
+        normalized.wire_format()
+    }
+
     pub fn finalize_result<Dev: FidoDevice>(&self, dev: &Dev, result: &mut MakeCredentialsResult) {
         let maybe_info = dev.get_authenticator_info();
 
@@ -315,6 +883,14 @@ impl MakeCredentials {
             Some(_) => AuthenticatorAttachment::CrossPlatform,
             None => AuthenticatorAttachment::Unknown,
         };
+        // CTAP1 requests don't carry the pinUvAuthParam
+        result.pin_uv_auth_protocol = match dev.get_protocol() {
+            FidoProtocol::CTAP2 => self
+                .pin_uv_auth_param
+                .as_ref()
+                .map(|param| param.pin_protocol.id()),
+            FidoProtocol::CTAP1 => None,
+        };
 
         // Handle extensions whose outputs are not encoded in the authenticator data.
         // 1. credProps
@@ -324,6 +900,9 @@ impl MakeCredentials {
         //      Note: a CTAP 2.0 authenticator is allowed to create a discoverable credential even
         //      if one was not requested, so there is a case in which we cannot confidently
         //      return `rk=false` here. We omit the response entirely in this case.
+        //      CTAP 2.1 requires authenticators to honor the rk option, so `rk=false` is
+        //      certain for them (as it is for CTAP1 devices, which only have non-discoverable
+        //      credentials).
         let dev_supports_rk = maybe_info.map_or(false, |info| info.options.resident_key);
         let requested_rk = self.options.resident_key.unwrap_or(false);
         let max_supported_version = maybe_info.map_or(AuthenticatorVersion::U2F_V2, |info| {
@@ -332,12 +911,20 @@ impl MakeCredentials {
         let rk_uncertain = max_supported_version == AuthenticatorVersion::FIDO_2_0
             && dev_supports_rk
             && !requested_rk;
+        //      Authenticators don't report a name for the credProps.authenticatorDisplayName
+        //      output, so we use the name of the model, if we know it.
         if self.extensions.cred_props == Some(true) && !rk_uncertain {
-            result
+            let aaguid = match &result.att_obj.auth_data.credential_data {
+                Some(credential_data) => Some(&credential_data.aaguid),
+                None => maybe_info.map(|info| &info.aaguid),
+            };
+            let display_name = aaguid.and_then(authenticator_display_name);
+            let cred_props = result
                 .extensions
                 .cred_props
-                .get_or_insert(Default::default())
-                .rk = requested_rk;
+                .get_or_insert(Default::default());
+            cred_props.rk = requested_rk;
+            cred_props.authenticator_display_name = display_name;
         }
 
         // 2. hmac-secret
@@ -350,6 +937,61 @@ impl MakeCredentials {
                 result.extensions.hmac_create_secret = Some(flag);
             }
         }
+
+        // 3. hmac-secret-mc
+        //      The authenticator returns the HMAC outputs encrypted with the shared secret we
+        //      used to encrypt the salts.
+        if let (Some(extension), Some(HmacSecretResponse::Secret(output)), Some(secret)) = (
+            &self.extensions.hmac_secret_mc,
+            &result.att_obj.auth_data.extensions.hmac_secret_mc,
+            dev.get_shared_secret(),
+        ) {
+            match extension.decrypt_output(secret, output) {
+                Ok(output) => result.extensions.hmac_get_secret = Some(output),
+                Err(e) => warn!("Failed to decrypt hmac-secret-mc output: {e:?}"),
+            }
+        }
+
+        // 4. credProtect
+        //      Authenticators echo the policy they applied. If they don't, the credential has
+        //      the default policy, which is userVerificationOptional.
+        if self.extensions.cred_protect.is_some() {
+            result.extensions.credential_protection_policy = Some(
+                result
+                    .att_obj
+                    .auth_data
+                    .extensions
+                    .cred_protect
+                    .unwrap_or(CredentialProtectionPolicy::UserVerificationOptional),
+            );
+        }
+
+        // 5. credBlob
+        //      The authenticator tells us in the authenticator data, whether it stored the blob.
+        if self.extensions.cred_blob.is_some() {
+            result.extensions.cred_blob = result.att_obj.auth_data.extensions.cred_blob;
+        }
+
+        // 6. largeBlobKey
+        //      The key is a member of the response, not of the authenticator data.
+        if self.extensions.large_blob_key == Some(true) {
+            result.extensions.large_blob_key = result.large_blob_key;
+        }
+
+        // 7. Enterprise attestation
+        //      CTAP 2.1 authenticators tell us with epAtt, whether they returned an enterprise
+        //      attestation. Otherwise, we have to guess: Only authenticators with enterprise
+        //      attestation enabled (ep == true) honor the request. If they do, the attestation
+        //      statement is not "none".
+        let ep_applied = result.ep_att.unwrap_or_else(|| {
+            let ep_enabled = maybe_info.map_or(false, |info| info.options.ep == Some(true));
+            ep_enabled && result.att_obj.att_stmt != AttestationStatement::None
+        });
+        result.enterprise_attestation = match self.enterprise_attestation {
+            Some(1) if ep_applied => Some(EnterpriseAttestationMode::VendorFacilitated),
+            Some(2) if ep_applied => Some(EnterpriseAttestationMode::PlatformManaged),
+            _ => None,
+        };
     }
 }
 
@@ -360,6 +1002,7 @@ impl PinUvAuthCommand for MakeCredentials {
     ) -> Result<(), AuthenticatorError> {
         let mut param = None;
         if let Some(token) = pin_uv_auth_token {
+            self.check_permissions(&token)?;
             param = Some(
                 token
                     .derive(self.client_data_hash.as_ref())
@@ -378,11 +1021,20 @@ impl PinUvAuthCommand for MakeCredentials {
         Some(&self.rp.id)
     }
 
-    fn can_skip_user_verification(
-        &mut self,
+    fn required_permissions(&self) -> PinUvAuthTokenPermission {
+        if self.exclude_list.is_empty() {
+            PinUvAuthTokenPermission::MakeCredential
+        } else {
+            // Pre-flighting the exclude list sends GetAssertion-commands
+            PinUvAuthTokenPermission::MakeCredential | PinUvAuthTokenPermission::GetAssertion
+        }
+    }
+
+    fn user_verification_requirement_reason(
+        &self,
         info: &AuthenticatorInfo,
         uv_req: UserVerificationRequirement,
-    ) -> bool {
+    ) -> UvReason {
         // TODO(MS): Handle here the case where we NEED a UV, the device supports PINs, but hasn't set a PIN.
         //           For this, the user has to be prompted to set a PIN first (see https://github.com/mozilla/authenticator-rs/issues/223)
 
@@ -403,7 +1055,15 @@ impl PinUvAuthCommand for MakeCredentials {
         // RP's requirement.
         let always_uv = info.options.always_uv == Some(true);
 
-        !always_uv && (!device_protected || make_cred_uv_not_required)
+        if always_uv {
+            UvReason::AlwaysUv
+        } else if !device_protected || make_cred_uv_not_required {
+            UvReason::NotRequired
+        } else if uv_req == UserVerificationRequirement::Required {
+            UvReason::RelyingPartyRequirement
+        } else {
+            UvReason::DeviceProtected
+        }
     }
 
     fn get_pin_uv_auth_param(&self) -> Option<&PinUvAuthParam> {
@@ -524,21 +1184,29 @@ impl RequestCtap2 for MakeCredentials {
         input: &[u8],
     ) -> Result<Self::Output, HIDError> {
         if input.is_empty() {
-            return Err(HIDError::Command(CommandError::InputTooSmall));
+            return Err(HIDError::Command(CommandError::EmptyResponse));
         }
 
         let status: StatusCode = input[0].into();
         debug!("response status code: {:?}", status);
         if input.len() == 1 {
             if status.is_ok() {
-                return Err(HIDError::Command(CommandError::InputTooSmall));
+                return Err(HIDError::Command(CommandError::EmptyResponse));
             }
             return Err(HIDError::Command(CommandError::StatusCode(status, None)));
         }
 
         if status.is_ok() {
-            let mut output: MakeCredentialsResult =
-                from_slice(&input[1..]).map_err(CommandError::Deserializing)?;
+            let raw_att_stmt = if self.skip_attestation_statement {
+                raw_att_stmt(&input[1..]).map_err(CommandError::from_response_body)?
+            } else {
+                None
+            };
+            let mut deserializer = serde_cbor::Deserializer::from_slice(&input[1..]);
+            let mut output = deserializer
+                .deserialize_bytes(MakeCredentialsResultVisitor { raw_att_stmt })
+                .and_then(|output| deserializer.end().map(|_| output))
+                .map_err(CommandError::from_response_body)?;
             self.finalize_result(dev, &mut output);
             Ok(output)
         } else {
@@ -596,25 +1264,52 @@ pub(crate) fn dummy_make_credentials_cmd() -> MakeCredentials {
 
 #[cfg(test)]
 pub mod test {
-    use super::{MakeCredentials, MakeCredentialsOptions, MakeCredentialsResult};
-    use crate::crypto::{COSEAlgorithm, COSEEC2Key, COSEKey, COSEKeyType, Curve};
-    use crate::ctap2::attestation::test::create_attestation_obj;
+    use super::{
+        dummy_make_credentials_cmd, raw_att_stmt, verify_batch_at, AttestationConveyance,
+        EnterpriseAttestationMode, MakeCredentials, MakeCredentialsExtensions,
+        MakeCredentialsOptions, MakeCredentialsResult, PolicyViolation, RegistrationPolicy,
+    };
+    use crate::crypto::{
+        COSEAlgorithm, COSEEC2Key, COSEKey, COSEKeyType, CryptoError, Curve, PinUvAuthProtocol,
+        PinUvAuthToken,
+    };
+    use crate::ctap2::attestation::test::{
+        create_attestation_obj, sample_tpm_att_stmt, SAMPLE_ANDROID_KEY_ROOT_CERT,
+        SAMPLE_ATTESTATION_OBJECT_ANDROID_KEY, SAMPLE_ATTESTATION_OBJECT_TPM,
+        SAMPLE_CLIENT_DATA_HASH, SAMPLE_VERIFICATION_TIME,
+    };
     use crate::ctap2::attestation::{
-        AAGuid, AttestationCertificate, AttestationObject, AttestationStatement,
-        AttestationStatementFidoU2F, AttestedCredentialData, AuthenticatorData,
-        AuthenticatorDataFlags, Signature,
+        AAGuid, AttestationCertificate, AttestationError, AttestationObject, AttestationStatement,
+        AttestationStatementAndroidKey, AttestationStatementAndroidSafetyNet,
+        AttestationStatementAppleAnonymous, AttestationStatementFidoU2F,
+        AttestationStatementPacked, AttestedCredentialData, AuthenticatorData,
+        AuthenticatorDataFlags, HmacSecretResponse, Signature, TrustStore,
+    };
+    use crate::ctap2::client_data::{
+        Challenge, ClientDataHash, CollectedClientData, TokenBinding, WebauthnType,
     };
-    use crate::ctap2::client_data::{Challenge, CollectedClientData, TokenBinding, WebauthnType};
-    use crate::ctap2::commands::{RequestCtap1, RequestCtap2};
+    use crate::ctap2::commands::client_pin::{Pin, PinError, PinUvAuthTokenPermission};
+    use crate::ctap2::commands::get_info::{
+        AuthenticatorInfo, AuthenticatorOptions, AuthenticatorVersion,
+    };
+    use crate::ctap2::commands::{Command, PinUvAuthCommand, RequestCtap1, RequestCtap2};
     use crate::ctap2::server::RpIdHash;
     use crate::ctap2::server::{
-        AuthenticatorAttachment, PublicKeyCredentialParameters, PublicKeyCredentialUserEntity,
-        RelyingParty,
+        AuthenticationExtensionsClientInputs, AuthenticatorAttachment, CredentialProperties,
+        CredentialProtectionPolicy, HmacGetSecretInput, HmacGetSecretOutput,
+        PublicKeyCredentialDescriptor, PublicKeyCredentialParameters,
+        PublicKeyCredentialUserEntity, RelyingParty,
     };
+    use crate::errors::AuthenticatorError;
     use crate::transport::device_selector::Device;
     use crate::transport::hid::HIDDevice;
+    use crate::transport::platform::recording::{RecordedRequest, RecordingDevice};
     use crate::transport::{FidoDevice, FidoProtocol};
     use base64::Engine;
+    use serde_cbor::{de::from_slice, ser, Value};
+    use std::collections::BTreeMap;
+    use std::convert::TryFrom;
+    use std::time::{Duration, UNIX_EPOCH};
 
     #[test]
     fn test_make_credentials_ctap2() {
@@ -651,6 +1346,7 @@ pub mod test {
             MakeCredentialsOptions {
                 resident_key: Some(true),
                 user_verification: None,
+                user_presence: None,
             },
             Default::default(),
         );
@@ -669,76 +1365,1328 @@ pub mod test {
             att_obj: create_attestation_obj(),
             attachment: AuthenticatorAttachment::Unknown,
             extensions: Default::default(),
+            enterprise_attestation: None,
+            ep_att: None,
+            large_blob_key: None,
+            pin_uv_auth_protocol: None,
         };
 
         assert_eq!(make_cred_result, expected);
     }
 
     #[test]
-    fn test_make_credentials_ctap1() {
-        let req = MakeCredentials::new(
-            CollectedClientData {
-                webauthn_type: WebauthnType::Create,
-                challenge: Challenge::new(vec![0x00, 0x01, 0x02, 0x03]),
-                origin: String::from("example.com"),
-                cross_origin: false,
-                token_binding: Some(TokenBinding::Present(String::from("AAECAw"))),
-            }
-            .hash()
-            .expect("failed to serialize client data"),
-            RelyingParty::from("example.com"),
-            Some(PublicKeyCredentialUserEntity {
-                id: base64::engine::general_purpose::URL_SAFE
-                    .decode("MIIBkzCCATigAwIBAjCCAZMwggE4oAMCAQIwggGTMII=")
-                    .unwrap(),
-                name: Some(String::from("johnpsmith@example.com")),
-                display_name: Some(String::from("John P. Smith")),
-            }),
+    fn test_attestation_object_webauthn_encoding() {
+        // The attestation object of the sample response, as a WebAuthn client would hand it to
+        // the relying party: the same map, but keyed by "fmt", "attStmt" and "authData".
+        let response: BTreeMap<u8, Value> =
+            from_slice(&MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2[1..]).unwrap();
+        let webauthn = Value::Map(
             vec![
-                PublicKeyCredentialParameters {
-                    alg: COSEAlgorithm::ES256,
-                },
-                PublicKeyCredentialParameters {
-                    alg: COSEAlgorithm::RS256,
-                },
-            ],
-            Vec::new(),
-            MakeCredentialsOptions {
-                resident_key: Some(true),
-                user_verification: None,
-            },
-            Default::default(),
+                (Value::Text("fmt".into()), response[&1].clone()),
+                (Value::Text("authData".into()), response[&2].clone()),
+                (Value::Text("attStmt".into()), response[&3].clone()),
+            ]
+            .into_iter()
+            .collect(),
         );
+        let expected = ser::to_vec(&webauthn).unwrap();
 
-        let (req_serialized, _) = req
-            .ctap1_format()
-            .expect("Failed to serialize MakeCredentials request");
+        let mut device = Device::new("commands/make_credentials").unwrap();
+        let result = dummy_make_credentials_cmd()
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
+        let encoded = ser::to_vec(&result.att_obj).expect("Failed to serialize");
+        assert_eq!(encoded, expected);
+        let decoded: AttestationObject = from_slice(&encoded).expect("Failed to deserialize");
+        assert_eq!(decoded, result.att_obj);
+    }
+
+    #[test]
+    fn test_make_credentials_enterprise_attestation() {
+        let mut req = dummy_make_credentials_cmd();
+        req.enterprise_attestation = Some(2);
+
+        let mut device = Device::new("commands/make_credentials").unwrap(); // not really used
+        let mut info = AuthenticatorInfo::default();
+        info.options.ep = Some(true);
+        device.set_authenticator_info(info.clone());
+        let make_cred_result = req
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
         assert_eq!(
-            req_serialized, MAKE_CREDENTIALS_SAMPLE_REQUEST_CTAP1,
-            "\nGot:      {req_serialized:X?}\nExpected: {MAKE_CREDENTIALS_SAMPLE_REQUEST_CTAP1:X?}"
+            make_cred_result.enterprise_attestation,
+            Some(EnterpriseAttestationMode::PlatformManaged)
         );
-        let mut device = Device::new("commands/make_credentials").unwrap(); // not really used
+
+        // Not requested
+        req.enterprise_attestation = None;
         let make_cred_result = req
-            .handle_response_ctap1(
-                &mut device,
-                Ok(()),
-                &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP1,
-                &(),
-            )
-            .expect("Failed to handle CTAP1 response");
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(make_cred_result.enterprise_attestation, None);
 
-        let att_obj = AttestationObject {
-            auth_data: AuthenticatorData {
-                rp_id_hash: RpIdHash::from(&[
-                    0xA3, 0x79, 0xA6, 0xF6, 0xEE, 0xAF, 0xB9, 0xA5, 0x5E, 0x37, 0x8C, 0x11, 0x80,
-                    0x34, 0xE2, 0x75, 0x1E, 0x68, 0x2F, 0xAB, 0x9F, 0x2D, 0x30, 0xAB, 0x13, 0xD2,
-                    0x12, 0x55, 0x86, 0xCE, 0x19, 0x47,
-                ])
-                .unwrap(),
-                flags: AuthenticatorDataFlags::USER_PRESENT | AuthenticatorDataFlags::ATTESTED,
-                counter: 0,
-                credential_data: Some(AttestedCredentialData {
-                    aaguid: AAGuid::default(),
+        // Requested, but not enabled on the device
+        req.enterprise_attestation = Some(1);
+        info.options.ep = Some(false);
+        device.set_authenticator_info(info);
+        let make_cred_result = req
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(make_cred_result.enterprise_attestation, None);
+
+        // The device tells us with epAtt, whether it applied enterprise attestation
+        let mut response = MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2.to_vec();
+        response[1] = 0xa4; // map(4)
+        response.extend_from_slice(&[0x04, 0xf5]); // epAtt: true
+        let make_cred_result = req
+            .handle_response_ctap2(&mut device, &response)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(make_cred_result.ep_att, Some(true));
+        assert_eq!(
+            make_cred_result.enterprise_attestation,
+            Some(EnterpriseAttestationMode::VendorFacilitated)
+        );
+
+        let mut info = AuthenticatorInfo::default();
+        info.options.ep = Some(true);
+        device.set_authenticator_info(info);
+        *response.last_mut().unwrap() = 0xf4; // epAtt: false
+        let make_cred_result = req
+            .handle_response_ctap2(&mut device, &response)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(make_cred_result.ep_att, Some(false));
+        assert_eq!(make_cred_result.enterprise_attestation, None);
+    }
+
+    #[test]
+    fn test_make_credentials_pin_uv_auth_protocol() {
+        let mut req = dummy_make_credentials_cmd();
+        let mut device = Device::new("commands/make_credentials").unwrap(); // not really used
+        let info = AuthenticatorInfo {
+            pin_protocols: Some(vec![2, 1]),
+            ..Default::default()
+        };
+        device.set_authenticator_info(info.clone());
+
+        let token = PinUvAuthToken::new_test_token(
+            PinUvAuthProtocol::try_from(&info).unwrap(),
+            vec![0x01; 32],
+            PinUvAuthTokenPermission::MakeCredential,
+        );
+        req.set_pin_uv_auth_param(Some(token))
+            .expect("Failed to set pinUvAuthParam");
+        let make_cred_result = req
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(make_cred_result.pin_uv_auth_protocol, Some(2));
+
+        req.set_pin_uv_auth_param(None)
+            .expect("Failed to set pinUvAuthParam");
+        let make_cred_result = req
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(make_cred_result.pin_uv_auth_protocol, None);
+    }
+
+    #[test]
+    fn test_make_credentials_cred_props() {
+        let mut req = dummy_make_credentials_cmd();
+        req.extensions.cred_props = Some(true);
+        req.options.resident_key = Some(false);
+
+        let mut device = Device::new("commands/make_credentials").unwrap(); // not really used
+        let mut info = AuthenticatorInfo {
+            versions: vec![
+                AuthenticatorVersion::FIDO_2_0,
+                AuthenticatorVersion::FIDO_2_1,
+            ],
+            options: AuthenticatorOptions {
+                resident_key: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        device.set_authenticator_info(info.clone());
+
+        // CTAP 2.1 devices only create discoverable credentials if asked to
+        let make_cred_result = req
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(
+            make_cred_result
+                .extensions
+                .cred_props
+                .map(|cred_props| cred_props.rk),
+            Some(false)
+        );
+
+        req.options.resident_key = Some(true);
+        let make_cred_result = req
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(
+            make_cred_result
+                .extensions
+                .cred_props
+                .map(|cred_props| cred_props.rk),
+            Some(true)
+        );
+
+        // CTAP 2.0 devices may create a discoverable credential anyway
+        info.versions = vec![AuthenticatorVersion::FIDO_2_0];
+        device.set_authenticator_info(info);
+        req.options.resident_key = Some(false);
+        let make_cred_result = req
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(make_cred_result.extensions.cred_props, None);
+
+        // Not requested
+        req.extensions.cred_props = None;
+        let make_cred_result = req
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(make_cred_result.extensions.cred_props, None);
+    }
+
+    #[test]
+    fn test_make_credentials_authenticator_display_name() {
+        let mut req = dummy_make_credentials_cmd();
+        req.options.resident_key = Some(true);
+        let mut device = Device::new("commands/make_credentials").unwrap(); // not really used
+        device.set_authenticator_info(AuthenticatorInfo {
+            versions: vec![AuthenticatorVersion::FIDO_2_1],
+            ..Default::default()
+        });
+
+        // Not requested
+        let make_cred_result = req
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(make_cred_result.extensions.cred_props, None);
+        let json = make_cred_result
+            .to_registration_json(&[1], b"{}")
+            .expect("Failed to encode the registration");
+        assert_eq!(json["clientExtensionResults"].get("credProps"), None);
+
+        // The sample response is from a Security Key by Yubico, which we only know about
+        // with the bundled metadata
+        req.extensions.cred_props = Some(true);
+        let make_cred_result = req
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
+        let json = make_cred_result
+            .to_registration_json(&[1], b"{}")
+            .expect("Failed to encode the registration");
+        #[cfg(feature = "aaguid_metadata")]
+        {
+            assert_eq!(
+                make_cred_result.extensions.cred_props,
+                Some(CredentialProperties {
+                    rk: true,
+                    authenticator_display_name: Some("Security Key by Yubico".to_string()),
+                })
+            );
+            assert_eq!(
+                json["clientExtensionResults"]["credProps"],
+                serde_json::json!({ "rk": true, "authenticatorDisplayName": "Security Key by Yubico" })
+            );
+        }
+        #[cfg(not(feature = "aaguid_metadata"))]
+        {
+            assert_eq!(
+                make_cred_result.extensions.cred_props,
+                Some(CredentialProperties {
+                    rk: true,
+                    authenticator_display_name: None,
+                })
+            );
+            assert_eq!(
+                json["clientExtensionResults"]["credProps"],
+                serde_json::json!({ "rk": true })
+            );
+        }
+    }
+
+    #[test]
+    fn test_make_credentials_restrict_enterprise_attestation() {
+        let allowed = vec![String::from("make.me.blink")];
+
+        let mut req = dummy_make_credentials_cmd();
+        req.enterprise_attestation = Some(1);
+        req.restrict_enterprise_attestation(&allowed);
+        assert_eq!(req.enterprise_attestation, Some(1));
+
+        req.rp = RelyingParty::from("example.org");
+        req.restrict_enterprise_attestation(&allowed);
+        assert_eq!(req.enterprise_attestation, None);
+
+        // Platform-managed enterprise attestation is not restricted by the client
+        req.enterprise_attestation = Some(2);
+        req.restrict_enterprise_attestation(&[]);
+        assert_eq!(req.enterprise_attestation, Some(2));
+    }
+
+    #[test]
+    fn test_make_credentials_drop_disabled_enterprise_attestation() {
+        let mut info = AuthenticatorInfo {
+            options: AuthenticatorOptions {
+                ep: Some(true),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut req = dummy_make_credentials_cmd();
+        req.enterprise_attestation = Some(1);
+        req.drop_disabled_enterprise_attestation(Some(&info));
+        assert_eq!(req.enterprise_attestation, Some(1));
+
+        info.options.ep = Some(false);
+        req.drop_disabled_enterprise_attestation(Some(&info));
+        assert_eq!(req.enterprise_attestation, None);
+
+        req.enterprise_attestation = Some(1);
+        req.drop_disabled_enterprise_attestation(None);
+        assert_eq!(req.enterprise_attestation, None);
+    }
+
+    #[test]
+    fn test_make_credentials_required_permissions() {
+        let mut req = dummy_make_credentials_cmd();
+        assert_eq!(
+            req.required_permissions(),
+            PinUvAuthTokenPermission::MakeCredential
+        );
+
+        // Pre-flighting the exclude list needs the GetAssertion-permission as well
+        req.exclude_list = vec![PublicKeyCredentialDescriptor {
+            id: vec![0x01, 0x02, 0x03],
+            transports: vec![],
+        }];
+        assert_eq!(
+            req.required_permissions(),
+            PinUvAuthTokenPermission::MakeCredential | PinUvAuthTokenPermission::GetAssertion
+        );
+    }
+
+    #[test]
+    fn test_make_credentials_skip_attestation_statement() {
+        let mut req = dummy_make_credentials_cmd();
+        let mut device = Device::new("commands/make_credentials").unwrap(); // not really used
+        let parsed = req
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
+
+        req.skip_attestation_statement = true;
+        let unparsed = req
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(unparsed.att_obj.auth_data, parsed.att_obj.auth_data);
+
+        let raw = match unparsed.att_obj.att_stmt {
+            AttestationStatement::Unparsed(ref fmt, ref raw) if fmt == "packed" => raw,
+            ref v => panic!("Unexpected attestation statement: {:?}", v),
+        };
+        // The statement is kept as the authenticator encoded it
+        assert!(MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2
+            .windows(raw.len())
+            .any(|window| window == &raw[..]));
+        // The raw attestation statement can still be parsed later on
+        let att_stmt = AttestationStatement::Packed(from_slice(raw).unwrap());
+        assert_eq!(att_stmt, parsed.att_obj.att_stmt);
+        // and serializes to the same attestation object
+        assert_eq!(
+            serde_cbor::to_vec(&unparsed.att_obj).unwrap(),
+            serde_cbor::to_vec(&parsed.att_obj).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_raw_att_stmt() {
+        // {1: "packed", 3: {"alg": -7}, 4: false}, with -7 not in its shortest encoding
+        let att_stmt = [0xa1, 0x63, b'a', b'l', b'g', 0x3a, 0x00, 0x00, 0x00, 0x06];
+        let mut response = vec![0xa3, 0x01, 0x66];
+        response.extend_from_slice(b"packed");
+        response.push(0x03);
+        response.extend_from_slice(&att_stmt);
+        response.extend_from_slice(&[0x04, 0xf4]);
+        assert_eq!(raw_att_stmt(&response).unwrap(), Some(&att_stmt[..]));
+
+        // {1: "none"}
+        assert_eq!(
+            raw_att_stmt(&[0xa1, 0x01, 0x64, b'n', b'o', b'n', b'e']).unwrap(),
+            None
+        );
+        assert!(raw_att_stmt(&[0x80]).is_err());
+        assert!(raw_att_stmt(&response[..response.len() - 3]).is_err());
+    }
+
+    // Device key taken from https://github.com/Yubico/python-fido2/blob/main/tests/test_ctap2.py
+    pub fn peer_key() -> COSEKey {
+        COSEKey {
+            alg: COSEAlgorithm::ECDH_ES_HKDF256,
+            key: COSEKeyType::EC2(COSEEC2Key {
+                curve: Curve::SECP256R1,
+                x: vec![
+                    0x05, 0x01, 0xd5, 0xbc, 0x78, 0xda, 0x92, 0x52, 0x56, 0x0a, 0x26, 0xcb, 0x08,
+                    0xfc, 0xc6, 0x0c, 0xbe, 0x0b, 0x6d, 0x3b, 0x8e, 0x1d, 0x1f, 0xce, 0xe5, 0x14,
+                    0xfa, 0xc0, 0xaf, 0x67, 0x51, 0x68,
+                ],
+                y: vec![
+                    0xd5, 0x51, 0xb3, 0xed, 0x46, 0xf6, 0x65, 0x73, 0x1f, 0x95, 0xb4, 0x53, 0x29,
+                    0x39, 0xc2, 0x5d, 0x91, 0xdb, 0x7e, 0xb8, 0x44, 0xbd, 0x96, 0xd4, 0xab, 0xd4,
+                    0x08, 0x37, 0x85, 0xf8, 0xdf, 0x47,
+                ],
+            }),
+        }
+    }
+
+    #[test]
+    fn test_make_credentials_hmac_secret_mc() {
+        let info = AuthenticatorInfo {
+            extensions: vec!["hmac-secret".to_string(), "hmac-secret-mc".to_string()],
+            pin_protocols: Some(vec![1]),
+            ..Default::default()
+        };
+        let secret = PinUvAuthProtocol::try_from(&info)
+            .unwrap()
+            .encapsulate(&peer_key())
+            .unwrap();
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.set_authenticator_info(info);
+        device.set_shared_secret(secret.clone());
+
+        let mut req = dummy_make_credentials_cmd();
+        req.extensions = AuthenticationExtensionsClientInputs {
+            hmac_create_secret: Some(true),
+            hmac_get_secret: Some(HmacGetSecretInput {
+                salt1: [0x01; 32],
+                salt2: Some([0x02; 32]),
+            }),
+            ..Default::default()
+        }
+        .into();
+        req.extensions
+            .hmac_secret_mc
+            .as_mut()
+            .unwrap()
+            .calculate(&secret)
+            .unwrap();
+        let serialized = req.wire_format().unwrap();
+        let request: Value = from_slice(&serialized).unwrap();
+        let extensions = match request {
+            Value::Map(m) => m.get(&Value::Integer(0x06)).cloned(),
+            _ => None,
+        };
+        let hmac_secret_mc = match extensions {
+            Some(Value::Map(m)) => m.get(&Value::Text("hmac-secret-mc".to_string())).cloned(),
+            _ => None,
+        };
+        assert!(matches!(hmac_secret_mc, Some(Value::Map(m)) if m.len() == 3));
+
+        // The authenticator encrypts output1 || output2 with the shared secret
+        let outputs = [[0x11; 32], [0x22; 32]].concat();
+        let mut result = MakeCredentialsResult {
+            att_obj: create_attestation_obj(),
+            attachment: AuthenticatorAttachment::Unknown,
+            extensions: Default::default(),
+            enterprise_attestation: None,
+            ep_att: None,
+            large_blob_key: None,
+            pin_uv_auth_protocol: None,
+        };
+        result.att_obj.auth_data.extensions.hmac_secret_mc = Some(HmacSecretResponse::Secret(
+            secret.encrypt(&outputs).unwrap(),
+        ));
+        req.finalize_result(&device, &mut result);
+        assert_eq!(
+            result.extensions.hmac_get_secret,
+            Some(HmacGetSecretOutput {
+                output1: [0x11; 32],
+                output2: Some([0x22; 32]),
+            })
+        );
+
+        // Without hmac-secret, hmac-secret-mc is not requested at all
+        let extensions: MakeCredentialsExtensions = AuthenticationExtensionsClientInputs {
+            hmac_get_secret: Some(Default::default()),
+            ..Default::default()
+        }
+        .into();
+        assert!(extensions.hmac_secret_mc.is_none());
+    }
+
+    #[test]
+    fn test_make_credentials_pin_token_permissions() {
+        let info = AuthenticatorInfo {
+            pin_protocols: Some(vec![2]),
+            options: AuthenticatorOptions {
+                client_pin: Some(true),
+                pin_uv_auth_token: Some(true),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let secret = PinUvAuthProtocol::try_from(&info)
+            .unwrap()
+            .encapsulate(&peer_key())
+            .unwrap();
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.set_authenticator_info(info);
+        device.set_shared_secret(secret.clone());
+        // pinUvAuthToken, encrypted with the shared secret
+        let pin_token = secret.encrypt(&[0x01; 32]).unwrap();
+        let mut response = vec![0x00, 0xa1, 0x02, 0x58, pin_token.len() as u8];
+        response.extend(pin_token);
+        device.add_response(&response);
+
+        let mut req = dummy_make_credentials_cmd();
+        let token = device
+            .get_pin_uv_auth_token_using_pin_with_permissions(
+                &Some(Pin::new("1234")),
+                PinUvAuthTokenPermission::MakeCredential,
+                req.get_rp_id(),
+                &|| true,
+            )
+            .expect("Failed to get pinUvAuthToken");
+        assert_eq!(token.permissions, PinUvAuthTokenPermission::MakeCredential);
+
+        let request = match &device.requests[..] {
+            [RecordedRequest::Ctap2(Command::ClientPin, payload)] => {
+                from_slice::<BTreeMap<u8, Value>>(payload).unwrap()
+            }
+            requests => panic!("Unexpected requests {:?}", requests),
+        };
+        assert_eq!(request.get(&0x02), Some(&Value::Integer(0x09)));
+        assert_eq!(request.get(&0x09), Some(&Value::Integer(0x01)));
+        assert_eq!(
+            request.get(&0x0A),
+            Some(&Value::Text("make.me.blink".to_string()))
+        );
+
+        req.set_pin_uv_auth_param(Some(token.clone()))
+            .expect("Failed to set pinUvAuthParam");
+        assert!(req.pin_uv_auth_param.is_some());
+
+        // Pre-flighting the exclude list needs the getAssertion permission, too
+        req.exclude_list = vec![PublicKeyCredentialDescriptor {
+            id: vec![0x01; 16],
+            transports: vec![],
+        }];
+        assert_matches!(
+            req.set_pin_uv_auth_param(Some(token)),
+            Err(AuthenticatorError::PinError(PinError::MissingPermissions))
+        );
+    }
+
+    #[test]
+    fn test_make_credentials_cred_protect_output() {
+        let mut req = dummy_make_credentials_cmd();
+        let mut device = Device::new("commands/make_credentials").unwrap(); // not really used
+
+        // Not requested
+        let result = req
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(result.extensions.credential_protection_policy, None);
+
+        // Requested, but not echoed by the authenticator
+        req.extensions.cred_protect = Some(CredentialProtectionPolicy::UserVerificationRequired);
+        let mut result = req
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(
+            result.extensions.credential_protection_policy,
+            Some(CredentialProtectionPolicy::UserVerificationOptional)
+        );
+
+        // Echoed with a lower level than requested
+        result.att_obj.auth_data.extensions.cred_protect =
+            Some(CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIDList);
+        req.finalize_result(&device, &mut result);
+        assert_eq!(
+            result.extensions.credential_protection_policy,
+            Some(CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIDList)
+        );
+        assert!(
+            CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIDList
+                < CredentialProtectionPolicy::UserVerificationRequired
+        );
+    }
+
+    #[test]
+    fn test_make_credentials_cred_blob() {
+        let mut req = dummy_make_credentials_cmd();
+        req.extensions = AuthenticationExtensionsClientInputs {
+            cred_blob: Some(vec![1, 2, 3]),
+            ..Default::default()
+        }
+        .into();
+
+        let mut expected = vec![0xa1]; // map(1)
+        expected.push(0x68); // text(8)
+        expected.extend(b"credBlob");
+        expected.extend([0x43, 1, 2, 3]); // bytes(3)
+        assert_eq!(ser::to_vec(&req.extensions).unwrap(), expected);
+
+        // Dropped, if the authenticator does not support it
+        let mut info = AuthenticatorInfo {
+            extensions: vec!["credBlob".to_string()],
+            max_cred_blob_length: Some(32),
+            ..Default::default()
+        };
+        req.normalize_for(&info);
+        assert_eq!(req.extensions.cred_blob, Some(vec![1, 2, 3]));
+        info.extensions = vec![];
+        let mut unsupported = req.clone();
+        unsupported.normalize_for(&info);
+        assert!(!unsupported.extensions.has_content());
+
+        // The authenticator tells us whether it stored the blob
+        let mut device = Device::new("commands/make_credentials").unwrap(); // not really used
+        let mut result = req
+            .handle_response_ctap2(&mut device, &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(result.extensions.cred_blob, None);
+        result.att_obj.auth_data.extensions.cred_blob = Some(true);
+        req.finalize_result(&device, &mut result);
+        assert_eq!(result.extensions.cred_blob, Some(true));
+        let json = result
+            .to_registration_json(&[1], b"{}")
+            .expect("Failed to encode the registration");
+        assert_eq!(json["clientExtensionResults"]["credBlob"], true);
+    }
+
+    #[test]
+    fn test_make_credentials_large_blob_key() {
+        let mut req = dummy_make_credentials_cmd();
+        req.extensions = AuthenticationExtensionsClientInputs {
+            large_blob_key: Some(true),
+            ..Default::default()
+        }
+        .into();
+
+        let mut expected = vec![0xa1]; // map(1)
+        expected.push(0x6c); // text(12)
+        expected.extend(b"largeBlobKey");
+        expected.push(0xf5); // true
+        assert_eq!(ser::to_vec(&req.extensions).unwrap(), expected);
+
+        // Only requested from authenticators that support largeBlobs
+        let mut info = AuthenticatorInfo {
+            options: AuthenticatorOptions {
+                large_blobs: Some(true),
+                ..Default::default()
+            },
+            extensions: vec!["largeBlobKey".to_string()],
+            ..Default::default()
+        };
+        let mut supported = req.clone();
+        supported.normalize_for(&info);
+        assert_eq!(supported.extensions.large_blob_key, Some(true));
+        info.options.large_blobs = Some(false);
+        let mut unsupported = req.clone();
+        unsupported.normalize_for(&info);
+        assert!(!unsupported.extensions.has_content());
+        info.options.large_blobs = None;
+        let mut unsupported = req.clone();
+        unsupported.normalize_for(&info);
+        assert!(!unsupported.extensions.has_content());
+
+        // The sample response, with largeBlobKey (0x05) added
+        let mut response = MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2.to_vec();
+        response[1] = 0xa4; // map(4)
+        response.extend([0x05, 0x58, 0x20]); // unsigned(5), bytes(32)
+        response.extend([0x42; 32]);
+
+        let mut device = Device::new("commands/make_credentials").unwrap(); // not really used
+        let result = req
+            .handle_response_ctap2(&mut device, &response)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(result.large_blob_key, Some([0x42; 32]));
+        assert_eq!(result.extensions.large_blob_key, Some([0x42; 32]));
+
+        // Not surfaced, if we did not ask for it
+        let result = unsupported
+            .handle_response_ctap2(&mut device, &response)
+            .expect("Failed to handle CTAP2 response");
+        assert_eq!(result.extensions.large_blob_key, None);
+
+        // The key has to be 32 bytes long
+        let mut response = MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2.to_vec();
+        response[1] = 0xa4; // map(4)
+        response.extend([0x05, 0x50]); // unsigned(5), bytes(16)
+        response.extend([0x42; 16]);
+        assert!(req.handle_response_ctap2(&mut device, &response).is_err());
+    }
+
+    #[test]
+    fn test_make_credentials_remove_unsupported_extensions() {
+        let requested: MakeCredentialsExtensions = AuthenticationExtensionsClientInputs {
+            credential_protection_policy: Some(
+                CredentialProtectionPolicy::UserVerificationRequired,
+            ),
+            hmac_create_secret: Some(true),
+            hmac_get_secret: Some(Default::default()),
+            min_pin_length: Some(true),
+            ..Default::default()
+        }
+        .into();
+
+        let mut info = AuthenticatorInfo {
+            extensions: vec!["credProtect".to_string(), "hmac-secret".to_string()],
+            ..Default::default()
+        };
+        assert!(info.supports_extension("credProtect"));
+        assert!(!info.supports_extension("credprotect"));
+        let mut extensions = requested.clone();
+        extensions.remove_unsupported(&info);
+        assert_eq!(
+            extensions.cred_protect,
+            Some(CredentialProtectionPolicy::UserVerificationRequired)
+        );
+        assert_eq!(extensions.hmac_secret, Some(true));
+        assert!(extensions.hmac_secret_mc.is_none());
+        assert_eq!(extensions.min_pin_length, None);
+
+        info.extensions = vec![];
+        let mut extensions = requested;
+        extensions.remove_unsupported(&info);
+        assert!(!extensions.has_content());
+    }
+
+    #[test]
+    fn test_make_credentials_normalize_for() {
+        let options_key = |req: &MakeCredentials| {
+            let wire = req.wire_format().expect("Failed to serialize");
+            match from_slice(&wire).expect("Invalid CBOR") {
+                Value::Map(map) => map.get(&Value::Integer(0x07)).cloned(),
+                _ => panic!("Not a map"),
+            }
+        };
+        let mut req = dummy_make_credentials_cmd();
+        req.options = MakeCredentialsOptions {
+            resident_key: Some(false),
+            user_verification: Some(false),
+            user_presence: None,
+        };
+        req.extensions.min_pin_length = Some(true);
+        req.enterprise_attestation = Some(1);
+
+        let mut info = AuthenticatorInfo {
+            versions: vec![
+                AuthenticatorVersion::FIDO_2_0,
+                AuthenticatorVersion::FIDO_2_1,
+            ],
+            extensions: vec!["minPinLength".to_string()],
+            options: AuthenticatorOptions {
+                ep: Some(true),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut normalized = req.clone();
+        normalized.normalize_for(&info);
+        assert_eq!(normalized.options.resident_key, Some(false));
+        assert_eq!(normalized.options.user_verification, Some(false));
+        assert_eq!(normalized.extensions.min_pin_length, Some(true));
+        assert_eq!(normalized.enterprise_attestation, Some(1));
+        assert!(options_key(&normalized).is_some());
+
+        info.versions = vec![AuthenticatorVersion::U2F_V2, AuthenticatorVersion::FIDO_2_0];
+        info.extensions = vec![];
+        info.options.ep = None;
+        let mut normalized = req.clone();
+        normalized.normalize_for(&info);
+        assert_eq!(normalized.options.resident_key, None);
+        assert_eq!(normalized.options.user_verification, None);
+        assert_eq!(normalized.extensions.min_pin_length, None);
+        assert_eq!(normalized.enterprise_attestation, None);
+        assert_eq!(options_key(&normalized), None);
+
+        // Options that differ from the default are kept
+        req.options.resident_key = Some(true);
+        req.options.user_verification = Some(true);
+        req.normalize_for(&info);
+        assert_eq!(req.options.resident_key, Some(true));
+        assert_eq!(req.options.user_verification, Some(true));
+        assert!(options_key(&req).is_some());
+    }
+
+    #[test]
+    fn test_make_credentials_user_presence_option() {
+        let options_key = |req: &MakeCredentials| {
+            let wire = req.wire_format().expect("Failed to serialize");
+            match from_slice(&wire).expect("Invalid CBOR") {
+                Value::Map(map) => map.get(&Value::Integer(0x07)).cloned(),
+                _ => panic!("Not a map"),
+            }
+        };
+        let mut req = dummy_make_credentials_cmd();
+        req.options = MakeCredentialsOptions {
+            user_presence: Some(true),
+            ..Default::default()
+        };
+        assert!(req.options.has_some());
+
+        let mut info = AuthenticatorInfo {
+            versions: vec![
+                AuthenticatorVersion::U2F_V2,
+                AuthenticatorVersion::FIDO_2_0,
+                AuthenticatorVersion::FIDO_2_1,
+            ],
+            ..Default::default()
+        };
+        let mut normalized = req.clone();
+        normalized.normalize_for(&info);
+        assert_eq!(
+            options_key(&normalized),
+            Some(Value::Map(BTreeMap::from([(
+                Value::Text("up".to_string()),
+                Value::Bool(true)
+            )])))
+        );
+
+        info.versions = vec![AuthenticatorVersion::U2F_V2, AuthenticatorVersion::FIDO_2_0];
+        let mut normalized = req.clone();
+        normalized.normalize_for(&info);
+        assert_eq!(normalized.options.user_presence, None);
+        assert!(!normalized.options.has_some());
+        assert_eq!(options_key(&normalized), None);
+    }
+
+    #[test]
+    fn test_make_credentials_wire_format_for() {
+        let keys = |wire: Vec<u8>| match from_slice(&wire).expect("Invalid CBOR") {
+            Value::Map(map) => map,
+            _ => panic!("Not a map"),
+        };
+        let mut req = dummy_make_credentials_cmd();
+        req.options.resident_key = Some(true);
+        req.options.user_presence = Some(true);
+        req.extensions.min_pin_length = Some(true);
+
+        let info = AuthenticatorInfo {
+            versions: vec![AuthenticatorVersion::U2F_V2, AuthenticatorVersion::FIDO_2_0],
+            ..Default::default()
+        };
+        let wire = keys(req.wire_format_for(&info).expect("Failed to serialize"));
+        // Only "rk" is left of the options, and there are no extensions
+        assert_eq!(
+            wire.get(&Value::Integer(0x07)),
+            Some(&Value::Map(BTreeMap::from([(
+                Value::Text("rk".to_string()),
+                Value::Bool(true)
+            )])))
+        );
+        assert_eq!(wire.get(&Value::Integer(0x06)), None);
+        // The request is unchanged, and serialized without filtering by wire_format()
+        assert_eq!(req.options.user_presence, Some(true));
+        let wire = keys(req.wire_format().expect("Failed to serialize"));
+        assert!(wire.contains_key(&Value::Integer(0x06)));
+
+        let info = AuthenticatorInfo {
+            versions: vec![
+                AuthenticatorVersion::FIDO_2_0,
+                AuthenticatorVersion::FIDO_2_1,
+            ],
+            extensions: vec!["minPinLength".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            req.wire_format_for(&info).expect("Failed to serialize"),
+            req.wire_format().expect("Failed to serialize")
+        );
+    }
+
+    #[test]
+    fn test_make_credentials_raw_extensions() {
+        let mut extensions = MakeCredentialsExtensions::default();
+        extensions
+            .raw_extensions
+            .insert("thirdPartyPayment".to_string(), Value::Bool(true));
+        assert!(extensions.has_content());
+        // Known extensions can't be overwritten
+        extensions
+            .raw_extensions
+            .insert("credProtect".to_string(), Value::Integer(3));
+        extensions.min_pin_length = Some(true);
+        extensions.hmac_secret = Some(true);
+
+        // Sorted by length first, then lexically
+        let mut expected = vec![0xa3]; // map(3)
+        expected.push(0x6b); // text(11)
+        expected.extend(b"hmac-secret");
+        expected.push(0xf5); // true
+        expected.push(0x6c); // text(12)
+        expected.extend(b"minPinLength");
+        expected.push(0xf5); // true
+        expected.push(0x71); // text(17)
+        expected.extend(b"thirdPartyPayment");
+        expected.push(0xf5); // true
+        assert_eq!(ser::to_vec(&extensions).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_make_credentials_missing_att_stmt() {
+        // Take the authData from the sample response, and drop the attStmt entry
+        let auth_data = &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2[11..161];
+        let mut response = vec![
+            0xa2, // map(2)
+            0x01, // unsigned(1)
+            0x64, // text(4)
+            0x6e, 0x6f, 0x6e, 0x65, // "none"
+            0x02, // unsigned(2)
+        ];
+        response.extend_from_slice(auth_data);
+
+        let make_cred_result: MakeCredentialsResult =
+            from_slice(&response).expect("Failed to parse response without attStmt");
+        assert_eq!(
+            make_cred_result.att_obj.att_stmt,
+            AttestationStatement::None
+        );
+        assert_eq!(
+            make_cred_result.att_obj.auth_data,
+            create_attestation_obj().auth_data
+        );
+
+        // Other formats still require an attestation statement
+        let mut response = vec![
+            0xa2, // map(2)
+            0x01, // unsigned(1)
+            0x66, // text(6)
+            0x70, 0x61, 0x63, 0x6b, 0x65, 0x64, // "packed"
+            0x02, // unsigned(2)
+        ];
+        response.extend_from_slice(auth_data);
+        assert!(from_slice::<MakeCredentialsResult>(&response).is_err());
+    }
+
+    // A makeCredential response with the authData of `create_attestation_obj()`
+    fn make_credentials_response(fmt: &str, att_stmt: Value) -> Vec<u8> {
+        let response = Value::Map(
+            vec![
+                (Value::Integer(0x01), Value::Text(fmt.to_string())),
+                (
+                    Value::Integer(0x02),
+                    Value::Bytes(create_attestation_obj().auth_data.to_vec()),
+                ),
+                (Value::Integer(0x03), att_stmt),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        ser::to_vec(&response).unwrap()
+    }
+
+    #[test]
+    fn test_make_credentials_attestation_formats() {
+        let response = make_credentials_response("tpm", sample_tpm_att_stmt());
+        let make_cred_result: MakeCredentialsResult =
+            from_slice(&response).expect("Failed to parse tpm response");
+        assert!(matches!(
+            make_cred_result.att_obj.att_stmt,
+            AttestationStatement::Tpm(ref att) if att.is_name_attested()
+        ));
+        assert_eq!(
+            make_cred_result.att_obj.auth_data,
+            create_attestation_obj().auth_data
+        );
+
+        let text = |data: &str| Value::Text(data.to_string());
+        let att_stmt = Value::Map(
+            vec![
+                (text("alg"), Value::Integer(-7)),
+                (text("sig"), Value::Bytes(vec![0x44; 8])),
+                (text("x5c"), Value::Array(vec![Value::Bytes(vec![0x55; 8])])),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let response = make_credentials_response("android-key", att_stmt);
+        let make_cred_result: MakeCredentialsResult =
+            from_slice(&response).expect("Failed to parse android-key response");
+        assert_eq!(
+            make_cred_result.att_obj.att_stmt,
+            AttestationStatement::AndroidKey(AttestationStatementAndroidKey {
+                alg: COSEAlgorithm::ES256,
+                sig: Signature(vec![0x44; 8]),
+                attestation_cert: vec![AttestationCertificate(vec![0x55; 8])],
+            })
+        );
+
+        let att_stmt = Value::Map(
+            vec![(text("x5c"), Value::Array(vec![Value::Bytes(vec![0x55; 8])]))]
+                .into_iter()
+                .collect(),
+        );
+        let response = make_credentials_response("apple", att_stmt);
+        let make_cred_result: MakeCredentialsResult =
+            from_slice(&response).expect("Failed to parse apple response");
+        assert_eq!(
+            make_cred_result.att_obj.att_stmt,
+            AttestationStatement::AppleAnonymous(AttestationStatementAppleAnonymous {
+                attestation_cert: vec![AttestationCertificate(vec![0x55; 8])],
+            })
+        );
+
+        let att_stmt = Value::Map(
+            vec![
+                (text("ver"), text("1")),
+                (text("response"), Value::Bytes(b"a.b".to_vec())),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let response = make_credentials_response("android-safetynet", att_stmt);
+        let make_cred_result: MakeCredentialsResult =
+            from_slice(&response).expect("Failed to parse android-safetynet response");
+        assert_eq!(
+            make_cred_result.att_obj.att_stmt,
+            AttestationStatement::AndroidSafetyNet(AttestationStatementAndroidSafetyNet {
+                ver: "1".to_string(),
+                response: b"a.b".to_vec(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_make_credentials_to_registration_json() {
+        let b64 = |data: &[u8]| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(data);
+        let client_data_json = br#"{"type":"webauthn.create","challenge":"AAECAw","origin":"example.com","crossOrigin":false}"#;
+        let mut result = MakeCredentialsResult {
+            att_obj: create_attestation_obj(),
+            attachment: AuthenticatorAttachment::CrossPlatform,
+            extensions: Default::default(),
+            enterprise_attestation: None,
+            ep_att: None,
+            large_blob_key: None,
+            pin_uv_auth_protocol: None,
+        };
+        result.extensions.cred_props = Some(CredentialProperties {
+            rk: true,
+            ..Default::default()
+        });
+        let credential_id = result
+            .att_obj
+            .auth_data
+            .credential_data
+            .as_ref()
+            .unwrap()
+            .credential_id
+            .clone();
+
+        let json = result
+            .to_registration_json(&credential_id, client_data_json)
+            .expect("Failed to convert result");
+        assert_eq!(json["id"], b64(&credential_id));
+        assert_eq!(json["rawId"], b64(&credential_id));
+        assert_eq!(json["type"], "public-key");
+        assert_eq!(json["authenticatorAttachment"], "cross-platform");
+        assert_eq!(json["response"]["clientDataJSON"], b64(client_data_json));
+        assert_eq!(json["response"]["transports"], serde_json::json!(["usb"]));
+        assert_eq!(
+            json["clientExtensionResults"],
+            serde_json::json!({ "credProps": { "rk": true } })
+        );
+        let att_obj = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(json["response"]["attestationObject"].as_str().unwrap())
+            .unwrap();
+        let att_obj: AttestationObject = from_slice(&att_obj).unwrap();
+        assert_eq!(att_obj, result.att_obj);
+
+        result.attachment = AuthenticatorAttachment::Unknown;
+        result.extensions = Default::default();
+        let json = result
+            .to_registration_json(&credential_id, client_data_json)
+            .expect("Failed to convert result");
+        assert!(json.get("authenticatorAttachment").is_none());
+        assert_eq!(json["response"]["transports"], serde_json::json!([]));
+        assert_eq!(json["clientExtensionResults"], serde_json::json!({}));
+    }
+
+    #[test]
+    fn test_make_credentials_check_policy() {
+        let mut client_data = CollectedClientData {
+            webauthn_type: WebauthnType::Create,
+            challenge: Challenge::from(vec![0x00, 0x01, 0x02, 0x03]),
+            origin: String::from("example.com"),
+            cross_origin: false,
+            token_binding: None,
+        };
+        let mut result = MakeCredentialsResult {
+            att_obj: create_attestation_obj(),
+            attachment: AuthenticatorAttachment::Unknown,
+            extensions: Default::default(),
+            enterprise_attestation: None,
+            ep_att: None,
+            large_blob_key: None,
+            pin_uv_auth_protocol: None,
+        };
+        let mut policy = RegistrationPolicy {
+            attestation: AttestationConveyance::Direct,
+            allowed_algorithms: vec![COSEAlgorithm::ES256],
+            ..Default::default()
+        };
+        // The packed statement of the sample is not over this client data
+        assert!(matches!(
+            result.check_policy(&policy, &client_data),
+            Err(PolicyViolation::Signature(CryptoError::VerificationFailed))
+        ));
+        // fido-u2f signatures are not verified, so the authenticator data can change below
+        let att_stmt = match &result.att_obj.att_stmt {
+            AttestationStatement::Packed(att) => {
+                AttestationStatementFidoU2F::new(att.attestation_cert[0].as_ref(), &[0x30, 0x00])
+            }
+            other => panic!("Unexpected attestation statement {:?}", other),
+        };
+        result.att_obj.att_stmt = AttestationStatement::FidoU2F(att_stmt);
+        assert!(result.check_policy(&policy, &client_data).is_ok());
+
+        client_data.webauthn_type = WebauthnType::Get;
+        assert!(matches!(
+            result.check_policy(&policy, &client_data),
+            Err(PolicyViolation::ClientData)
+        ));
+        client_data.webauthn_type = WebauthnType::Create;
+
+        policy.attestation = AttestationConveyance::Enterprise;
+        assert!(matches!(
+            result.check_policy(&policy, &client_data),
+            Err(PolicyViolation::Attestation(
+                AttestationConveyance::Enterprise
+            ))
+        ));
+        result.enterprise_attestation = Some(EnterpriseAttestationMode::VendorFacilitated);
+        assert!(result.check_policy(&policy, &client_data).is_ok());
+
+        policy.allowed_algorithms = vec![COSEAlgorithm::EDDSA];
+        assert!(matches!(
+            result.check_policy(&policy, &client_data),
+            Err(PolicyViolation::Algorithm(COSEAlgorithm::ES256))
+        ));
+        policy.allowed_algorithms = vec![];
+
+        policy.allowed_aaguids = vec![AAGuid([0; 16])];
+        assert!(matches!(
+            result.check_policy(&policy, &client_data),
+            Err(PolicyViolation::Aaguid(_))
+        ));
+        policy.allowed_aaguids = vec![];
+
+        // We only know about resident keys from credProps
+        policy.require_resident_key = true;
+        assert!(matches!(
+            result.check_policy(&policy, &client_data),
+            Err(PolicyViolation::ResidentKey)
+        ));
+        result.extensions.cred_props = Some(CredentialProperties {
+            rk: true,
+            ..Default::default()
+        });
+        assert!(result.check_policy(&policy, &client_data).is_ok());
+
+        policy.require_user_verification = true;
+        assert!(matches!(
+            result.check_policy(&policy, &client_data),
+            Err(PolicyViolation::UserVerification)
+        ));
+        result.att_obj.auth_data.flags |= AuthenticatorDataFlags::USER_VERIFIED;
+        assert!(result.check_policy(&policy, &client_data).is_ok());
+
+        policy.reject_backup_eligible = true;
+        result.att_obj.auth_data.flags |= AuthenticatorDataFlags::BACKUP_ELIGIBLE;
+        assert!(matches!(
+            result.check_policy(&policy, &client_data),
+            Err(PolicyViolation::BackupEligible)
+        ));
+
+        // Self attestation is not enough for direct attestation, and its signature has to
+        // be valid.
+        let policy = RegistrationPolicy {
+            attestation: AttestationConveyance::Direct,
+            ..Default::default()
+        };
+        result.att_obj.att_stmt = AttestationStatement::Packed(AttestationStatementPacked {
+            alg: COSEAlgorithm::ES256,
+            sig: Signature(vec![0x30, 0x00]),
+            attestation_cert: vec![],
+        });
+        assert!(matches!(
+            result.check_policy(&policy, &client_data),
+            Err(PolicyViolation::Signature(_))
+        ));
+
+        result.att_obj.att_stmt = AttestationStatement::None;
+        assert!(matches!(
+            result.check_policy(&policy, &client_data),
+            Err(PolicyViolation::Attestation(AttestationConveyance::Direct))
+        ));
+        assert!(result
+            .check_policy(&RegistrationPolicy::default(), &client_data)
+            .is_ok());
+
+        result.att_obj.auth_data.credential_data = None;
+        assert!(matches!(
+            result.check_policy(&policy, &client_data),
+            Err(PolicyViolation::MissingCredentialData)
+        ));
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let android = || {
+            from_slice::<AttestationObject>(&SAMPLE_ATTESTATION_OBJECT_ANDROID_KEY)
+                .expect("Failed to parse")
+        };
+        let mut none = android();
+        none.att_stmt = AttestationStatement::None;
+        let client_data_hash = || ClientDataHash(SAMPLE_CLIENT_DATA_HASH);
+        let items = [
+            (android(), client_data_hash()),
+            (android(), ClientDataHash([0; 32])),
+            (
+                from_slice(&SAMPLE_ATTESTATION_OBJECT_TPM).expect("Failed to parse"),
+                client_data_hash(),
+            ),
+            (none, client_data_hash()),
+        ];
+        let trust_store = TrustStore::new(&[AttestationCertificate(
+            SAMPLE_ANDROID_KEY_ROOT_CERT.to_vec(),
+        )])
+        .expect("Failed to parse");
+        let time = UNIX_EPOCH + Duration::from_secs(SAMPLE_VERIFICATION_TIME);
+
+        let mut policy = RegistrationPolicy::default();
+        let results = verify_batch_at(&items, &policy, &trust_store, time);
+        assert_eq!(results.len(), items.len());
+        let verified = results[0].as_ref().expect("Failed to verify");
+        let credential_data = items[0].0.auth_data.credential_data.as_ref().unwrap();
+        assert_eq!(verified.aaguid, credential_data.aaguid);
+        assert_eq!(verified.credential_id, credential_data.credential_id);
+        assert_eq!(
+            verified.credential_public_key,
+            credential_data.credential_public_key
+        );
+        assert_eq!(verified.counter, items[0].0.auth_data.counter);
+        assert!(verified.trusted);
+        assert!(matches!(
+            results[1],
+            Err(PolicyViolation::Signature(CryptoError::VerificationFailed))
+        ));
+        // The TPM root is not in the trust store
+        assert!(matches!(
+            results[2],
+            Err(PolicyViolation::Chain(AttestationError::UntrustedChain))
+        ));
+        assert!(matches!(results[3], Ok(ref verified) if !verified.trusted));
+
+        policy.attestation = AttestationConveyance::Direct;
+        let results = verify_batch_at(&items, &policy, &trust_store, time);
+        assert!(results[0].is_ok());
+        assert!(matches!(
+            results[3],
+            Err(PolicyViolation::Attestation(AttestationConveyance::Direct))
+        ));
+
+        // Certificates have to be valid at the time of verification
+        let expired = UNIX_EPOCH + Duration::from_secs(5_000_000_000);
+        assert!(matches!(
+            verify_batch_at(&items[..1], &policy, &trust_store, expired)[0],
+            Err(PolicyViolation::Chain(
+                AttestationError::CertificateNotValid
+            ))
+        ));
+
+        // Policies that need the full response are never met
+        for policy in [
+            RegistrationPolicy {
+                attestation: AttestationConveyance::Enterprise,
+                ..Default::default()
+            },
+            RegistrationPolicy {
+                require_resident_key: true,
+                ..Default::default()
+            },
+        ] {
+            assert!(verify_batch_at(&items, &policy, &trust_store, time)
+                .iter()
+                .all(|result| result.is_err()));
+        }
+
+        assert!(matches!(
+            TrustStore::new(&[AttestationCertificate(vec![0x30, 0x03, 0x02])]),
+            Err(AttestationError::MalformedCertificate)
+        ));
+    }
+
+    #[test]
+    fn test_make_credentials_ctap1() {
+        let req = MakeCredentials::new(
+            CollectedClientData {
+                webauthn_type: WebauthnType::Create,
+                challenge: Challenge::new(vec![0x00, 0x01, 0x02, 0x03]),
+                origin: String::from("example.com"),
+                cross_origin: false,
+                token_binding: Some(TokenBinding::Present(String::from("AAECAw"))),
+            }
+            .hash()
+            .expect("failed to serialize client data"),
+            RelyingParty::from("example.com"),
+            Some(PublicKeyCredentialUserEntity {
+                id: base64::engine::general_purpose::URL_SAFE
+                    .decode("MIIBkzCCATigAwIBAjCCAZMwggE4oAMCAQIwggGTMII=")
+                    .unwrap(),
+                name: Some(String::from("johnpsmith@example.com")),
+                display_name: Some(String::from("John P. Smith")),
+            }),
+            vec![
+                PublicKeyCredentialParameters {
+                    alg: COSEAlgorithm::ES256,
+                },
+                PublicKeyCredentialParameters {
+                    alg: COSEAlgorithm::RS256,
+                },
+            ],
+            Vec::new(),
+            MakeCredentialsOptions {
+                resident_key: Some(true),
+                user_verification: None,
+                user_presence: None,
+            },
+            Default::default(),
+        );
+
+        let (req_serialized, _) = req
+            .ctap1_format()
+            .expect("Failed to serialize MakeCredentials request");
+        assert_eq!(
+            req_serialized, MAKE_CREDENTIALS_SAMPLE_REQUEST_CTAP1,
+            "\nGot:      {req_serialized:X?}\nExpected: {MAKE_CREDENTIALS_SAMPLE_REQUEST_CTAP1:X?}"
+        );
+        let mut device = Device::new("commands/make_credentials").unwrap(); // not really used
+        let make_cred_result = req
+            .handle_response_ctap1(
+                &mut device,
+                Ok(()),
+                &MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP1,
+                &(),
+            )
+            .expect("Failed to handle CTAP1 response");
+
+        let att_obj = AttestationObject {
+            auth_data: AuthenticatorData {
+                rp_id_hash: RpIdHash::from(&[
+                    0xA3, 0x79, 0xA6, 0xF6, 0xEE, 0xAF, 0xB9, 0xA5, 0x5E, 0x37, 0x8C, 0x11, 0x80,
+                    0x34, 0xE2, 0x75, 0x1E, 0x68, 0x2F, 0xAB, 0x9F, 0x2D, 0x30, 0xAB, 0x13, 0xD2,
+                    0x12, 0x55, 0x86, 0xCE, 0x19, 0x47,
+                ])
+                .unwrap(),
+                flags: AuthenticatorDataFlags::USER_PRESENT | AuthenticatorDataFlags::ATTESTED,
+                counter: 0,
+                credential_data: Some(AttestedCredentialData {
+                    aaguid: AAGuid::default(),
                     credential_id: vec![
                         0x3E, 0xBD, 0x89, 0xBF, 0x77, 0xEC, 0x50, 0x97, 0x55, 0xEE, 0x9C, 0x26,
                         0x35, 0xEF, 0xAA, 0xAC, 0x7B, 0x2B, 0x9C, 0x5C, 0xEF, 0x17, 0x36, 0xC3,
@@ -830,6 +2778,10 @@ pub mod test {
             att_obj,
             attachment: AuthenticatorAttachment::Unknown,
             extensions: Default::default(),
+            enterprise_attestation: None,
+            ep_att: None,
+            large_blob_key: None,
+            pin_uv_auth_protocol: None,
         };
 
         assert_eq!(make_cred_result, expected);
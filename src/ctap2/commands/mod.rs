@@ -1,5 +1,5 @@
 use crate::crypto::{CryptoError, PinUvAuthParam, PinUvAuthToken};
-use crate::ctap2::commands::client_pin::{GetPinRetries, GetUvRetries, PinError};
+use crate::ctap2::commands::client_pin::{GetPinRetries, PinError, PinUvAuthTokenPermission};
 use crate::ctap2::commands::get_info::AuthenticatorInfo;
 use crate::ctap2::server::UserVerificationRequirement;
 use crate::errors::AuthenticatorError;
@@ -18,6 +18,7 @@ pub mod get_assertion;
 pub mod get_info;
 pub mod get_next_assertion;
 pub mod get_version;
+pub mod large_blobs;
 pub mod make_credentials;
 pub mod reset;
 pub mod selection;
@@ -143,11 +144,85 @@ pub(crate) trait PinUvAuthCommand: RequestCtap2 {
     fn get_pin_uv_auth_param(&self) -> Option<&PinUvAuthParam>;
     fn set_uv_option(&mut self, uv: Option<bool>);
     fn get_rp_id(&self) -> Option<&String>;
+    /// The permissions a pinUvAuthToken needs to have for this command.
+    fn required_permissions(&self) -> PinUvAuthTokenPermission;
+    /// Checks that `pin_uv_auth_token` was granted the permissions this command needs.
+    /// Authenticators reject a pinUvAuthParam derived from any other token with
+    /// PinAuthInvalid, so we rather fail early with a more helpful error.
+    fn check_permissions(
+        &self,
+        pin_uv_auth_token: &PinUvAuthToken,
+    ) -> Result<(), AuthenticatorError> {
+        let missing = self.required_permissions() - pin_uv_auth_token.permissions;
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            debug!("pinUvAuthToken is missing the permissions {:?}", missing);
+            Err(AuthenticatorError::PinError(PinError::MissingPermissions))
+        }
+    }
+    /// Why this command does or does not need user verification on the given authenticator.
+    fn user_verification_requirement_reason(
+        &self,
+        info: &AuthenticatorInfo,
+        uv_req: UserVerificationRequirement,
+    ) -> UvReason;
     fn can_skip_user_verification(
-        &mut self,
+        &self,
         info: &AuthenticatorInfo,
         uv_req: UserVerificationRequirement,
-    ) -> bool;
+    ) -> bool {
+        !self
+            .user_verification_requirement_reason(info, uv_req)
+            .requires_user_verification()
+    }
+}
+
+/// The reason a command needs user verification (PIN or built-in UV), or `NotRequired`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UvReason {
+    NotRequired,
+    /// The authenticator has the alwaysUv option enabled
+    AlwaysUv,
+    /// The authenticator is protected by a PIN or built-in UV, which CTAP requires for this
+    /// operation unless the relying party discourages user verification
+    DeviceProtected,
+    /// The relying party requires user verification
+    RelyingPartyRequirement,
+    /// Management commands can't be used without PIN/UV authorization
+    ManagementCommand,
+}
+
+impl UvReason {
+    pub fn requires_user_verification(&self) -> bool {
+        *self != UvReason::NotRequired
+    }
+}
+
+impl fmt::Display for UvReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UvReason::NotRequired => write!(f, "No user verification required."),
+            UvReason::AlwaysUv => {
+                write!(
+                    f,
+                    "Your key requires user verification for every operation."
+                )
+            }
+            UvReason::DeviceProtected => {
+                write!(
+                    f,
+                    "Your key requires a PIN or biometrics for this operation."
+                )
+            }
+            UvReason::RelyingPartyRequirement => {
+                write!(f, "The website requires user verification.")
+            }
+            UvReason::ManagementCommand => {
+                write!(f, "Managing your key requires a PIN or biometrics.")
+            }
+        }
+    }
 }
 
 pub(crate) fn repackage_pin_errors<D: FidoDevice>(
@@ -180,11 +255,7 @@ pub(crate) fn repackage_pin_errors<D: FidoDevice>(
         }
         HIDError::Command(CommandError::StatusCode(StatusCode::UvInvalid, _)) => {
             // If the internal UV failed, determine no. of left retries
-            let cmd = GetUvRetries::new();
-            // Treat any error as if the device returned a valid response without a uvRetries
-            // field.
-            let resp = dev.send_cbor(&cmd).unwrap_or_default();
-            AuthenticatorError::PinError(PinError::InvalidUv(resp.uv_retries))
+            AuthenticatorError::PinError(PinError::InvalidUv(dev.get_uv_retries().ok()))
         }
         HIDError::Command(CommandError::StatusCode(StatusCode::UvBlocked, _)) => {
             AuthenticatorError::PinError(PinError::UvBlocked)
@@ -208,6 +279,7 @@ pub enum Command {
     BioEnrollment = 0x09,
     CredentialManagement = 0x0A,
     Selection = 0x0B,
+    LargeBlobs = 0x0C,
     AuthenticatorConfig = 0x0D,
     BioEnrollmentPreview = 0x40,
     CredentialManagementPreview = 0x41,
@@ -447,6 +519,26 @@ pub enum CommandError {
     Json(json::Error),
     Crypto(CryptoError),
     UnsupportedPinProtocol,
+    RpIdHashMismatch,
+    /// The response did not even contain a status byte, or a successful response that
+    /// should carry data carried none.
+    EmptyResponse,
+    /// A successful response whose CBOR body ended prematurely. The HID layer reads as many
+    /// bytes as the authenticator announced, so the authenticator sent an incomplete message.
+    TruncatedResponse,
+    /// numberOfCredentials was zero, or sent along with a later assertion than the first
+    InvalidNumberOfCredentials,
+}
+
+impl CommandError {
+    /// Maps an error from parsing the body of a successful response.
+    pub(crate) fn from_response_body(e: CborError) -> Self {
+        if e.is_eof() {
+            CommandError::TruncatedResponse
+        } else {
+            CommandError::Deserializing(e)
+        }
+    }
 }
 
 impl fmt::Display for CommandError {
@@ -470,6 +562,14 @@ impl fmt::Display for CommandError {
             CommandError::UnsupportedPinProtocol => {
                 write!(f, "CommandError: Pin protocol is not supported")
             }
+            CommandError::RpIdHashMismatch => {
+                write!(f, "CommandError: Response is for a different relying party")
+            }
+            CommandError::EmptyResponse => write!(f, "CommandError: Response is empty"),
+            CommandError::TruncatedResponse => write!(f, "CommandError: Response is truncated"),
+            CommandError::InvalidNumberOfCredentials => {
+                write!(f, "CommandError: Invalid numberOfCredentials in assertion")
+            }
         }
     }
 }
@@ -23,7 +23,7 @@ impl RequestCtap2 for Reset {
         input: &[u8],
     ) -> Result<Self::Output, HIDError> {
         if input.is_empty() {
-            return Err(CommandError::InputTooSmall.into());
+            return Err(CommandError::EmptyResponse.into());
         }
 
         let status: StatusCode = input[0].into();
@@ -23,7 +23,7 @@ impl RequestCtap2 for Selection {
         input: &[u8],
     ) -> Result<Self::Output, HIDError> {
         if input.is_empty() {
-            return Err(CommandError::InputTooSmall.into());
+            return Err(CommandError::EmptyResponse.into());
         }
 
         let status: StatusCode = input[0].into();
@@ -54,6 +54,7 @@ pub mod tests {
     use super::*;
     use crate::consts::HIDCmd;
     use crate::transport::device_selector::Device;
+    use crate::transport::errors::CtapHidError;
     use crate::transport::{hid::HIDDevice, FidoDevice, FidoDeviceIO, FidoProtocol};
     use rand::{thread_rng, RngCore};
     use serde_cbor::{de::from_slice, Value};
@@ -120,4 +121,33 @@ pub mod tests {
             e => panic!("Not the expected response: {:?}", e),
         }
     }
+
+    #[test]
+    fn test_select_ctaphid_error() {
+        let mut device = Device::new("commands/selection").unwrap();
+        let mut cid = [0u8; 4];
+        thread_rng().fill_bytes(&mut cid);
+        device.set_cid(cid);
+
+        let mut msg = cid.to_vec();
+        msg.extend(vec![HIDCmd::Cbor.into(), 0x00, 0x1]); // cmd + bcnt
+        msg.extend(vec![0x0B]); // authenticatorSelection
+        device.add_write(&msg, 0);
+
+        // CTAPHID_ERROR frame instead of a CBOR response
+        let mut msg = cid.to_vec();
+        msg.extend(vec![HIDCmd::Error.into(), 0x00, 0x1]); // cmd + bcnt
+        msg.push(0x0B); // ERR_INVALID_CHANNEL
+        device.add_read(&msg, 0);
+
+        let response = device.send_cbor(&Selection {}).expect_err("Not an error!");
+        assert!(matches!(
+            response,
+            HIDError::CtapHid(CtapHidError::InvalidChannel)
+        ));
+        assert_eq!(
+            response.to_string(),
+            "Error: CTAPHID error 0x0b: invalid channel"
+        );
+    }
 }
@@ -0,0 +1,162 @@
+//! Signature counter checks, to detect cloned authenticators.
+//!
+//! See https://www.w3.org/TR/webauthn-2/#sctn-sign-counter: If either the stored or the new
+//! signature counter is non-zero, the new one has to be greater than the stored one. Otherwise
+//! the authenticator may have been cloned, or it malfunctions.
+
+use crate::ctap2::commands::get_assertion::Assertion;
+use crate::ctap2::commands::make_credentials::MakeCredentialsResult;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CounterVerdict {
+    /// The counter increased, this is the first time we see the credential, or the
+    /// authenticator does not implement a counter (it is always 0).
+    Ok,
+    /// The counter is lower than before. A strong sign of a cloned authenticator.
+    Regressed { last: u32, current: u32 },
+    /// The counter did not change, although it is non-zero.
+    Stale { last: u32 },
+    /// The counter increased by more than the configured maximum, see
+    /// `CounterTracker::with_max_increment`.
+    Jumped { last: u32, current: u32 },
+}
+
+impl CounterVerdict {
+    pub fn is_ok(&self) -> bool {
+        *self == CounterVerdict::Ok
+    }
+}
+
+/// Remembers the last signature counter of each credential it was shown.
+///
+/// The tracker only lives in memory. Relying parties that store counters themselves can seed
+/// it with `insert()`, and read the updated values back with `last_counter()`.
+#[derive(Clone, Debug, Default)]
+pub struct CounterTracker {
+    counters: HashMap<Vec<u8>, u32>,
+    max_increment: Option<u32>,
+}
+
+impl CounterTracker {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Also flags counters that increased by more than `max_increment` since the last time
+    /// we saw them. Authenticators with a global counter increase it for every credential, so
+    /// this needs to be chosen generously.
+    pub fn with_max_increment(max_increment: u32) -> Self {
+        CounterTracker {
+            max_increment: Some(max_increment),
+            ..Default::default()
+        }
+    }
+
+    pub fn insert(&mut self, credential_id: &[u8], counter: u32) {
+        self.counters.insert(credential_id.to_vec(), counter);
+    }
+
+    pub fn last_counter(&self, credential_id: &[u8]) -> Option<u32> {
+        self.counters.get(credential_id).copied()
+    }
+
+    /// Checks `counter` against the last counter of `credential_id`, and remembers it. The
+    /// stored counter never decreases, so a clone can't reset it to a lower value.
+    pub fn observe(&mut self, credential_id: &[u8], counter: u32) -> CounterVerdict {
+        let last = match self.counters.get_mut(credential_id) {
+            Some(last) => last,
+            None => {
+                self.insert(credential_id, counter);
+                return CounterVerdict::Ok;
+            }
+        };
+        let verdict = if *last == 0 && counter == 0 {
+            CounterVerdict::Ok
+        } else if counter < *last {
+            CounterVerdict::Regressed {
+                last: *last,
+                current: counter,
+            }
+        } else if counter == *last {
+            CounterVerdict::Stale { last: *last }
+        } else if matches!(self.max_increment, Some(max) if counter - *last > max) {
+            CounterVerdict::Jumped {
+                last: *last,
+                current: counter,
+            }
+        } else {
+            CounterVerdict::Ok
+        };
+        *last = counter.max(*last);
+        verdict
+    }
+
+    /// Checks the counter of a new credential. Returns None for CTAP1 registrations without
+    /// attested credential data.
+    pub fn observe_registration(
+        &mut self,
+        result: &MakeCredentialsResult,
+    ) -> Option<CounterVerdict> {
+        let auth_data = &result.att_obj.auth_data;
+        let credential_data = auth_data.credential_data.as_ref()?;
+        Some(self.observe(&credential_data.credential_id, auth_data.counter))
+    }
+
+    /// Checks the counter of an assertion. Returns None, if the authenticator did not tell us
+    /// which credential it used (allowed in CTAP 2.0 for single-entry allow lists).
+    pub fn observe_assertion(&mut self, assertion: &Assertion) -> Option<CounterVerdict> {
+        let credential = assertion.credentials.as_ref()?;
+        Some(self.observe(&credential.id, assertion.auth_data.counter))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CounterTracker, CounterVerdict};
+
+    #[test]
+    fn test_counter_tracker() {
+        let mut tracker = CounterTracker::new();
+        assert_eq!(tracker.observe(&[1], 5), CounterVerdict::Ok);
+        assert_eq!(tracker.observe(&[1], 6), CounterVerdict::Ok);
+        assert_eq!(tracker.observe(&[1], 6), CounterVerdict::Stale { last: 6 });
+        assert_eq!(
+            tracker.observe(&[1], 3),
+            CounterVerdict::Regressed {
+                last: 6,
+                current: 3
+            }
+        );
+        // A clone can't reset the counter
+        assert_eq!(tracker.last_counter(&[1]), Some(6));
+        assert_eq!(tracker.observe(&[1], 1000), CounterVerdict::Ok);
+
+        // Authenticators without counter
+        assert_eq!(tracker.observe(&[2], 0), CounterVerdict::Ok);
+        assert_eq!(tracker.observe(&[2], 0), CounterVerdict::Ok);
+        assert!(tracker.observe(&[2], 0).is_ok());
+
+        // Seeded from storage
+        tracker.insert(&[3], 10);
+        assert_eq!(
+            tracker.observe(&[3], 0),
+            CounterVerdict::Regressed {
+                last: 10,
+                current: 0
+            }
+        );
+
+        let mut tracker = CounterTracker::with_max_increment(100);
+        assert_eq!(tracker.observe(&[1], 5), CounterVerdict::Ok);
+        assert_eq!(tracker.observe(&[1], 105), CounterVerdict::Ok);
+        assert_eq!(
+            tracker.observe(&[1], 206),
+            CounterVerdict::Jumped {
+                last: 105,
+                current: 206
+            }
+        );
+        assert_eq!(tracker.last_counter(&[1]), Some(206));
+    }
+}
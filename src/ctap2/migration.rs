@@ -0,0 +1,285 @@
+//! Moving discoverable credentials from one authenticator to another.
+//!
+//! Private keys never leave an authenticator, so credentials can't be copied. Instead, each
+//! credential has to be registered anew on the new authenticator, which needs the relying
+//! party to provide a challenge and to store the new public key. What we can carry over are
+//! the relying party and user information, and the credential protection policy.
+//!
+//! The flow is:
+//!  1. List the credentials of the old authenticator using credential management
+//!     (`CredManagementCmd::GetCredentials`), and create a `CredentialMigration` from the
+//!     resulting `CredentialList`.
+//!  2. For each entry returned by `next_entry()`, ask the relying party for a registration
+//!     challenge, and call `register_next()` (or `register_args()` + `AuthenticatorService::
+//!     register()` + `record()`). Status updates, e.g. PIN prompts, are sent to the usual
+//!     status channel. Entries the relying party does not want to migrate can be skipped.
+//!  3. Once `is_done()`, the relying parties have to be told about the new credentials, and
+//!     the old ones can be deleted.
+
+use crate::authenticatorservice::{AuthenticatorService, RegisterArgs};
+use crate::crypto::COSEAlgorithm;
+use crate::ctap2::commands::credential_management::CredentialList;
+use crate::ctap2::server::{
+    AuthenticationExtensionsClientInputs, CredentialProtectionPolicy,
+    PublicKeyCredentialDescriptor, PublicKeyCredentialParameters, PublicKeyCredentialUserEntity,
+    RelyingParty, ResidentKeyRequirement, UserVerificationRequirement,
+};
+use crate::errors::AuthenticatorError;
+use crate::statecallback::StateCallback;
+use crate::StatusUpdate;
+use std::convert::TryFrom;
+use std::sync::mpsc::{channel, Sender};
+
+/// A credential of the old authenticator, that should be recreated on the new one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationEntry {
+    pub rp: RelyingParty,
+    pub user: PublicKeyCredentialUserEntity,
+    /// The credential on the old authenticator, e.g. to delete it once the relying party
+    /// knows about the new one.
+    pub old_credential: PublicKeyCredentialDescriptor,
+    /// The algorithm of the old credential, which we ask for again.
+    pub alg: COSEAlgorithm,
+    pub cred_protect: Option<CredentialProtectionPolicy>,
+}
+
+impl MigrationEntry {
+    /// Registration arguments for a discoverable credential with the same relying party, user
+    /// and protection policy as the old one. `client_data_hash` has to be computed from the
+    /// challenge of the relying party.
+    pub fn register_args(&self, client_data_hash: [u8; 32], origin: &str) -> RegisterArgs {
+        let extensions = AuthenticationExtensionsClientInputs {
+            cred_props: Some(true),
+            credential_protection_policy: self.cred_protect,
+            // Don't silently weaken the protection of the credential. UserVerificationOptional
+            // is the level of credentials without credProtect, so there is nothing to enforce.
+            enforce_credential_protection_policy: self
+                .cred_protect
+                .filter(|&policy| policy > CredentialProtectionPolicy::UserVerificationOptional)
+                .map(|_| true),
+            ..Default::default()
+        };
+        RegisterArgs {
+            client_data_hash,
+            relying_party: self.rp.clone(),
+            origin: origin.to_string(),
+            user: self.user.clone(),
+            pub_cred_params: vec![PublicKeyCredentialParameters { alg: self.alg }],
+            exclude_list: vec![],
+            user_verification_req: UserVerificationRequirement::Preferred,
+            resident_key_req: ResidentKeyRequirement::Required,
+            default_rk_cred_protect: None,
+            extensions,
+            pin: None,
+            use_ctap1_fallback: false,
+            enterprise_attestation: None,
+            enterprise_attestation_rp_ids: vec![],
+            skip_attestation_statement: false,
+            reject_backup_eligible: false,
+            deadline: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MigrationOutcome {
+    Migrated(crate::RegisterResult),
+    Failed(AuthenticatorError),
+    Skipped,
+}
+
+/// Keeps track of a migration, see the module documentation.
+#[derive(Debug)]
+pub struct CredentialMigration {
+    entries: Vec<MigrationEntry>,
+    outcomes: Vec<MigrationOutcome>,
+}
+
+impl CredentialMigration {
+    pub fn new(credentials: &CredentialList) -> Self {
+        let entries = credentials
+            .credential_list
+            .iter()
+            .flat_map(|rp_entry| {
+                rp_entry.credentials.iter().map(move |cred| MigrationEntry {
+                    rp: rp_entry.rp.clone(),
+                    user: cred.user.clone(),
+                    old_credential: cred.credential_id.clone(),
+                    alg: cred.public_key.alg,
+                    cred_protect: u8::try_from(cred.cred_protect)
+                        .ok()
+                        .and_then(|policy| CredentialProtectionPolicy::try_from(policy).ok()),
+                })
+            })
+            .collect();
+        CredentialMigration {
+            entries,
+            outcomes: Vec::new(),
+        }
+    }
+
+    /// The next credential to migrate, or None if we are done.
+    pub fn next_entry(&self) -> Option<&MigrationEntry> {
+        self.entries.get(self.outcomes.len())
+    }
+
+    /// Records the result of registering the entry returned by `next_entry()`.
+    pub fn record(&mut self, result: crate::Result<crate::RegisterResult>) {
+        if self.is_done() {
+            warn!("Recording a migration result, but all credentials were migrated already");
+            return;
+        }
+        self.outcomes.push(match result {
+            Ok(result) => MigrationOutcome::Migrated(result),
+            Err(e) => MigrationOutcome::Failed(e),
+        });
+    }
+
+    /// Skips the entry returned by `next_entry()`.
+    pub fn skip(&mut self) {
+        if !self.is_done() {
+            self.outcomes.push(MigrationOutcome::Skipped);
+        }
+    }
+
+    /// Registers the entry returned by `next_entry()` on the authenticator the user selects,
+    /// and records the result. This blocks until the registration finished. Returns None, if
+    /// all credentials were migrated already.
+    pub fn register_next(
+        &mut self,
+        service: &mut AuthenticatorService,
+        timeout: u64,
+        client_data_hash: [u8; 32],
+        origin: &str,
+        status: Sender<StatusUpdate>,
+    ) -> Option<&MigrationOutcome> {
+        let args = self.next_entry()?.register_args(client_data_hash, origin);
+        let (tx, rx) = channel();
+        let callback = StateCallback::new(Box::new(move |rv| {
+            let _ = tx.send(rv);
+        }));
+        let result = match service.register(timeout, args, status, callback) {
+            Ok(()) => rx.recv().unwrap_or(Err(AuthenticatorError::Platform)),
+            Err(e) => Err(e),
+        };
+        self.record(result);
+        self.outcomes.last()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.outcomes.len() >= self.entries.len()
+    }
+
+    /// Number of credentials that still need to be migrated or skipped.
+    pub fn remaining(&self) -> usize {
+        self.entries.len() - self.outcomes.len()
+    }
+
+    /// The entries handled so far, with their outcome.
+    pub fn outcomes(&self) -> impl Iterator<Item = (&MigrationEntry, &MigrationOutcome)> {
+        self.entries.iter().zip(self.outcomes.iter())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CredentialMigration, MigrationOutcome};
+    use crate::crypto::{COSEAlgorithm, COSEKey, COSEKeyType, COSEOKPKey, Curve};
+    use crate::ctap2::commands::credential_management::{
+        CredentialList, CredentialListEntry, CredentialRpListEntry,
+    };
+    use crate::ctap2::server::{
+        CredentialProtectionPolicy, PublicKeyCredentialDescriptor, PublicKeyCredentialUserEntity,
+        RelyingParty, ResidentKeyRequirement,
+    };
+    use crate::errors::AuthenticatorError;
+
+    fn credential(id: u8, cred_protect: u64) -> CredentialListEntry {
+        CredentialListEntry {
+            user: PublicKeyCredentialUserEntity {
+                id: vec![id],
+                name: Some(format!("user{id}")),
+                display_name: None,
+            },
+            credential_id: PublicKeyCredentialDescriptor {
+                id: vec![id; 16],
+                transports: vec![],
+            },
+            public_key: COSEKey {
+                alg: COSEAlgorithm::EDDSA,
+                key: COSEKeyType::OKP(COSEOKPKey {
+                    curve: Curve::Ed25519,
+                    x: vec![id; 32],
+                }),
+            },
+            cred_protect,
+            large_blob_key: None,
+        }
+    }
+
+    #[test]
+    fn test_credential_migration() {
+        let rp = |id: &str| RelyingParty::from(id);
+        let credentials = CredentialList {
+            existing_resident_credentials_count: 3,
+            max_possible_remaining_resident_credentials_count: 10,
+            credential_list: vec![
+                CredentialRpListEntry {
+                    rp: rp("example.com"),
+                    rp_id_hash: rp("example.com").hash(),
+                    credentials: vec![credential(1, 1), credential(2, 3)],
+                },
+                CredentialRpListEntry {
+                    rp: rp("example.org"),
+                    rp_id_hash: rp("example.org").hash(),
+                    credentials: vec![credential(3, 0)],
+                },
+            ],
+        };
+        let mut migration = CredentialMigration::new(&credentials);
+        assert_eq!(migration.remaining(), 3);
+
+        let entry = migration.next_entry().expect("No credential to migrate");
+        assert_eq!(entry.rp, rp("example.com"));
+        assert_eq!(entry.old_credential.id, vec![1; 16]);
+        let args = entry.register_args([0u8; 32], "https://example.com");
+        assert_eq!(args.user.id, vec![1]);
+        assert_eq!(args.resident_key_req, ResidentKeyRequirement::Required);
+        assert_eq!(args.pub_cred_params[0].alg, COSEAlgorithm::EDDSA);
+        assert_eq!(
+            args.extensions.credential_protection_policy,
+            Some(CredentialProtectionPolicy::UserVerificationOptional)
+        );
+        assert_eq!(args.extensions.enforce_credential_protection_policy, None);
+        migration.record(Err(AuthenticatorError::CancelledByUser));
+
+        let entry = migration.next_entry().expect("No credential to migrate");
+        let args = entry.register_args([0u8; 32], "https://example.com");
+        assert_eq!(
+            args.extensions.credential_protection_policy,
+            Some(CredentialProtectionPolicy::UserVerificationRequired)
+        );
+        assert_eq!(
+            args.extensions.enforce_credential_protection_policy,
+            Some(true)
+        );
+        migration.skip();
+
+        // An invalid policy is not carried over
+        let entry = migration.next_entry().expect("No credential to migrate");
+        assert_eq!(entry.rp, rp("example.org"));
+        assert_eq!(entry.cred_protect, None);
+        migration.skip();
+
+        assert!(migration.is_done());
+        assert_eq!(migration.next_entry(), None);
+        migration.skip();
+        let outcomes: Vec<_> = migration.outcomes().map(|(_, outcome)| outcome).collect();
+        assert_eq!(outcomes.len(), 3);
+        assert!(matches!(
+            outcomes[0],
+            MigrationOutcome::Failed(AuthenticatorError::CancelledByUser)
+        ));
+        assert!(matches!(outcomes[1], MigrationOutcome::Skipped));
+    }
+}
@@ -2,11 +2,13 @@ pub mod attestation;
 pub mod client_data;
 #[allow(dead_code)] // TODO(MS): Remove me asap
 pub mod commands;
+pub mod counter;
+pub mod migration;
 pub mod preflight;
 pub mod server;
 pub(crate) mod utils;
 
-use crate::authenticatorservice::{RegisterArgs, SignArgs};
+use crate::authenticatorservice::{Deadline, RegisterArgs, SignArgs};
 use crate::crypto::COSEAlgorithm;
 use crate::ctap2::client_data::ClientDataHash;
 use crate::ctap2::commands::authenticator_config::{
@@ -15,39 +17,41 @@ use crate::ctap2::commands::authenticator_config::{
 use crate::ctap2::commands::bio_enrollment::{
     BioEnrollment, BioEnrollmentCommand, BioEnrollmentResult, FingerprintSensorInfo,
 };
-use crate::ctap2::commands::client_pin::{
-    ChangeExistingPin, Pin, PinError, PinUvAuthTokenPermission, SetNewPin,
-};
+use crate::ctap2::commands::client_pin::{ChangeExistingPin, Pin, PinError, SetNewPin};
 use crate::ctap2::commands::credential_management::{
     CredManagementCommand, CredentialList, CredentialListEntry, CredentialManagement,
     CredentialManagementResult, CredentialRpListEntry,
 };
 use crate::ctap2::commands::get_assertion::{GetAssertion, GetAssertionOptions};
 use crate::ctap2::commands::make_credentials::{
-    dummy_make_credentials_cmd, MakeCredentials, MakeCredentialsOptions,
+    authenticator_display_name, dummy_make_credentials_cmd, MakeCredentials, MakeCredentialsOptions,
 };
 use crate::ctap2::commands::reset::Reset;
 use crate::ctap2::commands::{
     repackage_pin_errors, CommandError, PinUvAuthCommand, PinUvAuthResult, RequestCtap2, StatusCode,
 };
+use crate::ctap2::counter::{CounterTracker, CounterVerdict};
 use crate::ctap2::preflight::{
     do_credential_list_filtering_ctap1, do_credential_list_filtering_ctap2,
     silently_discover_credentials,
 };
 use crate::ctap2::server::{
-    CredentialProtectionPolicy, RelyingParty, ResidentKeyRequirement, UserVerificationRequirement,
+    CredentialProtectionPolicy, PublicKeyCredentialParameters, RelyingParty,
+    ResidentKeyRequirement, UserVerificationRequirement,
 };
 use crate::errors::{AuthenticatorError, UnsupportedOption};
 use crate::statecallback::StateCallback;
 use crate::status_update::{send_status, BioEnrollmentCmd, CredManagementCmd, InteractiveUpdate};
-use crate::transport::device_selector::{Device, DeviceSelectorEvent};
+use crate::transport::device_selector::{
+    Device, DeviceID, DeviceInfoAnswer, DeviceSelector, DeviceSelectorEvent,
+};
 use crate::transport::{errors::HIDError, hid::HIDDevice, FidoDevice, FidoDeviceIO, FidoProtocol};
 use crate::{ManageResult, ResetResult, StatusPinUv, StatusUpdate};
 use std::sync::mpsc::{channel, RecvError, Sender};
 use std::thread;
 use std::time::Duration;
 
-use self::commands::get_info::AuthenticatorVersion;
+use self::commands::get_info::{AuthenticatorInfo, AuthenticatorVersion};
 
 macro_rules! unwrap_option {
     ($item: expr, $callback: expr) => {
@@ -192,13 +196,57 @@ fn ask_user_for_pin(
     }
 }
 
+fn deadline_expired(deadline: Option<Deadline>) -> bool {
+    deadline.map_or(false, |d| d.has_expired())
+}
+
+/// Once the deadline has passed, any error we report is most likely the result of us
+/// cancelling the in-flight request, so it gets reported as a timeout instead.
+fn callback_with_deadline<T: 'static>(
+    callback: StateCallback<crate::Result<T>>,
+    deadline: Option<Deadline>,
+) -> StateCallback<crate::Result<T>> {
+    if deadline.is_none() {
+        return callback;
+    }
+    StateCallback::new(Box::new(move |rv: crate::Result<T>| {
+        callback.call(rv.map_err(|e| {
+            if deadline_expired(deadline) {
+                AuthenticatorError::Timeout
+            } else {
+                e
+            }
+        }))
+    }))
+}
+
+/// Turns successful results from backup eligible credentials into errors, if the caller only
+/// accepts device-bound credentials.
+fn callback_rejecting_backup_eligible<T: 'static>(
+    callback: StateCallback<crate::Result<T>>,
+    reject_backup_eligible: bool,
+    is_backup_eligible: fn(&T) -> bool,
+) -> StateCallback<crate::Result<T>> {
+    if !reject_backup_eligible {
+        return callback;
+    }
+    StateCallback::new(Box::new(move |rv: crate::Result<T>| {
+        callback.call(rv.and_then(|result| {
+            if is_backup_eligible(&result) {
+                Err(AuthenticatorError::BackupEligibleCredential)
+            } else {
+                Ok(result)
+            }
+        }))
+    }))
+}
+
 /// Try to fetch PinUvAuthToken from the device and derive from it PinUvAuthParam.
 /// Prefer UV, fallback to PIN.
 /// Prefer newer pinUvAuth-methods, if supported by the device.
 fn get_pin_uv_auth_param<Dev: FidoDevice, T: PinUvAuthCommand + RequestCtap2>(
     cmd: &mut T,
     dev: &mut Dev,
-    permission: PinUvAuthTokenPermission,
     skip_uv: bool,
     uv_req: UserVerificationRequirement,
     alive: &dyn Fn() -> bool,
@@ -233,9 +281,26 @@ fn get_pin_uv_auth_param<Dev: FidoDevice, T: PinUvAuthCommand + RequestCtap2>(
 
     // Check if the combination of device-protection and request-options
     // are allowing for 'discouraged', meaning no auth required.
-    if cmd.can_skip_user_verification(info, uv_req) {
+    let uv_reason = cmd.user_verification_requirement_reason(info, uv_req);
+    if !uv_reason.requires_user_verification() {
         return Ok(PinUvAuthResult::NoAuthRequired);
     }
+    debug!("User verification needed: {:?}", uv_reason);
+
+    // CTAP 2.1 devices with built-in UV can hand out a pinUvAuthToken using UV alone, and
+    // may require one for MakeCredential and GetAssertion even if no PIN is configured (or
+    // PINs are not supported at all). So we prefer this, independent of the PIN state.
+    if !skip_uv && supports_uv && info.options.pin_uv_auth_token == Some(true) {
+        let pin_auth_token = dev
+            .get_pin_uv_auth_token_using_uv_with_permissions(
+                cmd.required_permissions(),
+                cmd.get_rp_id(),
+                alive,
+            )
+            .map_err(|e| repackage_pin_errors(dev, e))?;
+        cmd.set_pin_uv_auth_param(Some(pin_auth_token.clone()))?;
+        return Ok(PinUvAuthResult::SuccessGetPinUvAuthTokenUsingUvWithPermissions(pin_auth_token));
+    }
 
     // Device does not support any (remaining) auth-method
     if (skip_uv || !supports_uv) && !supports_pin {
@@ -258,30 +323,20 @@ fn get_pin_uv_auth_param<Dev: FidoDevice, T: PinUvAuthCommand + RequestCtap2>(
     }
 
     if info.options.pin_uv_auth_token == Some(true) {
-        if !skip_uv && supports_uv {
-            // CTAP 2.1 - UV
-            let pin_auth_token = dev
-                .get_pin_uv_auth_token_using_uv_with_permissions(permission, cmd.get_rp_id(), alive)
-                .map_err(|e| repackage_pin_errors(dev, e))?;
-            cmd.set_pin_uv_auth_param(Some(pin_auth_token.clone()))?;
-            Ok(PinUvAuthResult::SuccessGetPinUvAuthTokenUsingUvWithPermissions(pin_auth_token))
-        } else {
-            // CTAP 2.1 - PIN
-            // We did not take the `!skip_uv && supports_uv` branch, so we have
-            // `(skip_uv || !supports_uv)`. Moreover we did not exit early in the
-            // `(skip_uv || !supports_uv) && !pin_configured` case. So we have
-            // `pin_configured`.
-            let pin_auth_token = dev
-                .get_pin_uv_auth_token_using_pin_with_permissions(
-                    pin,
-                    permission,
-                    cmd.get_rp_id(),
-                    alive,
-                )
-                .map_err(|e| repackage_pin_errors(dev, e))?;
-            cmd.set_pin_uv_auth_param(Some(pin_auth_token.clone()))?;
-            Ok(PinUvAuthResult::SuccessGetPinUvAuthTokenUsingPinWithPermissions(pin_auth_token))
-        }
+        // CTAP 2.1 - PIN
+        // We did not return early via UV above, so we have `(skip_uv || !supports_uv)`.
+        // Moreover we did not exit early in the `(skip_uv || !supports_uv) && !pin_configured`
+        // case. So we have `pin_configured`.
+        let pin_auth_token = dev
+            .get_pin_uv_auth_token_using_pin_with_permissions(
+                pin,
+                cmd.required_permissions(),
+                cmd.get_rp_id(),
+                alive,
+            )
+            .map_err(|e| repackage_pin_errors(dev, e))?;
+        cmd.set_pin_uv_auth_param(Some(pin_auth_token.clone()))?;
+        Ok(PinUvAuthResult::SuccessGetPinUvAuthTokenUsingPinWithPermissions(pin_auth_token))
     } else {
         // CTAP 2.0 fallback
         if !skip_uv && supports_uv && pin.is_none() {
@@ -319,7 +374,6 @@ fn determine_puap_if_needed<Dev: FidoDevice, T: PinUvAuthCommand + RequestCtap2>
     cmd: &mut T,
     dev: &mut Dev,
     mut skip_uv: bool,
-    permission: PinUvAuthTokenPermission,
     uv_req: UserVerificationRequirement,
     status: &Sender<StatusUpdate>,
     alive: &dyn Fn() -> bool,
@@ -328,7 +382,7 @@ fn determine_puap_if_needed<Dev: FidoDevice, T: PinUvAuthCommand + RequestCtap2>
     while alive() {
         debug!("-----------------------------------------------------------------");
         debug!("Getting pinUvAuthParam");
-        match get_pin_uv_auth_param(cmd, dev, permission, skip_uv, uv_req, alive, pin) {
+        match get_pin_uv_auth_param(cmd, dev, skip_uv, uv_req, alive, pin) {
             Ok(r) => {
                 return Ok(r);
             }
@@ -392,59 +446,37 @@ fn determine_puap_if_needed<Dev: FidoDevice, T: PinUvAuthCommand + RequestCtap2>
     Err(AuthenticatorError::CancelledByUser)
 }
 
-pub fn register<Dev: FidoDevice>(
-    dev: &mut Dev,
-    args: RegisterArgs,
-    status: Sender<crate::StatusUpdate>,
-    callback: StateCallback<crate::Result<crate::RegisterResult>>,
-    alive: &dyn Fn() -> bool,
-) -> bool {
-    let mut options = MakeCredentialsOptions::default();
-
-    if dev.get_protocol() == FidoProtocol::CTAP2 {
-        let info = match dev.get_authenticator_info() {
-            Some(info) => info,
-            None => {
-                callback.call(Err(HIDError::DeviceNotInitialized.into()));
-                return false;
-            }
-        };
+/// Checks whether an initialized device can fulfill a registration request, without sending
+/// anything to it. This allows to filter out unsuitable devices before asking the user to pick
+/// one of them.
+pub fn check_compatibility<Dev: FidoDevice>(
+    dev: &Dev,
+    args: &RegisterArgs,
+) -> Result<(), UnsupportedOption> {
+    check_compatibility_with(dev.get_protocol(), dev.get_authenticator_info(), args)
+}
 
-        // Set options based on the arguments and the device info.
-        // The user verification option will be set in `determine_puap_if_needed`.
-        options.resident_key = match args.resident_key_req {
-            ResidentKeyRequirement::Required => Some(true),
-            ResidentKeyRequirement::Preferred => {
-                // Use a resident key if the authenticator supports it
-                Some(info.options.resident_key)
-            }
-            ResidentKeyRequirement::Discouraged => Some(false),
-        }
-    } else {
+fn check_compatibility_with(
+    protocol: FidoProtocol,
+    info: Option<&AuthenticatorInfo>,
+    args: &RegisterArgs,
+) -> Result<(), UnsupportedOption> {
+    if protocol != FidoProtocol::CTAP2 {
         // Check that the request can be processed by a CTAP1 device.
         // See CTAP 2.1 Section 10.2. Some additional checks are performed in
         // MakeCredentials::RequestCtap1
         if args.resident_key_req == ResidentKeyRequirement::Required {
-            callback.call(Err(AuthenticatorError::UnsupportedOption(
-                UnsupportedOption::ResidentKey,
-            )));
-            return false;
+            return Err(UnsupportedOption::ResidentKey);
         }
         if args.user_verification_req == UserVerificationRequirement::Required {
-            callback.call(Err(AuthenticatorError::UnsupportedOption(
-                UnsupportedOption::UserVerification,
-            )));
-            return false;
+            return Err(UnsupportedOption::UserVerification);
         }
         if !args
             .pub_cred_params
             .iter()
             .any(|x| x.alg == COSEAlgorithm::ES256)
         {
-            callback.call(Err(AuthenticatorError::UnsupportedOption(
-                UnsupportedOption::PubCredParams,
-            )));
-            return false;
+            return Err(UnsupportedOption::PubCredParams);
         }
     }
 
@@ -453,20 +485,126 @@ pub fn register<Dev: FidoDevice>(
     // [not "Optional"], the platform SHOULD NOT create the credential in a way that does not
     // implement the requested protection policy. (For example, by creating it on an authenticator
     // that does not support this extension.)"
-    let dev_supports_cred_protect = dev
-        .get_authenticator_info()
-        .map_or(false, |info| info.supports_cred_protect());
+    let dev_supports_cred_protect = info.map_or(false, |info| info.supports_cred_protect());
     if args.extensions.enforce_credential_protection_policy == Some(true)
         && args.extensions.credential_protection_policy
             != Some(CredentialProtectionPolicy::UserVerificationOptional)
         && !dev_supports_cred_protect
     {
-        callback.call(Err(AuthenticatorError::UnsupportedOption(
-            UnsupportedOption::CredProtect,
-        )));
-        return false;
+        return Err(UnsupportedOption::CredProtect);
     }
 
+    // Platform-managed enterprise attestation is requested deliberately, so we don't want to
+    // silently return a regular attestation instead. Mode 1 requests get dropped in that case.
+    if args.enterprise_attestation == Some(2) && info.and_then(|info| info.options.ep) != Some(true)
+    {
+        return Err(UnsupportedOption::EnterpriseAttestation);
+    }
+
+    // Asking for a largeBlobKey only makes sense if the large-blob array can be accessed,
+    // and authenticators without largeBlobs support would reject the extension.
+    if args.extensions.large_blob_key == Some(true)
+        && info.and_then(|info| info.options.large_blobs) != Some(true)
+    {
+        return Err(UnsupportedOption::LargeBlobs);
+    }
+
+    // Authenticators that support credBlob have to accept at least 32 bytes. Longer blobs
+    // would be refused silently (with credBlob=false in the response).
+    if let (Some(cred_blob), Some(info)) = (&args.extensions.cred_blob, info) {
+        let max_cred_blob_length = info.max_cred_blob_length.unwrap_or(32);
+        if info.supports_extension("credBlob") && cred_blob.len() as u64 > max_cred_blob_length {
+            return Err(UnsupportedOption::CredBlob);
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the connected devices that can fulfill a registration request, together with their
+/// name: the HID product name, or the name of their model if the device didn't report one
+/// (see the `aaguid_metadata` feature). This waits at most `timeout` milliseconds for the
+/// devices to answer, see `DeviceSelector::info_for_all()`.
+///
+/// A custom UI can use this to offer only suitable devices, instead of letting all of them
+/// blink. Devices that didn't answer are not listed.
+pub fn find_eligible_devices(
+    args: &RegisterArgs,
+    timeout: u64,
+) -> crate::Result<Vec<(DeviceID, Option<String>)>> {
+    let infos = DeviceSelector::info_for_all(timeout)?;
+    Ok(eligible_devices(infos, args))
+}
+
+fn eligible_devices(
+    infos: Vec<DeviceInfoAnswer>,
+    args: &RegisterArgs,
+) -> Vec<(DeviceID, Option<String>)> {
+    infos
+        .into_iter()
+        .filter_map(|(id, product_name, info)| {
+            let (protocol, info) = match info {
+                Ok(info) if info.max_supported_version() == AuthenticatorVersion::U2F_V2 => {
+                    (FidoProtocol::CTAP1, Some(info))
+                }
+                Ok(info) => (FidoProtocol::CTAP2, Some(info)),
+                // CTAP1-only devices have no info
+                Err(HIDError::UnsupportedCommand) => (FidoProtocol::CTAP1, None),
+                Err(_) => return None,
+            };
+            check_compatibility_with(protocol, info.as_ref(), args).ok()?;
+            let name = product_name
+                .or_else(|| info.and_then(|info| authenticator_display_name(&info.aaguid)));
+            Some((id, name))
+        })
+        .collect()
+}
+
+/// Builds the MakeCredentials request for `args`, with everything but the PIN/UV auth
+/// parameters and the hmac-secret extension, which depend on the key agreement with `dev`.
+fn make_credentials_for<Dev: FidoDevice>(
+    dev: &Dev,
+    mut args: RegisterArgs,
+) -> Result<MakeCredentials, AuthenticatorError> {
+    PublicKeyCredentialParameters::validate_list(&args.pub_cred_params)?;
+    args.relying_party.truncate_name();
+
+    let mut options = MakeCredentialsOptions::default();
+
+    if dev.get_protocol() == FidoProtocol::CTAP2 {
+        let info = dev
+            .get_authenticator_info()
+            .ok_or(HIDError::DeviceNotInitialized)?;
+
+        // Set options based on the arguments and the device info.
+        // The user verification option will be set in `determine_puap_if_needed`.
+        options.resident_key = match args.resident_key_req {
+            ResidentKeyRequirement::Required => Some(true),
+            ResidentKeyRequirement::Preferred => {
+                // Use a resident key if the authenticator supports it
+                Some(info.options.resident_key)
+            }
+            ResidentKeyRequirement::Discouraged => Some(false),
+        };
+
+        // The authenticator would reject the request with CTAP2_ERR_UNSUPPORTED_ALGORITHM, but
+        // possibly only after the user entered a PIN or touched it.
+        if let Some(algorithms) = &info.algorithms {
+            if !args
+                .pub_cred_params
+                .iter()
+                .any(|param| algorithms.contains(param))
+            {
+                return Err(AuthenticatorError::NoSupportedAlgorithm {
+                    requested: args.pub_cred_params.iter().map(|p| p.alg).collect(),
+                    supported: algorithms.iter().map(|p| p.alg).collect(),
+                });
+            }
+        }
+    }
+
+    check_compatibility(dev, &args).map_err(AuthenticatorError::UnsupportedOption)?;
+
     let mut makecred = MakeCredentials::new(
         ClientDataHash(args.client_data_hash),
         args.relying_party,
@@ -476,48 +614,83 @@ pub fn register<Dev: FidoDevice>(
         options,
         args.extensions.into(),
     );
+    makecred.enterprise_attestation = args.enterprise_attestation;
+    makecred.restrict_enterprise_attestation(&args.enterprise_attestation_rp_ids);
+    makecred.skip_attestation_statement = args.skip_attestation_statement;
+    if makecred.options.resident_key == Some(true) && makecred.extensions.cred_protect.is_none() {
+        // Dropped below, if the authenticator does not support credProtect
+        makecred.extensions.cred_protect = args.default_rk_cred_protect;
+    }
+
+    match dev.get_authenticator_info() {
+        Some(info) => makecred.normalize_for(info),
+        None => {
+            // CTAP1 devices don't support any extensions, and we can't do a key agreement
+            makecred.extensions.hmac_secret_mc = None;
+            makecred.drop_disabled_enterprise_attestation(None);
+        }
+    }
+    Ok(makecred)
+}
+
+pub fn register<Dev: FidoDevice>(
+    dev: &mut Dev,
+    mut args: RegisterArgs,
+    status: Sender<crate::StatusUpdate>,
+    callback: StateCallback<crate::Result<crate::RegisterResult>>,
+    alive: &dyn Fn() -> bool,
+) -> bool {
+    let deadline = args.deadline;
+    let callback = callback_with_deadline(callback, deadline);
+    let callback = callback_rejecting_backup_eligible(
+        callback,
+        args.reject_backup_eligible,
+        crate::RegisterResult::is_backup_eligible,
+    );
+    let keep_alive = || alive() && !deadline_expired(deadline);
+    let alive: &dyn Fn() -> bool = &keep_alive;
+
+    let enforce_cred_protect = args.extensions.enforce_credential_protection_policy == Some(true);
+    let requested_cred_protect = args.extensions.credential_protection_policy;
+    let user_verification_req = args.user_verification_req;
+    let rk_preferred = args.resident_key_req == ResidentKeyRequirement::Preferred;
+    let mut pin = args.pin.take();
+
+    let mut makecred = unwrap_result!(make_credentials_for(dev, args), callback);
 
     let mut skip_uv = false;
-    let mut pin = args.pin;
+    let mut rk_fallback = false;
     while alive() {
-        // Requesting both because pre-flighting (credential list filtering)
-        // can potentially send GetAssertion-commands
-        let permissions =
-            PinUvAuthTokenPermission::MakeCredential | PinUvAuthTokenPermission::GetAssertion;
-
         let pin_uv_auth_result = unwrap_result!(
             determine_puap_if_needed(
                 &mut makecred,
                 dev,
                 skip_uv,
-                permissions,
-                args.user_verification_req,
+                user_verification_req,
                 &status,
                 alive,
                 &mut pin,
             ),
             callback
         );
+        // Use the shared secret in the extensions, if requested. The shared secret might not
+        // have been established yet, if no PIN or UV was needed.
+        if let Some(extension) = makecred.extensions.hmac_secret_mc.as_mut() {
+            let secret = match dev.get_shared_secret() {
+                Some(secret) => secret.clone(),
+                None => unwrap_result!(dev.establish_shared_secret(alive), callback),
+            };
+            unwrap_result!(extension.calculate(&secret), callback);
+        }
         // Do "pre-flight": Filter the exclude-list
-        if dev.get_protocol() == FidoProtocol::CTAP2 {
-            makecred.exclude_list = unwrap_result!(
-                do_credential_list_filtering_ctap2(
-                    dev,
-                    &makecred.exclude_list,
-                    &makecred.rp,
-                    pin_uv_auth_result.get_pin_uv_auth_token(),
-                ),
-                callback
-            );
-        } else {
-            let key_handle = do_credential_list_filtering_ctap1(
-                dev,
-                &makecred.exclude_list,
-                &makecred.rp,
-                &makecred.client_data_hash,
-            );
+        match makecred.preflight_exclude_list(dev, pin_uv_auth_result.get_pin_uv_auth_token()) {
+            Ok(()) => {}
+            // CTAP2 tokens return "CredentialExcluded" for the request with the filtered list,
+            // once the user touched them.
+            Err(AuthenticatorError::CredentialExcluded)
+                if dev.get_protocol() == FidoProtocol::CTAP2 => {}
             // That handle was already registered with the token
-            if key_handle.is_some() {
+            Err(AuthenticatorError::CredentialExcluded) => {
                 // Now we need to send a dummy registration request, to make the token blink
                 // Spec says "dummy appid and invalid challenge". We use the same, as we do for
                 // making the token blink upon device selection.
@@ -527,6 +700,15 @@ pub fn register<Dev: FidoDevice>(
                 callback.call(Err(AuthenticatorError::CredentialExcluded));
                 return false;
             }
+            Err(e) => {
+                callback.call(Err(e));
+                return false;
+            }
+        }
+
+        // The PIN/UV handling may have changed the options
+        if let Some(info) = dev.get_authenticator_info() {
+            makecred.normalize_for(info);
         }
 
         debug!("------------------------------------------------------------------");
@@ -535,18 +717,172 @@ pub fn register<Dev: FidoDevice>(
         send_status(&status, crate::StatusUpdate::PresenceRequired);
         let resp = dev.send_msg_cancellable(&makecred, alive);
         match resp {
-            Ok(result) => {
+            Ok(mut result) => {
+                // Some authenticators accept the credProtect extension, but silently store the
+                // credential with a lower protection level.
+                let applied_cred_protect = result.extensions.credential_protection_policy;
+                if enforce_cred_protect && applied_cred_protect < requested_cred_protect {
+                    callback.call(Err(AuthenticatorError::UnsupportedOption(
+                        UnsupportedOption::CredProtect,
+                    )));
+                    return false;
+                }
+                if rk_fallback && makecred.extensions.cred_props == Some(true) {
+                    // The key store is full, so even CTAP 2.0 authenticators (see
+                    // finalize_result) can't have created a discoverable credential.
+                    result
+                        .extensions
+                        .cred_props
+                        .get_or_insert_with(Default::default)
+                        .rk = false;
+                }
                 callback.call(Ok(result));
                 return true;
             }
+            Err(HIDError::Command(CommandError::StatusCode(StatusCode::KeyStoreFull, _)))
+                if rk_preferred && makecred.options.resident_key == Some(true) =>
+            {
+                // The relying party only preferred a discoverable credential, so we don't
+                // let a full key store block the registration.
+                info!("Key store full, falling back to a non-discoverable credential");
+                makecred.options.resident_key = Some(false);
+                rk_fallback = true;
+                continue;
+            }
             Err(e) => {
                 handle_errors!(e, status, callback, pin_uv_auth_result, skip_uv);
             }
         }
     }
+    if deadline_expired(deadline) {
+        callback.call(Err(AuthenticatorError::Timeout));
+    }
     false
 }
 
+/// One registration of `register_batch`.
+#[derive(Debug)]
+pub struct BatchRegistration {
+    pub client_data_hash: ClientDataHash,
+    pub result: crate::Result<crate::RegisterResult>,
+}
+
+impl BatchRegistration {
+    /// The signature counter of the new credential, if the registration succeeded.
+    pub fn sign_count(&self) -> Option<u32> {
+        self.result
+            .as_ref()
+            .ok()
+            .map(|result| result.att_obj.auth_data.counter)
+    }
+}
+
+/// Indices of the registrations in `batch`, whose signature counter is lower than the one of a
+/// successful registration before it, see `CounterTracker`. Counters that did not change are
+/// not an anomaly: Authenticators with per-credential counters report the same initial value
+/// for every new credential, and those that don't implement a counter always report 0.
+pub fn counter_anomalies(batch: &[BatchRegistration]) -> Vec<usize> {
+    // Every registration creates a new credential, so we track the counter of the device. With
+    // per-credential counters it never regresses either.
+    let mut tracker = CounterTracker::new();
+    batch
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, reg)| Some((idx, reg.sign_count()?)))
+        .filter(|(_, count)| {
+            matches!(
+                tracker.observe(&[], *count),
+                CounterVerdict::Regressed { .. }
+            )
+        })
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+/// Registers one credential per entry of `client_data_hashes` on `dev`, e.g. to stress the
+/// credential storage and the signature counter of a device in a test suite.
+///
+/// Unlike `register`, the user is asked for the PIN only once. CTAP 2.1 authenticators clear
+/// the permissions of a pinUvAuthToken once it was used for a registration, so a new token is
+/// requested for every entry. With built-in UV, the user has to verify for each of them.
+/// The exclude list is sent as given, without pre-flighting it.
+/// Errors that prevent the batch from starting are returned directly, while errors of
+/// single registrations are collected in the result, and the batch carries on. If no token
+/// can be obtained during the batch, the batch stops with that error as the last entry.
+pub fn register_batch<Dev: FidoDevice>(
+    dev: &mut Dev,
+    mut args: RegisterArgs,
+    client_data_hashes: &[[u8; 32]],
+    status: Sender<crate::StatusUpdate>,
+    alive: &dyn Fn() -> bool,
+) -> crate::Result<Vec<BatchRegistration>> {
+    let deadline = args.deadline;
+    let keep_alive = || alive() && !deadline_expired(deadline);
+    let alive: &dyn Fn() -> bool = &keep_alive;
+
+    let user_verification_req = args.user_verification_req;
+    let mut pin = args.pin.take();
+    let mut makecred = make_credentials_for(dev, args)?;
+
+    if let Some(info) = dev.get_authenticator_info() {
+        makecred.normalize_for(info);
+    }
+
+    let mut batch = Vec::with_capacity(client_data_hashes.len());
+    for client_data_hash in client_data_hashes {
+        if !alive() {
+            break;
+        }
+        // The pinUvAuthParam is computed over the clientDataHash, so it has to be set first
+        makecred.client_data_hash = ClientDataHash(*client_data_hash);
+        let prepared = determine_puap_if_needed(
+            &mut makecred,
+            dev,
+            false,
+            user_verification_req,
+            &status,
+            alive,
+            &mut pin,
+        )
+        .and_then(|_| match makecred.extensions.hmac_secret_mc.as_mut() {
+            Some(extension) => {
+                let secret = match dev.get_shared_secret() {
+                    Some(secret) => secret.clone(),
+                    None => dev.establish_shared_secret(alive)?,
+                };
+                extension.calculate(&secret)
+            }
+            None => Ok(()),
+        });
+        if let Err(e) = prepared {
+            if batch.is_empty() {
+                return Err(e);
+            }
+            batch.push(BatchRegistration {
+                client_data_hash: makecred.client_data_hash.clone(),
+                result: Err(e),
+            });
+            break;
+        }
+
+        send_status(&status, crate::StatusUpdate::PresenceRequired);
+        let result = dev
+            .send_msg_cancellable(&makecred, alive)
+            .map_err(AuthenticatorError::from);
+        debug!(
+            "Registration {} of {}: {:?}",
+            batch.len() + 1,
+            client_data_hashes.len(),
+            result.as_ref().map(|r| r.att_obj.auth_data.counter)
+        );
+        batch.push(BatchRegistration {
+            client_data_hash: makecred.client_data_hash.clone(),
+            result,
+        });
+    }
+    Ok(batch)
+}
+
 pub fn sign<Dev: FidoDevice>(
     dev: &mut Dev,
     args: SignArgs,
@@ -554,6 +890,16 @@ pub fn sign<Dev: FidoDevice>(
     callback: StateCallback<crate::Result<crate::SignResult>>,
     alive: &dyn Fn() -> bool,
 ) -> bool {
+    let deadline = args.deadline;
+    let callback = callback_with_deadline(callback, deadline);
+    let callback = callback_rejecting_backup_eligible(
+        callback,
+        args.reject_backup_eligible,
+        crate::SignResult::is_backup_eligible,
+    );
+    let keep_alive = || alive() && !deadline_expired(deadline);
+    let alive: &dyn Fn() -> bool = &keep_alive;
+
     if dev.get_protocol() == FidoProtocol::CTAP1 {
         // Check that the request can be processed by a CTAP1 device.
         // See CTAP 2.1 Section 10.3. Some additional checks are performed in
@@ -608,7 +954,6 @@ pub fn sign<Dev: FidoDevice>(
                 &mut get_assertion,
                 dev,
                 skip_uv,
-                PinUvAuthTokenPermission::GetAssertion,
                 args.user_verification_req,
                 &status,
                 alive,
@@ -616,6 +961,7 @@ pub fn sign<Dev: FidoDevice>(
             ),
             callback
         );
+        unwrap_result!(get_assertion.validate_options(), callback);
         // Third, use the shared secret in the extensions, if requested
         if let Some(extension) = get_assertion.extensions.hmac_secret.as_mut() {
             if let Some(secret) = dev.get_shared_secret() {
@@ -664,11 +1010,7 @@ pub fn sign<Dev: FidoDevice>(
             send_status(&status, crate::StatusUpdate::PresenceRequired);
             let msg = dummy_make_credentials_cmd();
             let _ = dev.send_msg_cancellable(&msg, alive); // Ignore answer, return "NoCredentials"
-            callback.call(Err(HIDError::Command(CommandError::StatusCode(
-                StatusCode::NoCredentials,
-                None,
-            ))
-            .into()));
+            callback.call(Err(AuthenticatorError::NoMatchingCredentials(Some(true))));
             return false;
         }
 
@@ -678,6 +1020,18 @@ pub fn sign<Dev: FidoDevice>(
         send_status(&status, crate::StatusUpdate::PresenceRequired);
         let mut results = match dev.send_msg_cancellable(&get_assertion, alive) {
             Ok(results) => results,
+            Err(HIDError::Command(CommandError::StatusCode(StatusCode::NoCredentials, _))) => {
+                // Pre-flighting found a match, but the authenticator still rejected the request
+                let allow_list_exhausted = if original_allow_list_was_empty {
+                    None
+                } else {
+                    Some(false)
+                };
+                callback.call(Err(AuthenticatorError::NoMatchingCredentials(
+                    allow_list_exhausted,
+                )));
+                return false;
+            }
             Err(e) => {
                 handle_errors!(e, status, callback, pin_uv_auth_result, skip_uv);
             }
@@ -697,7 +1051,61 @@ pub fn sign<Dev: FidoDevice>(
         );
         match rx.recv() {
             Ok(Some(index)) if index < results.len() => {
-                callback.call(Ok(results.swap_remove(index)));
+                let selected = results.swap_remove(index);
+                let credential = match selected.assertion.credentials {
+                    Some(ref credential) if args.reassert_selected_credential => credential.clone(),
+                    _ => {
+                        callback.call(Ok(selected));
+                        return true;
+                    }
+                };
+                // Re-run the assertion, targeting only the credential the user picked.
+                // CTAP 2.1 authenticators clear the permissions of the pinUvAuthToken after
+                // an assertion, so we need a new pinUvAuthParam.
+                get_assertion.allow_list = vec![credential];
+                let pin_uv_auth_result = match determine_puap_if_needed(
+                    &mut get_assertion,
+                    dev,
+                    skip_uv,
+                    args.user_verification_req,
+                    &status,
+                    alive,
+                    &mut pin,
+                ) {
+                    Ok(result) => result,
+                    Err(e) => {
+                        callback.call(Err(e));
+                        return true;
+                    }
+                };
+                if let (Some(extension), Some(secret)) = (
+                    get_assertion.extensions.hmac_secret.as_mut(),
+                    dev.get_shared_secret(),
+                ) {
+                    if let Err(e) = extension.calculate(secret) {
+                        callback.call(Err(e));
+                        return true;
+                    }
+                }
+                debug!("------------------------------------------------------------------");
+                debug!("{get_assertion:?} using {pin_uv_auth_result:?}");
+                debug!("------------------------------------------------------------------");
+                send_status(&status, crate::StatusUpdate::PresenceRequired);
+                match dev.send_msg_cancellable(&get_assertion, alive) {
+                    Ok(mut results) if !results.is_empty() => {
+                        callback.call(Ok(results.swap_remove(0)));
+                    }
+                    Ok(_)
+                    | Err(HIDError::Command(CommandError::StatusCode(
+                        StatusCode::NoCredentials,
+                        _,
+                    ))) => {
+                        callback.call(Err(AuthenticatorError::NoMatchingCredentials(Some(false))));
+                    }
+                    Err(e) => {
+                        callback.call(Err(e.into()));
+                    }
+                }
                 return true;
             }
             _ => {
@@ -706,6 +1114,9 @@ pub fn sign<Dev: FidoDevice>(
             }
         }
     }
+    if deadline_expired(deadline) {
+        callback.call(Err(AuthenticatorError::Timeout));
+    }
     false
 }
 
@@ -725,6 +1136,8 @@ pub(crate) fn reset_helper<T: From<ResetResult>>(
     send_status(&status, crate::StatusUpdate::PresenceRequired);
     let resp = dev.send_cbor_cancellable(&reset, keep_alive);
     if resp.is_ok() {
+        // The reset also regenerated the key agreement key of the device
+        dev.clear_shared_secret();
         // The DeviceSelector could already be dead, but it might also wait
         // for us to respond, in order to cancel all other tokens in case
         // we skipped the "blinking"-action and went straight for the actual
@@ -768,7 +1181,7 @@ pub(crate) fn set_or_change_pin_helper<T: From<()>>(
     };
 
     // If the device has a min PIN use that, otherwise default to 4 according to Spec
-    if new_pin.as_bytes().len() < authinfo.min_pin_length.unwrap_or(4) as usize {
+    if new_pin.as_bytes().len() < authinfo.min_pin_length().unwrap_or(4) as usize {
         callback.call(Err(AuthenticatorError::PinError(PinError::PinIsTooShort)));
         return;
     }
@@ -810,7 +1223,9 @@ pub(crate) fn set_or_change_pin_helper<T: From<()>>(
             if let Err(AuthenticatorError::PinError(PinError::InvalidPin(r))) = res {
                 was_invalid = true;
                 retries = r;
-                // We need to re-establish the shared secret for the next round.
+                // The device regenerated its key agreement key, so we need to re-establish
+                // the shared secret for the next round.
+                dev.clear_shared_secret();
                 match dev.establish_shared_secret(alive) {
                     Ok(s) => {
                         shared_secret = s;
@@ -904,7 +1319,7 @@ pub(crate) fn bio_enrollment(
             if !authinfo.versions.contains(&AuthenticatorVersion::FIDO_2_1) // Only 2.1 has a permission-system
                 || use_legacy_preview // Preview doesn't use permissions
                 || t.permissions
-                    .contains(PinUvAuthTokenPermission::BioEnrollment) =>
+                    .contains(bio_cmd.required_permissions()) =>
         {
             skip_puap = true;
             cached_puat = true;
@@ -920,7 +1335,6 @@ pub(crate) fn bio_enrollment(
                     &mut bio_cmd,
                     dev,
                     skip_uv,
-                    PinUvAuthTokenPermission::BioEnrollment,
                     UserVerificationRequirement::Preferred,
                     &status,
                     alive,
@@ -1160,6 +1574,7 @@ pub(crate) fn credential_management(
     let mut credential_result = CredentialList::new();
     let mut remaining_rps = 0;
     let mut remaining_cred_ids = 0;
+    let mut remaining_creds: u64 = 0;
     let mut current_rp = 0;
     let mut skip_puap = false;
     let mut cached_puat = false; // If we were provided with a cached puat from the outside
@@ -1174,7 +1589,7 @@ pub(crate) fn credential_management(
             if !authinfo.versions.contains(&AuthenticatorVersion::FIDO_2_1) // Only 2.1 has a permission-system
                 || use_legacy_preview // Preview doesn't use permissions
                 || t.permissions
-                    .contains(PinUvAuthTokenPermission::CredentialManagement) =>
+                    .contains(cred_management.required_permissions()) =>
         {
             skip_puap = true;
             cached_puat = true;
@@ -1190,7 +1605,6 @@ pub(crate) fn credential_management(
                     &mut cred_management,
                     dev,
                     skip_uv,
-                    PinUvAuthTokenPermission::CredentialManagement,
                     UserVerificationRequirement::Preferred,
                     &status,
                     alive,
@@ -1221,6 +1635,18 @@ pub(crate) fn credential_management(
                         credential_result.max_possible_remaining_resident_credentials_count =
                             max_possible_remaining_resident_credentials_count;
                         if existing_resident_credentials_count > 0 {
+                            remaining_creds = existing_resident_credentials_count;
+                            send_status(
+                                &status,
+                                StatusUpdate::InteractiveManagement(
+                                    InteractiveUpdate::CredentialManagementUpdate((
+                                        CredentialManagementResult::EnumerationProgress(
+                                            remaining_creds,
+                                        ),
+                                        Some(pin_uv_auth_result.clone()),
+                                    )),
+                                ),
+                            );
                             cred_management.subcommand = CredManagementCommand::EnumerateRPsBegin;
                             // We have to regenerate PUAP here. PUAT hasn't changed, but the content
                             // of the command has changed, and that is part of the PUAP-calculation
@@ -1359,6 +1785,18 @@ pub(crate) fn credential_management(
                         credential_result.credential_list[current_rp_backup]
                             .credentials
                             .push(key);
+                        remaining_creds = remaining_creds.saturating_sub(1);
+                        send_status(
+                            &status,
+                            StatusUpdate::InteractiveManagement(
+                                InteractiveUpdate::CredentialManagementUpdate((
+                                    CredentialManagementResult::EnumerationProgress(
+                                        remaining_creds,
+                                    ),
+                                    Some(pin_uv_auth_result.clone()),
+                                )),
+                            ),
+                        );
                         if we_are_done {
                             send_status(
                                 &status,
@@ -1443,6 +1881,11 @@ pub(crate) fn configure_authenticator(
         return false;
     }
 
+    if let Err(e) = authcfg.subcommand.check_limits(&authinfo) {
+        callback.call(Err(AuthenticatorError::AuthConfigError(e)));
+        return false;
+    }
+
     let mut skip_puap = false;
     let mut cached_puat = false; // If we were provided with a cached puat from the outside
     let mut pin_uv_auth_result = puat_result
@@ -1452,8 +1895,7 @@ pub(crate) fn configure_authenticator(
         Some(PinUvAuthResult::SuccessGetPinToken(t))
         | Some(PinUvAuthResult::SuccessGetPinUvAuthTokenUsingUvWithPermissions(t))
         | Some(PinUvAuthResult::SuccessGetPinUvAuthTokenUsingPinWithPermissions(t))
-            if t.permissions
-                .contains(PinUvAuthTokenPermission::AuthenticatorConfiguration) =>
+            if t.permissions.contains(authcfg.required_permissions()) =>
         {
             skip_puap = true;
             cached_puat = true;
@@ -1474,7 +1916,6 @@ pub(crate) fn configure_authenticator(
                     &mut authcfg,
                     dev,
                     skip_uv,
-                    PinUvAuthTokenPermission::AuthenticatorConfiguration,
                     UserVerificationRequirement::Preferred,
                     &status,
                     alive,
@@ -1492,6 +1933,7 @@ pub(crate) fn configure_authenticator(
         match resp {
             Ok(()) => {
                 let auth_info = unwrap_option!(dev.refresh_authenticator_info(), callback);
+                debug!("Authenticator info changed: {:?}", authinfo.diff(auth_info));
                 send_status(
                     &status,
                     StatusUpdate::InteractiveManagement(InteractiveUpdate::AuthConfigUpdate((
@@ -1516,3 +1958,1105 @@ pub(crate) fn configure_authenticator(
     }
     false
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        check_compatibility, configure_authenticator, counter_anomalies, credential_management,
+        eligible_devices, get_pin_uv_auth_param, make_credentials_for, register, register_batch,
+        sign,
+    };
+    use crate::authenticatorservice::{Deadline, RegisterArgs, SignArgs};
+    use crate::crypto::{
+        COSEAlgorithm, COSEKey, COSEKeyType, COSEOKPKey, Curve, PinUvAuthProtocol, PinUvAuthToken,
+    };
+    use crate::ctap2::attestation::AuthenticatorDataFlags;
+    use crate::ctap2::client_data::ClientDataHash;
+    use crate::ctap2::commands::authenticator_config::{
+        AuthConfigCommand, AuthConfigError, AuthConfigResult, AuthenticatorConfig, SetMinPINLength,
+    };
+    use crate::ctap2::commands::client_pin::test::KEY_AGREEMENT_RESPONSE;
+    use crate::ctap2::commands::client_pin::{GetKeyAgreement, PinError, PinUvAuthTokenPermission};
+    use crate::ctap2::commands::credential_management::{
+        CredManagementCommand, CredentialManagement, CredentialManagementResponse,
+        CredentialManagementResult,
+    };
+    use crate::ctap2::commands::get_assertion::test::GET_ASSERTION_SAMPLE_RESPONSE_CTAP2;
+    use crate::ctap2::commands::get_assertion::{GetAssertion, GetAssertionOptions};
+    use crate::ctap2::commands::get_info::{
+        AuthenticatorInfo, AuthenticatorOptions, AuthenticatorVersion, GetInfo,
+    };
+    use crate::ctap2::commands::make_credentials::dummy_make_credentials_cmd;
+    use crate::ctap2::commands::make_credentials::test::MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2;
+    use crate::ctap2::commands::{
+        Command, CommandError, PinUvAuthCommand, PinUvAuthResult, StatusCode,
+    };
+    use crate::ctap2::preflight::CheckKeyHandle;
+    use crate::ctap2::server::{
+        AuthenticationExtensionsClientInputs, CredentialProtectionPolicy,
+        PublicKeyCredentialDescriptor, PublicKeyCredentialParameters,
+        PublicKeyCredentialUserEntity, RelyingParty, ResidentKeyRequirement,
+        UserVerificationRequirement,
+    };
+    use crate::errors::{AuthenticatorError, UnsupportedOption};
+    use crate::statecallback::StateCallback;
+    use crate::status_update::{CredManagementCmd, InteractiveUpdate, StatusUpdate};
+    use crate::transport::device_selector::Device;
+    use crate::transport::errors::{ApduErrorStatus, HIDError};
+    use crate::transport::platform::recording::{RecordedRequest, RecordingDevice};
+    use crate::transport::{hid::HIDDevice, FidoDevice, FidoProtocol};
+    use crate::Pin;
+    use serde_cbor::Value;
+    use std::collections::BTreeMap;
+    use std::convert::TryFrom;
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use std::time::Duration;
+
+    fn test_register_args() -> RegisterArgs {
+        RegisterArgs {
+            client_data_hash: [0u8; 32],
+            relying_party: RelyingParty::from("example.com"),
+            origin: String::from("https://example.com"),
+            user: PublicKeyCredentialUserEntity {
+                id: vec![0x01],
+                name: Some(String::from("user")),
+                display_name: None,
+            },
+            pub_cred_params: vec![PublicKeyCredentialParameters {
+                alg: COSEAlgorithm::ES256,
+            }],
+            exclude_list: vec![],
+            user_verification_req: UserVerificationRequirement::Discouraged,
+            resident_key_req: ResidentKeyRequirement::Discouraged,
+            default_rk_cred_protect: None,
+            extensions: Default::default(),
+            pin: None,
+            use_ctap1_fallback: false,
+            enterprise_attestation: None,
+            enterprise_attestation_rp_ids: vec![],
+            skip_attestation_statement: false,
+            reject_backup_eligible: false,
+            deadline: None,
+        }
+    }
+
+    #[test]
+    fn test_uv_token_without_pin() {
+        // Built-in UV with pinUvAuthToken support, but no PIN configured
+        let info = AuthenticatorInfo {
+            options: AuthenticatorOptions {
+                user_verification: Some(true),
+                pin_uv_auth_token: Some(true),
+                client_pin: Some(false),
+                ..Default::default()
+            },
+            pin_protocols: Some(vec![1]),
+            ..Default::default()
+        };
+
+        let mut device = Device::new_skipping_serialization("ctap2/uv_token").unwrap();
+        device.set_authenticator_info(info.clone());
+        // We expect the UV token path to be taken, which starts with establishing a shared
+        // secret. The device then reports UV as blocked.
+        let pin_protocol = PinUvAuthProtocol::try_from(&info).unwrap();
+        device.add_upcoming_ctap2_request(&GetKeyAgreement::new(pin_protocol));
+        device.add_upcoming_ctap_error(HIDError::Command(CommandError::StatusCode(
+            StatusCode::UvBlocked,
+            None,
+        )));
+
+        let mut get_assertion = GetAssertion::new(
+            ClientDataHash([0u8; 32]),
+            RelyingParty::from("example.com"),
+            vec![],
+            GetAssertionOptions::default(),
+            Default::default(),
+        );
+        let res = get_pin_uv_auth_param(
+            &mut get_assertion,
+            &mut device,
+            false,
+            UserVerificationRequirement::Preferred,
+            &|| true,
+            &None,
+        );
+        assert_matches!(res, Err(AuthenticatorError::PinError(PinError::UvBlocked)));
+    }
+
+    #[test]
+    fn test_set_min_pin_length_forces_pin_change() {
+        let mut device = Device::new_skipping_serialization("ctap2/force_pin_change").unwrap();
+        let info = AuthenticatorInfo {
+            options: AuthenticatorOptions {
+                authnr_cfg: Some(true),
+                set_min_pin_length: Some(true),
+                ..Default::default()
+            },
+            force_pin_change: Some(false),
+            ..Default::default()
+        };
+        device.set_authenticator_info(info.clone());
+
+        let subcommand = AuthConfigCommand::SetMinPINLength(SetMinPINLength {
+            new_min_pin_length: None,
+            min_pin_length_rpids: None,
+            force_change_pin: Some(true),
+        });
+        device.add_upcoming_ctap2_request(&AuthenticatorConfig::new(subcommand.clone()));
+        device.add_upcoming_ctap_response(());
+        // The cached info is stale now and has to be fetched again
+        device.add_upcoming_ctap2_request(&GetInfo::default());
+        device.add_upcoming_ctap_response(AuthenticatorInfo {
+            force_pin_change: Some(true),
+            ..info
+        });
+
+        let (status_tx, status_rx) = channel();
+        let callback = StateCallback::new(Box::new(|_| {}));
+        assert!(configure_authenticator(
+            &mut device,
+            None,
+            subcommand,
+            status_tx,
+            callback,
+            &|| true
+        ));
+        assert_eq!(
+            device.get_authenticator_info().unwrap().force_pin_change,
+            Some(true)
+        );
+        assert_matches!(
+            status_rx.recv().unwrap(),
+            StatusUpdate::InteractiveManagement(InteractiveUpdate::AuthConfigUpdate((
+                AuthConfigResult::Success(AuthenticatorInfo {
+                    force_pin_change: Some(true),
+                    ..
+                }),
+                _
+            )))
+        );
+    }
+
+    #[test]
+    fn test_credential_management_progress() {
+        let info = AuthenticatorInfo {
+            versions: vec![AuthenticatorVersion::FIDO_2_1],
+            options: AuthenticatorOptions {
+                cred_mgmt: Some(true),
+                ..Default::default()
+            },
+            pin_protocols: Some(vec![1]),
+            ..Default::default()
+        };
+        let mut device = Device::new_skipping_serialization("ctap2/cred_mgmt").unwrap();
+        device.set_authenticator_info(info.clone());
+        let token = PinUvAuthToken::new_test_token(
+            PinUvAuthProtocol::try_from(&info).unwrap(),
+            vec![0x01; 32],
+            PinUvAuthTokenPermission::CredentialManagement,
+        );
+
+        let request = |subcommand| {
+            let mut request = CredentialManagement::new(subcommand, false);
+            request
+                .set_pin_uv_auth_param(Some(token.clone()))
+                .expect("Failed to set pinUvAuthParam");
+            request
+        };
+        let credential = |id: u8| CredentialManagementResponse {
+            user: Some(PublicKeyCredentialUserEntity {
+                id: vec![id],
+                name: None,
+                display_name: None,
+            }),
+            credential_id: Some(PublicKeyCredentialDescriptor {
+                id: vec![id; 16],
+                transports: vec![],
+            }),
+            public_key: Some(COSEKey {
+                alg: COSEAlgorithm::EDDSA,
+                key: COSEKeyType::OKP(COSEOKPKey {
+                    curve: Curve::Ed25519,
+                    x: vec![id; 32],
+                }),
+            }),
+            cred_protect: Some(1),
+            ..Default::default()
+        };
+        let rp = RelyingParty::from("example.com");
+
+        device.add_upcoming_ctap2_request(&request(CredManagementCommand::GetCredsMetadata));
+        device.add_upcoming_ctap_response(CredentialManagementResponse {
+            existing_resident_credentials_count: Some(2),
+            max_possible_remaining_resident_credentials_count: Some(8),
+            ..Default::default()
+        });
+        device.add_upcoming_ctap2_request(&request(CredManagementCommand::EnumerateRPsBegin));
+        device.add_upcoming_ctap_response(CredentialManagementResponse {
+            rp: Some(rp.clone()),
+            rp_id_hash: Some(rp.hash()),
+            total_rps: Some(1),
+            ..Default::default()
+        });
+        let mut begin = request(CredManagementCommand::EnumerateCredentialsBegin(rp.hash()));
+        device.add_upcoming_ctap2_request(&begin);
+        device.add_upcoming_ctap_response(CredentialManagementResponse {
+            total_credentials: Some(2),
+            ..credential(1)
+        });
+        // The pinUvAuthParam is not updated for the follow-up requests
+        begin.subcommand = CredManagementCommand::EnumerateCredentialsGetNextCredential;
+        device.add_upcoming_ctap2_request(&begin);
+        device.add_upcoming_ctap_response(credential(2));
+
+        let (status_tx, status_rx) = channel();
+        let callback = StateCallback::new(Box::new(|_| {}));
+        assert!(credential_management(
+            &mut device,
+            Some(PinUvAuthResult::SuccessGetPinUvAuthTokenUsingPinWithPermissions(token.clone())),
+            CredManagementCmd::GetCredentials,
+            status_tx,
+            callback,
+            &|| true
+        ));
+
+        let mut progress = vec![];
+        let list = loop {
+            match status_rx.recv().unwrap() {
+                StatusUpdate::InteractiveManagement(
+                    InteractiveUpdate::CredentialManagementUpdate((result, _)),
+                ) => match result {
+                    CredentialManagementResult::EnumerationProgress(remaining) => {
+                        progress.push(remaining)
+                    }
+                    CredentialManagementResult::CredentialList(list) => break list,
+                    other => panic!("Unexpected result {:?}", other),
+                },
+                other => panic!("Unexpected status {:?}", other),
+            }
+        };
+        assert_eq!(progress, vec![2, 1, 0]);
+        assert_eq!(list.existing_resident_credentials_count, 2);
+        assert_eq!(list.credential_list[0].credentials.len(), 2);
+    }
+
+    #[test]
+    fn test_set_min_pin_length_limits() {
+        let mut device = Device::new_skipping_serialization("ctap2/config_limits").unwrap();
+        device.set_authenticator_info(AuthenticatorInfo {
+            options: AuthenticatorOptions {
+                authnr_cfg: Some(true),
+                set_min_pin_length: Some(true),
+                ..Default::default()
+            },
+            extensions: vec![String::from("minPinLength")],
+            min_pin_length: Some(6),
+            max_rpids_for_set_min_pin_length: Some(1),
+            ..Default::default()
+        });
+
+        let check = |device: &mut Device, params: SetMinPINLength| {
+            let (tx, rx) = channel();
+            let callback = StateCallback::new(Box::new(move |res| tx.send(res).unwrap()));
+            let (status_tx, _status_rx) = channel();
+            assert!(!configure_authenticator(
+                device,
+                None,
+                AuthConfigCommand::SetMinPINLength(params),
+                status_tx,
+                callback,
+                &|| true
+            ));
+            rx.recv().unwrap()
+        };
+
+        // Nothing is sent to the device, so no requests are queued up.
+        assert_matches!(
+            check(
+                &mut device,
+                SetMinPINLength {
+                    new_min_pin_length: Some(4),
+                    min_pin_length_rpids: None,
+                    force_change_pin: None,
+                }
+            ),
+            Err(AuthenticatorError::AuthConfigError(
+                AuthConfigError::MinPINLengthDecreased(4, 6)
+            ))
+        );
+        assert_matches!(
+            check(
+                &mut device,
+                SetMinPINLength {
+                    new_min_pin_length: None,
+                    min_pin_length_rpids: Some(vec![
+                        String::from("example.com"),
+                        String::from("example.org"),
+                    ]),
+                    force_change_pin: None,
+                }
+            ),
+            Err(AuthenticatorError::AuthConfigError(
+                AuthConfigError::TooManyMinPINLengthRPIDs(2, 1)
+            ))
+        );
+
+        let mut info = device.get_authenticator_info().unwrap().clone();
+        info.extensions.clear();
+        device.set_authenticator_info(info.clone());
+        assert_matches!(
+            check(
+                &mut device,
+                SetMinPINLength {
+                    new_min_pin_length: None,
+                    min_pin_length_rpids: Some(vec![String::from("example.com")]),
+                    force_change_pin: None,
+                }
+            ),
+            Err(AuthenticatorError::AuthConfigError(
+                AuthConfigError::MinPINLengthExtensionUnsupported
+            ))
+        );
+
+        info.options.set_min_pin_length = None;
+        device.set_authenticator_info(info);
+        assert_matches!(
+            check(
+                &mut device,
+                SetMinPINLength {
+                    new_min_pin_length: Some(8),
+                    min_pin_length_rpids: None,
+                    force_change_pin: None,
+                }
+            ),
+            Err(AuthenticatorError::AuthConfigError(
+                AuthConfigError::SetMinPINLengthUnsupported
+            ))
+        );
+    }
+
+    #[test]
+    fn test_check_compatibility() {
+        let mut args = RegisterArgs {
+            resident_key_req: ResidentKeyRequirement::Required,
+            ..test_register_args()
+        };
+
+        let mut device = Device::new("ctap2/check_compatibility").unwrap();
+        device.set_authenticator_info(AuthenticatorInfo {
+            options: AuthenticatorOptions {
+                resident_key: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        assert_matches!(check_compatibility(&device, &args), Ok(()));
+
+        // CTAP1 devices can't create discoverable credentials
+        let mut u2f_device = Device::new("ctap2/check_compatibility_u2f").unwrap();
+        u2f_device.downgrade_to_ctap1();
+        assert_matches!(
+            check_compatibility(&u2f_device, &args),
+            Err(UnsupportedOption::ResidentKey)
+        );
+
+        // Enforcing credProtect requires support for the extension
+        args.extensions.credential_protection_policy =
+            Some(CredentialProtectionPolicy::UserVerificationRequired);
+        args.extensions.enforce_credential_protection_policy = Some(true);
+        assert_matches!(
+            check_compatibility(&device, &args),
+            Err(UnsupportedOption::CredProtect)
+        );
+        args.extensions.enforce_credential_protection_policy = None;
+
+        // A largeBlobKey needs the largeBlobs option
+        args.extensions.large_blob_key = Some(true);
+        assert_matches!(
+            check_compatibility(&device, &args),
+            Err(UnsupportedOption::LargeBlobs)
+        );
+        device.set_authenticator_info(AuthenticatorInfo {
+            options: AuthenticatorOptions {
+                resident_key: true,
+                large_blobs: Some(true),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        assert_matches!(check_compatibility(&device, &args), Ok(()));
+
+        // A credBlob has to fit, if the authenticator supports the extension. Otherwise the
+        // extension is dropped.
+        args.extensions.cred_blob = Some(vec![0; 33]);
+        assert_matches!(check_compatibility(&device, &args), Ok(()));
+        let mut info = AuthenticatorInfo {
+            options: AuthenticatorOptions {
+                resident_key: true,
+                large_blobs: Some(true),
+                ..Default::default()
+            },
+            extensions: vec!["credBlob".to_string()],
+            ..Default::default()
+        };
+        device.set_authenticator_info(info.clone());
+        assert_matches!(
+            check_compatibility(&device, &args),
+            Err(UnsupportedOption::CredBlob)
+        );
+        info.max_cred_blob_length = Some(64);
+        device.set_authenticator_info(info);
+        assert_matches!(check_compatibility(&device, &args), Ok(()));
+        args.extensions.cred_blob = Some(vec![0; 65]);
+        assert_matches!(
+            check_compatibility(&device, &args),
+            Err(UnsupportedOption::CredBlob)
+        );
+        args.extensions.cred_blob = None;
+
+        // Platform-managed enterprise attestation needs to be enabled
+        args.enterprise_attestation = Some(2);
+        assert_matches!(
+            check_compatibility(&device, &args),
+            Err(UnsupportedOption::EnterpriseAttestation)
+        );
+        args.enterprise_attestation = Some(1);
+        assert_matches!(check_compatibility(&device, &args), Ok(()));
+
+        // Only listing the devices that can fulfill the request
+        let large_blobs_info = AuthenticatorInfo {
+            versions: vec![AuthenticatorVersion::FIDO_2_1],
+            options: AuthenticatorOptions {
+                resident_key: true,
+                large_blobs: Some(true),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let u2f_info = AuthenticatorInfo {
+            versions: vec![AuthenticatorVersion::U2F_V2],
+            ..Default::default()
+        };
+        let infos = || {
+            vec![
+                (
+                    String::from("large blobs"),
+                    Some(String::from("Large Blob Key")),
+                    Ok(large_blobs_info.clone()),
+                ),
+                (
+                    String::from("no large blobs"),
+                    None,
+                    Ok(AuthenticatorInfo {
+                        versions: vec![AuthenticatorVersion::FIDO_2_1],
+                        ..Default::default()
+                    }),
+                ),
+                (String::from("u2f info"), None, Ok(u2f_info.clone())),
+                (
+                    String::from("u2f"),
+                    Some(String::from("U2F Key")),
+                    Err(HIDError::UnsupportedCommand),
+                ),
+                (String::from("broken"), None, Err(HIDError::DeviceError)),
+            ]
+        };
+        assert_eq!(
+            eligible_devices(infos(), &args),
+            vec![(
+                String::from("large blobs"),
+                Some(String::from("Large Blob Key"))
+            )]
+        );
+        args.resident_key_req = ResidentKeyRequirement::Discouraged;
+        args.extensions.large_blob_key = None;
+        assert_eq!(
+            eligible_devices(infos(), &args),
+            vec![
+                (
+                    String::from("large blobs"),
+                    Some(String::from("Large Blob Key"))
+                ),
+                (String::from("no large blobs"), None),
+                (String::from("u2f info"), None),
+                (String::from("u2f"), Some(String::from("U2F Key"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_supported_algorithm() {
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.set_authenticator_info(AuthenticatorInfo {
+            algorithms: Some(vec![PublicKeyCredentialParameters {
+                alg: COSEAlgorithm::ES256,
+            }]),
+            ..Default::default()
+        });
+        let args = |algs: &[COSEAlgorithm]| RegisterArgs {
+            pub_cred_params: algs
+                .iter()
+                .map(|&alg| PublicKeyCredentialParameters { alg })
+                .collect(),
+            ..test_register_args()
+        };
+
+        match make_credentials_for(&device, args(&[COSEAlgorithm::EDDSA, COSEAlgorithm::RS256])) {
+            Err(AuthenticatorError::NoSupportedAlgorithm {
+                requested,
+                supported,
+            }) => {
+                assert_eq!(requested, vec![COSEAlgorithm::EDDSA, COSEAlgorithm::RS256]);
+                assert_eq!(supported, vec![COSEAlgorithm::ES256]);
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+        assert!(
+            make_credentials_for(&device, args(&[COSEAlgorithm::RS256, COSEAlgorithm::ES256]))
+                .is_ok()
+        );
+
+        // Without a list of algorithms, it's up to the authenticator
+        device.set_authenticator_info(AuthenticatorInfo::default());
+        assert!(make_credentials_for(&device, args(&[COSEAlgorithm::EDDSA])).is_ok());
+        assert!(device.requests.is_empty());
+    }
+
+    #[test]
+    fn test_make_credentials_long_rp_name() {
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.set_authenticator_info(AuthenticatorInfo::default());
+        let args = RegisterArgs {
+            relying_party: RelyingParty {
+                id: String::from("example.com"),
+                name: Some("a".repeat(100)),
+            },
+            ..test_register_args()
+        };
+        let makecred = make_credentials_for(&device, args).expect("Long RP names are valid");
+        assert_eq!(makecred.rp.name, Some("a".repeat(64)));
+    }
+
+    #[test]
+    fn test_default_rk_cred_protect() {
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.set_authenticator_info(AuthenticatorInfo {
+            extensions: vec![String::from("credProtect")],
+            options: AuthenticatorOptions {
+                resident_key: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let args = |resident_key_req, credential_protection_policy| RegisterArgs {
+            resident_key_req,
+            default_rk_cred_protect: Some(
+                CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIDList,
+            ),
+            extensions: AuthenticationExtensionsClientInputs {
+                credential_protection_policy,
+                ..Default::default()
+            },
+            ..test_register_args()
+        };
+
+        let makecred = make_credentials_for(&device, args(ResidentKeyRequirement::Required, None))
+            .expect("Failed to build request");
+        assert_eq!(
+            makecred.extensions.cred_protect,
+            Some(CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIDList)
+        );
+
+        // Non-discoverable credentials are left alone
+        let makecred =
+            make_credentials_for(&device, args(ResidentKeyRequirement::Discouraged, None))
+                .expect("Failed to build request");
+        assert_eq!(makecred.extensions.cred_protect, None);
+
+        // The relying party has the last word
+        let makecred = make_credentials_for(
+            &device,
+            args(
+                ResidentKeyRequirement::Required,
+                Some(CredentialProtectionPolicy::UserVerificationOptional),
+            ),
+        )
+        .expect("Failed to build request");
+        assert_eq!(
+            makecred.extensions.cred_protect,
+            Some(CredentialProtectionPolicy::UserVerificationOptional)
+        );
+
+        // Only if the authenticator supports credProtect
+        device.authenticator_info.as_mut().unwrap().extensions = vec![];
+        let makecred = make_credentials_for(&device, args(ResidentKeyRequirement::Required, None))
+            .expect("Failed to build request");
+        assert_eq!(makecred.extensions.cred_protect, None);
+    }
+
+    #[test]
+    fn test_register_expired_deadline() {
+        let mut device = Device::new("ctap2/register_deadline").unwrap();
+        device.downgrade_to_ctap1();
+        let (status_tx, _status_rx) = channel();
+        let (tx, rx) = channel();
+        let callback = StateCallback::new(Box::new(move |rv| tx.send(rv).unwrap()));
+        let args = RegisterArgs {
+            deadline: Some(Deadline::after(Duration::ZERO)),
+            ..test_register_args()
+        };
+
+        assert!(!register(&mut device, args, status_tx, callback, &|| true));
+        assert_matches!(rx.recv().unwrap(), Err(AuthenticatorError::Timeout));
+    }
+
+    #[test]
+    fn test_register_batch() {
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.set_authenticator_info(AuthenticatorInfo::default());
+        // Offset of the signature counter in the sample response
+        const COUNTER: usize = 13 + 32 + 1;
+        let mut response = MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2.to_vec();
+        device.add_response(&response);
+        device.add_response(&[u8::from(StatusCode::KeyStoreFull)]);
+        // The counter went backwards
+        let mut regressed = response.clone();
+        regressed[COUNTER + 3] = 0x0a;
+        device.add_response(&regressed);
+        // The same counter again, as authenticators with per-credential counters report it
+        device.add_response(&response);
+
+        let (status_tx, _status_rx) = channel();
+        let args = test_register_args();
+        let hashes = [[1u8; 32], [2u8; 32], [3u8; 32], [4u8; 32]];
+        let batch = register_batch(&mut device, args, &hashes, status_tx, &|| true)
+            .expect("Failed to run the batch");
+
+        assert_eq!(batch.len(), 4);
+        assert_eq!(batch[0].client_data_hash, ClientDataHash([1u8; 32]));
+        assert_eq!(batch[0].sign_count(), Some(11));
+        assert_matches!(
+            batch[1].result,
+            Err(AuthenticatorError::HIDError(HIDError::Command(
+                CommandError::StatusCode(StatusCode::KeyStoreFull, _)
+            )))
+        );
+        assert_eq!(batch[1].sign_count(), None);
+        assert_eq!(batch[2].sign_count(), Some(10));
+        assert_eq!(batch[3].sign_count(), Some(11));
+        assert_eq!(counter_anomalies(&batch), vec![2]);
+
+        // One request per client data hash, and nothing else
+        assert_eq!(device.requests.len(), 4);
+        for (request, hash) in device.requests.iter().zip(hashes.iter()) {
+            match request {
+                RecordedRequest::Ctap2(Command::MakeCredentials, data) => {
+                    assert!(data.windows(32).any(|w| w == hash));
+                }
+                other => panic!("Unexpected request {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_register_batch_with_pin() {
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.set_authenticator_info(AuthenticatorInfo {
+            versions: vec![AuthenticatorVersion::FIDO_2_1],
+            options: AuthenticatorOptions {
+                client_pin: Some(true),
+                pin_uv_auth_token: Some(true),
+                ..Default::default()
+            },
+            pin_protocols: Some(vec![1]),
+            ..Default::default()
+        });
+        device.add_response(&[&[0x00][..], &KEY_AGREEMENT_RESPONSE[..]].concat());
+        let shared_secret = device
+            .establish_shared_secret(&|| true)
+            .expect("Failed to establish shared secret");
+        device.requests.clear();
+
+        // The mc permission is cleared after each registration, so every one needs a new token
+        let hashes = [[1u8; 32], [2u8; 32]];
+        for token in &[[0x01; 32], [0x02; 32]] {
+            let pin_token = shared_secret.encrypt(token).unwrap();
+            let mut response = vec![0x00, 0xa1, 0x02, 0x58, pin_token.len() as u8];
+            response.extend(pin_token);
+            device.add_response(&response);
+            device.add_response(&MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2);
+        }
+
+        let (status_tx, status_rx) = channel();
+        let args = RegisterArgs {
+            user_verification_req: UserVerificationRequirement::Required,
+            pin: Some(Pin::new("1234")),
+            ..test_register_args()
+        };
+        let batch = register_batch(&mut device, args, &hashes, status_tx, &|| true)
+            .expect("Failed to run the batch");
+        assert_eq!(batch.len(), 2);
+        assert!(batch.iter().all(|reg| reg.result.is_ok()));
+
+        // The PIN was given, so the user wasn't asked for it
+        assert!(!status_rx
+            .try_iter()
+            .any(|update| matches!(update, StatusUpdate::PinUvError(_))));
+
+        assert_eq!(device.requests.len(), 4);
+        for (requests, hash) in device.requests.chunks(2).zip(hashes.iter()) {
+            match requests {
+                [RecordedRequest::Ctap2(Command::ClientPin, pin_request), RecordedRequest::Ctap2(Command::MakeCredentials, makecred)] =>
+                {
+                    let pin_request: BTreeMap<u8, Value> =
+                        serde_cbor::from_slice(pin_request).unwrap();
+                    // getPinUvAuthTokenUsingPinWithPermissions, with the mc permission
+                    assert_eq!(pin_request.get(&0x02), Some(&Value::Integer(0x09)));
+                    assert_eq!(pin_request.get(&0x09), Some(&Value::Integer(0x01)));
+                    let makecred: BTreeMap<u8, Value> = serde_cbor::from_slice(makecred).unwrap();
+                    assert_eq!(makecred.get(&0x01), Some(&Value::Bytes(hash.to_vec())));
+                    assert!(makecred.contains_key(&0x08));
+                }
+                other => panic!("Unexpected requests {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_register_always_uv_discouraged() {
+        // Built-in UV, but no pinUvAuthToken support, so UV is requested with the "uv" option
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.set_authenticator_info(AuthenticatorInfo {
+            options: AuthenticatorOptions {
+                user_verification: Some(true),
+                always_uv: Some(true),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        // Offset of the flags in the sample response
+        const FLAGS: usize = 13 + 32;
+        let mut response = MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2.to_vec();
+        response[FLAGS] |= AuthenticatorDataFlags::USER_VERIFIED.bits();
+        device.add_response(&response);
+
+        let (status_tx, _status_rx) = channel();
+        let (tx, rx) = channel();
+        let callback = StateCallback::new(Box::new(move |rv| tx.send(rv).unwrap()));
+        let args = test_register_args();
+
+        assert!(register(&mut device, args, status_tx, callback, &|| true));
+        let result = rx.recv().unwrap().expect("Registration failed");
+        assert!(result
+            .att_obj
+            .auth_data
+            .flags
+            .contains(AuthenticatorDataFlags::USER_VERIFIED));
+
+        // Despite the relying party discouraging it, UV was requested
+        assert_eq!(device.requests.len(), 1);
+        let request: BTreeMap<u8, Value> = match &device.requests[0] {
+            RecordedRequest::Ctap2(Command::MakeCredentials, data) => {
+                serde_cbor::from_slice(data).expect("Invalid request")
+            }
+            other => panic!("Unexpected request {:?}", other),
+        };
+        let uv = match request.get(&0x07) {
+            Some(Value::Map(options)) => options.get(&Value::Text(String::from("uv"))),
+            other => panic!("Unexpected options {:?}", other),
+        };
+        assert_eq!(uv, Some(&Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_register_reject_backup_eligible() {
+        // Offset of the flags in the sample response
+        const FLAGS: usize = 13 + 32;
+        for (reject_backup_eligible, backup_eligible, accepted) in [
+            (true, true, false),
+            (true, false, true),
+            (false, true, true),
+        ] {
+            let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+            device.set_authenticator_info(AuthenticatorInfo::default());
+            let mut response = MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2.to_vec();
+            if backup_eligible {
+                response[FLAGS] |= AuthenticatorDataFlags::BACKUP_ELIGIBLE.bits();
+            }
+            device.add_response(&response);
+
+            let (status_tx, _status_rx) = channel();
+            let (tx, rx) = channel();
+            let callback = StateCallback::new(Box::new(move |rv| tx.send(rv).unwrap()));
+            let args = RegisterArgs {
+                reject_backup_eligible,
+                ..test_register_args()
+            };
+
+            assert!(register(&mut device, args, status_tx, callback, &|| true));
+            match rx.recv().unwrap() {
+                Ok(result) => {
+                    assert!(accepted);
+                    assert_eq!(result.is_backup_eligible(), backup_eligible);
+                }
+                Err(AuthenticatorError::BackupEligibleCredential) => assert!(!accepted),
+                Err(e) => panic!("Unexpected error {:?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_register_rk_preferred_key_store_full() {
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.set_authenticator_info(AuthenticatorInfo {
+            versions: vec![AuthenticatorVersion::FIDO_2_1],
+            options: AuthenticatorOptions {
+                resident_key: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        device.add_response(&[u8::from(StatusCode::KeyStoreFull)]);
+        device.add_response(&MAKE_CREDENTIALS_SAMPLE_RESPONSE_CTAP2);
+
+        let args = |resident_key_req| RegisterArgs {
+            resident_key_req,
+            extensions: AuthenticationExtensionsClientInputs {
+                cred_props: Some(true),
+                ..Default::default()
+            },
+            ..test_register_args()
+        };
+
+        let (status_tx, _status_rx) = channel();
+        let (tx, rx) = channel();
+        let callback = StateCallback::new(Box::new(move |rv| tx.send(rv).unwrap()));
+        assert!(register(
+            &mut device,
+            args(ResidentKeyRequirement::Preferred),
+            status_tx,
+            callback,
+            &|| true
+        ));
+        let result = rx.recv().unwrap().expect("Registration failed");
+        assert_eq!(
+            result.extensions.cred_props.map(|cred_props| cred_props.rk),
+            Some(false)
+        );
+
+        // The registration was retried without a discoverable credential
+        let rk_options: Vec<_> = device
+            .requests
+            .iter()
+            .map(|request| {
+                let request: BTreeMap<u8, Value> = match request {
+                    RecordedRequest::Ctap2(Command::MakeCredentials, data) => {
+                        serde_cbor::from_slice(data).expect("Invalid request")
+                    }
+                    other => panic!("Unexpected request {:?}", other),
+                };
+                match request.get(&0x07) {
+                    Some(Value::Map(options)) => {
+                        options.get(&Value::Text(String::from("rk"))).cloned()
+                    }
+                    other => panic!("Unexpected options {:?}", other),
+                }
+            })
+            .collect();
+        assert_eq!(
+            rk_options,
+            vec![Some(Value::Bool(true)), Some(Value::Bool(false))]
+        );
+
+        // Required discoverable credentials don't fall back
+        device.requests.clear();
+        device.add_response(&[u8::from(StatusCode::KeyStoreFull)]);
+        let (status_tx, _status_rx) = channel();
+        let (tx, rx) = channel();
+        let callback = StateCallback::new(Box::new(move |rv| tx.send(rv).unwrap()));
+        assert!(!register(
+            &mut device,
+            args(ResidentKeyRequirement::Required),
+            status_tx,
+            callback,
+            &|| true
+        ));
+        assert_matches!(
+            rx.recv().unwrap(),
+            Err(AuthenticatorError::HIDError(HIDError::Command(
+                CommandError::StatusCode(StatusCode::KeyStoreFull, _)
+            )))
+        );
+        assert_eq!(device.requests.len(), 1);
+    }
+
+    #[test]
+    fn test_sign_expired_deadline() {
+        let mut device = Device::new("ctap2/sign_deadline").unwrap();
+        device.downgrade_to_ctap1();
+        let (status_tx, _status_rx) = channel();
+        let (tx, rx) = channel();
+        let callback = StateCallback::new(Box::new(move |rv| tx.send(rv).unwrap()));
+        let args = SignArgs {
+            client_data_hash: [0u8; 32],
+            origin: String::from("https://example.com"),
+            relying_party_id: String::from("example.com"),
+            allow_list: vec![PublicKeyCredentialDescriptor {
+                id: vec![0x01; 32],
+                transports: vec![],
+            }],
+            user_verification_req: UserVerificationRequirement::Discouraged,
+            user_presence_req: true,
+            extensions: Default::default(),
+            pin: None,
+            use_ctap1_fallback: false,
+            reassert_selected_credential: false,
+            reject_backup_eligible: false,
+            deadline: Some(Deadline::after(Duration::ZERO)),
+        };
+
+        assert!(!sign(&mut device, args, status_tx, callback, &|| true));
+        assert_matches!(rx.recv().unwrap(), Err(AuthenticatorError::Timeout));
+    }
+
+    #[test]
+    fn test_sign_no_matching_credentials() {
+        let mut device =
+            Device::new_skipping_serialization("ctap2/no_matching_credentials").unwrap();
+        device.downgrade_to_ctap1();
+        let rp = RelyingParty::from("example.com");
+        let client_data_hash = ClientDataHash([0u8; 32]);
+        let allow_list = vec![
+            PublicKeyCredentialDescriptor {
+                id: vec![0x01; 32],
+                transports: vec![],
+            },
+            PublicKeyCredentialDescriptor {
+                id: vec![0x02; 32],
+                transports: vec![],
+            },
+        ];
+        // Pre-flighting checks every credential in the allow list
+        for credential in &allow_list {
+            device.add_upcoming_ctap1_request(&CheckKeyHandle {
+                key_handle: &credential.id,
+                client_data_hash: client_data_hash.as_ref(),
+                rp: &rp,
+            });
+            device.add_upcoming_ctap_error(HIDError::ApduStatus(ApduErrorStatus::WrongData));
+        }
+        // followed by a dummy request, to collect a touch
+        device.add_upcoming_ctap1_request(&dummy_make_credentials_cmd());
+        device.add_upcoming_ctap_error(HIDError::DeviceError);
+
+        let (status_tx, _status_rx) = channel();
+        let (tx, rx) = channel();
+        let callback = StateCallback::new(Box::new(move |rv| tx.send(rv).unwrap()));
+        let args = SignArgs {
+            client_data_hash: client_data_hash.0,
+            origin: String::from("https://example.com"),
+            relying_party_id: String::from("example.com"),
+            allow_list,
+            user_verification_req: UserVerificationRequirement::Discouraged,
+            user_presence_req: true,
+            extensions: Default::default(),
+            pin: None,
+            use_ctap1_fallback: false,
+            reassert_selected_credential: false,
+            reject_backup_eligible: false,
+            deadline: None,
+        };
+
+        assert!(!sign(&mut device, args, status_tx, callback, &|| true));
+        assert_matches!(
+            rx.recv().unwrap(),
+            Err(AuthenticatorError::NoMatchingCredentials(Some(true)))
+        );
+    }
+
+    #[test]
+    fn test_sign_reassert_selected_credential() {
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.set_authenticator_info(AuthenticatorInfo {
+            versions: vec![AuthenticatorVersion::FIDO_2_1],
+            options: AuthenticatorOptions {
+                client_pin: Some(true),
+                pin_uv_auth_token: Some(true),
+                ..Default::default()
+            },
+            pin_protocols: Some(vec![1]),
+            ..Default::default()
+        });
+        device.add_response(&[&[0x00][..], &KEY_AGREEMENT_RESPONSE[..]].concat());
+        let shared_secret = device
+            .establish_shared_secret(&|| true)
+            .expect("Failed to establish shared secret");
+        device.requests.clear();
+
+        let pin_token_response = |token: &[u8; 32]| {
+            let pin_token = shared_secret.encrypt(token).unwrap();
+            let mut response = vec![0x00, 0xa1, 0x02, 0x58, pin_token.len() as u8];
+            response.extend(pin_token);
+            response
+        };
+        // The sample ends with numberOfCredentials = 1
+        let mut first = GET_ASSERTION_SAMPLE_RESPONSE_CTAP2.to_vec();
+        *first.last_mut().unwrap() = 0x02;
+        // Subsequent responses carry no numberOfCredentials
+        let mut next = GET_ASSERTION_SAMPLE_RESPONSE_CTAP2.to_vec();
+        next.truncate(next.len() - 2);
+        next[1] = 0xA4; // map(4)
+        device.add_response(&pin_token_response(&[0x01; 32]));
+        device.add_response(&first);
+        device.add_response(&next);
+        // The assertion is repeated with a new token
+        device.add_response(&pin_token_response(&[0x02; 32]));
+        device.add_response(&GET_ASSERTION_SAMPLE_RESPONSE_CTAP2);
+
+        let (status_tx, status_rx) = channel();
+        let selector = thread::spawn(move || {
+            for update in status_rx {
+                if let StatusUpdate::SelectResultNotice(tx, users) = update {
+                    assert_eq!(users.len(), 2);
+                    tx.send(Some(1)).unwrap();
+                }
+            }
+        });
+        let (tx, rx) = channel();
+        let callback = StateCallback::new(Box::new(move |rv| tx.send(rv).unwrap()));
+        let args = SignArgs {
+            client_data_hash: [0u8; 32],
+            origin: String::from("https://example.com"),
+            relying_party_id: String::from("example.com"),
+            allow_list: vec![],
+            user_verification_req: UserVerificationRequirement::Required,
+            user_presence_req: true,
+            extensions: Default::default(),
+            pin: Some(Pin::new("1234")),
+            use_ctap1_fallback: false,
+            reassert_selected_credential: true,
+            reject_backup_eligible: false,
+            deadline: None,
+        };
+
+        assert!(sign(&mut device, args, status_tx, callback, &|| true));
+        assert!(rx.recv().unwrap().is_ok());
+        selector.join().unwrap();
+
+        let (first_assertion, reassertion) = match &device.requests[..] {
+            [RecordedRequest::Ctap2(Command::ClientPin, _), RecordedRequest::Ctap2(Command::GetAssertion, first_assertion), RecordedRequest::Ctap2(Command::GetNextAssertion, _), RecordedRequest::Ctap2(Command::ClientPin, _), RecordedRequest::Ctap2(Command::GetAssertion, reassertion)] => {
+                (first_assertion, reassertion)
+            }
+            other => panic!("Unexpected requests {:?}", other),
+        };
+        let first_assertion: BTreeMap<u8, Value> = serde_cbor::from_slice(first_assertion).unwrap();
+        let reassertion: BTreeMap<u8, Value> = serde_cbor::from_slice(reassertion).unwrap();
+        assert!(!first_assertion.contains_key(&0x03));
+        // The allow list only holds the selected credential
+        assert!(matches!(reassertion.get(&0x03), Some(Value::Array(list)) if list.len() == 1));
+        // pinUvAuthParam of the new token
+        assert!(reassertion.contains_key(&0x06));
+        assert_ne!(first_assertion.get(&0x06), reassertion.get(&0x06));
+    }
+}
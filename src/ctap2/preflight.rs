@@ -228,7 +228,7 @@ pub mod tests {
                 AAGuid, AttestedCredentialData, AuthenticatorData, AuthenticatorDataFlags,
                 Extension,
             },
-            commands::{CommandError, StatusCode},
+            commands::{make_credentials::dummy_make_credentials_cmd, CommandError, StatusCode},
             server::{AuthenticationExtensionsClientOutputs, AuthenticatorAttachment, Transport},
         },
         transport::{
@@ -297,9 +297,12 @@ pub mod tests {
                 },
                 signature: vec![],
                 user: None,
+                number_of_credentials: None,
+                user_selected: None,
             },
             attachment: AuthenticatorAttachment::Platform,
             extensions: AuthenticationExtensionsClientOutputs::default(),
+            pin_uv_auth_protocol: None,
         }
     }
 
@@ -527,4 +530,77 @@ pub mod tests {
         let res = silently_discover_credentials(&mut dev, &allow_list, &rp, &client_data_hash);
         assert_eq!(res, vec![allow_list[1].clone()]);
     }
+
+    #[test]
+    fn test_preflight_exclude_list_ctap2() {
+        let mut dev = Device::new_skipping_serialization("preflight").unwrap();
+        make_device_with_pin(&mut dev);
+        let mut info = dev.get_authenticator_info().unwrap().clone();
+        info.max_credential_count_in_list = Some(2);
+        info.max_credential_id_length = Some(20);
+        dev.set_authenticator_info(info);
+        let exclude_list = vec![
+            new_credential(4, 50), // too long
+            new_credential(3, 4),
+            new_credential(2, 4),
+            new_credential(1, 4),
+        ];
+
+        // Sent in batches of two, the last one holds a known credential
+        let mut makecred = dummy_make_credentials_cmd();
+        makecred.exclude_list = exclude_list.clone();
+        let rp = makecred.rp.clone();
+        dev.add_upcoming_ctap2_request(&new_silent_assert(&rp, &exclude_list[1..=2]));
+        dev.add_upcoming_ctap2_request(&new_silent_assert(&rp, &exclude_list[3..]));
+        dev.add_upcoming_ctap_error(HIDError::Command(CommandError::StatusCode(
+            StatusCode::NoCredentials,
+            None,
+        )));
+        dev.add_upcoming_ctap_response(vec![new_assertion_response(&rp, Some(&exclude_list[3]))]);
+        assert_matches!(
+            makecred.preflight_exclude_list(&mut dev, None),
+            Err(AuthenticatorError::CredentialExcluded)
+        );
+        assert_eq!(makecred.exclude_list, vec![exclude_list[3].clone()]);
+
+        // None of them is known, so the request is sent without exclude list
+        let mut makecred = dummy_make_credentials_cmd();
+        makecred.exclude_list = exclude_list.clone();
+        dev.add_upcoming_ctap2_request(&new_silent_assert(&rp, &exclude_list[1..=2]));
+        dev.add_upcoming_ctap2_request(&new_silent_assert(&rp, &exclude_list[3..]));
+        for _ in 0..2 {
+            dev.add_upcoming_ctap_error(HIDError::Command(CommandError::StatusCode(
+                StatusCode::NoCredentials,
+                None,
+            )));
+        }
+        assert_matches!(makecred.preflight_exclude_list(&mut dev, None), Ok(()));
+        assert!(makecred.exclude_list.is_empty());
+
+        // Nothing to do for an empty list
+        let mut makecred = dummy_make_credentials_cmd();
+        assert_matches!(makecred.preflight_exclude_list(&mut dev, None), Ok(()));
+    }
+
+    #[test]
+    fn test_preflight_exclude_list_ctap1() {
+        let mut dev = Device::new_skipping_serialization("preflight").unwrap();
+        make_device_simple_u2f(&mut dev);
+        let exclude_list = vec![new_credential(2, 4), new_credential(1, 4)];
+        let mut makecred = dummy_make_credentials_cmd();
+        makecred.exclude_list = exclude_list.clone();
+        let rp = makecred.rp.clone();
+        let cdh = makecred.client_data_hash.clone();
+        dev.add_upcoming_ctap1_request(&new_check_key_handle(&rp, &cdh, &exclude_list[0]));
+        dev.add_upcoming_ctap_error(HIDError::ApduStatus(
+            ApduErrorStatus::WrongData, // Not a registered cred
+        ));
+        dev.add_upcoming_ctap1_request(&new_check_key_handle(&rp, &cdh, &exclude_list[1]));
+        dev.add_upcoming_ctap_response(());
+        assert_matches!(
+            makecred.preflight_exclude_list(&mut dev, None),
+            Err(AuthenticatorError::CredentialExcluded)
+        );
+        assert_eq!(makecred.exclude_list, vec![exclude_list[1].clone()]);
+    }
 }
@@ -1,5 +1,6 @@
 use crate::crypto::COSEAlgorithm;
-use crate::{errors::AuthenticatorError, AuthenticatorTransports, KeyHandle};
+use crate::errors::{AuthenticatorError, UnsupportedOption};
+use crate::{AuthenticatorTransports, KeyHandle};
 use base64::Engine;
 use serde::de::MapAccess;
 use serde::{
@@ -11,6 +12,7 @@ use serde_bytes::{ByteBuf, Bytes};
 use sha2::{Digest, Sha256};
 use std::convert::{Into, TryFrom};
 use std::fmt;
+use std::net::{IpAddr, Ipv6Addr};
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct RpIdHash(pub [u8; 32]);
@@ -38,8 +40,28 @@ impl RpIdHash {
             Ok(RpIdHash(payload))
         }
     }
+
+    /// The SHA-256 hash of `rp_id`, as authenticators store it.
+    pub fn from_rp_id(rp_id: &str) -> RpIdHash {
+        let mut hasher = Sha256::new();
+        hasher.update(rp_id);
+
+        let mut output = [0u8; 32];
+        output.copy_from_slice(hasher.finalize().as_slice());
+
+        RpIdHash(output)
+    }
+
+    /// Whether this is the hash of `rp_id`, e.g. to check the authenticator data of an
+    /// assertion against the RP ID the relying party expects.
+    pub fn matches(&self, rp_id: &str) -> bool {
+        *self == RpIdHash::from_rp_id(rp_id)
+    }
 }
 
+const MAX_RP_ID_LENGTH: usize = 253;
+const MAX_RP_NAME_LENGTH: usize = 64;
+
 // NOTE: WebAuthn requires all fields and CTAP2 does not.
 #[derive(Debug, Serialize, Clone, Default, Deserialize, PartialEq, Eq)]
 pub struct RelyingParty {
@@ -59,14 +81,158 @@ impl RelyingParty {
         }
     }
 
+    /// Rejects RP IDs that are empty or longer than a domain name can be (253 bytes).
+    /// Authenticators tend to fail with opaque errors on those. The name is not checked, see
+    /// `truncate_name()`.
+    pub fn validate(&self) -> Result<(), AuthenticatorError> {
+        if self.id.is_empty() || self.id.len() > MAX_RP_ID_LENGTH {
+            return Err(AuthenticatorError::InvalidRelyingPartyInput);
+        }
+        Ok(())
+    }
+
+    /// Truncates the name to the 64 bytes authenticators are required to store, without
+    /// splitting a UTF-8 character. Clients may do this according to CTAP 2.1, section 6.8.
+    pub fn truncate_name(&mut self) {
+        if let Some(name) = self.name.as_mut() {
+            if name.len() > MAX_RP_NAME_LENGTH {
+                let end = (0..=MAX_RP_NAME_LENGTH)
+                    .rev()
+                    .find(|&i| name.is_char_boundary(i))
+                    .unwrap_or(0);
+                name.truncate(end);
+            }
+        }
+    }
+
     pub fn hash(&self) -> RpIdHash {
-        let mut hasher = Sha256::new();
-        hasher.update(&self.id);
+        RpIdHash::from_rp_id(&self.id)
+    }
 
-        let mut output = [0u8; 32];
-        output.copy_from_slice(hasher.finalize().as_slice());
+    /// Whether a credential for this RP ID may be created or used by `origin`, following
+    /// https://www.w3.org/TR/webauthn-2/#rp-id: The origin has to be secure, and the RP ID
+    /// has to be its host, or a parent domain of it. Ports are not part of the RP ID.
+    ///
+    /// Note that we don't consult the public suffix list, so an RP ID of e.g. "com" is not
+    /// rejected here.
+    pub fn is_valid_for_origin(&self, origin: &Origin) -> bool {
+        if !origin.is_secure() {
+            return false;
+        }
+        let rp_id = self.id.to_ascii_lowercase();
+        if origin.host == rp_id {
+            return true;
+        }
+        // IP addresses have no parent domains
+        !origin.is_ip_address() && origin.host.ends_with(&format!(".{rp_id}"))
+    }
+}
 
-        RpIdHash(output)
+/// A parsed (tuple) origin, e.g. `https://login.example.com:8443`, as opposed to the
+/// free-form origin string in `CollectedClientData`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    /// Lowercase scheme, e.g. "https"
+    pub scheme: String,
+    /// Lowercase host, IPv6 addresses without the brackets
+    pub host: String,
+    /// The port, if one was given explicitly
+    pub port: Option<u16>,
+}
+
+impl Origin {
+    /// Parses `scheme://host[:port]`. Userinfo, paths (other than a single trailing "/"),
+    /// queries and fragments are rejected, since they are not part of an origin.
+    pub fn parse(origin: &str) -> Result<Self, AuthenticatorError> {
+        let invalid = || AuthenticatorError::InvalidRelyingPartyInput;
+        let (scheme, rest) = origin.split_once("://").ok_or_else(invalid)?;
+        let authority = rest.strip_suffix('/').unwrap_or(rest);
+        if scheme.is_empty()
+            || !scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+            || authority.is_empty()
+            || authority.contains(|c| matches!(c, '/' | '?' | '#' | '@'))
+        {
+            return Err(invalid());
+        }
+
+        let (host, port) = if let Some(ipv6) = authority.strip_prefix('[') {
+            let (host, rest) = ipv6.split_once(']').ok_or_else(invalid)?;
+            if host.parse::<Ipv6Addr>().is_err() {
+                return Err(invalid());
+            }
+            match rest {
+                "" => (host, None),
+                _ => (host, Some(rest.strip_prefix(':').ok_or_else(invalid)?)),
+            }
+        } else {
+            match authority.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            }
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        let port = match port {
+            Some(port) if !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()) => {
+                Some(port.parse::<u16>().map_err(|_| invalid())?)
+            }
+            Some(_) => return Err(invalid()),
+            None => None,
+        };
+
+        Ok(Origin {
+            scheme: scheme.to_ascii_lowercase(),
+            host: host.to_ascii_lowercase(),
+            port,
+        })
+    }
+
+    /// The explicit port, or the default port of the scheme.
+    pub fn effective_port(&self) -> Option<u16> {
+        self.port.or(match self.scheme.as_str() {
+            "https" => Some(443),
+            "http" => Some(80),
+            _ => None,
+        })
+    }
+
+    pub fn is_ip_address(&self) -> bool {
+        self.host.parse::<IpAddr>().is_ok()
+    }
+
+    /// HTTPS origins, and HTTP origins on the local machine, which browsers treat as secure
+    /// contexts for development.
+    pub fn is_secure(&self) -> bool {
+        match self.scheme.as_str() {
+            "https" => true,
+            "http" => {
+                self.host == "localhost"
+                    || self.host.ends_with(".localhost")
+                    || self
+                        .host
+                        .parse::<IpAddr>()
+                        .map_or(false, |ip| ip.is_loopback())
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}://", self.scheme)?;
+        if self.host.contains(':') {
+            write!(f, "[{}]", self.host)?;
+        } else {
+            write!(f, "{}", self.host)?;
+        }
+        if let Some(port) = self.port {
+            write!(f, ":{port}")?;
+        }
+        Ok(())
     }
 }
 
@@ -80,11 +246,50 @@ pub struct PublicKeyCredentialUserEntity {
     pub display_name: Option<String>,
 }
 
+/// The signature algorithms whose credential public keys we can turn into a `COSEKey` (and
+/// a DER SubjectPublicKeyInfo), in order of preference.
+pub const SUPPORTED_ALGORITHMS: [COSEAlgorithm; 3] = [
+    COSEAlgorithm::ES256,
+    COSEAlgorithm::EDDSA,
+    COSEAlgorithm::RS256,
+];
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PublicKeyCredentialParameters {
     pub alg: COSEAlgorithm,
 }
 
+impl PublicKeyCredentialParameters {
+    /// The algorithms most relying parties ask for, which are all `SUPPORTED_ALGORITHMS`:
+    /// ES256, EdDSA and RS256, in this order.
+    pub fn default_algorithms() -> Vec<PublicKeyCredentialParameters> {
+        SUPPORTED_ALGORITHMS
+            .iter()
+            .map(|&alg| PublicKeyCredentialParameters { alg })
+            .collect()
+    }
+
+    /// Checks a pubKeyCredParams list before it is sent to an authenticator. An empty list
+    /// is rejected as `InvalidRelyingPartyInput`, and a list without any of the
+    /// `SUPPORTED_ALGORITHMS` as `UnsupportedOption::PubCredParams`.
+    pub fn validate_list(
+        params: &[PublicKeyCredentialParameters],
+    ) -> Result<(), AuthenticatorError> {
+        if params.is_empty() {
+            return Err(AuthenticatorError::InvalidRelyingPartyInput);
+        }
+        if !params
+            .iter()
+            .any(|param| SUPPORTED_ALGORITHMS.contains(&param.alg))
+        {
+            return Err(AuthenticatorError::UnsupportedOption(
+                UnsupportedOption::PubCredParams,
+            ));
+        }
+        Ok(())
+    }
+}
+
 impl TryFrom<i32> for PublicKeyCredentialParameters {
     type Error = AuthenticatorError;
     fn try_from(arg: i32) -> Result<Self, Self::Error> {
@@ -164,13 +369,81 @@ impl<'de> Deserialize<'de> for PublicKeyCredentialParameters {
     }
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize, Eq, Clone)]
-#[serde(rename_all = "lowercase")]
+/// Authenticator transports, as used in credential descriptors and in getInfo.
+/// See https://www.w3.org/TR/webauthn-3/#enum-transport
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Transport {
     USB,
     NFC,
     BLE,
     Internal,
+    Hybrid,
+    SmartCard,
+    /// Transports we don't know about are kept as-is, so they can be passed on unchanged
+    Unknown(String),
+}
+
+impl Transport {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Transport::USB => "usb",
+            Transport::NFC => "nfc",
+            Transport::BLE => "ble",
+            Transport::Internal => "internal",
+            Transport::Hybrid => "hybrid",
+            Transport::SmartCard => "smart-card",
+            Transport::Unknown(s) => s,
+        }
+    }
+}
+
+impl From<&str> for Transport {
+    fn from(s: &str) -> Self {
+        match s {
+            "usb" => Transport::USB,
+            "nfc" => Transport::NFC,
+            "ble" => Transport::BLE,
+            "internal" => Transport::Internal,
+            "hybrid" => Transport::Hybrid,
+            "smart-card" => Transport::SmartCard,
+            _ => Transport::Unknown(s.to_string()),
+        }
+    }
+}
+
+impl Serialize for Transport {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Transport {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TransportVisitor;
+
+        impl<'de> Visitor<'de> for TransportVisitor {
+            type Value = Transport;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: SerdeError,
+            {
+                Ok(Transport::from(v))
+            }
+        }
+
+        deserializer.deserialize_str(TransportVisitor)
+    }
 }
 
 impl From<AuthenticatorTransports> for Vec<Transport> {
@@ -305,13 +578,28 @@ pub enum UserVerificationRequirement {
     Required,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+// The variants are ordered by protection level, weakest first.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
 pub enum CredentialProtectionPolicy {
     UserVerificationOptional = 1,
     UserVerificationOptionalWithCredentialIDList = 2,
     UserVerificationRequired = 3,
 }
 
+impl TryFrom<u8> for CredentialProtectionPolicy {
+    type Error = AuthenticatorError;
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        match v {
+            1 => Ok(CredentialProtectionPolicy::UserVerificationOptional),
+            2 => Ok(CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIDList),
+            3 => Ok(CredentialProtectionPolicy::UserVerificationRequired),
+            _ => Err(AuthenticatorError::UnsupportedOption(
+                UnsupportedOption::CredProtect,
+            )),
+        }
+    }
+}
+
 impl Serialize for CredentialProtectionPolicy {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -339,17 +627,16 @@ impl<'de> Deserialize<'de> for CredentialProtectionPolicy {
             where
                 E: SerdeError,
             {
-                match v {
-                    1 => Ok(CredentialProtectionPolicy::UserVerificationOptional),
-                    2 => Ok(
-                        CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIDList,
-                    ),
-                    3 => Ok(CredentialProtectionPolicy::UserVerificationRequired),
-                    _ => Err(SerdeError::invalid_value(
-                        Unexpected::Unsigned(v),
-                        &"valid CredentialProtectionPolicy",
-                    )),
-                }
+                // Noncompliant firmware may echo anything in the credProtect extension output
+                u8::try_from(v)
+                    .ok()
+                    .and_then(|v| CredentialProtectionPolicy::try_from(v).ok())
+                    .ok_or_else(|| {
+                        SerdeError::invalid_value(
+                            Unexpected::Unsigned(v),
+                            &"valid CredentialProtectionPolicy",
+                        )
+                    })
             }
         }
 
@@ -360,23 +647,54 @@ impl<'de> Deserialize<'de> for CredentialProtectionPolicy {
 #[derive(Clone, Debug, Default)]
 pub struct AuthenticationExtensionsClientInputs {
     pub app_id: Option<String>,
+    /// An opaque blob to store with the new credential. Registration fails, if it is longer
+    /// than the authenticator allows (maxCredBlobLength).
+    pub cred_blob: Option<Vec<u8>>,
     pub cred_props: Option<bool>,
     pub credential_protection_policy: Option<CredentialProtectionPolicy>,
     pub enforce_credential_protection_policy: Option<bool>,
     pub hmac_create_secret: Option<bool>,
+    /// Salts to evaluate the hmac-secret of a new credential with during registration
+    /// ("hmac-secret-mc"). Only used together with `hmac_create_secret`, and ignored by
+    /// authenticators that don't support it.
+    pub hmac_get_secret: Option<HmacGetSecretInput>,
+    /// Request a largeBlobKey for the new credential. Registration fails, if the
+    /// authenticator does not support the largeBlobs option.
+    pub large_blob_key: Option<bool>,
     pub min_pin_length: Option<bool>,
 }
 
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HmacGetSecretInput {
+    pub salt1: [u8; 32],
+    pub salt2: Option<[u8; 32]>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HmacGetSecretOutput {
+    pub output1: [u8; 32],
+    pub output2: Option<[u8; 32]>,
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct CredentialProperties {
     pub rk: bool,
+    /// The name of the authenticator model, if it is known (see `AAGuid::metadata()`).
+    pub authenticator_display_name: Option<String>,
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct AuthenticationExtensionsClientOutputs {
     pub app_id: Option<bool>,
+    /// Whether the authenticator stored the credBlob.
+    pub cred_blob: Option<bool>,
     pub cred_props: Option<CredentialProperties>,
+    /// The protection level the authenticator actually applied, if credProtect was requested.
+    pub credential_protection_policy: Option<CredentialProtectionPolicy>,
     pub hmac_create_secret: Option<bool>,
+    pub hmac_get_secret: Option<HmacGetSecretOutput>,
+    /// The key to encrypt the large-blob array entry of the new credential with.
+    pub large_blob_key: Option<[u8; 32]>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -389,10 +707,158 @@ pub enum AuthenticatorAttachment {
 #[cfg(test)]
 mod test {
     use super::{
-        COSEAlgorithm, PublicKeyCredentialDescriptor, PublicKeyCredentialParameters,
-        PublicKeyCredentialUserEntity, RelyingParty, Transport,
+        COSEAlgorithm, CredentialProtectionPolicy, Origin, PublicKeyCredentialDescriptor,
+        PublicKeyCredentialParameters, PublicKeyCredentialUserEntity, RelyingParty, RpIdHash,
+        Transport,
     };
-    use serde_cbor::from_slice;
+    use crate::ctap2::attestation::Extension;
+    use crate::errors::{AuthenticatorError, UnsupportedOption};
+    use serde_cbor::{from_slice, to_vec};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_validate_pub_cred_params() {
+        assert_matches!(
+            PublicKeyCredentialParameters::validate_list(&[]),
+            Err(AuthenticatorError::InvalidRelyingPartyInput)
+        );
+        // Not a signature algorithm
+        let params = [PublicKeyCredentialParameters {
+            alg: COSEAlgorithm::A128GCM,
+        }];
+        assert_matches!(
+            PublicKeyCredentialParameters::validate_list(&params),
+            Err(AuthenticatorError::UnsupportedOption(
+                UnsupportedOption::PubCredParams
+            ))
+        );
+        // A signature algorithm, but one whose keys we can't parse
+        let params = [PublicKeyCredentialParameters {
+            alg: COSEAlgorithm::ES384,
+        }];
+        assert_matches!(
+            PublicKeyCredentialParameters::validate_list(&params),
+            Err(AuthenticatorError::UnsupportedOption(
+                UnsupportedOption::PubCredParams
+            ))
+        );
+        let params = [
+            PublicKeyCredentialParameters {
+                alg: COSEAlgorithm::A128GCM,
+            },
+            PublicKeyCredentialParameters {
+                alg: COSEAlgorithm::ES256,
+            },
+        ];
+        assert_matches!(
+            PublicKeyCredentialParameters::validate_list(&params),
+            Ok(())
+        );
+        assert_matches!(
+            PublicKeyCredentialParameters::validate_list(
+                &PublicKeyCredentialParameters::default_algorithms()
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_cred_protect_policy_serialization() {
+        for (value, policy) in [
+            (1, CredentialProtectionPolicy::UserVerificationOptional),
+            (
+                2,
+                CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIDList,
+            ),
+            (3, CredentialProtectionPolicy::UserVerificationRequired),
+        ] {
+            assert_eq!(CredentialProtectionPolicy::try_from(value).unwrap(), policy);
+            let serialized = to_vec(&policy).unwrap();
+            assert_eq!(serialized, vec![value]);
+            let deserialized: CredentialProtectionPolicy = from_slice(&serialized).unwrap();
+            assert_eq!(deserialized, policy);
+        }
+
+        for value in [0u8, 4, 0xff] {
+            assert!(CredentialProtectionPolicy::try_from(value).is_err());
+        }
+        // 0, 4 and 256
+        for serialized in [&[0x00][..], &[0x04], &[0x19, 0x01, 0x00]] {
+            assert!(from_slice::<CredentialProtectionPolicy>(serialized).is_err());
+        }
+
+        // credProtect echo in the authData extensions: {"credProtect": 0}
+        let extensions = [
+            0xa1, 0x6b, 0x63, 0x72, 0x65, 0x64, 0x50, 0x72, 0x6f, 0x74, 0x65, 0x63, 0x74, 0x00,
+        ];
+        assert!(from_slice::<Extension>(&extensions).is_err());
+        let mut extensions = extensions.to_vec();
+        extensions[13] = 0x03;
+        assert_eq!(
+            from_slice::<Extension>(&extensions).unwrap().cred_protect,
+            Some(CredentialProtectionPolicy::UserVerificationRequired)
+        );
+    }
+
+    #[test]
+    fn test_origin_parse() {
+        let origin = Origin::parse("HTTPS://Login.Example.com:8443").unwrap();
+        assert_eq!(
+            origin,
+            Origin {
+                scheme: String::from("https"),
+                host: String::from("login.example.com"),
+                port: Some(8443),
+            }
+        );
+        assert_eq!(origin.to_string(), "https://login.example.com:8443");
+        assert_eq!(origin.effective_port(), Some(8443));
+
+        let origin = Origin::parse("https://example.com/").unwrap();
+        assert_eq!(origin.port, None);
+        assert_eq!(origin.effective_port(), Some(443));
+
+        let origin = Origin::parse("http://[::1]:8080").unwrap();
+        assert_eq!(origin.host, "::1");
+        assert_eq!(origin.to_string(), "http://[::1]:8080");
+        assert!(origin.is_secure());
+
+        for invalid in [
+            "example.com",
+            "https://",
+            "https://example.com:",
+            "https://example.com:65536",
+            "https://example.com:+1",
+            "https://example.com/path",
+            "https://user@example.com",
+            "https://[::1",
+            "https://[example.com]",
+            "://example.com",
+        ] {
+            assert!(Origin::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_rp_id_valid_for_origin() {
+        let rp = RelyingParty::from("example.com");
+        let valid = |origin: &str| rp.is_valid_for_origin(&Origin::parse(origin).unwrap());
+        assert!(valid("https://example.com"));
+        assert!(valid("https://login.example.com:8443"));
+        assert!(!valid("https://notexample.com"));
+        assert!(!valid("https://example.com.evil.org"));
+        // Insecure
+        assert!(!valid("http://example.com"));
+        assert!(!valid("ftp://example.com"));
+
+        // Local development
+        let rp = RelyingParty::from("localhost");
+        assert!(rp.is_valid_for_origin(&Origin::parse("http://localhost:3000").unwrap()));
+        let rp = RelyingParty::from("127.0.0.1");
+        assert!(rp.is_valid_for_origin(&Origin::parse("http://127.0.0.1:3000").unwrap()));
+        let rp = RelyingParty::from("0.1");
+        assert!(!rp.is_valid_for_origin(&Origin::parse("https://127.0.0.1").unwrap()));
+    }
 
     fn create_user() -> PublicKeyCredentialUserEntity {
         PublicKeyCredentialUserEntity {
@@ -425,6 +891,110 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_rp_id_hash() {
+        let hash = RpIdHash::from_rp_id("example.com");
+        assert_eq!(hash, RelyingParty::from("example.com").hash());
+        assert_eq!(
+            hash.as_ref()[..4],
+            [0xa3, 0x79, 0xa6, 0xf6] // SHA-256("example.com")
+        );
+        assert!(hash.matches("example.com"));
+        assert!(!hash.matches("example.org"));
+        assert!(!hash.matches("Example.com"));
+
+        // A hash stored by the relying party
+        let stored = hash.0.to_vec();
+        assert_eq!(RpIdHash::from(&stored).unwrap(), hash);
+    }
+
+    #[test]
+    fn test_validate_rp() {
+        assert!(RelyingParty::from("example.com").validate().is_ok());
+        assert!(RelyingParty::from("").validate().is_err());
+        assert!(RelyingParty::from("a".repeat(254)).validate().is_err());
+
+        // Empty and long names are fine, the latter get truncated
+        let mut rp = RelyingParty::from("example.com");
+        rp.name = Some(String::new());
+        assert!(rp.validate().is_ok());
+        rp.name = Some("a".repeat(65));
+        assert!(rp.validate().is_ok());
+    }
+
+    #[test]
+    fn test_truncate_rp_name() {
+        let mut rp = RelyingParty::from("example.com");
+        rp.truncate_name();
+        assert_eq!(rp.name, None);
+
+        rp.name = Some(String::from("Acme"));
+        rp.truncate_name();
+        assert_eq!(rp.name.as_deref(), Some("Acme"));
+
+        rp.name = Some("a".repeat(65));
+        rp.truncate_name();
+        assert_eq!(rp.name, Some("a".repeat(64)));
+
+        // "ä" takes two bytes, and the 64th byte is in the middle of one
+        rp.name = Some(format!("a{}", "ä".repeat(40)));
+        rp.truncate_name();
+        assert_eq!(rp.name, Some(format!("a{}", "ä".repeat(31))));
+    }
+
+    #[test]
+    fn test_transport_serialization() {
+        let transports = vec![
+            Transport::USB,
+            Transport::NFC,
+            Transport::BLE,
+            Transport::Internal,
+            Transport::Hybrid,
+            Transport::SmartCard,
+            Transport::Unknown(String::from("carrier-pigeon")),
+        ];
+        let payload = serde_cbor::to_vec(&transports).unwrap();
+        assert_eq!(
+            payload,
+            vec![
+                0x87, // array(7)
+                0x63, // text(3)
+                0x75, 0x73, 0x62, // "usb"
+                0x63, // text(3)
+                0x6e, 0x66, 0x63, // "nfc"
+                0x63, // text(3)
+                0x62, 0x6c, 0x65, // "ble"
+                0x68, // text(8)
+                0x69, 0x6e, 0x74, 0x65, 0x72, 0x6e, 0x61, 0x6c, // "internal"
+                0x66, // text(6)
+                0x68, 0x79, 0x62, 0x72, 0x69, 0x64, // "hybrid"
+                0x6a, // text(10)
+                0x73, 0x6d, 0x61, 0x72, 0x74, 0x2d, 0x63, 0x61, 0x72, 0x64, // "smart-card"
+                0x6e, // text(14)
+                0x63, 0x61, 0x72, 0x72, 0x69, 0x65, 0x72, 0x2d, 0x70, 0x69, 0x67, 0x65, 0x6f,
+                0x6e, // "carrier-pigeon"
+            ]
+        );
+        let parsed: Vec<Transport> = from_slice(&payload).unwrap();
+        assert_eq!(parsed, transports);
+    }
+
+    #[test]
+    fn test_default_algorithms() {
+        let algs: Vec<COSEAlgorithm> = PublicKeyCredentialParameters::default_algorithms()
+            .into_iter()
+            .map(|p| p.alg)
+            .collect();
+        assert_eq!(
+            algs,
+            vec![
+                COSEAlgorithm::ES256,
+                COSEAlgorithm::EDDSA,
+                COSEAlgorithm::RS256
+            ]
+        );
+    }
+
     #[test]
     fn test_deserialize_user() {
         // This includes an obsolete "icon" field to test that deserialization
@@ -1,5 +1,6 @@
-use serde::de;
-use serde_cbor::Deserializer;
+use serde::{de, ser, Serialize, Serializer};
+use serde_cbor::{Deserializer, Value};
+use std::collections::BTreeMap;
 use std::io::Read;
 
 pub fn serde_parse_err<E: de::Error>(s: &str) -> E {
@@ -17,6 +18,13 @@ where
 
 // Parsing routines
 
+pub fn read_be_u64<R: Read, E: de::Error>(data: &mut R) -> Result<u64, E> {
+    let mut buf = [0; 8];
+    data.read_exact(&mut buf)
+        .map_err(|_| serde_parse_err("u64"))?;
+    Ok(u64::from_be_bytes(buf))
+}
+
 pub fn read_be_u32<R: Read, E: de::Error>(data: &mut R) -> Result<u32, E> {
     let mut buf = [0; 4];
     data.read_exact(&mut buf)
@@ -37,3 +45,52 @@ pub fn read_byte<R: Read, E: de::Error>(data: &mut R) -> Result<u8, E> {
         _ => Err(serde_parse_err("u8")),
     }
 }
+
+/// The authenticator extension inputs of a request (0x06), in CTAP2 canonical order.
+///
+/// Besides the extensions we know, callers can send raw inputs for extensions we don't
+/// model (yet). Raw inputs never replace known extensions, even if those are not set.
+#[derive(Default)]
+pub(crate) struct ExtensionsMap {
+    entries: BTreeMap<Value, Value>,
+    known: Vec<&'static str>,
+    // Reported when serializing the map
+    error: Option<serde_cbor::Error>,
+}
+
+impl ExtensionsMap {
+    pub(crate) fn insert<T: Serialize + ?Sized>(&mut self, name: &'static str, input: Option<&T>) {
+        self.known.push(name);
+        match input.map(serde_cbor::value::to_value) {
+            Some(Ok(value)) => {
+                self.entries.insert(Value::Text(name.to_string()), value);
+            }
+            Some(Err(e)) => self.error = Some(e),
+            None => {}
+        }
+    }
+
+    pub(crate) fn insert_raw(&mut self, raw: &BTreeMap<String, Value>) {
+        for (name, input) in raw {
+            if self.known.contains(&name.as_str()) {
+                warn!("Ignoring raw input of the known extension {name}");
+                continue;
+            }
+            self.entries
+                .insert(Value::Text(name.clone()), input.clone());
+        }
+    }
+}
+
+impl Serialize for ExtensionsMap {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if let Some(e) = &self.error {
+            return Err(ser::Error::custom(e));
+        }
+        // serde_cbor orders Values canonically
+        self.entries.serialize(serializer)
+    }
+}
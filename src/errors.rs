@@ -2,7 +2,10 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-pub use crate::ctap2::commands::{client_pin::PinError, CommandError};
+use crate::crypto::COSEAlgorithm;
+pub use crate::ctap2::commands::{
+    authenticator_config::AuthConfigError, client_pin::PinError, CommandError,
+};
 pub use crate::transport::errors::HIDError;
 use std::fmt;
 use std::io;
@@ -13,8 +16,11 @@ use std::sync::mpsc;
 
 #[derive(Debug)]
 pub enum UnsupportedOption {
+    CredBlob,
     CredProtect,
     EmptyAllowList,
+    EnterpriseAttestation,
+    LargeBlobs,
     MaxPinLength,
     PubCredParams,
     ResidentKey,
@@ -36,9 +42,23 @@ pub enum AuthenticatorError {
     HIDError(HIDError),
     CryptoError,
     PinError(PinError),
+    AuthConfigError(AuthConfigError),
     UnsupportedOption(UnsupportedOption),
     CancelledByUser,
     CredentialExcluded,
+    /// The authenticator holds none of the requested credentials. If the request had an
+    /// allow list, this tells whether all of its entries were checked (across all chunks
+    /// sent to the authenticator) without a match.
+    NoMatchingCredentials(Option<bool>),
+    BackupEligibleCredential,
+    InvalidOptionCombination(&'static str),
+    Timeout,
+    /// The authenticator supports none of the requested credential algorithms, according to
+    /// the algorithms it lists in getInfo.
+    NoSupportedAlgorithm {
+        requested: Vec<COSEAlgorithm>,
+        supported: Vec<COSEAlgorithm>,
+    },
 }
 
 impl std::error::Error for AuthenticatorError {}
@@ -68,6 +88,9 @@ impl fmt::Display for AuthenticatorError {
                 write!(f, "The cryptography implementation encountered an error")
             }
             AuthenticatorError::PinError(ref e) => write!(f, "PIN Error: {e}"),
+            AuthenticatorError::AuthConfigError(ref e) => {
+                write!(f, "Authenticator config error: {e}")
+            }
             AuthenticatorError::UnsupportedOption(ref e) => {
                 write!(f, "Unsupported option: {e:?}")
             }
@@ -77,6 +100,30 @@ impl fmt::Display for AuthenticatorError {
             AuthenticatorError::CredentialExcluded => {
                 write!(f, "Credential excluded.")
             }
+            AuthenticatorError::NoMatchingCredentials(Some(true)) => {
+                write!(f, "No matching credentials, allow list exhausted.")
+            }
+            AuthenticatorError::NoMatchingCredentials(_) => {
+                write!(f, "No matching credentials.")
+            }
+            AuthenticatorError::BackupEligibleCredential => {
+                write!(f, "Credential is backup eligible.")
+            }
+            AuthenticatorError::InvalidOptionCombination(reason) => {
+                write!(f, "Invalid combination of options: {reason}")
+            }
+            AuthenticatorError::Timeout => {
+                write!(f, "Operation timed out.")
+            }
+            AuthenticatorError::NoSupportedAlgorithm {
+                ref requested,
+                ref supported,
+            } => {
+                write!(
+                    f,
+                    "None of the requested algorithms {requested:?} is supported, only {supported:?}."
+                )
+            }
         }
     }
 }
@@ -41,17 +41,22 @@ pub mod errors;
 pub mod statecallback;
 pub use ctap2::attestation::AttestationObject;
 pub use ctap2::commands::bio_enrollment::BioEnrollmentResult;
-pub use ctap2::commands::client_pin::{Pin, PinError};
+pub use ctap2::commands::client_pin::{Pin, PinError, PinRetries, PinUvAuthTokenPermission};
 pub use ctap2::commands::credential_management::CredentialManagementResult;
 pub use ctap2::commands::get_assertion::{Assertion, GetAssertionResult};
-pub use ctap2::commands::get_info::AuthenticatorInfo;
+pub use ctap2::commands::get_info::{AuthenticatorInfo, Capabilities, InfoDiff};
 pub use ctap2::commands::make_credentials::MakeCredentialsResult;
+pub use ctap2::commands::UvReason;
 use serde::Serialize;
 pub use statemachine::StateMachine;
 pub use status_update::{
     BioEnrollmentCmd, CredManagementCmd, InteractiveRequest, InteractiveUpdate, StatusPinUv,
     StatusUpdate,
 };
+pub use transport::device_selector::{DeviceID, DeviceSelector};
+pub use transport::frame_log::{
+    clear_frame_logger, set_frame_logger, FrameDirection, FrameLogRecord, FrameRedaction,
+};
 pub use transport::{FidoDevice, FidoDeviceIO, FidoProtocol, VirtualFidoDevice};
 
 // Keep this in sync with the constants in u2fhid-capi.h.
@@ -34,6 +34,8 @@ impl StateMachine {
     fn init_device(
         info: DeviceBuildParameters,
         selector: &Sender<DeviceSelectorEvent>,
+        status: &Sender<crate::StatusUpdate>,
+        keep_alive: &dyn Fn() -> bool,
     ) -> Option<Device> {
         // Create a new device.
         let mut dev = match Device::new(info) {
@@ -45,8 +47,8 @@ impl StateMachine {
             }
         };
 
-        // Try initializing it.
-        if let Err(e) = dev.init() {
+        // Try initializing it. Some devices want to be touched before they answer getInfo.
+        if let Err(e) = dev.init_cancellable(Some(status), keep_alive) {
             warn!("error while initializing device: {}", e);
             let _ = selector.send(DeviceSelectorEvent::NotAToken(dev.id()));
             return None;
@@ -130,7 +132,7 @@ impl StateMachine {
             cbc.clone(),
             status,
             move |info, selector, status, alive| {
-                let mut dev = match Self::init_device(info, &selector) {
+                let mut dev = match Self::init_device(info, &selector, &status, alive) {
                     Some(dev) => dev,
                     None => return,
                 };
@@ -171,7 +173,7 @@ impl StateMachine {
             callback.clone(),
             status,
             move |info, selector, status, alive| {
-                let mut dev = match Self::init_device(info, &selector) {
+                let mut dev = match Self::init_device(info, &selector, &status, alive) {
                     Some(dev) => dev,
                     None => return,
                 };
@@ -219,7 +221,7 @@ impl StateMachine {
             callback.clone(),
             status,
             move |info, selector, status, alive| {
-                let mut dev = match Self::init_device(info, &selector) {
+                let mut dev = match Self::init_device(info, &selector, &status, alive) {
                     Some(dev) => dev,
                     None => return,
                 };
@@ -257,7 +259,7 @@ impl StateMachine {
             callback.clone(),
             status,
             move |info, selector, status, alive| {
-                let mut dev = match Self::init_device(info, &selector) {
+                let mut dev = match Self::init_device(info, &selector, &status, alive) {
                     Some(dev) => dev,
                     None => return,
                 };
@@ -308,7 +310,7 @@ impl StateMachine {
             callback.clone(),
             status,
             move |info, selector, status, alive| {
-                let mut dev = match Self::init_device(info, &selector) {
+                let mut dev = match Self::init_device(info, &selector, &status, alive) {
                     Some(dev) => dev,
                     None => return,
                 };
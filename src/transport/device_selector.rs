@@ -1,14 +1,28 @@
+use crate::ctap2::commands::get_info::AuthenticatorInfo;
+use crate::statecallback::StateCallback;
+use crate::transport::errors::HIDError;
 use crate::transport::hid::HIDDevice;
+use crate::transport::platform::transaction::Transaction;
+use crate::transport::FidoDevice;
 
 pub use crate::transport::platform::device::Device;
 
 use runloop::RunLoop;
 use std::collections::{HashMap, HashSet};
-use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
-use std::time::Duration;
+use std::io;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 pub type DeviceID = <Device as HIDDevice>::Id;
 pub type DeviceBuildParameters = <Device as HIDDevice>::BuildParameters;
+/// The answer of a device to `DeviceSelector::info_for_all()`: its ID, its HID product name
+/// and the result of getInfo
+pub type DeviceInfoAnswer = (
+    DeviceID,
+    Option<String>,
+    Result<AuthenticatorInfo, HIDError>,
+);
 
 trait DeviceSelectorEventMarker {}
 
@@ -176,6 +190,118 @@ impl DeviceSelector {
         let _ = self.sender.send(DeviceSelectorEvent::Cancel);
         self.runloop.cancel();
     }
+
+    /// Runs getInfo on every device that is connected within `timeout` milliseconds, all of
+    /// them in parallel, and reports it together with the HID product name of the device.
+    /// Devices that can't be opened or don't answer are reported with their error, CTAP1-only
+    /// devices with `HIDError::UnsupportedCommand`, as they have no info.
+    ///
+    /// This returns as soon as all connected devices have answered. Devices that are still
+    /// busy when the timeout expires are reported with an `io::ErrorKind::TimedOut` error.
+    pub fn info_for_all(timeout: u64) -> crate::Result<Vec<DeviceInfoAnswer>> {
+        let (events_tx, events_rx) = channel();
+        let events_tx = Mutex::new(events_tx);
+        let (status_tx, _status_rx) = channel();
+        let callback = StateCallback::new(Box::new(|_: crate::Result<()>| {}));
+        let mut transaction = Transaction::new(
+            timeout,
+            callback,
+            status_tx,
+            move |params, selector, _status, alive| {
+                let events = match events_tx.lock() {
+                    Ok(events_tx) => events_tx.clone(),
+                    Err(_) => return,
+                };
+                match Device::new(params) {
+                    Ok(mut dev) => {
+                        report_info(dev.id(), &selector, &events, alive, || {
+                            if let Err(e) = dev.init_cancellable(None, alive) {
+                                return (None, Err(e));
+                            }
+                            let info = dev
+                                .get_authenticator_info()
+                                .cloned()
+                                .ok_or(HIDError::UnsupportedCommand);
+                            (dev.get_device_info().product_name(), info)
+                        });
+                    }
+                    Err((e, id)) => report_info(id, &selector, &events, alive, || (None, Err(e))),
+                }
+            },
+        )?;
+
+        let infos = collect_infos(&events_rx, Duration::from_millis(timeout));
+        transaction.cancel();
+        Ok(infos)
+    }
+}
+
+/// Progress of the getInfo requests of `DeviceSelector::info_for_all()`
+enum InfoEvent {
+    Started(DeviceID),
+    Answered(
+        DeviceID,
+        Option<String>,
+        Result<AuthenticatorInfo, HIDError>,
+    ),
+    AllAnswered,
+}
+
+/// Queries the info of a single device and reports it to `info_for_all()`. Afterwards, the
+/// device registers as a token with the selector, which tells all tokens to continue or blink
+/// once every connected device has answered. That's how we know we are done.
+fn report_info<F>(
+    id: DeviceID,
+    selector: &Sender<DeviceSelectorEvent>,
+    events: &Sender<InfoEvent>,
+    alive: &dyn Fn() -> bool,
+    query: F,
+) where
+    F: FnOnce() -> (Option<String>, Result<AuthenticatorInfo, HIDError>),
+{
+    let _ = events.send(InfoEvent::Started(id.clone()));
+    let (name, result) = query();
+    let _ = events.send(InfoEvent::Answered(id.clone(), name, result));
+
+    let (tx, rx) = channel();
+    if selector
+        .send(DeviceSelectorEvent::ImAToken((id, tx)))
+        .is_err()
+    {
+        return;
+    }
+    while alive() {
+        match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(DeviceCommand::Blink) | Ok(DeviceCommand::Continue) => {
+                let _ = events.send(InfoEvent::AllAnswered);
+                break;
+            }
+            Ok(DeviceCommand::Cancel) | Ok(DeviceCommand::Removed) => break,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+fn collect_infos(events: &Receiver<InfoEvent>, timeout: Duration) -> Vec<DeviceInfoAnswer> {
+    let deadline = Instant::now() + timeout;
+    let mut pending = Vec::new();
+    let mut infos = Vec::new();
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        match events.recv_timeout(remaining) {
+            Ok(InfoEvent::Started(id)) => pending.push(id),
+            Ok(InfoEvent::Answered(id, name, result)) => {
+                pending.retain(|pending_id| pending_id != &id);
+                infos.push((id, name, result));
+            }
+            Ok(InfoEvent::AllAnswered) | Err(_) => break,
+        }
+    }
+    infos.extend(pending.into_iter().map(|id| {
+        let timed_out = io::Error::from(io::ErrorKind::TimedOut);
+        (id, None, Err(HIDError::IO(None, timed_out)))
+    }));
+    infos
 }
 
 #[cfg(test)]
@@ -183,8 +309,12 @@ pub mod tests {
     use super::*;
     use crate::{
         consts::Capability,
-        ctap2::commands::get_info::{AuthenticatorInfo, AuthenticatorOptions},
-        transport::FidoDevice,
+        ctap2::commands::get_info::{
+            AuthenticatorInfo, AuthenticatorOptions, AuthenticatorVersion, GetInfo,
+        },
+        ctap2::commands::get_version::{GetVersion, U2FInfo},
+        transport::errors::CtapHidError,
+        transport::{FidoDevice, FidoProtocol},
         u2ftypes::U2FDeviceInfo,
     };
 
@@ -261,6 +391,76 @@ pub mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn test_is_ctap2_capable() {
+        // CBOR capability, no getInfo yet
+        let mut dev = Device::new("device selector 1").unwrap();
+        dev.set_device_info(gen_info(dev.id()));
+        assert!(dev.is_ctap2_capable());
+
+        // getInfo only lists U2F
+        dev.set_authenticator_info(AuthenticatorInfo {
+            versions: vec![AuthenticatorVersion::U2F_V2],
+            ..Default::default()
+        });
+        assert!(!dev.is_ctap2_capable());
+
+        // getInfo lists CTAP2
+        make_device_with_pin(&mut dev);
+        dev.set_authenticator_info(AuthenticatorInfo {
+            versions: vec![AuthenticatorVersion::U2F_V2, AuthenticatorVersion::FIDO_2_0],
+            ..Default::default()
+        });
+        assert!(dev.is_ctap2_capable());
+
+        // No CBOR capability
+        let mut dev = Device::new("device selector 2").unwrap();
+        let mut info = gen_info(dev.id());
+        info.cap_flags = Capability::WINK;
+        dev.set_device_info(info);
+        assert!(!dev.is_ctap2_capable());
+
+        // Already downgraded
+        let mut dev = Device::new("device selector 3").unwrap();
+        make_device_simple_u2f(&mut dev);
+        assert!(!dev.is_ctap2_capable());
+    }
+
+    #[test]
+    fn test_prefer_ctap2() {
+        // CBOR capability without NMSG, so the device speaks both CTAP1 and CTAP2
+        let both = |id: &str| {
+            let mut dev = Device::new_skipping_serialization(id).unwrap();
+            let mut info = gen_info(dev.id());
+            info.cap_flags = Capability::WINK | Capability::CBOR;
+            dev.set_device_info(info);
+            dev.set_cid([1, 2, 3, 4]); // Already initialized
+            dev
+        };
+
+        let mut dev = both("ctap1 and ctap2");
+        assert!(dev.is_ctap2_capable());
+        dev.add_upcoming_ctap2_request(&GetInfo::default());
+        dev.add_upcoming_ctap_response(AuthenticatorInfo {
+            versions: vec![AuthenticatorVersion::U2F_V2, AuthenticatorVersion::FIDO_2_0],
+            ..Default::default()
+        });
+        dev.init().expect("Failed to init device");
+        assert_eq!(dev.get_protocol(), FidoProtocol::CTAP2);
+        assert!(dev.is_ctap2_capable());
+
+        // CTAP1 is only used, if getInfo fails
+        let mut dev = both("ctap1 fallback");
+        dev.add_upcoming_ctap2_request(&GetInfo::default());
+        dev.add_upcoming_ctap_error(HIDError::CtapHid(CtapHidError::InvalidCmd));
+        dev.add_upcoming_ctap1_request(&GetVersion::default());
+        dev.add_upcoming_ctap_response(U2FInfo::U2F_V2);
+        dev.init().expect("Failed to init device");
+        assert_eq!(dev.get_protocol(), FidoProtocol::CTAP1);
+        assert!(dev.get_authenticator_info().is_none());
+        assert!(!dev.is_ctap2_capable());
+    }
+
     #[test]
     fn test_device_selector_one_token_no_late_adds() {
         let mut devices = vec![
@@ -474,4 +674,84 @@ pub mod tests {
             DeviceCommand::Continue
         );
     }
+
+    #[test]
+    fn test_info_for_all() {
+        let devices = vec![
+            Device::new("info for all 1").unwrap(),
+            Device::new("info for all 2").unwrap(),
+        ];
+        let info = AuthenticatorInfo {
+            versions: vec![AuthenticatorVersion::FIDO_2_0],
+            ..Default::default()
+        };
+
+        // All devices answer, so we don't wait for the timeout
+        let selector = DeviceSelector::run();
+        add_devices(devices.iter(), &selector);
+        let (events_tx, events_rx) = channel();
+        let threads: Vec<_> = devices
+            .iter()
+            .map(|dev| {
+                let id = dev.id();
+                let selector = selector.clone_sender();
+                let events = events_tx.clone();
+                let info = info.clone();
+                std::thread::spawn(move || {
+                    report_info(id.clone(), &selector, &events, &|| true, || {
+                        (Some(id), Ok(info))
+                    })
+                })
+            })
+            .collect();
+        let start = Instant::now();
+        let infos = collect_infos(&events_rx, Duration::from_secs(10));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert_eq!(infos.len(), 2);
+        for dev in &devices {
+            assert!(infos.iter().any(|(id, name, res)| {
+                id == &dev.id() && name.as_ref() == Some(id) && res.is_ok()
+            }));
+        }
+        threads.into_iter().for_each(|t| t.join().unwrap());
+
+        // The second device doesn't answer in time
+        let mut selector = DeviceSelector::run();
+        add_devices(devices.iter(), &selector);
+        let (events_tx, events_rx) = channel();
+        let (release_tx, release_rx) = channel::<()>();
+        let answering = {
+            let id = devices[0].id();
+            let selector = selector.clone_sender();
+            let events = events_tx.clone();
+            std::thread::spawn(move || {
+                report_info(id, &selector, &events, &|| true, || (None, Ok(info)))
+            })
+        };
+        let hanging = {
+            let id = devices[1].id();
+            let selector = selector.clone_sender();
+            let events = events_tx.clone();
+            std::thread::spawn(move || {
+                report_info(id, &selector, &events, &|| true, || {
+                    let _ = release_rx.recv();
+                    (None, Err(HIDError::DeviceError))
+                })
+            })
+        };
+        let infos = collect_infos(&events_rx, Duration::from_millis(200));
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].0, devices[0].id());
+        assert!(infos[0].2.is_ok());
+        assert_eq!(infos[1].0, devices[1].id());
+        assert_matches!(
+            infos[1].2,
+            Err(HIDError::IO(None, ref e)) if e.kind() == io::ErrorKind::TimedOut
+        );
+
+        release_tx.send(()).unwrap();
+        selector.stop();
+        answering.join().unwrap();
+        hanging.join().unwrap();
+    }
 }
@@ -1,5 +1,5 @@
 use crate::consts::{SW_CONDITIONS_NOT_SATISFIED, SW_NO_ERROR, SW_WRONG_DATA, SW_WRONG_LENGTH};
-use crate::ctap2::commands::CommandError;
+use crate::ctap2::commands::{CommandError, StatusCode};
 use std::fmt;
 use std::io;
 use std::path;
@@ -36,6 +36,74 @@ impl fmt::Display for ApduErrorStatus {
     }
 }
 
+/// Error codes of CTAPHID_ERROR responses, see
+/// https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-20210615.html#usb-hid-error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CtapHidError {
+    InvalidCmd,
+    InvalidPar,
+    InvalidLen,
+    InvalidSeq,
+    MsgTimeout,
+    ChannelBusy,
+    LockRequired,
+    InvalidChannel,
+    Other,
+    Unknown(u8),
+}
+
+impl From<u8> for CtapHidError {
+    fn from(code: u8) -> Self {
+        match code {
+            0x01 => CtapHidError::InvalidCmd,
+            0x02 => CtapHidError::InvalidPar,
+            0x03 => CtapHidError::InvalidLen,
+            0x04 => CtapHidError::InvalidSeq,
+            0x05 => CtapHidError::MsgTimeout,
+            0x06 => CtapHidError::ChannelBusy,
+            0x0A => CtapHidError::LockRequired,
+            0x0B => CtapHidError::InvalidChannel,
+            0x7F => CtapHidError::Other,
+            other => CtapHidError::Unknown(other),
+        }
+    }
+}
+
+impl From<CtapHidError> for u8 {
+    fn from(error: CtapHidError) -> u8 {
+        match error {
+            CtapHidError::InvalidCmd => 0x01,
+            CtapHidError::InvalidPar => 0x02,
+            CtapHidError::InvalidLen => 0x03,
+            CtapHidError::InvalidSeq => 0x04,
+            CtapHidError::MsgTimeout => 0x05,
+            CtapHidError::ChannelBusy => 0x06,
+            CtapHidError::LockRequired => 0x0A,
+            CtapHidError::InvalidChannel => 0x0B,
+            CtapHidError::Other => 0x7F,
+            CtapHidError::Unknown(other) => other,
+        }
+    }
+}
+
+impl fmt::Display for CtapHidError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match *self {
+            CtapHidError::InvalidCmd => "invalid command",
+            CtapHidError::InvalidPar => "invalid parameter",
+            CtapHidError::InvalidLen => "invalid message length",
+            CtapHidError::InvalidSeq => "invalid message sequencing",
+            CtapHidError::MsgTimeout => "message timed out",
+            CtapHidError::ChannelBusy => "channel busy",
+            CtapHidError::LockRequired => "command requires channel lock",
+            CtapHidError::InvalidChannel => "invalid channel",
+            CtapHidError::Other => "unspecified error",
+            CtapHidError::Unknown(_) => "unknown error",
+        };
+        write!(f, "CTAPHID error 0x{:02x}: {description}", u8::from(*self))
+    }
+}
+
 #[allow(unused)]
 #[derive(Debug)]
 pub enum HIDError {
@@ -49,10 +117,24 @@ pub enum HIDError {
     UnexpectedVersion,
     IO(Option<path::PathBuf>, io::Error),
     UnexpectedCmd(u8),
+    /// The device answered with a CTAPHID_ERROR frame
+    CtapHid(CtapHidError),
     Command(CommandError),
     ApduStatus(ApduErrorStatus),
 }
 
+impl HIDError {
+    /// Whether the device is busy with a transaction on another channel. This can be
+    /// reported both on the CTAPHID level and as a CTAP2 status code.
+    pub fn is_channel_busy(&self) -> bool {
+        matches!(
+            self,
+            HIDError::CtapHid(CtapHidError::ChannelBusy)
+                | HIDError::Command(CommandError::StatusCode(StatusCode::ChannelBusy, _))
+        )
+    }
+}
+
 impl From<io::Error> for HIDError {
     fn from(e: io::Error) -> HIDError {
         HIDError::IO(None, e)
@@ -65,6 +147,12 @@ impl From<CommandError> for HIDError {
     }
 }
 
+impl From<CtapHidError> for HIDError {
+    fn from(e: CtapHidError) -> HIDError {
+        HIDError::CtapHid(e)
+    }
+}
+
 impl From<ApduErrorStatus> for HIDError {
     fn from(e: ApduErrorStatus) -> HIDError {
         HIDError::ApduStatus(e)
@@ -90,6 +178,7 @@ impl fmt::Display for HIDError {
             HIDError::IO(ref p, ref e) => write!(f, "Error: Ioerror({p:?}): {e}"),
             HIDError::Command(ref e) => write!(f, "Error: Error issuing command: {e}"),
             HIDError::UnexpectedCmd(s) => write!(f, "Error: Unexpected status: {s}"),
+            HIDError::CtapHid(ref e) => write!(f, "Error: {e}"),
             HIDError::ApduStatus(ref status) => {
                 write!(f, "Error: Unexpected apdu status: {status:?}")
             }
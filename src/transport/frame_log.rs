@@ -0,0 +1,416 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Optional logging of the raw CTAPHID frames exchanged with a device, for debugging
+//! interoperability problems. Nothing is logged unless a logger is installed with
+//! `set_frame_logger()`.
+//!
+//! PIN-related secrets (newPinEnc, pinHashEnc and the encrypted pinUvAuthToken) are always
+//! blanked out before frames are handed to the logger. pinUvAuthParam and clientDataHash are
+//! blanked out depending on the `FrameRedaction` settings. Redacted values keep their length,
+//! so the logged frames have the same layout as the ones sent on the wire.
+
+use crate::consts::{
+    HIDCmd, CONT_HEADER_SIZE, INIT_HEADER_SIZE, PARAMETER_SIZE, U2FAPDUHEADER_SIZE,
+    U2F_AUTHENTICATE, U2F_REGISTER, U2F_VERSION,
+};
+use serde::Deserialize;
+use serde_cbor::Value;
+use std::convert::TryInto;
+use std::sync::RwLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameDirection {
+    /// From the host to the device
+    Outgoing,
+    /// From the device to the host
+    Incoming,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameRedaction {
+    pub pin_uv_auth_param: bool,
+    pub client_data_hash: bool,
+}
+
+impl Default for FrameRedaction {
+    fn default() -> Self {
+        FrameRedaction {
+            pin_uv_auth_param: true,
+            client_data_hash: true,
+        }
+    }
+}
+
+/// One CTAPHID message, split into the frames (HID reports, without report ID) it was sent
+/// or received in.
+#[derive(Debug)]
+pub struct FrameLogRecord<'a> {
+    pub direction: FrameDirection,
+    pub cid: [u8; 4],
+    pub hid_cmd: HIDCmd,
+    /// Name of the command of the transaction this message belongs to, e.g. "GetAssertion"
+    /// for both the request and the response.
+    pub command: &'a str,
+    pub frames: &'a [Vec<u8>],
+}
+
+pub type FrameLogger = dyn Fn(&FrameLogRecord) + Send + Sync;
+
+static FRAME_LOGGER: RwLock<Option<(Box<FrameLogger>, FrameRedaction)>> = RwLock::new(None);
+
+pub fn set_frame_logger(logger: Box<FrameLogger>, redaction: FrameRedaction) {
+    if let Ok(mut guard) = FRAME_LOGGER.write() {
+        *guard = Some((logger, redaction));
+    }
+}
+
+pub fn clear_frame_logger() {
+    if let Ok(mut guard) = FRAME_LOGGER.write() {
+        *guard = None;
+    }
+}
+
+/// Hands a message to the installed logger, if any. `request_cmd` and `request` are the
+/// command and payload that started the transaction, and are used to decode and redact
+/// `payload` (which is identical to `request` for outgoing messages).
+pub(crate) fn log_message(
+    direction: FrameDirection,
+    cid: &[u8; 4],
+    (request_cmd, request): (HIDCmd, &[u8]),
+    (hid_cmd, payload): (HIDCmd, &[u8]),
+    report_size: usize,
+) {
+    let guard = match FRAME_LOGGER.read() {
+        Ok(guard) => guard,
+        Err(_) => return,
+    };
+    let (logger, redaction) = match guard.as_ref() {
+        Some(l) => l,
+        None => return,
+    };
+
+    let command = command_name(request_cmd, request);
+    let mut payload = payload.to_vec();
+    match (direction, hid_cmd) {
+        (FrameDirection::Outgoing, HIDCmd::Cbor) => redact_ctap2_request(&mut payload, redaction),
+        (FrameDirection::Outgoing, HIDCmd::Msg) => redact_ctap1_request(&mut payload, redaction),
+        (FrameDirection::Incoming, HIDCmd::Cbor) => {
+            if let Some(command) = request.first() {
+                redact_ctap2_response(*command, &mut payload);
+            }
+        }
+        _ => {}
+    }
+    let frames = frames(cid, hid_cmd, &payload, report_size);
+    logger(&FrameLogRecord {
+        direction,
+        cid: *cid,
+        hid_cmd,
+        command: &command,
+        frames: &frames,
+    });
+}
+
+fn command_name(cmd: HIDCmd, payload: &[u8]) -> String {
+    let name = match (cmd, payload) {
+        (HIDCmd::Cbor, [command, ..]) => match *command {
+            0x01 => "MakeCredentials",
+            0x02 => "GetAssertion",
+            0x04 => "GetInfo",
+            0x06 => "ClientPin",
+            0x07 => "Reset",
+            0x08 => "GetNextAssertion",
+            0x09 => "BioEnrollment",
+            0x0A => "CredentialManagement",
+            0x0B => "Selection",
+            0x0C => "LargeBlobs",
+            0x0D => "AuthenticatorConfig",
+            0x40 => "BioEnrollmentPreview",
+            0x41 => "CredentialManagementPreview",
+            _ => "UnknownCborCommand",
+        },
+        (HIDCmd::Msg, [_, ins, ..]) => match *ins {
+            U2F_REGISTER => "U2F_REGISTER",
+            U2F_AUTHENTICATE => "U2F_AUTHENTICATE",
+            U2F_VERSION => "U2F_VERSION",
+            _ => "UnknownU2FCommand",
+        },
+        _ => return format!("{cmd:?}"),
+    };
+    name.to_string()
+}
+
+/// Splits `payload` into CTAPHID frames the same way `U2FHIDInit::write()` and
+/// `U2FHIDCont::write()` do, including the zero padding.
+fn frames(cid: &[u8; 4], cmd: HIDCmd, payload: &[u8], report_size: usize) -> Vec<Vec<u8>> {
+    let mut result = Vec::new();
+    let mut frame = vec![0u8; report_size];
+    frame[..4].copy_from_slice(cid);
+    frame[4] = cmd.into();
+    frame[5] = (payload.len() >> 8) as u8;
+    frame[6] = payload.len() as u8;
+    let count = std::cmp::min(payload.len(), report_size - INIT_HEADER_SIZE);
+    frame[INIT_HEADER_SIZE..INIT_HEADER_SIZE + count].copy_from_slice(&payload[..count]);
+    result.push(frame);
+
+    for (sequence, chunk) in payload[count..]
+        .chunks(report_size - CONT_HEADER_SIZE)
+        .enumerate()
+    {
+        let mut frame = vec![0u8; report_size];
+        frame[..4].copy_from_slice(cid);
+        frame[4] = sequence as u8;
+        frame[CONT_HEADER_SIZE..CONT_HEADER_SIZE + chunk.len()].copy_from_slice(chunk);
+        result.push(frame);
+    }
+    result
+}
+
+fn redact_ctap2_request(payload: &mut [u8], redaction: &FrameRedaction) {
+    let (command, map) = match payload.split_first_mut() {
+        Some(split) => split,
+        None => return,
+    };
+    // (clientDataHash, pinUvAuthParam, always redacted)
+    let (client_data_hash, pin_uv_auth_param, secrets): (_, _, &[u64]) = match *command {
+        0x01 => (Some(0x01), Some(0x08), &[]),     // MakeCredentials
+        0x02 => (Some(0x02), Some(0x06), &[]),     // GetAssertion
+        0x06 => (None, Some(0x04), &[0x05, 0x06]), // ClientPin: newPinEnc, pinHashEnc
+        0x09 | 0x40 => (None, Some(0x05), &[]),    // BioEnrollment
+        0x0A | 0x41 | 0x0D => (None, Some(0x04), &[]), // CredentialManagement, Config
+        0x0C => (None, Some(0x05), &[]),           // LargeBlobs
+        _ => (None, None, &[]),
+    };
+    let mut keys = secrets.to_vec();
+    if redaction.client_data_hash {
+        keys.extend(client_data_hash);
+    }
+    if redaction.pin_uv_auth_param {
+        keys.extend(pin_uv_auth_param);
+    }
+    redact_map_entries(map, &keys);
+}
+
+fn redact_ctap1_request(payload: &mut [u8], redaction: &FrameRedaction) {
+    // Both U2F_REGISTER and U2F_AUTHENTICATE start with the challenge parameter, which is
+    // the clientDataHash.
+    let is_register_or_authenticate = matches!(
+        payload.get(1),
+        Some(&U2F_REGISTER) | Some(&U2F_AUTHENTICATE)
+    );
+    if redaction.client_data_hash && is_register_or_authenticate {
+        if let Some(challenge) =
+            payload.get_mut(U2FAPDUHEADER_SIZE..U2FAPDUHEADER_SIZE + PARAMETER_SIZE)
+        {
+            challenge.fill(0);
+        }
+    }
+}
+
+fn redact_ctap2_response(command: u8, payload: &mut [u8]) {
+    let map = match payload.split_first_mut() {
+        Some((_status, map)) => map,
+        None => return,
+    };
+    // ClientPin: pinUvAuthToken
+    if command == 0x06 {
+        redact_map_entries(map, &[0x02]);
+    }
+}
+
+/// Zeroes the content of the values of the given integer keys in a CBOR map, leaving the
+/// encoding intact. If the map can't be parsed, all of it is zeroed, to be on the safe side.
+fn redact_map_entries(map: &mut [u8], keys: &[u64]) {
+    if keys.is_empty() || map.is_empty() {
+        return;
+    }
+    let mut ranges = Vec::new();
+    if find_map_entries(map, keys, &mut ranges).is_none() {
+        map.fill(0);
+        return;
+    }
+    for (start, end) in ranges {
+        map[start..end].fill(0);
+    }
+}
+
+fn find_map_entries(map: &[u8], keys: &[u64], ranges: &mut Vec<(usize, usize)>) -> Option<()> {
+    // Definite length maps only, which is all canonical CTAP2 CBOR has.
+    let (len, mut pos) = cbor_header(map, 0xa0)?;
+    for _ in 0..len {
+        let (key, key_len) = next_value(&map[pos..])?;
+        pos += key_len;
+        let (_, value_len) = next_value(&map[pos..])?;
+        if let Value::Integer(key) = key {
+            if keys.iter().any(|k| i128::from(*k) == key) {
+                // Keep the header of the value, so the length is still visible
+                let (_, header_len) = cbor_header(&map[pos..], map[pos] & 0xe0)?;
+                ranges.push((pos + header_len, pos + value_len));
+            }
+        }
+        pos += value_len;
+    }
+    Some(())
+}
+
+/// Returns the argument of a CBOR header of the given major type, and the length of the header.
+fn cbor_header(data: &[u8], major_type: u8) -> Option<(u64, usize)> {
+    let first = *data.first()?;
+    if first & 0xe0 != major_type {
+        return None;
+    }
+    let info = first & 0x1f;
+    let (len, header_len) = match info {
+        0..=23 => (u64::from(info), 1),
+        24 => (u64::from(*data.get(1)?), 2),
+        25 => (
+            u64::from(u16::from_be_bytes(data.get(1..3)?.try_into().ok()?)),
+            3,
+        ),
+        26 => (
+            u64::from(u32::from_be_bytes(data.get(1..5)?.try_into().ok()?)),
+            5,
+        ),
+        27 => (u64::from_be_bytes(data.get(1..9)?.try_into().ok()?), 9),
+        _ => return None,
+    };
+    Some((len, header_len))
+}
+
+fn next_value(data: &[u8]) -> Option<(Value, usize)> {
+    let mut deserializer = serde_cbor::Deserializer::from_slice(data);
+    let value = Value::deserialize(&mut deserializer).ok()?;
+    Some((value, deserializer.byte_offset()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        command_name, frames, redact_ctap1_request, redact_ctap2_request, redact_ctap2_response,
+        FrameRedaction,
+    };
+    use crate::consts::HIDCmd;
+
+    #[test]
+    fn test_frames() {
+        let payload: Vec<u8> = (0..70).collect();
+        let frames = frames(&[1, 2, 3, 4], HIDCmd::Cbor, &payload, 64);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0][..7], [1, 2, 3, 4, 0x90, 0x00, 70]);
+        assert_eq!(frames[0][7..], payload[..57]);
+        assert_eq!(frames[1][..5], [1, 2, 3, 4, 0x00]);
+        assert_eq!(frames[1][5..18], payload[57..]);
+        assert!(frames[1][18..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn test_command_name() {
+        assert_eq!(command_name(HIDCmd::Cbor, &[0x02, 0xa0]), "GetAssertion");
+        assert_eq!(command_name(HIDCmd::Msg, &[0x00, 0x03]), "U2F_VERSION");
+        assert_eq!(command_name(HIDCmd::Init, &[0u8; 8]), "Init");
+    }
+
+    #[test]
+    fn test_redact_get_assertion() {
+        let request = [
+            0x02, // GetAssertion
+            0xa3, // map(3)
+            0x01, // unsigned(1)
+            0x63, 0x61, 0x62, 0x63, // "abc"
+            0x02, // unsigned(2)
+            0x42, 0x11, 0x22, // bytes(2), clientDataHash
+            0x06, // unsigned(6)
+            0x42, 0x33, 0x44, // bytes(2), pinUvAuthParam
+        ];
+
+        let mut payload = request;
+        redact_ctap2_request(&mut payload, &FrameRedaction::default());
+        assert_eq!(payload[..7], request[..7]);
+        assert_eq!(
+            payload[7..],
+            [0x02, 0x42, 0x00, 0x00, 0x06, 0x42, 0x00, 0x00]
+        );
+
+        let mut payload = request;
+        redact_ctap2_request(
+            &mut payload,
+            &FrameRedaction {
+                pin_uv_auth_param: true,
+                client_data_hash: false,
+            },
+        );
+        assert_eq!(payload[..11], request[..11]);
+        assert_eq!(payload[11..], [0x06, 0x42, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_redact_client_pin() {
+        // newPinEnc and pinHashEnc are redacted, even if not requested
+        let request = [
+            0x06, // ClientPin
+            0xa2, // map(2)
+            0x05, // unsigned(5)
+            0x41, 0x11, // bytes(1), newPinEnc
+            0x06, // unsigned(6)
+            0x41, 0x22, // bytes(1), pinHashEnc
+        ];
+        let mut payload = request;
+        redact_ctap2_request(
+            &mut payload,
+            &FrameRedaction {
+                pin_uv_auth_param: false,
+                client_data_hash: false,
+            },
+        );
+        assert_eq!(payload, [0x06, 0xa2, 0x05, 0x41, 0x00, 0x06, 0x41, 0x00]);
+
+        let mut response = [
+            0x00, // status
+            0xa1, // map(1)
+            0x02, // unsigned(2)
+            0x42, 0x11, 0x22, // bytes(2), pinUvAuthToken
+        ];
+        redact_ctap2_response(0x06, &mut response);
+        assert_eq!(response, [0x00, 0xa1, 0x02, 0x42, 0x00, 0x00]);
+
+        // Garbage gets blanked out completely
+        let mut payload = [0x06, 0xa2, 0x05, 0x5f, 0x11];
+        redact_ctap2_request(&mut payload, &FrameRedaction::default());
+        assert_eq!(payload, [0x06, 0x00, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_redact_large_blobs() {
+        let request = [
+            0x0C, // LargeBlobs
+            0xa5, // map(5)
+            0x02, // unsigned(2)
+            0x42, 0x11, 0x22, // bytes(2), set
+            0x03, // unsigned(3)
+            0x00, // unsigned(0), offset
+            0x04, // unsigned(4)
+            0x02, // unsigned(2), length
+            0x05, // unsigned(5)
+            0x42, 0x33, 0x44, // bytes(2), pinUvAuthParam
+            0x06, // unsigned(6)
+            0x02, // unsigned(2), pinUvAuthProtocol
+        ];
+        let mut payload = request;
+        redact_ctap2_request(&mut payload, &FrameRedaction::default());
+        assert_eq!(payload[..10], request[..10]);
+        assert_eq!(payload[10..], [0x05, 0x42, 0x00, 0x00, 0x06, 0x02]);
+    }
+
+    #[test]
+    fn test_redact_ctap1() {
+        let mut apdu = vec![0x00, 0x02, 0x03, 0x00, 0x00, 0x00, 0x41];
+        apdu.extend([0xaa; 32]); // challenge
+        apdu.extend([0xbb; 32]); // application
+        apdu.extend([0x00, 0x00]);
+        redact_ctap1_request(&mut apdu, &FrameRedaction::default());
+        assert!(apdu[7..39].iter().all(|b| *b == 0));
+        assert!(apdu[39..71].iter().all(|b| *b == 0xbb));
+    }
+}
@@ -1,9 +1,10 @@
 use super::TestDevice;
 use crate::consts::{HIDCmd, CID_BROADCAST};
 use crate::ctap2::commands::{CommandError, RequestCtap1, RequestCtap2, Retryable, StatusCode};
-use crate::transport::errors::{ApduErrorStatus, HIDError};
+use crate::transport::errors::{ApduErrorStatus, CtapHidError, HIDError};
+use crate::transport::frame_log::{self, FrameDirection};
 use crate::transport::{FidoDevice, FidoDeviceIO, FidoProtocol};
-use crate::u2ftypes::{U2FDeviceInfo, U2FHIDCont, U2FHIDInit, U2FHIDInitResp};
+use crate::u2ftypes::{U2FDeviceInfo, U2FHIDCont, U2FHIDInit, U2FHIDInitResp, UNKNOWN_DEVICE_NAME};
 use crate::util::io_err;
 use rand::{thread_rng, RngCore};
 use std::cmp::Eq;
@@ -46,7 +47,10 @@ pub trait HIDDevice: FidoDevice + Read + Write {
 
         // Send Init to broadcast address to create a new channel
         self.set_cid(CID_BROADCAST);
-        let (cmd, raw) = HIDDevice::sendrecv(self, HIDCmd::Init, &nonce, &|| true)?;
+        let (cmd, raw) = HIDDevice::sendrecv(self, HIDCmd::Init, &nonce, &|_| true)?;
+        if cmd == HIDCmd::Error {
+            return Err(ctaphid_error(&raw));
+        }
         if cmd != HIDCmd::Init {
             return Err(HIDError::DeviceError);
         }
@@ -60,7 +64,7 @@ pub trait HIDDevice: FidoDevice + Read + Write {
             .unwrap_or_else(|_| String::from("Unknown Vendor"));
         let product = self
             .get_property("Product")
-            .unwrap_or_else(|_| String::from("Unknown Device"));
+            .unwrap_or_else(|_| String::from(UNKNOWN_DEVICE_NAME));
 
         let info = U2FDeviceInfo {
             vendor_name: vendor.as_bytes().to_vec(),
@@ -87,12 +91,27 @@ pub trait HIDDevice: FidoDevice + Read + Write {
         &mut self,
         cmd: HIDCmd,
         send: &[u8],
-        keep_alive: &dyn Fn() -> bool,
+        keep_alive: &dyn Fn(u8) -> bool,
     ) -> io::Result<(HIDCmd, Vec<u8>)> {
+        let request = (cmd, send);
         self.u2f_write(cmd.into(), send)?;
         debug!("sent to Device {:?} cmd={:?}: {:?}", self.id(), cmd, send);
+        frame_log::log_message(
+            FrameDirection::Outgoing,
+            self.get_cid(),
+            request,
+            request,
+            self.out_rpt_size(),
+        );
         loop {
             let (cmd, data) = self.u2f_read()?;
+            frame_log::log_message(
+                FrameDirection::Incoming,
+                self.get_cid(),
+                request,
+                (cmd, &data),
+                self.in_rpt_size(),
+            );
             if cmd != HIDCmd::Keepalive {
                 debug!(
                     "got from Device {:?} status={:?}: {:?}",
@@ -103,9 +122,9 @@ pub trait HIDDevice: FidoDevice + Read + Write {
                 return Ok((cmd, data));
             }
             // The authenticator might send us HIDCmd::Keepalive messages indefinitely, e.g. if
-            // it's waiting for user presence. The keep_alive function is used to cancel the
-            // transaction.
-            if !keep_alive() {
+            // it's waiting for user presence. The keep_alive function gets the status of the
+            // message (e.g. STATUS_UPNEEDED), and is used to cancel the transaction.
+            if !keep_alive(data.first().copied().unwrap_or_default()) {
                 break;
             }
         }
@@ -114,12 +133,27 @@ pub trait HIDDevice: FidoDevice + Read + Write {
         // side as well. There's nothing to do for U2F/CTAP1 devices.
         if self.get_protocol() == FidoProtocol::CTAP2 {
             self.u2f_write(u8::from(HIDCmd::Cancel), &[])?;
+            frame_log::log_message(
+                FrameDirection::Outgoing,
+                self.get_cid(),
+                request,
+                (HIDCmd::Cancel, &[]),
+                self.out_rpt_size(),
+            );
         }
         // For CTAP2 devices we expect to read
         //  (HIDCmd::Cbor, [CTAP2_ERR_KEEPALIVE_CANCEL])
         // for U2F/CTAP1 we expect to read
         //  (HIDCmd::Keepalive, [status]).
-        self.u2f_read()
+        let (cmd, data) = self.u2f_read()?;
+        frame_log::log_message(
+            FrameDirection::Incoming,
+            self.get_cid(),
+            request,
+            (cmd, &data),
+            self.in_rpt_size(),
+        );
+        Ok((cmd, data))
     }
 
     fn u2f_write(&mut self, cmd: u8, send: &[u8]) -> io::Result<()> {
@@ -175,10 +209,10 @@ impl<T: HIDDevice + TestDevice> FidoDeviceIO for T {
         }
     }
 
-    fn send_cbor_cancellable<Req: RequestCtap2>(
+    fn send_cbor_cancellable_with_status<Req: RequestCtap2>(
         &mut self,
         msg: &Req,
-        keep_alive: &dyn Fn() -> bool,
+        keep_alive: &dyn Fn(u8) -> bool,
     ) -> Result<Req::Output, HIDError> {
         debug!("sending {:?} to {:?}", msg, self);
         #[cfg(test)]
@@ -197,10 +231,10 @@ impl<T: HIDDevice + TestDevice> FidoDeviceIO for T {
         let buf = buf;
 
         let (cmd, resp) = self.sendrecv(HIDCmd::Cbor, &buf, keep_alive)?;
-        if cmd == HIDCmd::Cbor {
-            Ok(msg.handle_response_ctap2(self, &resp)?)
-        } else {
-            Err(HIDError::UnexpectedCmd(cmd.into()))
+        match cmd {
+            HIDCmd::Cbor => Ok(msg.handle_response_ctap2(self, &resp)?),
+            HIDCmd::Error => Err(ctaphid_error(&resp)),
+            _ => Err(HIDError::UnexpectedCmd(cmd.into())),
         }
     }
 
@@ -220,7 +254,7 @@ impl<T: HIDDevice + TestDevice> FidoDeviceIO for T {
 
         while keep_alive() {
             // sendrecv will not block with a CTAP1 device
-            let (cmd, mut data) = self.sendrecv(HIDCmd::Msg, &data, &|| true)?;
+            let (cmd, mut data) = self.sendrecv(HIDCmd::Msg, &data, &|_| true)?;
             if cmd == HIDCmd::Msg {
                 if data.len() < 2 {
                     return Err(io_err("Unexpected Response: shorter than expected").into());
@@ -239,6 +273,8 @@ impl<T: HIDDevice + TestDevice> FidoDeviceIO for T {
                     }
                     Err(Retryable::Error(e)) => return Err(e),
                 }
+            } else if cmd == HIDCmd::Error {
+                return Err(ctaphid_error(&data));
             } else {
                 return Err(HIDError::UnexpectedCmd(cmd.into()));
             }
@@ -250,3 +286,11 @@ impl<T: HIDDevice + TestDevice> FidoDeviceIO for T {
         )))
     }
 }
+
+/// Maps the payload of a CTAPHID_ERROR response, which is a single error code byte.
+fn ctaphid_error(payload: &[u8]) -> HIDError {
+    match payload.first() {
+        Some(code) => HIDError::CtapHid(CtapHidError::from(*code)),
+        None => HIDError::UnexpectedCmd(HIDCmd::Error.into()),
+    }
+}
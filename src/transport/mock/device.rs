@@ -211,7 +211,7 @@ impl HIDDevice for Device {
 
         // Send Init to broadcast address to create a new channel
         self.set_cid(CID_BROADCAST);
-        let (cmd, raw) = HIDDevice::sendrecv(self, HIDCmd::Init, &nonce, &|| true)?;
+        let (cmd, raw) = HIDDevice::sendrecv(self, HIDCmd::Init, &nonce, &|_| true)?;
         if cmd != HIDCmd::Init {
             return Err(HIDError::DeviceError);
         }
@@ -310,6 +310,10 @@ impl FidoDevice for Device {
         // Nothing
     }
 
+    fn clear_shared_secret(&mut self) {
+        // Nothing
+    }
+
     fn get_authenticator_info(&self) -> Option<&AuthenticatorInfo> {
         self.authenticator_info.as_ref()
     }
@@ -325,4 +329,8 @@ impl FidoDevice for Device {
     fn downgrade_to_ctap1(&mut self) {
         self.protocol = FidoProtocol::CTAP1;
     }
+
+    fn reset_channel(&mut self) {
+        self.set_cid(CID_BROADCAST);
+    }
 }
@@ -3,4 +3,5 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 pub mod device;
+pub mod recording;
 pub mod transaction;
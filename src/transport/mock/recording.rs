@@ -0,0 +1,413 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::crypto::SharedSecret;
+use crate::ctap2::commands::get_info::AuthenticatorInfo;
+use crate::ctap2::commands::{
+    Command, CommandError, RequestCtap1, RequestCtap2, Retryable, StatusCode,
+};
+use crate::transport::errors::{ApduErrorStatus, HIDError};
+use crate::transport::{FidoDevice, FidoDeviceIO, FidoProtocol};
+use std::collections::VecDeque;
+
+/// A request as it was put on the wire by a `RecordingDevice`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordedRequest {
+    /// CTAP1 APDU, as produced by `RequestCtap1::ctap1_format()`
+    Ctap1(Vec<u8>),
+    /// CTAP2 command byte and CBOR payload, as produced by `RequestCtap2::wire_format()`
+    Ctap2(Command, Vec<u8>),
+}
+
+/// In-memory device, that records every request sent to it and answers with scripted
+/// responses. Unlike `Device`, there is no HID framing involved, so tests can assert the
+/// exact payloads of a whole sequence of commands.
+///
+/// Responses are the raw bytes the authenticator would send back: For CTAP2 the status byte
+/// followed by the CBOR payload, for CTAP1 the response data followed by the status word.
+/// They are passed through the regular `handle_response_*` functions of the requests.
+/// CTAP2 responses can be preceded by keep-alive messages, see `add_keepalive()`.
+#[derive(Debug)]
+pub struct RecordingDevice {
+    pub protocol: FidoProtocol,
+    pub authenticator_info: Option<AuthenticatorInfo>,
+    pub shared_secret: Option<SharedSecret>,
+    pub requests: Vec<RecordedRequest>,
+    responses: VecDeque<ScriptedResponse>,
+}
+
+#[derive(Debug)]
+enum ScriptedResponse {
+    Keepalive(u8),
+    Response(Result<Vec<u8>, HIDError>),
+}
+
+impl RecordingDevice {
+    pub fn new(protocol: FidoProtocol) -> Self {
+        RecordingDevice {
+            protocol,
+            authenticator_info: None,
+            shared_secret: None,
+            requests: Vec::new(),
+            responses: VecDeque::new(),
+        }
+    }
+
+    pub fn add_response(&mut self, response: &[u8]) {
+        self.responses
+            .push_back(ScriptedResponse::Response(Ok(response.to_vec())));
+    }
+
+    pub fn add_error(&mut self, error: HIDError) {
+        self.responses
+            .push_back(ScriptedResponse::Response(Err(error)));
+    }
+
+    /// Makes the device send a keep-alive message with the given status (e.g.
+    /// `STATUS_UPNEEDED` while waiting for user presence) before the next CTAP2 response.
+    pub fn add_keepalive(&mut self, status: u8) {
+        self.responses
+            .push_back(ScriptedResponse::Keepalive(status));
+    }
+
+    fn next_response(&mut self) -> Result<Vec<u8>, HIDError> {
+        match self.responses.pop_front() {
+            Some(ScriptedResponse::Response(resp)) => resp,
+            Some(ScriptedResponse::Keepalive(_)) => panic!("Unexpected keep-alive message!"),
+            None => panic!("No scripted response left!"),
+        }
+    }
+}
+
+impl Drop for RecordingDevice {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            assert!(
+                self.responses.is_empty(),
+                "Not all scripted responses were used"
+            );
+        }
+    }
+}
+
+impl FidoDeviceIO for RecordingDevice {
+    fn send_msg_cancellable<Out, Req: RequestCtap1<Output = Out> + RequestCtap2<Output = Out>>(
+        &mut self,
+        msg: &Req,
+        keep_alive: &dyn Fn() -> bool,
+    ) -> Result<Out, HIDError> {
+        match self.get_protocol() {
+            FidoProtocol::CTAP1 => self.send_ctap1_cancellable(msg, keep_alive),
+            FidoProtocol::CTAP2 => self.send_cbor_cancellable(msg, keep_alive),
+        }
+    }
+
+    fn send_cbor_cancellable_with_status<Req: RequestCtap2>(
+        &mut self,
+        msg: &Req,
+        keep_alive: &dyn Fn(u8) -> bool,
+    ) -> Result<Req::Output, HIDError> {
+        let data = msg.wire_format()?;
+        self.requests
+            .push(RecordedRequest::Ctap2(msg.command(), data));
+        while let Some(&ScriptedResponse::Keepalive(status)) = self.responses.front() {
+            self.responses.pop_front();
+            if !keep_alive(status) {
+                return Err(HIDError::Command(CommandError::StatusCode(
+                    StatusCode::KeepaliveCancel,
+                    None,
+                )));
+            }
+        }
+        let resp = self.next_response()?;
+        msg.handle_response_ctap2(self, &resp)
+    }
+
+    fn send_ctap1_cancellable<Req: RequestCtap1>(
+        &mut self,
+        msg: &Req,
+        keep_alive: &dyn Fn() -> bool,
+    ) -> Result<Req::Output, HIDError> {
+        let (data, add_info) = msg.ctap1_format()?;
+        while keep_alive() {
+            self.requests.push(RecordedRequest::Ctap1(data.clone()));
+            let mut resp = self.next_response()?;
+            if resp.len() < 2 {
+                return Err(HIDError::Command(CommandError::InputTooSmall));
+            }
+            let status = resp.split_off(resp.len() - 2);
+            let status = ApduErrorStatus::from([status[0], status[1]]);
+            match msg.handle_response_ctap1(self, status, &resp, &add_info) {
+                Ok(out) => return Ok(out),
+                Err(Retryable::Retry) => continue,
+                Err(Retryable::Error(e)) => return Err(e),
+            }
+        }
+        Err(HIDError::Command(CommandError::StatusCode(
+            StatusCode::KeepaliveCancel,
+            None,
+        )))
+    }
+}
+
+impl FidoDevice for RecordingDevice {
+    fn pre_init(&mut self) -> Result<(), HIDError> {
+        Ok(())
+    }
+
+    fn initialized(&self) -> bool {
+        true
+    }
+
+    fn is_u2f(&mut self) -> bool {
+        true
+    }
+
+    fn should_try_ctap2(&self) -> bool {
+        self.protocol == FidoProtocol::CTAP2
+    }
+
+    fn get_authenticator_info(&self) -> Option<&AuthenticatorInfo> {
+        self.authenticator_info.as_ref()
+    }
+
+    fn set_authenticator_info(&mut self, authenticator_info: AuthenticatorInfo) {
+        self.authenticator_info = Some(authenticator_info);
+    }
+
+    fn get_protocol(&self) -> FidoProtocol {
+        self.protocol
+    }
+
+    fn downgrade_to_ctap1(&mut self) {
+        self.protocol = FidoProtocol::CTAP1;
+    }
+
+    fn reset_channel(&mut self) {
+        // There is no HID channel to allocate
+    }
+
+    fn get_shared_secret(&self) -> Option<&SharedSecret> {
+        self.shared_secret.as_ref()
+    }
+
+    fn set_shared_secret(&mut self, secret: SharedSecret) {
+        self.shared_secret = Some(secret);
+    }
+
+    fn clear_shared_secret(&mut self) {
+        self.shared_secret = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RecordedRequest, RecordingDevice};
+    use crate::consts::{STATUS_PROCESSING, STATUS_UPNEEDED};
+    use crate::crypto::{
+        COSEAlgorithm, COSEEC2Key, COSEKey, COSEKeyType, Curve, PinUvAuthProtocol,
+    };
+    use crate::ctap2::commands::client_pin::GetKeyAgreement;
+    use crate::ctap2::commands::get_info::tests::AUTHENTICATOR_INFO_PAYLOAD;
+    use crate::ctap2::commands::get_info::AuthenticatorInfo;
+    use crate::ctap2::commands::get_version::GetVersion;
+    use crate::ctap2::commands::make_credentials::dummy_make_credentials_cmd;
+    use crate::ctap2::commands::{Command, CommandError, RequestCtap1, RequestCtap2, StatusCode};
+    use crate::transport::errors::{CtapHidError, HIDError};
+    use crate::transport::{FidoDevice, FidoDeviceIO, FidoProtocol};
+    use crate::StatusUpdate;
+    use std::collections::BTreeMap;
+    use std::convert::TryFrom;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_recording_device_ctap2() {
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        let mut info_response = vec![0x00];
+        info_response.extend_from_slice(&AUTHENTICATOR_INFO_PAYLOAD);
+        device.add_response(&info_response);
+        // PinAuthInvalid, which is what tokens answer to the dummy request
+        device.add_response(&[0x33]);
+
+        device.init().expect("Failed to init device");
+        assert!(device.get_authenticator_info().is_some());
+
+        let dummy = dummy_make_credentials_cmd();
+        assert!(device.send_msg(&dummy).is_err());
+
+        assert_eq!(
+            device.requests,
+            vec![
+                RecordedRequest::Ctap2(Command::GetInfo, vec![]),
+                RecordedRequest::Ctap2(
+                    Command::MakeCredentials,
+                    dummy.wire_format().expect("Failed to serialize request")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recording_device_get_info_channel_busy() {
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.add_error(HIDError::CtapHid(CtapHidError::ChannelBusy));
+        // CTAP1_ERR_CHANNEL_BUSY as CTAP2 status code
+        device.add_response(&[0x06]);
+        let mut info_response = vec![0x00];
+        info_response.extend_from_slice(&AUTHENTICATOR_INFO_PAYLOAD);
+        device.add_response(&info_response);
+
+        device.init().expect("Failed to init device");
+        assert!(device.get_authenticator_info().is_some());
+        assert_eq!(device.get_protocol(), FidoProtocol::CTAP2);
+        assert_eq!(
+            device.requests,
+            vec![RecordedRequest::Ctap2(Command::GetInfo, vec![]); 3]
+        );
+
+        // Don't fall back to CTAP1, if the device stays busy
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        for _ in 0..4 {
+            device.add_error(HIDError::CtapHid(CtapHidError::ChannelBusy));
+        }
+        assert!(matches!(
+            device.init(),
+            Err(HIDError::CtapHid(CtapHidError::ChannelBusy))
+        ));
+        assert_eq!(device.get_protocol(), FidoProtocol::CTAP2);
+        assert_eq!(device.requests.len(), 4);
+    }
+
+    #[test]
+    fn test_recording_device_get_info_presence_required() {
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.add_keepalive(STATUS_UPNEEDED);
+        device.add_keepalive(STATUS_UPNEEDED);
+        let mut info_response = vec![0x00];
+        info_response.extend_from_slice(&AUTHENTICATOR_INFO_PAYLOAD);
+        device.add_response(&info_response);
+
+        let (tx, rx) = channel();
+        device
+            .init_cancellable(Some(&tx), &|| true)
+            .expect("Failed to init device");
+        assert!(device.get_authenticator_info().is_some());
+        // Only one prompt, no matter how many keep-alive messages we got
+        assert!(matches!(rx.try_recv(), Ok(StatusUpdate::PresenceRequired)));
+        assert!(rx.try_recv().is_err());
+
+        // Cancelling while waiting for the touch does not fall back to CTAP1
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.add_keepalive(STATUS_UPNEEDED);
+        assert!(matches!(
+            device.init_cancellable(Some(&tx), &|| false),
+            Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::KeepaliveCancel,
+                _
+            )))
+        ));
+        assert!(matches!(rx.try_recv(), Ok(StatusUpdate::PresenceRequired)));
+        assert_eq!(device.get_protocol(), FidoProtocol::CTAP2);
+        assert_eq!(
+            device.requests,
+            vec![RecordedRequest::Ctap2(Command::GetInfo, vec![])]
+        );
+    }
+
+    #[test]
+    fn test_recording_device_get_info_processing() {
+        // Slow devices send keep-alive messages while processing, which is no reason to
+        // prompt the user
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.add_keepalive(STATUS_PROCESSING);
+        device.add_keepalive(STATUS_PROCESSING);
+        let mut info_response = vec![0x00];
+        info_response.extend_from_slice(&AUTHENTICATOR_INFO_PAYLOAD);
+        device.add_response(&info_response);
+
+        let (tx, rx) = channel();
+        device
+            .init_cancellable(Some(&tx), &|| true)
+            .expect("Failed to init device");
+        assert!(device.get_authenticator_info().is_some());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_recording_device_ctap1() {
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP1);
+        device.add_response(&[0x55, 0x32, 0x46, 0x5f, 0x56, 0x32, 0x90, 0x00]);
+
+        let cmd = GetVersion::default();
+        device.send_ctap1(&cmd).expect("Failed to send GetVersion");
+
+        let (expected, _) = cmd.ctap1_format().expect("Failed to serialize request");
+        assert_eq!(device.requests, vec![RecordedRequest::Ctap1(expected)]);
+    }
+
+    fn key_agreement_response() -> Vec<u8> {
+        // Device key taken from https://github.com/Yubico/python-fido2/blob/main/tests/test_ctap2.py
+        let key = COSEKey {
+            alg: COSEAlgorithm::ECDH_ES_HKDF256,
+            key: COSEKeyType::EC2(COSEEC2Key {
+                curve: Curve::SECP256R1,
+                x: vec![
+                    0x05, 0x01, 0xd5, 0xbc, 0x78, 0xda, 0x92, 0x52, 0x56, 0x0a, 0x26, 0xcb, 0x08,
+                    0xfc, 0xc6, 0x0c, 0xbe, 0x0b, 0x6d, 0x3b, 0x8e, 0x1d, 0x1f, 0xce, 0xe5, 0x14,
+                    0xfa, 0xc0, 0xaf, 0x67, 0x51, 0x68,
+                ],
+                y: vec![
+                    0xd5, 0x51, 0xb3, 0xed, 0x46, 0xf6, 0x65, 0x73, 0x1f, 0x95, 0xb4, 0x53, 0x29,
+                    0x39, 0xc2, 0x5d, 0x91, 0xdb, 0x7e, 0xb8, 0x44, 0xbd, 0x96, 0xd4, 0xab, 0xd4,
+                    0x08, 0x37, 0x85, 0xf8, 0xdf, 0x47,
+                ],
+            }),
+        };
+        let mut response = vec![0x00];
+        let mut payload = BTreeMap::new();
+        payload.insert(0x01, key);
+        response.extend(serde_cbor::to_vec(&payload).unwrap());
+        response
+    }
+
+    #[test]
+    fn test_recording_device_key_agreement_reuse() {
+        let info = AuthenticatorInfo {
+            pin_protocols: Some(vec![1]),
+            ..Default::default()
+        };
+        let key_agreement = RecordedRequest::Ctap2(
+            Command::ClientPin,
+            GetKeyAgreement::new(PinUvAuthProtocol::try_from(&info).unwrap())
+                .wire_format()
+                .unwrap(),
+        );
+        let mut device = RecordingDevice::new(FidoProtocol::CTAP2);
+        device.set_authenticator_info(info);
+        device.add_response(&key_agreement_response());
+
+        let first = device.establish_shared_secret(&|| true).unwrap();
+        let second = device.establish_shared_secret(&|| true).unwrap();
+        assert_eq!(first.client_input(), second.client_input());
+        assert_eq!(device.requests, vec![key_agreement.clone()]);
+
+        // Errors that don't affect the key agreement key keep the shared secret
+        device.invalidate_shared_secret_on(&HIDError::Command(CommandError::StatusCode(
+            StatusCode::OperationDenied,
+            None,
+        )));
+        device.establish_shared_secret(&|| true).unwrap();
+        assert_eq!(device.requests, vec![key_agreement.clone()]);
+
+        // After a wrong PIN, the device regenerates its key, and we have to ask again
+        device.invalidate_shared_secret_on(&HIDError::Command(CommandError::StatusCode(
+            StatusCode::PinInvalid,
+            None,
+        )));
+        device.add_response(&key_agreement_response());
+        let third = device.establish_shared_secret(&|| true).unwrap();
+        assert_ne!(first.client_input(), third.client_input());
+        assert_eq!(device.requests, vec![key_agreement.clone(), key_agreement]);
+    }
+}
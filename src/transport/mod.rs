@@ -1,12 +1,14 @@
+use crate::consts::STATUS_UPNEEDED;
 use crate::crypto::{PinUvAuthProtocol, PinUvAuthToken, SharedSecret};
 use crate::ctap2::commands::client_pin::{
-    ClientPIN, ClientPinResponse, GetKeyAgreement, GetPinToken,
+    ClientPIN, ClientPinResponse, GetKeyAgreement, GetPinRetries, GetPinToken,
     GetPinUvAuthTokenUsingPinWithPermissions, GetPinUvAuthTokenUsingUvWithPermissions,
-    PinUvAuthTokenPermission,
+    GetUvRetries, PinRetries, PinUvAuthTokenPermission,
 };
 use crate::ctap2::commands::get_assertion::{GetAssertion, GetAssertionResult};
 use crate::ctap2::commands::get_info::{AuthenticatorInfo, AuthenticatorVersion, GetInfo};
 use crate::ctap2::commands::get_version::{GetVersion, U2FInfo};
+use crate::ctap2::commands::large_blobs::{LargeBlobArray, LargeBlobs};
 use crate::ctap2::commands::make_credentials::{
     dummy_make_credentials_cmd, MakeCredentials, MakeCredentialsResult,
 };
@@ -14,15 +16,23 @@ use crate::ctap2::commands::reset::Reset;
 use crate::ctap2::commands::selection::Selection;
 use crate::ctap2::commands::{CommandError, RequestCtap1, RequestCtap2, StatusCode};
 use crate::ctap2::preflight::CheckKeyHandle;
+use crate::status_update::{send_status, StatusUpdate};
 use crate::transport::device_selector::BlinkResult;
 use crate::transport::errors::HIDError;
 
 use crate::Pin;
+use serde::de::Error as SerdeError;
+use serde_cbor::error::Error as CborError;
+use std::cell::Cell;
 use std::convert::TryFrom;
 use std::fmt;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
 
 pub mod device_selector;
 pub mod errors;
+pub mod frame_log;
 pub mod hid;
 
 #[cfg(all(
@@ -71,6 +81,10 @@ pub mod platform;
 #[path = "mock/mod.rs"]
 pub mod platform;
 
+/// How often getInfo is retried during `init()`, while another channel keeps the device busy.
+const GET_INFO_BUSY_RETRIES: usize = 3;
+const GET_INFO_BUSY_DELAY: Duration = Duration::from_millis(100);
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FidoProtocol {
     CTAP1,
@@ -103,6 +117,16 @@ pub trait FidoDeviceIO {
         &mut self,
         msg: &Req,
         keep_alive: &dyn Fn() -> bool,
+    ) -> Result<Req::Output, HIDError> {
+        self.send_cbor_cancellable_with_status(msg, &|_| keep_alive())
+    }
+
+    /// Like `send_cbor_cancellable()`, but `keep_alive` gets the status of every keep-alive
+    /// message of the authenticator, e.g. `STATUS_UPNEEDED` while it waits for a touch.
+    fn send_cbor_cancellable_with_status<Req: RequestCtap2>(
+        &mut self,
+        msg: &Req,
+        keep_alive: &dyn Fn(u8) -> bool,
     ) -> Result<Req::Output, HIDError>;
 
     fn send_ctap1_cancellable<Req: RequestCtap1>(
@@ -155,25 +179,101 @@ where
     // it's a HID device and it does not have the CBOR capability).
     fn get_protocol(&self) -> FidoProtocol;
 
+    /// Whether the device supports CTAP2, e.g. to tell U2F-only security keys apart without a
+    /// failed CTAP2 round trip. A successful getInfo is authoritative. Before that, we go by
+    /// the CBOR capability flag and whether we've already fallen back to CTAP1.
+    fn is_ctap2_capable(&self) -> bool {
+        match self.get_authenticator_info() {
+            Some(info) => info.max_supported_version() != AuthenticatorVersion::U2F_V2,
+            None => self.should_try_ctap2() && self.get_protocol() == FidoProtocol::CTAP2,
+        }
+    }
+
     // We do not provide a generic `set_protocol(..)` function as this would have complicated
     // interactions with the AuthenticatorInfo state.
     fn downgrade_to_ctap1(&mut self);
 
+    /// Forgets the allocated channel, so that the next `pre_init()` requests a new one.
+    fn reset_channel(&mut self);
+
     fn get_shared_secret(&self) -> Option<&SharedSecret>;
     fn set_shared_secret(&mut self, secret: SharedSecret);
+    fn clear_shared_secret(&mut self);
+
+    /// Forgets the shared secret, if `error` indicates that the authenticator regenerated its
+    /// key agreement key. This happens e.g. after a wrong PIN was sent, and a stale shared
+    /// secret would make the next PIN attempt fail as well.
+    fn invalidate_shared_secret_on(&mut self, error: &HIDError) {
+        if let HIDError::Command(CommandError::StatusCode(
+            StatusCode::PinInvalid
+            | StatusCode::PinAuthInvalid
+            | StatusCode::PinBlocked
+            | StatusCode::PinAuthBlocked,
+            _,
+        )) = error
+        {
+            self.clear_shared_secret();
+        }
+    }
 
     fn init(&mut self) -> Result<(), HIDError> {
+        self.init_cancellable(None, &|| true)
+    }
+
+    /// Like `init()`, but getInfo can be cancelled through `keep_alive`.
+    ///
+    /// A few authenticators only answer getInfo after they were touched, and send keep-alive
+    /// messages until then. On the first of those asking for user presence, we send
+    /// `StatusUpdate::PresenceRequired` to `status`, so the user gets prompted. Keep-alive
+    /// messages of slow devices that are merely processing the request don't prompt. Note that this blocks until the user touches
+    /// the device, or `keep_alive` returns false.
+    fn init_cancellable(
+        &mut self,
+        status: Option<&Sender<StatusUpdate>>,
+        keep_alive: &dyn Fn() -> bool,
+    ) -> Result<(), HIDError> {
         self.pre_init()?;
 
         if self.should_try_ctap2() {
             let command = GetInfo::default();
-            if let Ok(info) = self.send_cbor(&command) {
-                debug!("{:?}", info);
-                if info.max_supported_version() == AuthenticatorVersion::U2F_V2 {
-                    self.downgrade_to_ctap1();
+            let presence_requested = Cell::new(false);
+            let wait_for_info = |keepalive_status| {
+                if keepalive_status == STATUS_UPNEEDED && !presence_requested.replace(true) {
+                    debug!("Device is waiting for user presence before answering getInfo");
+                    if let Some(status) = status {
+                        send_status(status, StatusUpdate::PresenceRequired);
+                    }
+                }
+                keep_alive()
+            };
+            let mut result = self.send_cbor_cancellable_with_status(&command, &wait_for_info);
+            // Another client (e.g. a browser) may be in the middle of a transaction with the
+            // device. That is usually over quickly, so we try again on a fresh channel instead
+            // of failing, or worse, wrongly falling back to CTAP1.
+            let mut retries = GET_INFO_BUSY_RETRIES;
+            while retries > 0 && matches!(result, Err(ref e) if e.is_channel_busy()) {
+                retries -= 1;
+                debug!("Channel busy during getInfo, retrying ({retries} retries left)");
+                thread::sleep(GET_INFO_BUSY_DELAY);
+                self.reset_channel();
+                self.pre_init()?;
+                result = self.send_cbor_cancellable_with_status(&command, &wait_for_info);
+            }
+            match result {
+                Ok(info) => {
+                    debug!("{:?}", info);
+                    if info.max_supported_version() == AuthenticatorVersion::U2F_V2 {
+                        self.downgrade_to_ctap1();
+                    }
+                    self.set_authenticator_info(info);
+                    return Ok(());
                 }
-                self.set_authenticator_info(info);
-                return Ok(());
+                Err(e) if e.is_channel_busy() => return Err(e),
+                // Cancelled while waiting for the touch, which says nothing about CTAP1 support
+                Err(
+                    e @ HIDError::Command(CommandError::StatusCode(StatusCode::KeepaliveCancel, _)),
+                ) => return Err(e),
+                Err(_) => {}
             }
         }
 
@@ -241,8 +341,14 @@ where
 
         let pin_protocol = PinUvAuthProtocol::try_from(info)?;
 
-        // Not reusing the shared secret here, if it exists, since we might start again
-        // with a different PIN (e.g. if the last one was wrong)
+        // Reuse the key agreement of earlier clientPIN calls, unless the authenticator
+        // signalled that it regenerated its key (see `invalidate_shared_secret_on()`).
+        if let Some(shared_secret) = self.get_shared_secret() {
+            if shared_secret.pin_protocol.id() == pin_protocol.id() {
+                return Ok(shared_secret.clone());
+            }
+        }
+
         let pin_command = GetKeyAgreement::new(pin_protocol.clone());
         let resp = self.send_cbor_cancellable(&pin_command, alive)?;
         if let Some(device_key_agreement_key) = resp.key_agreement {
@@ -258,6 +364,68 @@ where
         }
     }
 
+    /// Reads the whole serialized large-blob array, fragment by fragment, and checks its
+    /// integrity. Decrypting the entries needs the largeBlobKeys of the respective credentials.
+    fn read_large_blob_array(
+        &mut self,
+        alive: &dyn Fn() -> bool,
+    ) -> Result<LargeBlobArray, HIDError> {
+        // maxFragmentLength is maxMsgSize - 64, where maxMsgSize defaults to 1024
+        let max_msg_size = self
+            .get_authenticator_info()
+            .and_then(|info| info.max_msg_size)
+            .unwrap_or(1024);
+        let max_fragment_len = max_msg_size.saturating_sub(64).max(1) as u64;
+        // Don't read forever from authenticators that keep sending full fragments.
+        // maxSerializedLargeBlobArray defaults to 1024, the minimum the spec allows.
+        let max_array_len = self
+            .get_authenticator_info()
+            .and_then(|info| info.max_ser_large_blob_array)
+            .unwrap_or(1024);
+
+        let mut array = Vec::new();
+        loop {
+            if !alive() {
+                return Err(HIDError::Command(CommandError::StatusCode(
+                    StatusCode::KeepaliveCancel,
+                    None,
+                )));
+            }
+            let cmd = LargeBlobs::new(max_fragment_len, array.len() as u64);
+            let fragment = self.send_cbor_cancellable(&cmd, alive)?.config;
+            let done = (fragment.len() as u64) < max_fragment_len;
+            array.extend(fragment);
+            if array.len() as u64 > max_array_len {
+                return Err(HIDError::Command(CommandError::Deserializing(
+                    CborError::custom("large blob array exceeds maxSerializedLargeBlobArray"),
+                )));
+            }
+            if done {
+                break;
+            }
+        }
+
+        let array = LargeBlobArray(array);
+        array.cbor_array()?;
+        Ok(array)
+    }
+
+    /// Remaining PIN attempts, see `PinRetries`.
+    fn get_pin_retries(&mut self) -> Result<PinRetries, HIDError> {
+        let resp = self.send_cbor(&GetPinRetries::new())?;
+        PinRetries::try_from(&resp).map_err(HIDError::Command)
+    }
+
+    /// Remaining attempts of the built-in user verification. Once they are used up, the
+    /// authenticator returns UvBlocked and the PIN has to be used instead.
+    fn get_uv_retries(&mut self) -> Result<u8, HIDError> {
+        let resp = self.send_cbor(&GetUvRetries::new())?;
+        resp.uv_retries
+            .ok_or(HIDError::Command(CommandError::MissingRequiredField(
+                "uv_retries",
+            )))
+    }
+
     /// CTAP 2.0-only version:
     /// "Getting pinUvAuthToken using getPinToken (superseded)"
     fn get_pin_token(
@@ -270,19 +438,33 @@ where
             .as_ref()
             .ok_or(CommandError::StatusCode(StatusCode::PinRequired, None))?;
 
-        // Not reusing the shared secret here, if it exists, since we might start again
-        // with a different PIN (e.g. if the last one was wrong)
         let shared_secret = self.establish_shared_secret(alive)?;
 
         let pin_command = GetPinToken::new(&shared_secret, pin);
-        let resp = self.send_cbor_cancellable(&pin_command, alive)?;
+        let resp = match self.send_cbor_cancellable(&pin_command, alive) {
+            Ok(resp) => resp,
+            Err(e) => {
+                self.invalidate_shared_secret_on(&e);
+                return Err(e);
+            }
+        };
         if let Some(encrypted_pin_token) = resp.pin_token {
             // CTAP 2.1 spec:
             // If authenticatorClientPIN's getPinToken subcommand is invoked, default permissions
             // of `mc` and `ga` (value 0x03) are granted for the returned pinUvAuthToken.
-            let default_permissions = PinUvAuthTokenPermission::default();
+            // Authenticators without pinUvAuthToken-support have no permission system, and
+            // accept the token e.g. for the preview versions of credential management, too.
+            let permissions = if self
+                .get_authenticator_info()
+                .and_then(|info| info.options.pin_uv_auth_token)
+                == Some(true)
+            {
+                PinUvAuthTokenPermission::default()
+            } else {
+                PinUvAuthTokenPermission::all()
+            };
             let pin_token = shared_secret
-                .decrypt_pin_token(default_permissions, encrypted_pin_token.as_ref())
+                .decrypt_pin_token(permissions, encrypted_pin_token.as_ref())
                 .map_err(CommandError::from)?;
             Ok(pin_token)
         } else {
@@ -298,7 +480,6 @@ where
         rp_id: Option<&String>,
         alive: &dyn Fn() -> bool,
     ) -> Result<PinUvAuthToken, HIDError> {
-        // Explicitly not reusing the shared secret here
         let shared_secret = self.establish_shared_secret(alive)?;
         let pin_command = GetPinUvAuthTokenUsingUvWithPermissions::new(
             &shared_secret,
@@ -306,7 +487,13 @@ where
             rp_id.cloned(),
         );
 
-        let resp = self.send_cbor_cancellable(&pin_command, alive)?;
+        let resp = match self.send_cbor_cancellable(&pin_command, alive) {
+            Ok(resp) => resp,
+            Err(e) => {
+                self.invalidate_shared_secret_on(&e);
+                return Err(e);
+            }
+        };
 
         if let Some(encrypted_pin_token) = resp.pin_token {
             let pin_token = shared_secret
@@ -332,8 +519,6 @@ where
             .as_ref()
             .ok_or(CommandError::StatusCode(StatusCode::PinRequired, None))?;
 
-        // Not reusing the shared secret here, if it exists, since we might start again
-        // with a different PIN (e.g. if the last one was wrong)
         let shared_secret = self.establish_shared_secret(alive)?;
         let pin_command = GetPinUvAuthTokenUsingPinWithPermissions::new(
             &shared_secret,
@@ -342,7 +527,13 @@ where
             rp_id.cloned(),
         );
 
-        let resp = self.send_cbor_cancellable(&pin_command, alive)?;
+        let resp = match self.send_cbor_cancellable(&pin_command, alive) {
+            Ok(resp) => resp,
+            Err(e) => {
+                self.invalidate_shared_secret_on(&e);
+                return Err(e);
+            }
+        };
 
         if let Some(encrypted_pin_token) = resp.pin_token {
             let pin_token = shared_secret
@@ -206,6 +206,10 @@ impl FidoDevice for Device {
         self.secret = Some(secret);
     }
 
+    fn clear_shared_secret(&mut self) {
+        self.secret = None;
+    }
+
     fn get_authenticator_info(&self) -> Option<&AuthenticatorInfo> {
         self.authenticator_info.as_ref()
     }
@@ -221,4 +225,8 @@ impl FidoDevice for Device {
     fn downgrade_to_ctap1(&mut self) {
         self.protocol = FidoProtocol::CTAP1;
     }
+
+    fn reset_channel(&mut self) {
+        self.cid = CID_BROADCAST;
+    }
 }
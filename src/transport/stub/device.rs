@@ -101,6 +101,10 @@ impl FidoDevice for Device {
         unimplemented!()
     }
 
+    fn clear_shared_secret(&mut self) {
+        unimplemented!()
+    }
+
     fn get_shared_secret(&self) -> Option<&SharedSecret> {
         unimplemented!()
     }
@@ -112,4 +116,8 @@ impl FidoDevice for Device {
     fn downgrade_to_ctap1(&mut self) {
         unimplemented!()
     }
+
+    fn reset_channel(&mut self) {
+        unimplemented!()
+    }
 }
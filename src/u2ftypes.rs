@@ -278,6 +278,9 @@ impl CTAP1RequestAPDU {
     }
 }
 
+/// The `device_name` of devices that don't report a HID product name
+pub(crate) const UNKNOWN_DEVICE_NAME: &str = "Unknown Device";
+
 #[derive(Clone, Debug, Serialize)]
 pub struct U2FDeviceInfo {
     pub vendor_name: Vec<u8>,
@@ -289,6 +292,16 @@ pub struct U2FDeviceInfo {
     pub cap_flags: Capability,
 }
 
+impl U2FDeviceInfo {
+    /// The HID product name of the device, if it reported one
+    pub fn product_name(&self) -> Option<String> {
+        match str::from_utf8(&self.device_name) {
+            Ok(name) if !name.is_empty() && name != UNKNOWN_DEVICE_NAME => Some(name.to_string()),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for U2FDeviceInfo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -311,7 +324,8 @@ impl fmt::Display for U2FDeviceInfo {
 
 #[cfg(test)]
 pub(crate) mod tests {
-    use super::CTAP1RequestAPDU;
+    use super::{CTAP1RequestAPDU, U2FDeviceInfo, UNKNOWN_DEVICE_NAME};
+    use crate::consts::Capability;
 
     #[test]
     fn test_ctap1_serialize() {
@@ -338,4 +352,24 @@ pub(crate) mod tests {
         let big = [0xFF; 65536];
         assert!(CTAP1RequestAPDU::serialize(1, 2, &big).is_err());
     }
+
+    #[test]
+    fn test_product_name() {
+        let info = |name: &[u8]| U2FDeviceInfo {
+            vendor_name: b"Yubico".to_vec(),
+            device_name: name.to_vec(),
+            version_interface: 2,
+            version_major: 5,
+            version_minor: 4,
+            version_build: 3,
+            cap_flags: Capability::CBOR,
+        };
+        assert_eq!(
+            info(b"YubiKey OTP+FIDO+CCID").product_name(),
+            Some(String::from("YubiKey OTP+FIDO+CCID"))
+        );
+        assert_eq!(info(UNKNOWN_DEVICE_NAME.as_bytes()).product_name(), None);
+        assert_eq!(info(b"").product_name(), None);
+        assert_eq!(info(&[0xff, 0xfe]).product_name(), None);
+    }
 }